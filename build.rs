@@ -0,0 +1,57 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    // Embed the commit this was built from so that cached findings from other builds of the same
+    // version aren't reused. Builds from outside of a git checkout (such as from crates.io) leave
+    // it empty, and rely on the version alone.
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash.trim());
+    for path in [".git/HEAD", ".git/refs", ".git/packed-refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    // The commit doesn't cover uncommitted changes, so the sources are hashed as well
+    let mut hasher = DefaultHasher::new();
+    hash_dir(Path::new("src"), &mut hasher);
+    println!("cargo:rustc-env=SOURCE_HASH={:016x}", hasher.finish());
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Hashes the paths and contents of the files in `dir` and its subdirectories, in a stable
+/// order.
+fn hash_dir(dir: &Path, hasher: &mut DefaultHasher) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            hash_dir(&path, hasher);
+        } else if let Ok(contents) = fs::read(&path) {
+            path.hash(hasher);
+            contents.hash(hasher);
+        }
+    }
+}
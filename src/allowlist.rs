@@ -0,0 +1,268 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Generates an `allowed_external_types` list that allows every unapproved type of a run.
+//!
+//! This gives crates that already expose many external types a starting point for a config,
+//! which can then be narrowed down over time. For workspaces, the lists can also be generated
+//! from every type that the members expose, so that their configs stay consistent.
+
+use crate::error::{SuppressionMechanism, ValidationError, ValidationErrors};
+use crate::fmt_config;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use toml_edit::{value, Array, DocumentMut, Item, Table};
+
+/// Number of distinct types from one crate above which the whole crate is allowed, rather than
+/// each of its types.
+const MAX_TYPES_PER_CRATE: usize = 3;
+
+const HEADER: &str =
+    "# Allows the external types that were unapproved when this file was generated\n";
+
+const MEMBERS_HEADER: &str =
+    "# Allows the external types that were exposed when this file was generated\n";
+
+/// Path to the config table within a workspace's `Cargo.toml`.
+pub const WORKSPACE_TABLE_PATH: &[&str] = &["workspace", "metadata", "cargo_check_external_types"];
+
+/// Returns the names of the types in `errors` that aren't approved by the config.
+pub fn unapproved_types(errors: &ValidationErrors) -> BTreeSet<String> {
+    errors
+        .iter()
+        .filter(|error| matches!(error, ValidationError::UnapprovedExternalTypeRef { .. }))
+        .flat_map(|error| error.type_names().map(String::from))
+        .collect()
+}
+
+/// Returns the names of the types in `errors` that the config needs to allow: the unapproved
+/// types, and the types that `allowed_external_types` or `allowed_external_types_exact`
+/// already allow. Types allowed by `allowed_external_types_in` are left to those tables.
+pub fn exposed_types(errors: &ValidationErrors) -> BTreeSet<String> {
+    let allowed = [
+        SuppressionMechanism::AllowedExternalTypes,
+        SuppressionMechanism::AllowedExternalTypesExact,
+    ]
+    .into_iter()
+    .flat_map(|mechanism| errors.suppressed(mechanism))
+    .filter(|error| matches!(error, ValidationError::UnapprovedExternalTypeRef { .. }))
+    .map(|error| error.type_name().to_string());
+    unapproved_types(errors)
+        .into_iter()
+        .chain(allowed)
+        .collect()
+}
+
+/// External types that each member of a workspace exposes.
+#[derive(Debug, Default)]
+pub struct MemberAllowlists {
+    types_by_member: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl MemberAllowlists {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds types that `member` exposes.
+    pub fn add(&mut self, member: impl Into<String>, types: BTreeSet<String>) {
+        self.types_by_member
+            .entry(member.into())
+            .or_default()
+            .extend(types);
+    }
+
+    /// Returns the types that any member exposes.
+    pub fn union(&self) -> BTreeSet<String> {
+        self.types_by_member.values().flatten().cloned().collect()
+    }
+
+    /// Returns the names of the members along with the types they expose, ordered by name.
+    pub fn members(&self) -> impl Iterator<Item = (&str, &BTreeSet<String>)> {
+        self.types_by_member
+            .iter()
+            .map(|(member, types)| (member.as_str(), types))
+    }
+
+    /// Returns the types that no member other than `member` exposes.
+    pub fn only_exposed_by(&self, member: &str) -> BTreeSet<&str> {
+        let Some(types) = self.types_by_member.get(member) else {
+            return BTreeSet::new();
+        };
+        types
+            .iter()
+            .filter(|&type_name| {
+                self.types_by_member
+                    .iter()
+                    .all(|(other, types)| other == member || !types.contains(type_name))
+            })
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Returns the patterns that allow all of the `types`. Crates with more than a few types are
+/// allowed with a single glob, which is written with `**` when the config uses
+/// `anchored_patterns` so that it still covers the crate's modules.
+pub fn allowlist_patterns(types: &BTreeSet<String>, anchored: bool) -> Vec<String> {
+    let mut types_by_crate: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for type_name in types {
+        let crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
+        types_by_crate
+            .entry(crate_name)
+            .or_default()
+            .push(type_name);
+    }
+    let crate_glob = if anchored { "::**" } else { "::*" };
+    types_by_crate
+        .into_iter()
+        .flat_map(|(crate_name, types)| {
+            if types.len() > MAX_TYPES_PER_CRATE {
+                vec![format!("{crate_name}{crate_glob}")]
+            } else {
+                types.into_iter().map(String::from).collect()
+            }
+        })
+        .collect()
+}
+
+/// Renders a standalone TOML config file with the given `allowed_external_types`.
+pub fn render_allowlist(patterns: &[String]) -> Result<String> {
+    render_allowlist_table(HEADER, &[], patterns)
+}
+
+/// Renders a TOML document with a config table at `table_path`, such as
+/// [`WORKSPACE_TABLE_PATH`], that allows the types the workspace members exposed.
+pub fn render_member_allowlist(table_path: &[&str], patterns: &[String]) -> Result<String> {
+    render_allowlist_table(MEMBERS_HEADER, table_path, patterns)
+}
+
+fn render_allowlist_table(
+    header: &str,
+    table_path: &[&str],
+    patterns: &[String],
+) -> Result<String> {
+    let mut document = DocumentMut::new();
+    let mut table = document.as_table_mut();
+    for (i, &key) in table_path.iter().enumerate() {
+        let item = table.entry(key).or_insert_with(|| {
+            let mut new_table = Table::new();
+            // Only the innermost table needs a header of its own
+            new_table.set_implicit(i + 1 < table_path.len());
+            Item::Table(new_table)
+        });
+        table = item.as_table_mut().expect("new table");
+    }
+    table["allowed_external_types"] = value(patterns.iter().collect::<Array>());
+    fmt_config::format_config(&format!("{header}{document}"), table_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorLocation;
+
+    fn unapproved(type_names: &[&str]) -> ValidationErrors {
+        let mut errors = ValidationErrors::new();
+        for (i, type_name) in type_names.iter().enumerate() {
+            errors.add(ValidationError::unapproved_external_type_ref(
+                *type_name,
+                &ErrorLocation::StructField,
+                format!("root::Thing{i}"),
+                None,
+                None,
+                None,
+            ));
+        }
+        errors.add(ValidationError::hidden_item(
+            &ErrorLocation::StructField,
+            "root::Hidden",
+            None,
+        ));
+        errors
+    }
+
+    #[test]
+    fn collapse_crates_with_many_types() {
+        let errors = unapproved(&[
+            "few::One",
+            "few::One",
+            "few::module::Two",
+            "many::A",
+            "many::B",
+            "many::c::C",
+            "many::D",
+        ]);
+        let types = unapproved_types(&errors);
+        assert_eq!(6, types.len());
+        assert_eq!(
+            vec!["few::One", "few::module::Two", "many::*"],
+            allowlist_patterns(&types, false)
+        );
+        assert_eq!(
+            vec!["few::One", "few::module::Two", "many::**"],
+            allowlist_patterns(&types, true)
+        );
+        assert!(allowlist_patterns(&unapproved_types(&unapproved(&[])), false).is_empty());
+    }
+
+    #[test]
+    fn types_exposed_by_members() {
+        let mut errors = unapproved(&["ext::Unapproved"]);
+        for error in unapproved(&["ext::Allowed"]).iter() {
+            errors.suppress(SuppressionMechanism::AllowedExternalTypes, error.clone());
+        }
+        for error in unapproved(&["ext::Scoped"]).iter() {
+            errors.suppress(SuppressionMechanism::AllowedExternalTypesIn, error.clone());
+        }
+        let types = exposed_types(&errors);
+        assert_eq!(
+            vec!["ext::Allowed", "ext::Unapproved"],
+            types.iter().collect::<Vec<_>>()
+        );
+
+        let mut members = MemberAllowlists::new();
+        members.add("a", types);
+        members.add(
+            "b",
+            exposed_types(&unapproved(&["ext::Allowed", "other::Type"])),
+        );
+        assert_eq!(3, members.union().len());
+        assert_eq!(
+            BTreeSet::from(["ext::Unapproved"]),
+            members.only_exposed_by("a")
+        );
+        assert_eq!(
+            BTreeSet::from(["other::Type"]),
+            members.only_exposed_by("b")
+        );
+        assert!(members.only_exposed_by("c").is_empty());
+    }
+
+    #[test]
+    fn render_member_allowlist_as_toml() {
+        let patterns = vec!["a::One".to_string()];
+        assert_eq!(
+            format!(
+                "{MEMBERS_HEADER}[workspace.metadata.cargo_check_external_types]\n\
+                allowed_external_types = [\n    \"a::One\",\n]\n"
+            ),
+            render_member_allowlist(WORKSPACE_TABLE_PATH, &patterns).unwrap()
+        );
+    }
+
+    #[test]
+    fn render_allowlist_as_toml() {
+        let patterns = vec!["b::*".to_string(), "a::One".to_string()];
+        assert_eq!(
+            format!("{HEADER}allowed_external_types = [\n    \"a::One\",\n    \"b::*\",\n]\n"),
+            render_allowlist(&patterns).unwrap()
+        );
+        assert_eq!(
+            format!("{HEADER}allowed_external_types = []\n"),
+            render_allowlist(&[]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,176 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Records the findings of a run in a baseline file, so that they can be suppressed in later
+//! runs while any new finding is still reported.
+//!
+//! Findings are matched by their kind, the type they're about, and the item that contains the
+//! reference, rather than by their location, so that moving code around doesn't invalidate the
+//! baseline.
+
+use crate::error::{SuppressionMechanism, ValidationError, ValidationErrors};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+/// Findings that are known and accepted.
+#[derive(Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Baseline {
+    findings: BTreeSet<BaselineEntry>,
+}
+
+/// The parts of a finding that a baseline matches it by.
+#[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct BaselineEntry {
+    /// Kind of the finding, as in [`ValidationError::kind`]
+    pub kind: String,
+    /// Path of the type that the finding is about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_name: Option<String>,
+    /// Path of the item that contains the reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<String>,
+}
+
+impl BaselineEntry {
+    fn new(error: &ValidationError) -> Self {
+        Self {
+            kind: error.kind().to_string(),
+            type_name: match error {
+                ValidationError::HiddenItem { .. } | ValidationError::PatternType { .. } => None,
+                _ => Some(error.type_name().to_string()),
+            },
+            item: error.in_what_type().map(String::from),
+        }
+    }
+}
+
+impl Baseline {
+    /// Returns a baseline with every finding in `errors` that's reported or that was
+    /// suppressed by a baseline.
+    pub fn from_errors(errors: &ValidationErrors) -> Self {
+        Self {
+            findings: errors
+                .iter()
+                .chain(errors.suppressed(SuppressionMechanism::Baseline))
+                .map(BaselineEntry::new)
+                .collect(),
+        }
+    }
+
+    /// Reads the baseline file at `path`.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read baseline file {path:?}"))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse baseline file {path:?}"))
+    }
+
+    /// Writes the baseline to the file at `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs::write(path, contents).with_context(|| format!("failed to write baseline file {path:?}"))
+    }
+
+    /// Returns true if the baseline records `error`.
+    pub fn contains(&self, error: &ValidationError) -> bool {
+        self.findings.contains(&BaselineEntry::new(error))
+    }
+
+    /// Returns the entries that don't match any of the findings in `errors`, either reported or
+    /// suppressed by the baseline. These have been fixed, and can be removed from the baseline.
+    pub fn stale_entries<'a>(
+        &'a self,
+        errors: &ValidationErrors,
+    ) -> impl Iterator<Item = &'a BaselineEntry> {
+        let current = Self::from_errors(errors).findings;
+        self.findings
+            .iter()
+            .filter(move |entry| !current.contains(entry))
+    }
+
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorLocation;
+    use rustdoc_types::Span;
+
+    fn unapproved(type_name: &str, in_what_type: &str, line: usize) -> ValidationError {
+        let span = Span {
+            filename: "src/lib.rs".into(),
+            begin: (line, 0),
+            end: (line, 10),
+        };
+        ValidationError::unapproved_external_type_ref(
+            type_name,
+            &ErrorLocation::StructField,
+            in_what_type,
+            Some(&span),
+            None,
+            None,
+        )
+    }
+
+    fn errors(errors: impl IntoIterator<Item = ValidationError>) -> ValidationErrors {
+        let mut result = ValidationErrors::new();
+        for error in errors {
+            result.add(error);
+        }
+        result
+    }
+
+    #[test]
+    fn match_findings_regardless_of_location() {
+        let baseline = Baseline::from_errors(&errors([
+            unapproved("ext::One", "my_crate::Thing", 1),
+            unapproved("ext::One", "my_crate::Thing", 7),
+            ValidationError::fields_stripped(&crate::path::Path::new("my_crate")),
+        ]));
+        assert_eq!(2, baseline.len());
+        assert!(baseline.contains(&unapproved("ext::One", "my_crate::Thing", 42)));
+        assert!(!baseline.contains(&unapproved("ext::Two", "my_crate::Thing", 1)));
+        assert!(!baseline.contains(&unapproved("ext::One", "my_crate::Other", 1)));
+    }
+
+    #[test]
+    fn find_stale_entries() {
+        let baseline = Baseline::from_errors(&errors([
+            unapproved("ext::Fixed", "my_crate::Thing", 1),
+            unapproved("ext::Kept", "my_crate::Thing", 2),
+        ]));
+        let mut current = errors([]);
+        current.suppress(
+            SuppressionMechanism::Baseline,
+            unapproved("ext::Kept", "my_crate::Thing", 3),
+        );
+        let stale: Vec<_> = baseline.stale_entries(&current).collect();
+        assert_eq!(1, stale.len());
+        assert_eq!(Some("ext::Fixed"), stale[0].type_name.as_deref());
+    }
+
+    #[test]
+    fn round_trip_through_json() {
+        let baseline =
+            Baseline::from_errors(&errors([unapproved("ext::One", "my_crate::Thing", 1)]));
+        let json = serde_json::to_string(&baseline).unwrap();
+        assert_eq!(
+            r#"{"findings":[{"kind":"unapproved_external_type_ref","type_name":"ext::One","item":"my_crate::Thing"}]}"#,
+            json
+        );
+        assert_eq!(baseline, serde_json::from_str(&json).unwrap());
+    }
+}
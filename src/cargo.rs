@@ -3,13 +3,95 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
+use crate::format::{self, MIN_FORMAT_VERSION};
 use crate::here;
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use cargo_platform::Cfg;
 use rustdoc_types::{Crate, FORMAT_VERSION};
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Output};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+/// Version of the `rustdoc-types` crate that rustdoc's JSON output is parsed with. This must be
+/// updated along with the dependency in `Cargo.toml`.
+pub const RUSTDOC_TYPES_VERSION: &str = "0.32.2";
+
+/// Oldest Rust nightly known to produce the newest supported [`FORMAT_VERSION`]. Older nightlies
+/// may work too if their format version can be upgraded (see [`MIN_FORMAT_VERSION`]).
+pub const EARLIEST_SUPPORTED_NIGHTLY: &str = "nightly-2024-10-29";
+
+/// Newest Rust nightly the tool has been tested against. This must be updated along with the
+/// `rust-toolchain.toml` file.
+pub const LATEST_TESTED_NIGHTLY: &str = "nightly-2024-10-29";
+
+/// Which rustdoc JSON format versions and Rust nightlies this build of the tool works with.
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    /// Version of this tool
+    pub version: &'static str,
+    /// Oldest rustdoc JSON `format_version` that can be parsed
+    pub min_format_version: u32,
+    /// Newest rustdoc JSON `format_version` that can be parsed
+    pub max_format_version: u32,
+    /// Version of the `rustdoc-types` crate used to parse the JSON
+    pub rustdoc_types_version: &'static str,
+    /// Oldest recommended nightly
+    pub earliest_nightly: &'static str,
+    /// Newest recommended nightly
+    pub latest_nightly: &'static str,
+}
+
+impl VersionInfo {
+    /// Returns the version info for this build of the tool.
+    pub fn current() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            // Older versions are upgraded to the version `rustdoc-types` was generated for
+            min_format_version: MIN_FORMAT_VERSION,
+            max_format_version: FORMAT_VERSION,
+            rustdoc_types_version: RUSTDOC_TYPES_VERSION,
+            earliest_nightly: EARLIEST_SUPPORTED_NIGHTLY,
+            latest_nightly: LATEST_TESTED_NIGHTLY,
+        }
+    }
+}
+
+impl fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "cargo-check-external-types {}", self.version)?;
+        if self.min_format_version == self.max_format_version {
+            writeln!(
+                f,
+                "rustdoc JSON format version: {}",
+                self.min_format_version
+            )?;
+        } else {
+            writeln!(
+                f,
+                "rustdoc JSON format versions: {} to {}",
+                self.min_format_version, self.max_format_version
+            )?;
+        }
+        writeln!(f, "rustdoc-types version: {}", self.rustdoc_types_version)?;
+        if self.earliest_nightly == self.latest_nightly {
+            writeln!(f, "recommended toolchain: {}", self.latest_nightly)
+        } else {
+            writeln!(
+                f,
+                "recommended toolchains: {} to {}",
+                self.earliest_nightly, self.latest_nightly
+            )
+        }
+    }
+}
 
 #[derive(Deserialize)]
 struct CrateFormatVersion {
@@ -17,6 +99,23 @@ struct CrateFormatVersion {
 }
 
 /// Runs the `cargo rustdoc` command required to produce Rustdoc's JSON output with a nightly compiler.
+///
+/// Only the lib name, crate path, and target directory are required. Everything else is
+/// configured with builder methods before calling [`run`](CargoRustDocJson::run).
+///
+/// # Example
+///
+/// ```no_run
+/// use cargo_check_external_types::cargo::CargoRustDocJson;
+///
+/// let package = CargoRustDocJson::new("my_crate", "path/to/my-crate", "path/to/target")
+///     .features(vec!["some-feature".into()])
+///     .target("x86_64-unknown-linux-gnu")
+///     .toolchain("nightly")
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Clone)]
 pub struct CargoRustDocJson {
     /// Name of the lib target, by default this is the crate name but may be customized in the
     /// `[lib]` section of the Cargo.toml file.
@@ -25,70 +124,327 @@ pub struct CargoRustDocJson {
     crate_path: PathBuf,
     /// Expected `target/` directory where the output will be
     target_path: PathBuf,
-    /// Features to enable
-    features: Vec<String>,
+    /// Features to enable, or `None` for the default features
+    features: Option<Vec<String>>,
     /// Target triple
     target: Option<String>,
+    /// Whether to pass along the `RUSTDOCFLAGS` from the environment or cargo config
+    inherit_rustdocflags: bool,
+    /// Whether the inherited flags come after the tool's own flags, so that they win when a flag
+    /// is given more than once
+    inherited_rustdocflags_last: bool,
+    /// Additional flags to pass to rustdoc
+    rustdoc_flags: Vec<String>,
+    /// Whether to include items marked `#[doc(hidden)]` in the output
+    document_hidden_items: bool,
+    /// Additional environment variables to set for cargo
+    env: Vec<(String, String)>,
+    /// Rustup toolchain to run cargo with, such as `nightly`
+    toolchain: Option<String>,
+    /// Directory to build into instead of `target_path`
+    output_dir: Option<PathBuf>,
+    /// Package to document instead of the one at `crate_path`
+    package: Option<String>,
+    /// Whether Cargo.lock must be up to date
+    locked: bool,
+    /// Whether Cargo.lock must be up to date and the network must not be accessed
+    frozen: bool,
+    /// Whether the network must not be accessed
+    offline: bool,
+    /// Cargo profile to build with, such as `release`
+    profile: Option<String>,
+    /// Cargo config overrides to pass with `--config`
+    cargo_config: Vec<String>,
+    /// Unstable cargo flags to pass with `-Z`
+    unstable_flags: Vec<String>,
+    /// Whether to install a compatible nightly and rerun with it if the toolchain can't be used
+    install_toolchain: bool,
+    /// Files and directories that JSON from an earlier run must be newer than to be reused, or
+    /// `None` to always run rustdoc
+    reuse_inputs: Option<Vec<PathBuf>>,
+    /// Whether to print the command line before running it
+    print_command: bool,
+    /// Whether to print where the JSON is
+    print_json_path: bool,
+    /// File to copy the JSON to
+    copy_json_to: Option<PathBuf>,
+    /// Output of `rustc -vV` for the toolchain, once it's been asked for
+    rustc_version: OnceLock<Option<String>>,
+    /// The `rustdocflags` from the cargo config, once they've been looked up
+    configured_rustdocflags: OnceLock<Vec<String>>,
 }
 
 impl CargoRustDocJson {
+    /// Creates a builder for the lib target `lib_name` of the crate at `crate_path`, where
+    /// `target_path` is the `target/` directory that cargo builds into for that crate.
     pub fn new(
         lib_name: impl Into<String>,
         crate_path: impl Into<PathBuf>,
         target_path: impl Into<PathBuf>,
-        features: Vec<String>,
-        target: Option<String>,
     ) -> Self {
         CargoRustDocJson {
             lib_name: lib_name.into(),
             crate_path: crate_path.into(),
             target_path: target_path.into(),
-            features,
-            target,
+            features: None,
+            target: None,
+            inherit_rustdocflags: true,
+            inherited_rustdocflags_last: false,
+            rustdoc_flags: Vec::new(),
+            document_hidden_items: false,
+            env: Vec::new(),
+            toolchain: None,
+            output_dir: None,
+            package: None,
+            locked: false,
+            frozen: false,
+            offline: false,
+            profile: None,
+            cargo_config: Vec::new(),
+            unstable_flags: Vec::new(),
+            install_toolchain: false,
+            reuse_inputs: None,
+            print_command: false,
+            print_json_path: false,
+            copy_json_to: None,
+            rustc_version: OnceLock::new(),
+            configured_rustdocflags: OnceLock::new(),
         }
     }
 
-    pub fn run(&self) -> Result<Crate> {
-        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    /// Sets the features to enable. Once they're set, default features are disabled, so the
+    /// default features must be listed explicitly if they're wanted.
+    pub fn features(mut self, features: Vec<String>) -> Self {
+        self.features = Some(features);
+        self
+    }
 
-        let mut command = Command::new(cargo);
-        command.current_dir(&self.crate_path).arg("rustdoc");
-        if !self.features.is_empty() {
-            command.arg("--no-default-features").arg("--features");
-            command.arg(self.features.join(","));
-        }
-        if let Some(target) = &self.target {
-            command.arg("--target").arg(target);
+    /// Sets the target triple to document for.
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Sets whether the `RUSTDOCFLAGS` from the environment, or the `rustdocflags` from the
+    /// cargo config if they aren't set, are passed along to rustdoc. Defaults to true.
+    pub fn inherit_rustdocflags(mut self, inherit: bool) -> Self {
+        self.inherit_rustdocflags = inherit;
+        self
+    }
+
+    /// Sets whether the inherited flags are passed after the tool's own flags, rather than
+    /// before, so that they take precedence for options where the last one wins. Inherited
+    /// `--output-format` flags are always dropped. Defaults to false.
+    pub fn inherited_rustdocflags_last(mut self, last: bool) -> Self {
+        self.inherited_rustdocflags_last = last;
+        self
+    }
+
+    /// Adds a flag to pass to rustdoc, in addition to the flags required for JSON output.
+    pub fn rustdoc_flag(mut self, flag: impl Into<String>) -> Self {
+        self.rustdoc_flags.push(flag.into());
+        self
+    }
+
+    /// Sets whether items marked `#[doc(hidden)]` are included in the output, with rustdoc's
+    /// `--document-hidden-items`. Without it, they're left out of the index, so references to
+    /// them can't be resolved. Defaults to false.
+    pub fn document_hidden_items(mut self, document: bool) -> Self {
+        self.document_hidden_items = document;
+        self
+    }
+
+    /// Sets an environment variable for the cargo invocation.
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Runs cargo through rustup with the given toolchain, such as `nightly`.
+    pub fn toolchain(mut self, toolchain: impl Into<String>) -> Self {
+        self.toolchain = Some(toolchain.into());
+        // A clone that's rerun with another toolchain mustn't keep what the first one looked up
+        self.rustc_version = OnceLock::new();
+        self.configured_rustdocflags = OnceLock::new();
+        self
+    }
+
+    /// Builds into the given directory (with `--target-dir`) rather than the crate's usual
+    /// `target/` directory.
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(output_dir.into());
+        self
+    }
+
+    /// Documents the given package (such as a dependency) of the project at `crate_path`, rather
+    /// than the project itself. Features can't be set for packages outside of the workspace.
+    pub fn package(mut self, package: impl Into<String>) -> Self {
+        self.package = Some(package.into());
+        self
+    }
+
+    /// Sets whether cargo must leave Cargo.lock as it is, with `--locked`. Defaults to false.
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Sets whether cargo must leave Cargo.lock as it is and not access the network, with
+    /// `--frozen`. Defaults to false.
+    pub fn frozen(mut self, frozen: bool) -> Self {
+        self.frozen = frozen;
+        self
+    }
+
+    /// Sets whether cargo must not access the network, with `--offline`. Defaults to false.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Builds with the given cargo profile, such as `release` or a custom `docs` profile, rather
+    /// than the `dev` profile. Profile settings like `debug-assertions` change which
+    /// `cfg`-gated items are documented.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Overrides a cargo config value with `--config`, as `KEY=VALUE` or a path to a config
+    /// file. It's also used when reading `rustdocflags` from the cargo config.
+    pub fn cargo_config(mut self, config: impl Into<String>) -> Self {
+        self.cargo_config.push(config.into());
+        self
+    }
+
+    /// Adds an unstable flag to pass to cargo with `-Z`, such as `bindeps`.
+    pub fn unstable_flag(mut self, flag: impl Into<String>) -> Self {
+        self.unstable_flags.push(flag.into());
+        self
+    }
+
+    /// Sets whether a nightly that produces the supported [`FORMAT_VERSION`] is installed with
+    /// rustup (if it isn't already) and used when the toolchain can't produce JSON that can be
+    /// parsed. Defaults to false.
+    pub fn install_toolchain(mut self, install: bool) -> Self {
+        self.install_toolchain = install;
+        self
+    }
+
+    /// Reuses the JSON from an earlier run with the same settings, rather than running rustdoc
+    /// again, if it was written after the last change to any of the `inputs`. Directories in
+    /// `inputs` are searched recursively, skipping hidden and `target/` directories.
+    pub fn reuse_if_newer_than(mut self, inputs: Vec<PathBuf>) -> Self {
+        self.reuse_inputs = Some(inputs);
+        self
+    }
+
+    /// Sets whether the `cargo rustdoc` command line, including the directory it's run in and the
+    /// environment variables it sets, is printed to stderr before it's run. Defaults to false.
+    pub fn print_command(mut self, print: bool) -> Self {
+        self.print_command = print;
+        self
+    }
+
+    /// Sets whether the path of the JSON is printed to stderr once it's generated (or reused).
+    /// Defaults to false.
+    pub fn print_json_path(mut self, print: bool) -> Self {
+        self.print_json_path = print;
+        self
+    }
+
+    /// Copies the JSON to `path` once it's generated (or reused), creating its directory if
+    /// needed.
+    pub fn copy_json_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.copy_json_to = Some(path.into());
+        self
+    }
+
+    /// Runs rustdoc and parses its JSON output.
+    ///
+    /// If the toolchain can't produce the JSON or produces an unsupported format version, the
+    /// error is an [`IncompatibleToolchain`] that suggests a compatible nightly.
+    pub fn run(&self) -> Result<Crate> {
+        match self.run_with_toolchain() {
+            Err(err) if self.install_toolchain && err.is::<IncompatibleToolchain>() => {
+                let toolchain = install_compatible_toolchain()?;
+                if self.toolchain.as_deref() == Some(toolchain.as_str()) {
+                    return Err(err);
+                }
+                eprintln!("Rerunning rustdoc with toolchain {toolchain}...");
+                self.clone().toolchain(toolchain).run_with_toolchain()
+            }
+            result => result,
         }
-        command
-            .arg("--")
-            .arg("--document-private-items")
-            .arg("-Z")
-            .arg("unstable-options")
-            .arg("--output-format")
-            .arg("json");
-        let output = command
-            .output()
-            .context(here!("failed to run nightly rustdoc"))?;
-        handle_failure("rustdoc", &output)?;
+    }
 
-        let mut output_file_name = self
-            .target_path
-            .canonicalize()
-            .context(here!("failed to canonicalize {:?}", self.target_path))?;
-        if let Some(target) = &self.target {
-            output_file_name.push(target);
+    /// Returns the results that [`cache_results`](Self::cache_results) stored under `key` for
+    /// the JSON from the same settings, if none of the reuse inputs changed since. Results are
+    /// only cached when the JSON can be reused (see
+    /// [`reuse_if_newer_than`](Self::reuse_if_newer_than)).
+    pub fn cached_results<T: DeserializeOwned>(&self, key: u64) -> Option<T> {
+        let file = self.results_file(&self.command(), key)?;
+        if !self.is_fresh(&file) {
+            return None;
         }
-        output_file_name.push(format!("doc/{}.json", self.lib_name.replace('-', "_")));
+        // Results that can't be read are computed again
+        let results = serde_json::from_str(&fs::read_to_string(&file).ok()?).ok()?;
+        eprintln!("Reusing results from {}", file.display());
+        Some(results)
+    }
+
+    /// Stores `results` computed from the JSON under `key`, so that later runs with the same
+    /// settings can skip running rustdoc and computing them. `key` must identify everything
+    /// other than the JSON that the results depend on.
+    pub fn cache_results<T: Serialize>(&self, key: u64, results: &T) -> Result<()> {
+        let Some(file) = self.results_file(&self.command(), key) else {
+            return Ok(());
+        };
+        let json = serde_json::to_string(results).context(here!())?;
+        fs::write(&file, json).with_context(|| here!("failed to write results to {file:?}"))
+    }
 
-        let json = fs::read_to_string(output_file_name).context(here!())?;
+    fn run_with_toolchain(&self) -> Result<Crate> {
+        let mut command = self.command();
+        let reused_file = self.reused_file(&command);
+        let json = match &reused_file {
+            Some(file) if self.is_fresh(file) => {
+                eprintln!("Reusing rustdoc JSON output from {}", file.display());
+                fs::read_to_string(file).context(here!())?
+            }
+            _ => {
+                let json = self.run_command(&mut command)?;
+                if let Some(file) = &reused_file {
+                    fs::write(file, &json)
+                        .with_context(|| here!("failed to write rustdoc JSON to {file:?}"))?;
+                }
+                json
+            }
+        };
+        // The JSON is emitted before it's parsed, so that it can be looked at if parsing fails
+        if self.print_json_path {
+            let file = match reused_file {
+                Some(file) => file,
+                None => self.output_file()?,
+            };
+            eprintln!("Rustdoc JSON is at {}", file.display());
+        }
+        if let Some(path) = &self.copy_json_to {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .with_context(|| here!("failed to create directory {dir:?}"))?;
+            }
+            fs::write(path, &json)
+                .with_context(|| here!("failed to copy rustdoc JSON to {path:?}"))?;
+            eprintln!("Copied rustdoc JSON to {}", path.display());
+        }
         let format_version: CrateFormatVersion = serde_json::from_str(&json)
             .context("Failed to find `format_version` in rustdoc JSON output.")
             .context(here!())?;
-        if format_version.format_version != FORMAT_VERSION {
-            bail!(
+        if !format::is_supported(format_version.format_version) {
+            return Err(IncompatibleToolchain::new(format!(
                 "The version of rustdoc being used produces JSON format version {0}, but \
-                this tool requires format version {1}. This can happen if the locally \
+                this tool supports format versions {1} to {2}. This can happen if the locally \
                 installed version of rustdoc doesn't match the rustdoc JSON types from \
                 the `rustdoc-types` crate.\n\n\
                 If this occurs with the latest Rust nightly and the latest version of this \
@@ -96,15 +452,559 @@ impl CargoRustDocJson {
                 format version.\n\n\
                 Otherwise, you'll need to determine a Rust nightly version that matches \
                 this tool's supported format version (or vice versa).",
-                format_version.format_version,
-                FORMAT_VERSION
-            );
+                format_version.format_version, MIN_FORMAT_VERSION, FORMAT_VERSION
+            ))
+            .into());
         }
-        let package: Crate = serde_json::from_str(&json)
-            .context("Failed to parse rustdoc output.")
-            .context(here!())?;
+        let package = format::parse_crate(&json, format_version.format_version).context(here!())?;
         Ok(package)
     }
+
+    /// Returns a cargo command for the toolchain, with the config overrides and unstable flags,
+    /// run in the crate's directory.
+    fn cargo(&self) -> Command {
+        let mut command = match &self.toolchain {
+            // `+toolchain` is only understood by the rustup proxy, so don't use `$CARGO`
+            Some(toolchain) => {
+                let mut command = Command::new("cargo");
+                command.arg(format!("+{toolchain}"));
+                command
+            }
+            None => Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())),
+        };
+        command.current_dir(&self.crate_path);
+        for config in &self.cargo_config {
+            command.arg("--config").arg(config);
+        }
+        for flag in &self.unstable_flags {
+            command.arg("-Z").arg(flag);
+        }
+        command
+    }
+
+    /// Returns the `cargo rustdoc` command to run.
+    fn command(&self) -> Command {
+        let mut command = self.cargo();
+        // Only document the lib target, even if the crate has binaries too
+        command.arg("rustdoc").arg("--lib");
+        if let Some(package) = &self.package {
+            command.arg("--package").arg(package);
+        }
+        if let Some(features) = &self.features {
+            command.arg("--no-default-features");
+            if !features.is_empty() {
+                command.arg("--features").arg(features.join(","));
+            }
+        }
+        if let Some(target) = &self.target {
+            command.arg("--target").arg(target);
+        }
+        if let Some(output_dir) = &self.output_dir {
+            command.arg("--target-dir").arg(output_dir);
+        }
+        if let Some(profile) = &self.profile {
+            command.arg("--profile").arg(profile);
+        }
+        for (enabled, flag) in [
+            (self.locked, "--locked"),
+            (self.frozen, "--frozen"),
+            (self.offline, "--offline"),
+        ] {
+            if enabled {
+                command.arg(flag);
+            }
+        }
+        command.envs(self.env.iter().map(|(key, value)| (key, value)));
+        // Cargo gives precedence to `CARGO_ENCODED_RUSTDOCFLAGS`, so pass all flags through it
+        // rather than `--` args to make sure none of them get lost. Since it overrides the
+        // `rustdocflags` in the cargo config too, those are inherited along with the environment's.
+        let inherited = if self.inherit_rustdocflags {
+            inherited_rustdocflags().unwrap_or_else(|| self.configured_rustdocflags().to_vec())
+        } else {
+            Vec::new()
+        };
+        let mut flags = self.rustdoc_flags.clone();
+        if self.document_hidden_items {
+            flags.push("--document-hidden-items".into());
+        }
+        command.env_remove("RUSTDOCFLAGS").env(
+            "CARGO_ENCODED_RUSTDOCFLAGS",
+            rustdoc_flags(inherited, flags, self.inherited_rustdocflags_last).join("\x1f"),
+        );
+        command
+    }
+
+    /// Returns the `rustdocflags` from the cargo config that cargo would use for the target,
+    /// which are the target's `target.<triple>.rustdocflags` if they're set, or else
+    /// `build.rustdocflags`. Like cargo, `target.<cfg>` tables are ignored. They're looked up
+    /// once, since that runs rustc and cargo.
+    fn configured_rustdocflags(&self) -> &[String] {
+        self.configured_rustdocflags.get_or_init(|| {
+            let target = match &self.target {
+                Some(target) => Some(target.clone()),
+                None => rustc_version_field("host").ok(),
+            };
+            target
+                .and_then(|target| self.config_value(&format!("target.{target}.rustdocflags")))
+                .or_else(|| self.config_value("build.rustdocflags"))
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns the flags in the cargo config value at `key`, which is either a list of flags or
+    /// a string of space-separated flags. Returns `None` if it isn't set, or if it can't be read
+    /// since `cargo config` isn't stable yet.
+    fn config_value(&self, key: &str) -> Option<Vec<String>> {
+        let output = self
+            .cargo()
+            .args(["-Zunstable-options", "config", "get", key])
+            .args(["--format", "json-value"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        parse_config_flags(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Runs `command`, and returns the JSON that rustdoc wrote.
+    fn run_command(&self, command: &mut Command) -> Result<String> {
+        if self.print_command {
+            eprintln!("Running `{}`", format_command(command));
+        }
+        let output = command
+            .output()
+            .context(here!("failed to run nightly rustdoc"))?;
+        if !output.status.success()
+            && is_toolchain_failure(&String::from_utf8_lossy(&output.stderr))
+        {
+            return Err(
+                IncompatibleToolchain::new(capture_error("rustdoc", &output).to_string()).into(),
+            );
+        }
+        handle_failure("rustdoc", &output)?;
+        fs::read_to_string(self.output_file()?).context(here!())
+    }
+
+    /// Returns the file that the JSON from `command` is kept in for reuse, if it's reused.
+    fn reused_file(&self, command: &Command) -> Option<PathBuf> {
+        self.reuse_inputs.as_ref()?;
+        Some(self.doc_dir().join(format!(
+            "{}.{:016x}.json",
+            self.lib_name.replace('-', "_"),
+            fingerprint(command, self.rustc_version())
+        )))
+    }
+
+    /// Returns the output of `rustc -vV` for the toolchain that rustdoc is run with, or `None` if
+    /// rustc can't be run.
+    fn rustc_version(&self) -> Option<&str> {
+        self.rustc_version
+            .get_or_init(|| {
+                let mut command = match &self.toolchain {
+                    Some(toolchain) => {
+                        let mut command = Command::new("rustc");
+                        command.arg(format!("+{toolchain}"));
+                        command
+                    }
+                    None => Command::new(rustc()),
+                };
+                // The toolchain can be picked by a `rust-toolchain.toml` in the crate's directory
+                let output = command
+                    .current_dir(&self.crate_path)
+                    .arg("-vV")
+                    .output()
+                    .ok()?;
+                output
+                    .status
+                    .success()
+                    .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+            })
+            .as_deref()
+    }
+
+    /// Returns the file that results computed from the JSON from `command` are cached in under
+    /// `key`, if the JSON is reused and `command` produced it. Rustdoc may have been rerun with
+    /// another toolchain instead, which caches its JSON under another command.
+    fn results_file(&self, command: &Command, key: u64) -> Option<PathBuf> {
+        let file = self.reused_file(command).filter(|file| file.exists())?;
+        Some(file.with_extension(format!("results-{key:016x}.json")))
+    }
+
+    /// Returns the file that rustdoc writes the JSON to.
+    fn output_file(&self) -> Result<PathBuf> {
+        let doc_dir = self.doc_dir();
+        Ok(doc_dir
+            .canonicalize()
+            .context(here!("failed to canonicalize {:?}", doc_dir))?
+            .join(format!("{}.json", self.lib_name.replace('-', "_"))))
+    }
+
+    /// Returns the `doc/` directory that rustdoc writes the JSON to.
+    fn doc_dir(&self) -> PathBuf {
+        let mut doc_dir = self
+            .output_dir
+            .as_ref()
+            .unwrap_or(&self.target_path)
+            .clone();
+        if let Some(target) = &self.target {
+            doc_dir.push(target);
+        }
+        doc_dir.join("doc")
+    }
+
+    /// Returns true if the JSON in `file` was written after the last change to any of the
+    /// reuse inputs.
+    fn is_fresh(&self, file: &Path) -> bool {
+        let Ok(written) = fs::metadata(file).and_then(|metadata| metadata.modified()) else {
+            return false;
+        };
+        let skip = [Some(&self.target_path), self.output_dir.as_ref()];
+        self.reuse_inputs.iter().flatten().all(|input| {
+            latest_modification(input, &skip)
+                .is_ok_and(|modified| modified.map_or(true, |modified| modified < written))
+        })
+    }
+}
+
+/// Returns a hash of everything that `command` runs with, so that JSON is only reused for the
+/// same program, arguments, environment, and directory, and for the same `rustc -vV` output.
+/// Updating a toolchain in place doesn't change the command, but changes the version.
+fn fingerprint(command: &Command, rustc_version: Option<&str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    rustc_version.hash(&mut hasher);
+    command.get_program().hash(&mut hasher);
+    command.get_args().for_each(|arg| arg.hash(&mut hasher));
+    command.get_envs().for_each(|env| env.hash(&mut hasher));
+    command.get_current_dir().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Formats `command` as a shell command line that runs it the same way, such as
+/// `cd /my-crate && env -u RUSTDOCFLAGS CARGO_ENCODED_RUSTDOCFLAGS=... cargo rustdoc --lib`.
+fn format_command(command: &Command) -> String {
+    let mut parts = Vec::new();
+    if let Some(dir) = command.get_current_dir() {
+        parts.push(format!("cd {} &&", shell_quote(&dir.to_string_lossy())));
+    }
+    // `env` takes its options before the variables it sets
+    let mut envs: Vec<_> = command.get_envs().collect();
+    envs.sort_by_key(|(key, value)| (value.is_some(), *key));
+    if !envs.is_empty() {
+        parts.push("env".into());
+    }
+    for (key, value) in envs {
+        let key = key.to_string_lossy();
+        parts.push(match value {
+            Some(value) => format!("{key}={}", shell_quote(&value.to_string_lossy())),
+            None => format!("-u {key}"),
+        });
+    }
+    parts.push(shell_quote(&command.get_program().to_string_lossy()));
+    parts.extend(
+        command
+            .get_args()
+            .map(|arg| shell_quote(&arg.to_string_lossy())),
+    );
+    parts.join(" ")
+}
+
+/// Quotes `value` for a POSIX shell if it has any special characters. Control characters, such
+/// as the separators in `CARGO_ENCODED_RUSTDOCFLAGS`, are escaped in `$'...'` quotes.
+fn shell_quote(value: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !value.is_empty() && value.chars().all(is_plain) {
+        value.to_string()
+    } else if value.chars().any(|c| c.is_control()) {
+        let mut quoted = String::from("$'");
+        for c in value.chars() {
+            match c {
+                '\\' | '\'' => {
+                    quoted.push('\\');
+                    quoted.push(c);
+                }
+                c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        quoted
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Returns when `path`, or the latest of the files in it if it's a directory, was last modified,
+/// or `None` if it doesn't exist or is an empty directory. Hidden and `target/` directories, and
+/// the directories in `skip`, are skipped since builds write to them.
+fn latest_modification(path: &Path, skip: &[Option<&PathBuf>]) -> io::Result<Option<SystemTime>> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    if !metadata.is_dir() {
+        return metadata.modified().map(Some);
+    }
+    let mut latest = None;
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let is_skipped = skip.iter().flatten().any(|skipped| **skipped == path);
+        if path.is_dir() && (name.starts_with('.') || name == "target" || is_skipped) {
+            continue;
+        }
+        latest = latest.max(latest_modification(&path, skip)?);
+    }
+    Ok(latest)
+}
+
+/// Rustdoc failed, or produced JSON in an unsupported format version, because of the toolchain
+/// it was run with. It's displayed with a suggestion of a compatible nightly to use instead.
+#[derive(Debug)]
+pub struct IncompatibleToolchain {
+    message: String,
+    suggestion: String,
+}
+
+impl IncompatibleToolchain {
+    fn new(message: String) -> Self {
+        Self {
+            message,
+            suggestion: toolchain_suggestion(find_compatible_toolchain(&installed_toolchains())),
+        }
+    }
+}
+
+impl fmt::Display for IncompatibleToolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n\n{}", self.message.trim_end(), self.suggestion)
+    }
+}
+
+impl std::error::Error for IncompatibleToolchain {}
+
+/// Returns true if rustdoc's `stderr` shows that it failed because of the toolchain rather than
+/// the crate, such as when it isn't a nightly or isn't installed.
+fn is_toolchain_failure(stderr: &str) -> bool {
+    stderr.contains("is only accepted on the nightly compiler")
+        || stderr.contains("may not be used on the stable release channel")
+        || (stderr.contains("toolchain") && stderr.contains("is not installed"))
+}
+
+/// Suggests how to get the `compatible` installed toolchain used, or how to install one if
+/// there's none.
+fn toolchain_suggestion(compatible: Option<String>) -> String {
+    match compatible {
+        Some(toolchain) => format!(
+            "The compatible toolchain {toolchain} is installed. Rerun with \
+            `--toolchain {toolchain}` to use it."
+        ),
+        None => format!(
+            "Install the compatible toolchain with `rustup toolchain install \
+            {LATEST_TESTED_NIGHTLY}` and rerun with `--toolchain {LATEST_TESTED_NIGHTLY}`, or \
+            rerun with `--install-toolchain` to do both."
+        ),
+    }
+}
+
+/// Returns the toolchains that rustup has installed, such as
+/// `nightly-2024-10-29-x86_64-unknown-linux-gnu`, or none if rustup isn't available.
+fn installed_toolchains() -> Vec<String> {
+    match Command::new("rustup").args(["toolchain", "list"]).output() {
+        Ok(output) if output.status.success() => {
+            parse_toolchain_list(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Parses the output of `rustup toolchain list`, which marks some toolchains with suffixes like
+/// ` (default)`.
+fn parse_toolchain_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(String::from)
+        .collect()
+}
+
+/// Returns the newest of the `installed` nightlies from [`EARLIEST_SUPPORTED_NIGHTLY`] to
+/// [`LATEST_TESTED_NIGHTLY`], without its host triple.
+fn find_compatible_toolchain(installed: &[String]) -> Option<String> {
+    installed
+        .iter()
+        .filter_map(|toolchain| toolchain.get(..LATEST_TESTED_NIGHTLY.len()))
+        .filter(|nightly| {
+            nightly.starts_with("nightly-")
+                && (EARLIEST_SUPPORTED_NIGHTLY..=LATEST_TESTED_NIGHTLY).contains(nightly)
+        })
+        .max()
+        .map(String::from)
+}
+
+/// Returns a compatible nightly toolchain, installing [`LATEST_TESTED_NIGHTLY`] with rustup if
+/// none is installed yet.
+pub fn install_compatible_toolchain() -> Result<String> {
+    if let Some(toolchain) = find_compatible_toolchain(&installed_toolchains()) {
+        return Ok(toolchain);
+    }
+    eprintln!("Installing toolchain {LATEST_TESTED_NIGHTLY}...");
+    let output = Command::new("rustup")
+        .args([
+            "toolchain",
+            "install",
+            LATEST_TESTED_NIGHTLY,
+            "--profile",
+            "minimal",
+        ])
+        .output()
+        .context(here!("failed to run rustup"))?;
+    handle_failure(&format!("install {LATEST_TESTED_NIGHTLY}"), &output)?;
+    Ok(LATEST_TESTED_NIGHTLY.to_string())
+}
+
+/// A target that rustdoc documents the crate for, along with the `cfg` values that are set
+/// when compiling for it.
+#[derive(Debug)]
+pub struct TargetInfo {
+    /// Target triple, such as `x86_64-unknown-linux-gnu`
+    pub name: String,
+    pub cfg: Vec<Cfg>,
+}
+
+impl TargetInfo {
+    /// Asks rustc about the given target triple, or about the host if there's none.
+    pub fn query(target: Option<&str>) -> Result<Self> {
+        let name = match target {
+            Some(target) => target.to_string(),
+            None => rustc_version_field("host")?,
+        };
+        let output = Command::new(rustc())
+            .args(["--print", "cfg", "--target", &name])
+            .output()
+            .context(here!("failed to run rustc"))?;
+        handle_failure("get the target cfg from rustc", &output)?;
+        let cfg = parse_cfg(&String::from_utf8_lossy(&output.stdout))?;
+        Ok(Self { name, cfg })
+    }
+}
+
+/// Returns the release of rustc, such as `1.84.0-nightly`, and fails if it isn't a nightly,
+/// since rustdoc's JSON output is only available on nightly.
+pub fn check_nightly_toolchain() -> Result<String> {
+    let release = rustc_version_field("release")?;
+    if !release.contains("nightly") && !release.ends_with("-dev") {
+        bail!(
+            "the Rust toolchain is {release}, but a nightly toolchain is required to produce \
+            rustdoc's JSON output. Install one with `rustup toolchain install {LATEST_TESTED_NIGHTLY}`, \
+            and run this tool with `cargo +{LATEST_TESTED_NIGHTLY} check-external-types`."
+        );
+    }
+    Ok(release)
+}
+
+/// Returns the rustc that cargo would use.
+fn rustc() -> String {
+    std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string())
+}
+
+/// Returns the value of the given field in the output of `rustc -vV`, such as `host`.
+fn rustc_version_field(field: &str) -> Result<String> {
+    let output = Command::new(rustc())
+        .arg("-vV")
+        .output()
+        .context(here!("failed to run rustc"))?;
+    handle_failure("get the version from rustc", &output)?;
+    parse_version_field(&String::from_utf8_lossy(&output.stdout), field)
+}
+
+/// Finds a field in the output of `rustc -vV`.
+fn parse_version_field(output: &str, field: &str) -> Result<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(": "))
+        .map(String::from)
+        .ok_or_else(|| anyhow!("failed to find the {field} in `rustc -vV`"))
+}
+
+/// Parses the output of `rustc --print cfg`.
+fn parse_cfg(output: &str) -> Result<Vec<Cfg>> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse()
+                .with_context(|| format!("failed to parse cfg from rustc: {line}"))
+        })
+        .collect()
+}
+
+/// Returns the flags set in `CARGO_ENCODED_RUSTDOCFLAGS` or `RUSTDOCFLAGS`, following the same
+/// precedence as Cargo, or `None` if neither is set.
+fn inherited_rustdocflags() -> Option<Vec<String>> {
+    if let Ok(encoded) = std::env::var("CARGO_ENCODED_RUSTDOCFLAGS") {
+        Some(
+            encoded
+                .split('\x1f')
+                .filter(|flag| !flag.is_empty())
+                .map(String::from)
+                .collect(),
+        )
+    } else if let Ok(flags) = std::env::var("RUSTDOCFLAGS") {
+        Some(flags.split_whitespace().map(String::from).collect())
+    } else {
+        None
+    }
+}
+
+/// Parses the output of `cargo config get --format json-value` for a flags value, which is
+/// either a list of flags or a string of space-separated flags.
+fn parse_config_flags(output: &str) -> Option<Vec<String>> {
+    match serde_json::from_str(output).ok()? {
+        serde_json::Value::String(flags) => {
+            Some(flags.split_whitespace().map(String::from).collect())
+        }
+        value => serde_json::from_value(value).ok(),
+    }
+}
+
+/// Merges the given inherited and additional flags with the flags required to produce JSON
+/// output. The inherited flags come first unless `inherited_last` is true.
+///
+/// Inherited output format flags are dropped since they would conflict with the JSON output.
+fn rustdoc_flags(
+    inherited: Vec<String>,
+    additional: Vec<String>,
+    inherited_last: bool,
+) -> Vec<String> {
+    let mut kept = Vec::with_capacity(inherited.len());
+    let mut inherited = inherited.into_iter();
+    while let Some(flag) = inherited.next() {
+        if flag == "--output-format" {
+            inherited.next();
+        } else if !flag.starts_with("--output-format=") {
+            kept.push(flag);
+        }
+    }
+    let mut flags = additional;
+    flags.extend(
+        [
+            "--document-private-items",
+            "-Z",
+            "unstable-options",
+            "--output-format",
+            "json",
+        ]
+        .map(String::from),
+    );
+    if inherited_last {
+        flags.extend(kept);
+        flags
+    } else {
+        kept.extend(flags);
+        kept
+    }
 }
 
 pub fn handle_failure(operation_name: &str, output: &Output) -> Result<(), anyhow::Error> {
@@ -128,3 +1028,179 @@ pub fn capture_error(operation_name: &str, output: &Output) -> anyhow::Error {
     );
     anyhow::Error::msg(message)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        find_compatible_toolchain, format_command, is_toolchain_failure, parse_cfg,
+        parse_config_flags, parse_toolchain_list, parse_version_field, rustdoc_flags, shell_quote,
+        LATEST_TESTED_NIGHTLY, RUSTDOC_TYPES_VERSION,
+    };
+    use cargo_platform::Cfg;
+
+    #[test]
+    fn merge_rustdoc_flags() {
+        let inherited = [
+            "--cap-lints",
+            "warn",
+            "--output-format",
+            "html",
+            "--sysroot=/sys",
+        ];
+        assert_eq!(
+            vec![
+                "--cap-lints",
+                "warn",
+                "--sysroot=/sys",
+                "--document-private-items",
+                "-Z",
+                "unstable-options",
+                "--output-format",
+                "json"
+            ],
+            rustdoc_flags(inherited.map(String::from).to_vec(), Vec::new(), false)
+        );
+        assert_eq!(
+            vec![
+                "--document-hidden-items",
+                "--document-private-items",
+                "-Z",
+                "unstable-options",
+                "--output-format",
+                "json",
+                "--cap-lints",
+                "warn",
+                "--sysroot=/sys",
+            ],
+            rustdoc_flags(
+                inherited.map(String::from).to_vec(),
+                vec!["--document-hidden-items".into()],
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn quote_for_shell() {
+        assert_eq!("--cfg", shell_quote("--cfg"));
+        assert_eq!("''", shell_quote(""));
+        assert_eq!("'my crate'", shell_quote("my crate"));
+        assert_eq!("'it'\\''s'", shell_quote("it's"));
+        assert_eq!("$'--cfg\\x1fdocsrs'", shell_quote("--cfg\x1fdocsrs"));
+        assert_eq!("$'a\\'b\\x0a'", shell_quote("a'b\n"));
+    }
+
+    #[test]
+    fn format_commands() {
+        let mut command = std::process::Command::new("cargo");
+        command
+            .current_dir("/my crate")
+            .args(["rustdoc", "--lib"])
+            .env_remove("RUSTDOCFLAGS")
+            .env("CARGO_ENCODED_RUSTDOCFLAGS", "--cfg\x1fdocsrs");
+        assert_eq!(
+            "cd '/my crate' && env -u RUSTDOCFLAGS CARGO_ENCODED_RUSTDOCFLAGS=$'--cfg\\x1fdocsrs' \
+            cargo rustdoc --lib",
+            format_command(&command)
+        );
+    }
+
+    #[test]
+    fn parse_configured_flags() {
+        assert_eq!(
+            Some(vec!["--cfg".to_string(), "docsrs".into()]),
+            parse_config_flags(r#"["--cfg","docsrs"]"#)
+        );
+        assert_eq!(
+            Some(vec!["--cfg".to_string(), "docsrs".into()]),
+            parse_config_flags(r#""--cfg  docsrs""#)
+        );
+        assert_eq!(None, parse_config_flags("error"));
+    }
+
+    #[test]
+    fn version_info_matches_manifests() {
+        let manifest: toml::Value =
+            toml::from_str(include_str!("../Cargo.toml")).expect("valid Cargo.toml");
+        assert_eq!(
+            Some(RUSTDOC_TYPES_VERSION),
+            manifest["dependencies"]["rustdoc-types"].as_str()
+        );
+        let toolchain: toml::Value =
+            toml::from_str(include_str!("../rust-toolchain.toml")).expect("valid toolchain file");
+        assert_eq!(
+            Some(LATEST_TESTED_NIGHTLY),
+            toolchain["toolchain"]["channel"].as_str()
+        );
+    }
+
+    #[test]
+    fn find_compatible_toolchains() {
+        let installed = parse_toolchain_list(&format!(
+            "stable-x86_64-unknown-linux-gnu (default)\n\
+            nightly-x86_64-unknown-linux-gnu\n\
+            nightly-2023-01-01-x86_64-unknown-linux-gnu\n\
+            {LATEST_TESTED_NIGHTLY}-x86_64-unknown-linux-gnu (active)\n"
+        ));
+        assert_eq!(
+            vec![
+                "stable-x86_64-unknown-linux-gnu".to_string(),
+                "nightly-x86_64-unknown-linux-gnu".into(),
+                "nightly-2023-01-01-x86_64-unknown-linux-gnu".into(),
+                format!("{LATEST_TESTED_NIGHTLY}-x86_64-unknown-linux-gnu"),
+            ],
+            installed
+        );
+        assert_eq!(
+            Some(LATEST_TESTED_NIGHTLY.to_string()),
+            find_compatible_toolchain(&installed)
+        );
+        assert_eq!(None, find_compatible_toolchain(&installed[..3]));
+    }
+
+    #[test]
+    fn detect_toolchain_failures() {
+        assert!(is_toolchain_failure(
+            "error: the option `Z` is only accepted on the nightly compiler\n"
+        ));
+        assert!(is_toolchain_failure(
+            "error[E0554]: `#![feature]` may not be used on the stable release channel\n"
+        ));
+        assert!(is_toolchain_failure(
+            "error: toolchain 'nightly-2020-01-01-x86_64-unknown-linux-gnu' is not installed\n"
+        ));
+        assert!(!is_toolchain_failure(
+            "error[E0425]: cannot find value `x` in this scope\n"
+        ));
+    }
+
+    #[test]
+    fn parse_rustc_cfg() {
+        let cfg = parse_cfg("debug_assertions\ntarget_os=\"windows\"\nwindows\n").unwrap();
+        assert_eq!(
+            vec![
+                Cfg::Name("debug_assertions".into()),
+                Cfg::KeyPair("target_os".into(), "windows".into()),
+                Cfg::Name("windows".into()),
+            ],
+            cfg
+        );
+    }
+
+    #[test]
+    fn parse_rustc_version() {
+        let output = "rustc 1.84.0-nightly (3f1be1ec7 2024-10-28)\n\
+            binary: rustc\n\
+            host: x86_64-unknown-linux-gnu\n\
+            release: 1.84.0-nightly\n";
+        assert_eq!(
+            "x86_64-unknown-linux-gnu",
+            parse_version_field(output, "host").unwrap()
+        );
+        assert_eq!(
+            "1.84.0-nightly",
+            parse_version_field(output, "release").unwrap()
+        );
+        assert!(parse_version_field(output, "commit-hash").is_err());
+    }
+}
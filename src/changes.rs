@@ -0,0 +1,150 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Finds the lines that changed since a git ref, so that only findings in new code are
+//! reported.
+
+use crate::cargo::handle_failure;
+use crate::here;
+use anyhow::{Context, Result};
+use rustdoc_types::Span;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Lines that were added or changed, by file.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ChangedLines {
+    files: BTreeMap<PathBuf, Vec<RangeInclusive<usize>>>,
+}
+
+impl ChangedLines {
+    /// Returns the lines in `dir` that changed between `git_ref` and the working tree, with file
+    /// paths relative to `dir`.
+    pub fn since(git_ref: &str, dir: &Path) -> Result<Self> {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args([
+                "diff",
+                "--unified=0",
+                "--relative",
+                "--no-color",
+                "--no-ext-diff",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+                git_ref,
+                "--",
+            ])
+            .output()
+            .context(here!("failed to run git"))?;
+        handle_failure(
+            &format!("get the changes since `{git_ref}` from git"),
+            &output,
+        )?;
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parses the output of `git diff --unified=0`.
+    fn parse(diff: &str) -> Self {
+        let mut files: BTreeMap<PathBuf, Vec<_>> = BTreeMap::new();
+        let mut current = None;
+        for line in diff.lines() {
+            if let Some(path) = line.strip_prefix("+++ ") {
+                // Deleted files are `/dev/null`, and don't have any lines left to report on
+                current = path.strip_prefix("b/").map(PathBuf::from);
+            } else if let (Some(hunk), Some(path)) = (line.strip_prefix("@@ "), &current) {
+                if let Some(lines) = parse_hunk_header(hunk) {
+                    files.entry(path.clone()).or_default().push(lines);
+                }
+            }
+        }
+        Self { files }
+    }
+
+    /// Returns true if any of the lines that `span` covers changed. The span's file is either
+    /// relative to the same directory as the changes, or an absolute path in `dir`.
+    pub fn overlaps(&self, span: &Span, dir: &Path) -> bool {
+        let file = span.filename.strip_prefix(dir).unwrap_or(&span.filename);
+        self.files.get(file).is_some_and(|ranges| {
+            ranges
+                .iter()
+                .any(|lines| *lines.start() <= span.end.0 && span.begin.0 <= *lines.end())
+        })
+    }
+}
+
+/// Returns the lines of the new file that the hunk with the header `@@ -a,b +c,d @@` touches.
+fn parse_hunk_header(hunk: &str) -> Option<RangeInclusive<usize>> {
+    let new = hunk.split(' ').find_map(|part| part.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (new.parse().ok()?, 1),
+    };
+    Some(if count == 0 {
+        // Lines were only deleted after line `start`, which changes the lines around them, such
+        // as when an attribute is removed from an item
+        start..=start + 1
+    } else {
+        start..=start + count - 1
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3,0 +4,2 @@ pub struct Kept;
++pub struct Added;
++pub fn added() {}
+@@ -10 +12 @@ pub struct Other;
+-pub fn changed(_: u8) {}
++pub fn changed(_: u16) {}
+@@ -20,2 +22,0 @@ impl Other {
+-    #[doc(hidden)]
+-    pub fn hidden() {}
+diff --git a/src/old.rs b/src/old.rs
+deleted file mode 100644
+--- a/src/old.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-pub struct Old;
+";
+
+    fn span(file: &str, begin: usize, end: usize) -> Span {
+        Span {
+            filename: file.into(),
+            begin: (begin, 0),
+            end: (end, 10),
+        }
+    }
+
+    #[test]
+    fn parse_diff() {
+        let changes = ChangedLines::parse(DIFF);
+        assert_eq!(
+            BTreeMap::from([(PathBuf::from("src/lib.rs"), vec![4..=5, 12..=12, 22..=23])]),
+            changes.files
+        );
+    }
+
+    #[test]
+    fn overlapping_spans() {
+        let changes = ChangedLines::parse(DIFF);
+        let dir = Path::new("/project");
+        assert!(changes.overlaps(&span("src/lib.rs", 5, 5), dir));
+        assert!(changes.overlaps(&span("/project/src/lib.rs", 5, 5), dir));
+        assert!(changes.overlaps(&span("src/lib.rs", 1, 30), dir));
+        assert!(changes.overlaps(&span("src/lib.rs", 23, 25), dir));
+        assert!(!changes.overlaps(&span("src/lib.rs", 6, 11), dir));
+        assert!(!changes.overlaps(&span("src/other.rs", 5, 5), dir));
+        assert!(!changes.overlaps(&span("/elsewhere/src/lib.rs", 5, 5), dir));
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Compares the violations in two reports written with the `json` output format, so that a
+//! branch can be checked for new exposure against a report from its base.
+//!
+//! Violations are matched by their kind, message, and context rather than by their location,
+//! so that moving code around doesn't make a violation look new.
+
+use crate::error::{ErrorLevel, ErrorPrinter};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// A violation read from a `json` report.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct Violation {
+    pub level: ErrorLevel,
+    pub kind: String,
+    pub message: String,
+    #[serde(default)]
+    pub context: String,
+    #[serde(default)]
+    pub location: Option<ViolationLocation>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct ViolationLocation {
+    pub file: String,
+    pub begin: (usize, usize),
+}
+
+impl Violation {
+    fn key(&self) -> (&str, &str, &str) {
+        (&self.kind, &self.message, &self.context)
+    }
+}
+
+#[derive(Deserialize)]
+struct Report {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    errors: Vec<Violation>,
+}
+
+/// Reads the violations from the `json` report at `path`.
+pub fn read_report(path: &Path) -> Result<Vec<Violation>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read report {path:?}"))?;
+    parse_report(&contents).with_context(|| format!("failed to parse report {path:?}"))
+}
+
+fn parse_report(contents: &str) -> Result<Vec<Violation>> {
+    let report: Report = serde_json::from_str(contents)?;
+    if report.status == "failure" {
+        bail!(
+            "the report is from a run that failed: {}",
+            report.message.unwrap_or_default()
+        );
+    }
+    Ok(report.errors)
+}
+
+/// Violations that were added, removed, or unchanged between two reports.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct Comparison {
+    /// Violations that are only in the new report
+    pub added: Vec<Violation>,
+    /// Violations that are only in the old report
+    pub removed: Vec<Violation>,
+    /// Violations that are in both reports, as they are in the new report
+    pub unchanged: Vec<Violation>,
+}
+
+impl Comparison {
+    /// Compares the violations of an `old` report to those of a `new` one. A violation that
+    /// occurs more often in the new report than in the old one counts as added for each of the
+    /// extra occurrences.
+    pub fn new(old: &[Violation], new: &[Violation]) -> Self {
+        let mut by_key: BTreeMap<_, (Vec<&Violation>, Vec<&Violation>)> = BTreeMap::new();
+        for violation in old {
+            by_key.entry(violation.key()).or_default().0.push(violation);
+        }
+        for violation in new {
+            by_key.entry(violation.key()).or_default().1.push(violation);
+        }
+        let mut comparison = Self::default();
+        for (old, new) in by_key.into_values() {
+            let common = old.len().min(new.len());
+            comparison
+                .unchanged
+                .extend(new[..common].iter().copied().cloned());
+            comparison
+                .added
+                .extend(new[common..].iter().copied().cloned());
+            comparison
+                .removed
+                .extend(old[common..].iter().copied().cloned());
+        }
+        comparison
+    }
+
+    /// Writes out the added, removed, and unchanged violations, followed by their counts.
+    pub fn print(&self, out: &mut dyn Write) -> Result<()> {
+        for (heading, violations) in [
+            ("Added", &self.added),
+            ("Removed", &self.removed),
+            ("Unchanged", &self.unchanged),
+        ] {
+            writeln!(out, "{heading} ({}):", violations.len())?;
+            for violation in violations {
+                print_violation(out, violation)?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(
+            out,
+            "{} added, {} removed, {} unchanged",
+            self.added.len(),
+            self.removed.len(),
+            self.unchanged.len()
+        )?;
+        Ok(())
+    }
+}
+
+fn print_violation(out: &mut dyn Write, violation: &Violation) -> Result<()> {
+    ErrorPrinter::print_error_level(out, violation.level)?;
+    writeln!(out, "{}", violation.message)?;
+    if let Some(location) = &violation.location {
+        writeln!(
+            out,
+            "  --> {}:{}:{}",
+            location.file, location.begin.0, location.begin.1
+        )?;
+    }
+    if !violation.context.is_empty() {
+        writeln!(out, "  {}", violation.context)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn violation(type_name: &str, in_what_type: &str, line: usize) -> Violation {
+        Violation {
+            level: ErrorLevel::Error,
+            kind: "unapproved_external_type_ref".into(),
+            message: format!("Unapproved external type `{type_name}` referenced in public API"),
+            context: format!("in struct field of `{in_what_type}`"),
+            location: Some(ViolationLocation {
+                file: "src/lib.rs".into(),
+                begin: (line, 4),
+            }),
+        }
+    }
+
+    #[test]
+    fn compare_regardless_of_location() {
+        let old = [
+            violation("ext::Kept", "my_crate::Thing", 1),
+            violation("ext::Fixed", "my_crate::Thing", 2),
+        ];
+        let new = [
+            violation("ext::New", "my_crate::Thing", 1),
+            violation("ext::Kept", "my_crate::Thing", 10),
+        ];
+        assert_eq!(
+            Comparison {
+                added: vec![violation("ext::New", "my_crate::Thing", 1)],
+                removed: vec![violation("ext::Fixed", "my_crate::Thing", 2)],
+                unchanged: vec![violation("ext::Kept", "my_crate::Thing", 10)],
+            },
+            Comparison::new(&old, &new)
+        );
+    }
+
+    #[test]
+    fn count_extra_occurrences_as_added() {
+        let old = [violation("ext::One", "my_crate::Thing", 1)];
+        let new = [
+            violation("ext::One", "my_crate::Thing", 1),
+            violation("ext::One", "my_crate::Thing", 2),
+        ];
+        let comparison = Comparison::new(&old, &new);
+        assert_eq!(
+            vec![violation("ext::One", "my_crate::Thing", 2)],
+            comparison.added
+        );
+        assert!(comparison.removed.is_empty());
+        assert_eq!(1, comparison.unchanged.len());
+    }
+
+    #[test]
+    fn reject_failed_runs() {
+        let err =
+            parse_report(r#"{"status": "failure", "phase": "config", "message": "bad config"}"#)
+                .unwrap_err();
+        assert_eq!(
+            "the report is from a run that failed: bad config",
+            err.to_string()
+        );
+        assert!(parse_report(r#"{"status": "success", "errors": []}"#)
+            .unwrap()
+            .is_empty());
+    }
+}
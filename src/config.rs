@@ -3,14 +3,29 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
-use serde::de::{SeqAccess, Visitor};
-use serde::{Deserialize, Deserializer};
+use crate::dependency::SourceKind;
+use crate::error::ValidationError;
+use anyhow::{anyhow, bail, Context};
+use cargo_platform::{Cfg, Platform};
+use regex::Regex;
+use serde::de::{Error as _, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
 use std::fmt;
-use wildmatch::WildMatch;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Key of the list of config files that a config is based on.
+pub(crate) const EXTENDS_KEY: &str = "extends";
 
 /// Struct representation of the Cargo.toml metadata, or TOML config files, that specify which
 /// external types are allowed.
-#[derive(Debug, Deserialize)]
+///
+/// Serializing a config writes it in the same form that it's read in, so that the config that
+/// results from merging and defaults can be shown.
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Config {
     /// Whether or not to allow types from `alloc`. Defaults to true.
     #[serde(default = "default_allow_std")]
@@ -24,7 +39,19 @@ pub struct Config {
     #[serde(default = "default_allow_std")]
     pub allow_std: bool,
 
-    /// List of globs for allowed external types
+    /// List of globs for allowed external types. Entries prefixed with `regex:` are regular
+    /// expressions that must match the whole type name instead.
+    ///
+    /// Entries prefixed with `!` are negated, and exclude the types they match from the entries
+    /// before them. When several entries match a type, the last one decides.
+    ///
+    /// Entries can also be tables with a `reason` for why the types are allowed, and with
+    /// `acknowledged` set to allow types from crates below 1.0 when `deny_unstable_crates` is set:
+    /// ```toml
+    /// allowed_external_types = [
+    ///     { pattern = "hyper::*", reason = "Re-exported connector API", acknowledged = true },
+    /// ]
+    /// ```
     ///
     /// For example, to allow every type in a crate:
     /// ```toml
@@ -39,8 +66,168 @@ pub struct Config {
     ///     "crate_name::path::to_module::*"
     /// ]
     /// ```
-    #[serde(deserialize_with = "deserialize_vec_wild_match")]
-    pub allowed_external_types: Vec<WildMatch>,
+    #[serde(deserialize_with = "deserialize_vec_pattern")]
+    pub allowed_external_types: Vec<Pattern>,
+
+    /// List of fully qualified external types that are allowed, without any wildcard matching
+    ///
+    /// For example, to allow `bytes::Bytes` without also allowing `bytes::BytesMut`:
+    /// ```toml
+    /// allowed_external_types_exact = ["bytes::Bytes"]
+    /// ```
+    #[serde(default)]
+    pub allowed_external_types_exact: Vec<String>,
+
+    /// List of globs for external types that must never be exposed, even if they match
+    /// `allowed_external_types` or are from `alloc`, `core`, or `std`
+    ///
+    /// For example, to allow everything in a crate except for one of its modules:
+    /// ```toml
+    /// allowed_external_types = ["crate_name::*"]
+    /// denied_external_types = ["crate_name::sync::*"]
+    /// ```
+    #[serde(default, deserialize_with = "deserialize_vec_pattern")]
+    pub denied_external_types: Vec<Pattern>,
+
+    /// List of globs for types from `alloc`, `core`, or `std` that must never be exposed, even
+    /// when `allow_std` is true. Patterns are written for `std` paths, and also match the same
+    /// paths in `alloc` and `core`, since `std` re-exports their types.
+    ///
+    /// For example, to keep platform-specific types out of a platform-neutral API:
+    /// ```toml
+    /// denied_std_types = ["std::os::*", "std::path::PathBuf"]
+    /// ```
+    #[serde(default, deserialize_with = "deserialize_std_patterns")]
+    pub denied_std_types: Vec<Pattern>,
+
+    /// Whether or not `*` in `allowed_external_types` and `denied_external_types` is anchored to `::` path separators.
+    /// When enabled, `*` only matches within a single path segment, and `**` matches any
+    /// number of segments, mirroring filesystem glob conventions. Defaults to false.
+    ///
+    /// For example, with anchored patterns, `crate_name::*` matches `crate_name::Type`, but not
+    /// `crate_name::module::Type`, while `crate_name::**` matches both.
+    #[serde(default)]
+    pub anchored_patterns: bool,
+
+    /// External types that are only allowed when referenced from certain items of the crate
+    /// being checked. Keys are globs for the paths of those items, and values are lists of
+    /// patterns like in `allowed_external_types`. Denied types are still denied.
+    ///
+    /// For example, to only allow `libc` types in the `ffi` module:
+    /// ```toml
+    /// [allowed_external_types_in]
+    /// "crate_name::ffi::*" = ["libc::*"]
+    /// ```
+    #[serde(
+        default,
+        deserialize_with = "deserialize_scoped_patterns",
+        serialize_with = "serialize_scoped_patterns"
+    )]
+    pub allowed_external_types_in: Vec<ScopedPatterns>,
+
+    /// Lists of types that are only allowed or denied when checking for certain targets. Keys are
+    /// target triples or `cfg(..)` expressions, like in Cargo's `[target]` tables, and the lists
+    /// of the matching targets are added to the end of the top-level lists.
+    ///
+    /// For example, to only allow `windows_sys` types when checking for Windows:
+    /// ```toml
+    /// [target.'cfg(windows)']
+    /// allowed_external_types = ["windows_sys::*"]
+    /// ```
+    #[serde(
+        default,
+        deserialize_with = "deserialize_target_configs",
+        serialize_with = "serialize_target_configs",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub target: Vec<TargetConfig>,
+
+    /// List of dependency source kinds (`registry`, `git`, or `path`) whose types must never
+    /// be exposed, even if they match `allowed_external_types`.
+    ///
+    /// For example, to prevent exposing types from unpublished git dependencies:
+    /// ```toml
+    /// denied_dependency_sources = ["git"]
+    /// ```
+    #[serde(default)]
+    pub denied_dependency_sources: Vec<SourceKind>,
+
+    /// Whether or not to deny types from dependencies whose version is below 1.0, even if they're
+    /// allowed, unless the `allowed_external_types` entry that allows them is marked as
+    /// `acknowledged`. Such crates make breaking changes in minor versions, which then become
+    /// breaking changes of the crate being checked. Defaults to false.
+    ///
+    /// For example, to only allow `bytes` types while it's below 1.0:
+    /// ```toml
+    /// deny_unstable_crates = true
+    /// allowed_external_types = [{ pattern = "bytes::*", acknowledged = true }]
+    /// ```
+    #[serde(default)]
+    pub deny_unstable_crates: bool,
+
+    /// Whether or not to allow every type from the direct dependencies that are marked with
+    /// `public = true` in Cargo.toml, as part of Cargo's public dependency feature. Denied types
+    /// are still denied. Defaults to false.
+    #[serde(default)]
+    pub allow_public_dependencies: bool,
+
+    /// Whether or not to also check items with restricted visibility, such as `pub(crate)` or
+    /// `pub(in path)`. These can't be reached from outside of the crate, so this is only useful
+    /// for internal audits. Note that rustdoc records private items as restricted to their
+    /// parent module, so they're included as well. Defaults to false.
+    #[serde(default)]
+    pub include_restricted_visibility: bool,
+
+    /// Whether or not to also check items marked `#[doc(hidden)]`, and the hidden fields of
+    /// structs, as if they were part of the public API. This is for crates whose hidden items
+    /// are still used by other crates. Defaults to false.
+    #[serde(default)]
+    pub include_hidden_items: bool,
+
+    /// Whether or not to also check the bounds and items of blanket implementations of public
+    /// traits, such as `impl<T: external::Trait> MyTrait for T`. These make the external trait
+    /// part of what `MyTrait` promises, but they aren't checked by default since they're not in
+    /// the `impl` blocks of any one type. Defaults to false.
+    #[serde(default)]
+    pub check_blanket_impls: bool,
+
+    /// Whether or not to also check the types that where bounds apply to, such as the
+    /// `Vec<external::Type>` in `where Vec<external::Type>: Extend<T>`. These aren't checked by
+    /// default since crates like `pin-project-lite` generate where bounds on private types.
+    /// Defaults to false.
+    #[serde(default)]
+    pub check_where_bounds: bool,
+
+    /// Whether or not to also match external types against the lists in this config by the path
+    /// they're written with, such as `futures::stream::Stream`, rather than only by their path
+    /// in the crate that defines them, such as `futures_core::stream::Stream`. Only paths that
+    /// start with a crate name can be matched. Defaults to false.
+    #[serde(default)]
+    pub match_surface_paths: bool,
+
+    /// Severity of each kind of finding, overriding whether it's reported as an error or a
+    /// warning. Keys are the kinds of findings, as shown in the `json` output format, and values
+    /// are `error`, `warn`, or `off`. Findings that are `off` aren't reported at all. Findings
+    /// about the config itself, `unused_approval` and `duplicate_approved`, are only reported
+    /// when they're given a severity here.
+    ///
+    /// For example, to fail on types from hidden modules, and to ignore stripped fields:
+    /// ```toml
+    /// [severity]
+    /// hidden_module = "error"
+    /// fields_stripped = "off"
+    /// ```
+    #[serde(default, deserialize_with = "deserialize_severity")]
+    pub severity: BTreeMap<String, Severity>,
+
+    /// How a package's config is combined with the config in the workspace's metadata.
+    /// Defaults to `extend`.
+    ///
+    /// With `extend`, lists such as `allowed_external_types` contain the entries from both, and
+    /// other settings in the package's config override the workspace's. With `replace`, the
+    /// workspace's config is ignored.
+    #[serde(default)]
+    pub merge_strategy: MergeStrategy,
 }
 
 impl Config {
@@ -49,15 +236,146 @@ impl Config {
         let type_crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
         match type_crate_name {
             _ if type_crate_name == root_crate_name => true,
+            _ if self.denies_type(root_crate_name, type_name).is_some() => false,
+            _ if self.denies_std_type(type_name).is_some() => false,
             "alloc" => self.allow_alloc,
             "core" => self.allow_core,
             "std" => self.allow_std,
+            _ => self.allows_type_by_pattern(root_crate_name, type_name),
+        }
+    }
+
+    /// Returns true if the given `type_name` is an external type that matches one of the
+    /// `allowed_external_types` or `allowed_external_types_exact`.
+    pub fn allows_type_by_pattern(&self, root_crate_name: &str, type_name: &str) -> bool {
+        let type_crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
+        match type_crate_name {
+            _ if type_crate_name == root_crate_name => false,
+            "alloc" | "core" | "std" => false,
+            _ => {
+                self.allows_type_exactly(type_name)
+                    || self.allowing_pattern(root_crate_name, type_name).is_some()
+            }
+        }
+    }
+
+    /// Returns the pattern in `allowed_external_types` that allows the given `type_name`, if any.
+    pub fn allowing_pattern(&self, root_crate_name: &str, type_name: &str) -> Option<&Pattern> {
+        let type_crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
+        match type_crate_name {
+            _ if type_crate_name == root_crate_name => None,
+            "alloc" | "core" | "std" => None,
             _ => self
-                .allowed_external_types
-                .iter()
-                .any(|glob| glob.matches(type_name)),
+                .last_match(&self.allowed_external_types, type_name)
+                .filter(|pattern| !pattern.is_negated()),
+        }
+    }
+
+    /// Returns the pattern in `allowed_external_types_in` that allows the given external
+    /// `type_name` to be referenced from the item at `item_path`, if any. When several scopes
+    /// match the item, the pattern that matches last within the last of them is returned.
+    pub fn scoped_allowing_pattern(
+        &self,
+        root_crate_name: &str,
+        item_path: &str,
+        type_name: &str,
+    ) -> Option<&Pattern> {
+        let type_crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
+        if type_crate_name == root_crate_name {
+            return None;
+        }
+        self.allowed_external_types_in
+            .iter()
+            .rev()
+            .filter(|scoped| self.pattern_matches(&scoped.scope, item_path))
+            .find_map(|scoped| {
+                self.last_match(&scoped.patterns, type_name)
+                    .filter(|pattern| !pattern.is_negated())
+            })
+    }
+
+    /// Returns true if the given `type_name` is listed in `allowed_external_types_exact`.
+    pub fn allows_type_exactly(&self, type_name: &str) -> bool {
+        self.allowed_external_types_exact
+            .iter()
+            .any(|allowed| allowed == type_name)
+    }
+
+    /// Returns the entry in `allowed_external_types` that allows the whole external crate
+    /// `crate_name` to be re-exported, if any. Only an entry of exactly `crate_name` does, since
+    /// patterns such as `crate_name::*` are meant to allow the types in the crate.
+    pub fn crate_reexport_pattern(&self, crate_name: &str) -> Option<&Pattern> {
+        self.allowed_external_types
+            .iter()
+            .rev()
+            .find(|pattern| !pattern.is_negated() && pattern.as_str() == crate_name)
+    }
+
+    /// Returns the pattern in `denied_external_types` that the given `type_name` matches, if
+    /// any. Types from the root crate are never denied.
+    pub fn denies_type(&self, root_crate_name: &str, type_name: &str) -> Option<&Pattern> {
+        let type_crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
+        if type_crate_name == root_crate_name {
+            return None;
+        }
+        self.last_match(&self.denied_external_types, type_name)
+            .filter(|pattern| !pattern.is_negated())
+    }
+
+    /// Returns the pattern in `denied_std_types` that the given `type_name` matches, if any. Only
+    /// types from `alloc`, `core`, and `std` can match, and they're matched by their `std` path.
+    pub fn denies_std_type(&self, type_name: &str) -> Option<&Pattern> {
+        let (crate_name, rest) = type_name.split_once("::")?;
+        if !matches!(crate_name, "alloc" | "core" | "std") {
+            return None;
+        }
+        self.last_match(&self.denied_std_types, &format!("std::{rest}"))
+            .filter(|pattern| !pattern.is_negated())
+    }
+
+    /// Returns the last of the `patterns` that matches the given `type_name`. Since later
+    /// patterns take precedence, this decides whether a negated pattern carves the type out.
+    fn last_match<'a>(&self, patterns: &'a [Pattern], type_name: &str) -> Option<&'a Pattern> {
+        patterns
+            .iter()
+            .rev()
+            .find(|pattern| self.pattern_matches(pattern, type_name))
+    }
+
+    /// Returns true if `pattern` matches `name`, taking `anchored_patterns` into account.
+    fn pattern_matches(&self, pattern: &Pattern, name: &str) -> bool {
+        if self.anchored_patterns {
+            pattern.matches_anchored(name)
+        } else {
+            pattern.matches(name)
         }
     }
+
+    /// Adds the lists in the `target` tables that apply to the target with the given name and
+    /// `cfg` values to the top-level lists.
+    pub fn apply_target(&mut self, target_name: &str, cfg: &[Cfg]) {
+        for target in std::mem::take(&mut self.target) {
+            if target.platform.matches(target_name, cfg) {
+                self.allowed_external_types
+                    .extend(target.allowed_external_types);
+                self.allowed_external_types_exact
+                    .extend(target.allowed_external_types_exact);
+                self.denied_external_types
+                    .extend(target.denied_external_types);
+            }
+        }
+    }
+
+    /// Returns the configured severity for the given kind of finding, if the `severity` table
+    /// overrides it.
+    pub fn severity(&self, kind: &str) -> Option<Severity> {
+        self.severity.get(kind).copied()
+    }
+
+    /// Returns true if types from dependencies with the given `source_kind` may be exposed.
+    pub fn allows_source_kind(&self, source_kind: SourceKind) -> bool {
+        !self.denied_dependency_sources.contains(&source_kind)
+    }
 }
 
 impl Default for Config {
@@ -67,7 +385,595 @@ impl Default for Config {
             allow_core: default_allow_std(),
             allow_std: default_allow_std(),
             allowed_external_types: Default::default(),
+            allowed_external_types_exact: Default::default(),
+            denied_external_types: Default::default(),
+            denied_std_types: Default::default(),
+            allowed_external_types_in: Default::default(),
+            target: Default::default(),
+            anchored_patterns: false,
+            denied_dependency_sources: Default::default(),
+            deny_unstable_crates: false,
+            allow_public_dependencies: false,
+            include_restricted_visibility: false,
+            include_hidden_items: false,
+            check_blanket_impls: false,
+            check_where_bounds: false,
+            match_surface_paths: false,
+            severity: Default::default(),
+            merge_strategy: MergeStrategy::default(),
+        }
+    }
+}
+
+/// How a kind of finding is reported.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Reported as an error, which fails the check
+    Error,
+    /// Reported as a warning
+    #[serde(alias = "warning")]
+    Warn,
+    /// Not reported
+    Off,
+}
+
+/// How a package's config is combined with the workspace's config.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Lists are concatenated, and other settings in the package override the workspace
+    #[default]
+    Extend,
+    /// The package's config is used as is
+    Replace,
+}
+
+impl Config {
+    /// Reads the TOML, JSON, or YAML config file at `path`, along with the config files it `extends`.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let config = read_config_file(path, &mut Vec::new())?;
+        Config::deserialize(config).context("failed to parse config file")
+    }
+
+    /// Parses the config from the raw workspace and package config tables, merging them
+    /// according to the package's `merge_strategy`.
+    pub fn from_layers(
+        workspace: &serde_json::Value,
+        package: &serde_json::Value,
+    ) -> Result<Self, serde_json::Error> {
+        let strategy = match package.get("merge_strategy") {
+            Some(strategy) => MergeStrategy::deserialize(strategy)?,
+            None => MergeStrategy::default(),
+        };
+        match strategy {
+            MergeStrategy::Extend => Config::deserialize(merge_layers(workspace.clone(), package)),
+            MergeStrategy::Replace => Config::deserialize(package),
+        }
+    }
+
+    /// Returns a JSON Schema for the config, as it's written in config files and the Cargo.toml
+    /// metadata, for editors and other tools to validate configs with.
+    pub fn json_schema() -> Value {
+        let bool_setting = |description: &str, default: bool| json!({"type": "boolean", "description": description, "default": default});
+        let pattern_list = |description: &str| json!({"description": description, "$ref": "#/definitions/pattern_list"});
+        let exact_list = json!({
+            "description": "Fully qualified external types that are allowed, without any \
+                wildcard matching",
+            "type": "array",
+            "items": {"type": "string", "minLength": 1},
+        });
+        let severity_levels = json!({"enum": ["error", "warn", "warning", "off"]});
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "cargo-check-external-types config",
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "allow_alloc": bool_setting("Whether or not to allow types from `alloc`", true),
+                "allow_core": bool_setting("Whether or not to allow types from `core`", true),
+                "allow_std": bool_setting("Whether or not to allow types from `std`", true),
+                "allowed_external_types": pattern_list(
+                    "Globs or `regex:` patterns for allowed external types. Entries prefixed \
+                    with `!` exclude the types they match from the entries before them. \
+                    Required unless the workspace's config has it."
+                ),
+                "allowed_external_types_exact": exact_list,
+                "denied_external_types": pattern_list(
+                    "Patterns for external types that must never be exposed, even if they're \
+                    allowed or from `alloc`, `core`, or `std`"
+                ),
+                "denied_std_types": pattern_list(
+                    "Patterns for `std` types that must never be exposed, even when `allow_std` \
+                    is true. They also match the same paths in `alloc` and `core`."
+                ),
+                "anchored_patterns": bool_setting(
+                    "Whether `*` only matches within a single path segment, with `**` matching \
+                    any number of segments",
+                    false
+                ),
+                "allowed_external_types_in": {
+                    "description": "Patterns for external types that are only allowed when \
+                        referenced from the items matching the key",
+                    "type": "object",
+                    "additionalProperties": {"$ref": "#/definitions/pattern_list"},
+                },
+                "target": {
+                    "description": "Lists that only apply when checking for the target triple \
+                        or `cfg(..)` expression in the key",
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "allowed_external_types": {"$ref": "#/definitions/pattern_list"},
+                            "allowed_external_types_exact": exact_list,
+                            "denied_external_types": {"$ref": "#/definitions/pattern_list"},
+                        },
+                    },
+                },
+                "denied_dependency_sources": {
+                    "description": "Kinds of dependency sources whose types must never be exposed",
+                    "type": "array",
+                    "items": {"enum": ["registry", "git", "path"]},
+                },
+                "deny_unstable_crates": bool_setting(
+                    "Whether or not to deny types from crates below 1.0 unless the entry that \
+                    allows them is `acknowledged`",
+                    false
+                ),
+                "allow_public_dependencies": bool_setting(
+                    "Whether or not to allow every type from dependencies marked with \
+                    `public = true`",
+                    false
+                ),
+                "include_restricted_visibility": bool_setting(
+                    "Whether or not to also check items with restricted visibility, such as \
+                    `pub(crate)`",
+                    false
+                ),
+                "include_hidden_items": bool_setting(
+                    "Whether or not to also check items marked `#[doc(hidden)]`",
+                    false
+                ),
+                "check_blanket_impls": bool_setting(
+                    "Whether or not to also check blanket implementations of public traits",
+                    false
+                ),
+                "check_where_bounds": bool_setting(
+                    "Whether or not to also check the types that where bounds apply to",
+                    false
+                ),
+                "match_surface_paths": bool_setting(
+                    "Whether or not to also match external types by the path they're written with",
+                    false
+                ),
+                "severity": {
+                    "description": "Severity of each kind of finding",
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": ValidationError::KINDS
+                        .iter()
+                        .map(|&kind| (kind.to_string(), severity_levels.clone()))
+                        .collect::<Map<_, _>>(),
+                },
+                "merge_strategy": {
+                    "description": "How the package's config is combined with the workspace's",
+                    "enum": ["extend", "replace"],
+                    "default": "extend",
+                },
+                EXTENDS_KEY: {
+                    "description": "Config files to merge this config over, relative to this one",
+                    "type": "array",
+                    "items": {"type": "string"},
+                },
+            },
+            "definitions": {
+                "pattern_list": {
+                    "type": "array",
+                    "items": {
+                        "oneOf": [
+                            {"type": "string", "minLength": 1},
+                            {
+                                "type": "object",
+                                "additionalProperties": false,
+                                "required": ["pattern"],
+                                "properties": {
+                                    "pattern": {"type": "string", "minLength": 1},
+                                    "reason": {
+                                        "type": "string",
+                                        "description": "Why the types are allowed or denied",
+                                    },
+                                    "acknowledged": {
+                                        "type": "boolean",
+                                        "description": "Whether the types are allowed even if \
+                                            they're from a crate below 1.0 and \
+                                            `deny_unstable_crates` is set",
+                                        "default": false,
+                                    },
+                                },
+                            },
+                        ],
+                    },
+                },
+            },
+        })
+    }
+}
+
+/// Merges the raw config table `overlay` into `base`. Lists such as `allowed_external_types`
+/// contain the entries from both, tables such as `allowed_external_types_in` are merged the same
+/// way, and other settings in `overlay` override the ones in `base`.
+fn merge_layers(base: Value, overlay: &Value) -> Value {
+    match (base, overlay) {
+        (Value::Object(mut merged), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match (merged.get_mut(key), value) {
+                    (Some(Value::Array(existing)), Value::Array(extra)) => {
+                        existing.extend(extra.iter().cloned())
+                    }
+                    (Some(existing @ Value::Object(_)), Value::Object(_)) => {
+                        *existing = merge_layers(existing.take(), value);
+                    }
+                    _ => {
+                        merged.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            Value::Object(merged)
+        }
+        (_, overlay) => overlay.clone(),
+    }
+}
+
+/// Replaces the `extends` list of the raw config table `config` with the contents of the
+/// config files it lists, which are relative to `base_dir`. The files are merged in order, and
+/// then `config` is merged over them, the same way package config extends workspace config.
+///
+/// For example, to share a base allowlist between several crates:
+/// ```toml
+/// extends = ["../shared/external-types.toml"]
+/// allowed_external_types = ["local_only::*"]
+/// ```
+pub fn resolve_extends(config: Value, base_dir: &Path) -> anyhow::Result<Value> {
+    resolve_extends_from(config, base_dir, &mut Vec::new())
+}
+
+/// Resolves `extends` in `config`, where `chain` has the config files that are currently being
+/// read, starting with the outermost one.
+fn resolve_extends_from(
+    mut config: Value,
+    base_dir: &Path,
+    chain: &mut Vec<PathBuf>,
+) -> anyhow::Result<Value> {
+    let Some(extends) = config
+        .as_object_mut()
+        .and_then(|config| config.remove(EXTENDS_KEY))
+    else {
+        return Ok(config);
+    };
+    let paths: Vec<PathBuf> = serde_json::from_value(extends)
+        .context("`extends` must be a list of paths to config files")?;
+    let mut merged = Value::Object(Map::new());
+    for path in paths {
+        let base = read_config_file(&base_dir.join(path), chain)?;
+        merged = merge_layers(merged, &base);
+    }
+    Ok(merge_layers(merged, &config))
+}
+
+/// Formats that config files can be written in, chosen by their file extension.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConfigFormat {
+    /// Files ending in `.json`
+    Json,
+    /// Files ending in `.yaml` or `.yml`
+    Yaml,
+    /// Files with any other extension
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Returns the format of the config file at `path`.
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(ConfigFormat::Json),
+            Some("yaml" | "yml") => Ok(ConfigFormat::Yaml),
+            _ => Ok(ConfigFormat::Toml),
+        }
+    }
+
+    /// Parses the contents of a config file in this format into a raw config table.
+    fn parse(self, contents: &str) -> anyhow::Result<Value> {
+        Ok(match self {
+            ConfigFormat::Json => serde_json::from_str(contents)?,
+            ConfigFormat::Yaml => serde_yaml::from_str(contents)?,
+            ConfigFormat::Toml => toml::from_str(contents)?,
+        })
+    }
+}
+
+/// Reads the raw config table in the file at `path`, and resolves its `extends`.
+fn read_config_file(path: &Path, chain: &mut Vec<PathBuf>) -> anyhow::Result<Value> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("failed to read config file {path:?}"))?;
+    if chain.contains(&path) {
+        let cycle: Vec<_> = chain
+            .iter()
+            .skip_while(|&extending| *extending != path)
+            .chain(Some(&path))
+            .map(|path| path.display().to_string())
+            .collect();
+        bail!(
+            "config files extend each other in a cycle: {}",
+            cycle.join(" -> ")
+        );
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read config file {path:?}"))?;
+    let config = ConfigFormat::from_path(&path)?
+        .parse(&contents)
+        .with_context(|| format!("failed to parse config file {path:?}"))?;
+    let base_dir = path.parent().expect("files have a parent").to_path_buf();
+    chain.push(path);
+    let resolved = resolve_extends_from(config, &base_dir, chain);
+    chain.pop();
+    resolved
+}
+
+/// Prefix that marks a pattern as a regular expression rather than a glob.
+pub(crate) const REGEX_PREFIX: &str = "regex:";
+
+/// Pattern for matching type names, either a glob such as `crate_name::module::*`, or a regular
+/// expression prefixed with `regex:`, such as `regex:aws_[a-z]+::.*`.
+///
+/// Globs support `*` and `?` wildcards, `**` for any number of path segments, `{a,b}`
+/// alternation, and character classes such as `[a-z]` or `[!0-9]`.
+///
+/// Either kind of pattern can be negated with a `!` prefix, such as `!http::uri::*`.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    /// The pattern as written in the config
+    source: String,
+    negated: bool,
+    matcher: Matcher,
+    /// Why the types matching the pattern are allowed or denied
+    reason: Option<String>,
+    /// Whether the types matching the pattern are allowed even if they're from a crate below 1.0
+    acknowledged: bool,
+}
+
+/// The kinds of patterns that type names can be matched with.
+#[derive(Clone, Debug)]
+enum Matcher {
+    /// Glob translated into regular expressions for the two ways it can be matched
+    Glob { unanchored: Regex, anchored: Regex },
+    /// Regular expression that must match the whole type name
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Parses the given pattern. This fails if it's a regular expression or glob that isn't
+    /// valid.
+    pub fn new(source: impl Into<String>) -> anyhow::Result<Self> {
+        let source = source.into();
+        let (negated, pattern) = match source.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, source.as_str()),
+        };
+        let matcher = match pattern.strip_prefix(REGEX_PREFIX) {
+            Some(regex) => Matcher::Regex(
+                Regex::new(&format!("^(?:{regex})$"))
+                    .map_err(|err| anyhow!("invalid regex in pattern `{source}`: {err}"))?,
+            ),
+            None => {
+                let compile = |anchored| {
+                    glob_to_regex(pattern, anchored)
+                        .and_then(|regex| Ok(Regex::new(&regex)?))
+                        .map_err(|err| anyhow!("invalid glob in pattern `{source}`: {err}"))
+                };
+                Matcher::Glob {
+                    unanchored: compile(false)?,
+                    anchored: compile(true)?,
+                }
+            }
+        };
+        Ok(Self {
+            source,
+            negated,
+            matcher,
+            reason: None,
+            acknowledged: false,
+        })
+    }
+
+    /// Sets the reason for why the types matching the pattern are allowed or denied.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Marks the types matching the pattern as allowed even if they're from a crate below 1.0.
+    pub fn acknowledged(mut self) -> Self {
+        self.acknowledged = true;
+        self
+    }
+
+    /// Returns true if the types matching the pattern are allowed even if they're from a crate
+    /// below 1.0.
+    pub fn is_acknowledged(&self) -> bool {
+        self.acknowledged
+    }
+
+    /// Returns the pattern as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the reason for why the types matching the pattern are allowed or denied, if the
+    /// config gives one.
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// Returns the name of the crate that all of the types the pattern matches are from, if the
+    /// pattern is a glob that spells it out, such as `tokio_util` in `tokio_util::codec::*`.
+    pub fn crate_name(&self) -> Option<&str> {
+        if !matches!(self.matcher, Matcher::Glob { .. }) {
+            return None;
+        }
+        let pattern = self.source.trim_start_matches('!');
+        let crate_name = pattern.split("::").next()?;
+        let is_literal =
+            !crate_name.is_empty() && !crate_name.contains(['*', '?', '[', ']', '{', '}']);
+        is_literal.then_some(crate_name)
+    }
+
+    /// Returns true if the pattern excludes the types it matches rather than including them.
+    /// The match methods ignore negation.
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// Returns true if the pattern matches the given type name, with `*` matching across
+    /// `::` separators.
+    pub fn matches(&self, type_name: &str) -> bool {
+        match &self.matcher {
+            Matcher::Glob { unanchored, .. } => unanchored.is_match(type_name),
+            Matcher::Regex(regex) => regex.is_match(type_name),
+        }
+    }
+
+    /// Returns true if the pattern matches the given type name, with `*` only matching
+    /// within a single path segment and `**` matching any number of segments. Regular
+    /// expressions are matched the same way as with [`matches`](Pattern::matches).
+    pub fn matches_anchored(&self, type_name: &str) -> bool {
+        match &self.matcher {
+            Matcher::Glob { anchored, .. } => anchored.is_match(type_name),
+            Matcher::Regex(regex) => regex.is_match(type_name),
+        }
+    }
+}
+
+/// Translates a glob into a regular expression that matches whole type names.
+///
+/// When `anchored`, wildcards don't match across `::` separators, except for `**` when it's a
+/// whole path segment, which matches any number of segments.
+fn glob_to_regex(glob: &str, anchored: bool) -> anyhow::Result<String> {
+    const SEGMENTS_AFTER: &str = "(?:::[^:]+)*";
+    const SEGMENTS_BEFORE: &str = "(?:[^:]+::)*";
+    let (any, one) = if anchored {
+        ("[^:]*", "[^:]")
+    } else {
+        (".*", ".")
+    };
+    // Whether the rest of the glob starts at the end of a path segment
+    let segment_ends = |rest: &str| rest.is_empty() || rest.starts_with(['}', ',', ':']);
+
+    let mut regex = String::from("^(?:");
+    let mut open_alternations = 0;
+    let mut segment_start = true;
+    let mut rest = glob;
+    while let Some(c) = rest.chars().next() {
+        if anchored && rest.starts_with("::**") && segment_ends(&rest[4..]) {
+            regex.push_str(SEGMENTS_AFTER);
+            rest = &rest[4..];
+            segment_start = false;
+            continue;
+        }
+        if anchored && segment_start && rest.starts_with("**::") {
+            regex.push_str(SEGMENTS_BEFORE);
+            rest = &rest[4..];
+            continue;
         }
+        let whole_segment = std::mem::take(&mut segment_start);
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '*' => match rest.strip_prefix('*') {
+                // A `**` segment of its own matches everything
+                Some(after) if anchored && whole_segment && segment_ends(after) => {
+                    regex.push_str(".*");
+                    rest = after;
+                }
+                Some(after) => {
+                    regex.push_str(any);
+                    rest = after;
+                }
+                None => regex.push_str(any),
+            },
+            '?' => regex.push_str(one),
+            '[' => {
+                let (class, after) =
+                    glob_class(rest).ok_or_else(|| anyhow!("unclosed character class `[`"))?;
+                regex.push_str(&class);
+                rest = after;
+            }
+            '{' => {
+                open_alternations += 1;
+                segment_start = true;
+                regex.push_str("(?:");
+            }
+            ',' if open_alternations > 0 => {
+                segment_start = true;
+                regex.push('|');
+            }
+            '}' if open_alternations > 0 => {
+                open_alternations -= 1;
+                regex.push(')');
+            }
+            _ => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    if open_alternations > 0 {
+        bail!("unclosed alternation `{{`");
+    }
+    regex.push_str(")$");
+    Ok(regex)
+}
+
+/// Translates the character class at the start of `glob` (just after the `[`) into a regular
+/// expression class, and returns it along with the rest of the glob after the closing `]`.
+fn glob_class(glob: &str) -> Option<(String, &str)> {
+    let mut class = String::from("[");
+    let mut chars = glob.char_indices().peekable();
+    if let Some((_, '!' | '^')) = chars.peek() {
+        class.push('^');
+        chars.next();
+    }
+    let mut first = true;
+    for (index, c) in chars {
+        match c {
+            // A `]` right after the opening bracket is part of the class
+            ']' if !first => {
+                class.push(']');
+                return Some((class, &glob[index + 1..]));
+            }
+            '\\' | '[' | ']' | '&' | '~' | '^' => {
+                class.push('\\');
+                class.push(c);
+            }
+            _ => class.push(c),
+        }
+        first = false;
+    }
+    None
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.reason.is_none() && !self.acknowledged {
+            return serializer.serialize_str(&self.source);
+        }
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("pattern", &self.source)?;
+        if let Some(reason) = &self.reason {
+            map.serialize_entry("reason", reason)?;
+        }
+        if self.acknowledged {
+            map.serialize_entry("acknowledged", &true)?;
+        }
+        map.end()
     }
 }
 
@@ -75,13 +981,26 @@ const fn default_allow_std() -> bool {
     true
 }
 
-struct VecWildMatchDeserializer;
+/// An entry in a pattern list, which is either just the pattern or a table with a reason.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PatternEntry {
+    Pattern(String),
+    WithReason {
+        pattern: String,
+        reason: Option<String>,
+        #[serde(default)]
+        acknowledged: bool,
+    },
+}
+
+struct VecPatternDeserializer;
 
-impl<'de> Visitor<'de> for VecWildMatchDeserializer {
-    type Value = Vec<WildMatch>;
+impl<'de> Visitor<'de> for VecPatternDeserializer {
+    type Value = Vec<Pattern>;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("list of glob strings")
+        f.write_str("list of glob or `regex:` strings, or tables with a `pattern` and `reason`")
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -89,24 +1008,183 @@ impl<'de> Visitor<'de> for VecWildMatchDeserializer {
         A: SeqAccess<'de>,
     {
         let mut result = Vec::new();
-        while let Some(value) = seq.next_element::<String>()? {
-            result.push(WildMatch::new(&value));
+        while let Some(entry) = seq.next_element::<PatternEntry>()? {
+            let (pattern, reason, acknowledged) = match entry {
+                PatternEntry::Pattern(pattern) => (pattern, None, false),
+                PatternEntry::WithReason {
+                    pattern,
+                    reason,
+                    acknowledged,
+                } => (pattern, reason, acknowledged),
+            };
+            let mut pattern = Pattern::new(pattern).map_err(A::Error::custom)?;
+            if let Some(reason) = reason {
+                pattern = pattern.with_reason(reason);
+            }
+            if acknowledged {
+                pattern = pattern.acknowledged();
+            }
+            result.push(pattern);
         }
         Ok(result)
     }
 }
 
-fn deserialize_vec_wild_match<'de, D>(de: D) -> Result<Vec<WildMatch>, D::Error>
+fn deserialize_vec_pattern<'de, D>(de: D) -> Result<Vec<Pattern>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    de.deserialize_any(VecPatternDeserializer)
+}
+
+fn deserialize_std_patterns<'de, D>(de: D) -> Result<Vec<Pattern>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let patterns = deserialize_vec_pattern(de)?;
+    for pattern in &patterns {
+        let source = pattern.as_str().trim_start_matches('!');
+        if !source.starts_with(REGEX_PREFIX) && !source.starts_with("std::") {
+            return Err(D::Error::custom(format!(
+                "patterns in `denied_std_types` must start with `std::`: `{}`",
+                pattern.as_str()
+            )));
+        }
+    }
+    Ok(patterns)
+}
+
+/// Lists of types in a `target` table that only apply when checking for `platform`.
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetConfig {
+    #[serde(skip)]
+    pub platform: Platform,
+    pub allowed_external_types: Vec<Pattern>,
+    pub allowed_external_types_exact: Vec<String>,
+    pub denied_external_types: Vec<Pattern>,
+}
+
+#[derive(Deserialize)]
+struct TargetLists {
+    #[serde(default, deserialize_with = "deserialize_vec_pattern")]
+    allowed_external_types: Vec<Pattern>,
+    #[serde(default)]
+    allowed_external_types_exact: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_vec_pattern")]
+    denied_external_types: Vec<Pattern>,
+}
+
+fn deserialize_target_configs<'de, D>(de: D) -> Result<Vec<TargetConfig>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    de.deserialize_any(VecWildMatchDeserializer)
+    BTreeMap::<String, TargetLists>::deserialize(de)?
+        .into_iter()
+        .map(|(platform, lists)| {
+            let platform = platform
+                .parse()
+                .map_err(|err| D::Error::custom(format!("invalid target `{platform}`: {err}")))?;
+            Ok(TargetConfig {
+                platform,
+                allowed_external_types: lists.allowed_external_types,
+                allowed_external_types_exact: lists.allowed_external_types_exact,
+                denied_external_types: lists.denied_external_types,
+            })
+        })
+        .collect()
+}
+
+fn serialize_target_configs<S>(targets: &[TargetConfig], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_map(
+        targets
+            .iter()
+            .map(|target| (target.platform.to_string(), target)),
+    )
+}
+
+fn deserialize_severity<'de, D>(de: D) -> Result<BTreeMap<String, Severity>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let severity = BTreeMap::<String, Severity>::deserialize(de)?;
+    if let Some(kind) = severity
+        .keys()
+        .find(|kind| !ValidationError::KINDS.contains(&kind.as_str()))
+    {
+        return Err(D::Error::custom(format!(
+            "unknown kind of finding in `severity`: `{kind}`. Expected one of: {}",
+            ValidationError::KINDS.join(", ")
+        )));
+    }
+    Ok(severity)
+}
+
+/// Patterns in `allowed_external_types_in` that only apply to the items matching `scope`.
+#[derive(Clone, Debug)]
+pub struct ScopedPatterns {
+    pub scope: Pattern,
+    pub patterns: Vec<Pattern>,
+}
+
+#[derive(Deserialize)]
+struct PatternList(#[serde(deserialize_with = "deserialize_vec_pattern")] Vec<Pattern>);
+
+fn deserialize_scoped_patterns<'de, D>(de: D) -> Result<Vec<ScopedPatterns>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, PatternList>::deserialize(de)?
+        .into_iter()
+        .map(|(scope, PatternList(patterns))| {
+            let scope = Pattern::new(scope).map_err(D::Error::custom)?;
+            if scope.is_negated() {
+                return Err(D::Error::custom(format!(
+                    "scopes in `allowed_external_types_in` can't be negated: `{}`",
+                    scope.as_str()
+                )));
+            }
+            Ok(ScopedPatterns { scope, patterns })
+        })
+        .collect()
+}
+
+fn serialize_scoped_patterns<S>(scoped: &[ScopedPatterns], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_map(
+        scoped
+            .iter()
+            .map(|scoped| (scoped.scope.as_str(), &scoped.patterns)),
+    )
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Config;
-    use wildmatch::WildMatch;
+    use super::{resolve_extends, Config, MergeStrategy, Pattern, Severity};
+    use crate::dependency::SourceKind;
+    use crate::error::ValidationError;
+    use cargo_platform::Cfg;
+    use serde_json::json;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Writes the given config files into a fresh directory for the test called `name`.
+    fn write_configs(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("cargo-check-external-types-tests")
+            .join(name);
+        let _ = fs::remove_dir_all(&dir);
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
 
     #[test]
     fn deserialize_config() {
@@ -125,12 +1203,304 @@ mod tests {
         assert!(!config.allowed_external_types[0].matches("other::something"));
         assert!(config.allowed_external_types[1].matches("another_test::something::foo::something"));
         assert!(!config.allowed_external_types[1].matches("another_test::other::foo::something"));
+        assert!(config.allowed_external_types_exact.is_empty());
+        assert!(config.denied_external_types.is_empty());
+        assert!(!config.anchored_patterns);
+        assert!(config.denied_dependency_sources.is_empty());
+        assert!(!config.deny_unstable_crates);
+        assert!(!config.allow_public_dependencies);
+        assert!(!config.include_restricted_visibility);
+        assert!(!config.check_blanket_impls);
+        assert!(!config.check_where_bounds);
+        assert!(!config.match_surface_paths);
+        assert_eq!(MergeStrategy::Extend, config.merge_strategy);
+    }
+
+    #[test]
+    fn merge_config_layers() {
+        let workspace = json!({
+            "allow_std": false,
+            "anchored_patterns": true,
+            "allowed_external_types": ["workspace::*"],
+        });
+        let extended = Config::from_layers(
+            &workspace,
+            &json!({"anchored_patterns": false, "allowed_external_types": ["package::*"]}),
+        )
+        .unwrap();
+        assert!(!extended.allow_std);
+        assert!(!extended.anchored_patterns);
+        assert!(extended.allows_type("root", "workspace::module::Type"));
+        assert!(extended.allows_type("root", "package::Type"));
+
+        let replaced = Config::from_layers(
+            &workspace,
+            &json!({"merge_strategy": "replace", "allowed_external_types": ["package::*"]}),
+        )
+        .unwrap();
+        assert!(replaced.allow_std);
+        assert!(!replaced.allows_type("root", "workspace::Type"));
+        assert!(replaced.allows_type("root", "package::Type"));
+
+        assert!(Config::from_layers(&workspace, &json!({"merge_strategy": "other"})).is_err());
+    }
+
+    #[test]
+    fn deserialize_denied_dependency_sources() {
+        let config = r#"
+            allowed_external_types = []
+            denied_dependency_sources = ["git", "path"]
+        "#;
+        let config: Config = toml::from_str(config).unwrap();
+        assert_eq!(
+            vec![SourceKind::Git, SourceKind::Path],
+            config.denied_dependency_sources
+        );
+        assert!(config.allows_source_kind(SourceKind::Registry));
+        assert!(!config.allows_source_kind(SourceKind::Git));
+        assert!(!config.allows_source_kind(SourceKind::Path));
+    }
+
+    #[test]
+    fn test_denied_types_take_precedence() {
+        let config: Config = toml::from_str(
+            r#"
+            allowed_external_types = ["tokio::*"]
+            denied_external_types = ["tokio::sync::*", "std::sync::Mutex", "root::*"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.allows_type("root", "tokio::net::TcpStream"));
+        assert!(!config.allows_type("root", "tokio::sync::Mutex"));
+        assert_eq!(
+            Some("tokio::sync::*"),
+            config
+                .denies_type("root", "tokio::sync::Mutex")
+                .map(Pattern::as_str)
+        );
+        assert!(config.allows_type("root", "std::sync::Arc"));
+        assert!(!config.allows_type("root", "std::sync::Mutex"));
+        assert!(config.allows_type("root", "root::Thing"));
+        assert!(config.denies_type("root", "root::Thing").is_none());
+        assert!(config
+            .denies_type("root", "tokio::net::TcpStream")
+            .is_none());
+    }
+
+    #[test]
+    fn test_allows_type_exactly() {
+        let config: Config = toml::from_str(
+            r#"
+            allowed_external_types = []
+            allowed_external_types_exact = ["bytes::Bytes", "http::*"]
+            denied_external_types = ["http::*"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.allows_type("root", "bytes::Bytes"));
+        assert!(config.allows_type_exactly("bytes::Bytes"));
+        assert!(!config.allows_type("root", "bytes::BytesMut"));
+        assert!(!config.allows_type("root", "bytes::Bytes::Inner"));
+        // No wildcard semantics, and denials still take precedence
+        assert!(!config.allows_type("root", "http::Request"));
+        assert!(!config.allows_type("root", "http::*"));
+    }
+
+    #[test]
+    fn test_allows_type_regex() {
+        let config: Config = toml::from_str(
+            r#"
+            anchored_patterns = true
+            allowed_external_types = ["regex:aws_(config|types)::.*", "regex:tokio::(io|net)::\\w+"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.allows_type("root", "aws_config::Config"));
+        assert!(config.allows_type("root", "aws_types::region::Region"));
+        assert!(!config.allows_type("root", "aws_smithy_types::Blob"));
+        assert!(config.allows_type("root", "tokio::io::AsyncRead"));
+        // Regexes have to match the whole type name
+        assert!(!config.allows_type("root", "tokio::io::util::Take"));
+        assert!(!config.allows_type("root", "my_tokio::io::AsyncRead"));
+
+        let err = toml::from_str::<Config>(r#"allowed_external_types = ["regex:("]"#).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("invalid regex in pattern `regex:(`"));
+    }
+
+    #[test]
+    fn test_negated_patterns() {
+        let config: Config = toml::from_str(
+            r#"
+            allowed_external_types = ["http::*", "!http::uri::*", "http::uri::Uri", "!regex:.*Error"]
+            denied_external_types = ["tokio::*", "!tokio::time::*"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.allowed_external_types[1].is_negated());
+        assert!(config.allows_type("root", "http::Request"));
+        assert!(!config.allows_type("root", "http::uri::Authority"));
+        assert!(config.allows_type("root", "http::uri::Uri"));
+        assert!(!config.allows_type("root", "http::header::InvalidHeaderValueError"));
+        assert!(!config.allows_type("root", "hyper::Body"));
+
+        assert_eq!(
+            Some("tokio::*"),
+            config
+                .denies_type("root", "tokio::sync::Mutex")
+                .map(Pattern::as_str)
+        );
+        assert!(config.denies_type("root", "tokio::time::Instant").is_none());
+    }
+
+    #[test]
+    fn test_pattern_reasons() {
+        let config: Config = toml::from_str(
+            r#"
+            allowed_external_types = [
+                "bytes::*",
+                { pattern = "hyper::*", reason = "Re-exported connector API" },
+                { pattern = "http::*" },
+            ]
+            denied_external_types = [{ pattern = "hyper::client::*", reason = "Unstable" }]
+            "#,
+        )
+        .unwrap();
+        let reasons: Vec<_> = config
+            .allowed_external_types
+            .iter()
+            .map(Pattern::reason)
+            .collect();
+        assert_eq!(vec![None, Some("Re-exported connector API"), None], reasons);
+        assert_eq!(
+            Some("hyper::*"),
+            config
+                .allowing_pattern("root", "hyper::Body")
+                .map(Pattern::as_str)
+        );
+        assert_eq!(
+            Some("Unstable"),
+            config
+                .denies_type("root", "hyper::client::Client")
+                .and_then(Pattern::reason)
+        );
+        assert!(
+            toml::from_str::<Config>(r#"allowed_external_types = [{ reason = "x" }]"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_pattern_crate_names() {
+        let crate_name = |pattern: &str| {
+            Pattern::new(pattern)
+                .unwrap()
+                .crate_name()
+                .map(String::from)
+        };
+        assert_eq!(
+            Some("tokio_util".into()),
+            crate_name("tokio_util::codec::*")
+        );
+        assert_eq!(Some("tokio-util".into()), crate_name("tokio-util::*"));
+        assert_eq!(Some("http".into()), crate_name("!http::uri::*"));
+        assert_eq!(Some("bytes".into()), crate_name("bytes"));
+        assert_eq!(None, crate_name("aws_*::Client"));
+        assert_eq!(None, crate_name("{http,http_body}::*"));
+        assert_eq!(None, crate_name("**::Error"));
+        assert_eq!(None, crate_name("regex:tokio::.*"));
+    }
+
+    #[test]
+    fn test_acknowledged_patterns() {
+        let config: Config = toml::from_str(
+            r#"
+            deny_unstable_crates = true
+            allowed_external_types = [
+                "bytes::*",
+                { pattern = "hyper::*", acknowledged = true },
+                { pattern = "http::*", reason = "Re-exported", acknowledged = false },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert!(config.deny_unstable_crates);
+        let acknowledged: Vec<_> = config
+            .allowed_external_types
+            .iter()
+            .map(Pattern::is_acknowledged)
+            .collect();
+        assert_eq!(vec![false, true, false], acknowledged);
+        assert_eq!(
+            json!([
+                "bytes::*",
+                {"pattern": "hyper::*", "acknowledged": true},
+                {"pattern": "http::*", "reason": "Re-exported"},
+            ]),
+            serde_json::to_value(&config.allowed_external_types).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_glob_syntax() {
+        let pattern = Pattern::new("futures_{core,util}::*").unwrap();
+        assert!(pattern.matches("futures_core::Stream"));
+        assert!(pattern.matches("futures_util::stream::Map"));
+        assert!(!pattern.matches("futures_io::AsyncRead"));
+        assert!(pattern.matches_anchored("futures_util::Stream"));
+        assert!(!pattern.matches_anchored("futures_util::stream::Map"));
+
+        let pattern = Pattern::new("tokio::{io,net::tcp}::**").unwrap();
+        assert!(pattern.matches_anchored("tokio::io::AsyncRead"));
+        assert!(pattern.matches_anchored("tokio::net::tcp::OwnedReadHalf"));
+        assert!(!pattern.matches_anchored("tokio::net::TcpStream"));
+
+        let pattern = Pattern::new("http::[A-Z]?*::[!_]*").unwrap();
+        assert!(pattern.matches("http::Uri::Builder"));
+        assert!(!pattern.matches("http::uri::Builder"));
+        assert!(!pattern.matches("http::Uri::_Private"));
+        assert!(!pattern.matches("http::U::Builder"));
+
+        // Brackets, braces, and commas outside of alternations are literal when unmatched
+        assert!(Pattern::new("a::b,c}").unwrap().matches("a::b,c}"));
+        assert!(Pattern::new("**").unwrap().matches_anchored("a::b::C"));
+
+        let err = Pattern::new("a::{b,c").unwrap_err();
+        assert_eq!(
+            "invalid glob in pattern `a::{b,c`: unclosed alternation `{`",
+            err.to_string()
+        );
+        assert!(Pattern::new("a::[bc").is_err());
+    }
+
+    #[test]
+    fn test_allows_type_anchored() {
+        let config: Config = toml::from_str(
+            r#"
+            anchored_patterns = true
+            allowed_external_types = ["one::*", "two::**", "three::*::Type", "four::**::Type"]
+            "#,
+        )
+        .unwrap();
+        assert!(config.allows_type("root", "one::Type"));
+        assert!(!config.allows_type("root", "one::module::Type"));
+        assert!(config.allows_type("root", "two::Type"));
+        assert!(config.allows_type("root", "two::module::Type"));
+        assert!(config.allows_type("root", "three::module::Type"));
+        assert!(!config.allows_type("root", "three::Type"));
+        assert!(!config.allows_type("root", "three::module::nested::Type"));
+        assert!(config.allows_type("root", "four::Type"));
+        assert!(config.allows_type("root", "four::module::nested::Type"));
+        assert!(!config.allows_type("root", "four::module::Other"));
     }
 
     #[test]
     fn test_allows_type() {
         let config = Config {
-            allowed_external_types: vec![WildMatch::new("one::*"), WildMatch::new("two::*")],
+            allowed_external_types: vec![
+                Pattern::new("one::*").unwrap(),
+                Pattern::new("two::*").unwrap(),
+            ],
             ..Default::default()
         };
         assert!(config.allows_type("root", "alloc::System"));
@@ -141,5 +1511,361 @@ mod tests {
         assert!(config.allows_type("root", "one::thing"));
         assert!(config.allows_type("root", "two::thing"));
         assert!(!config.allows_type("root", "three::thing"));
+
+        assert!(config.allows_type_by_pattern("root", "one::thing"));
+        assert!(!config.allows_type_by_pattern("root", "std::vec::Vec"));
+        assert!(!config.allows_type_by_pattern("root", "root::thing"));
+        assert!(!config.allows_type_by_pattern("root", "three::thing"));
+    }
+
+    #[test]
+    fn test_allowed_external_types_in() {
+        let config: Config = serde_json::from_value(json!({
+            "allowed_external_types": ["everywhere::*"],
+            "allowed_external_types_in": {
+                "root::ffi::*": ["libc::*", "!libc::unstable::*"],
+                "root::ffi::raw::*": ["std::os::*"],
+            },
+        }))
+        .unwrap();
+        let scoped = |item_path, type_name| {
+            config
+                .scoped_allowing_pattern("root", item_path, type_name)
+                .map(Pattern::as_str)
+        };
+        assert_eq!(Some("libc::*"), scoped("root::ffi::call", "libc::c_int"));
+        assert_eq!(
+            Some("libc::*"),
+            scoped("root::ffi::raw::call", "libc::c_int")
+        );
+        assert_eq!(None, scoped("root::other::call", "libc::c_int"));
+        assert_eq!(None, scoped("root::ffi::call", "libc::unstable::Thing"));
+        assert_eq!(
+            Some("std::os::*"),
+            scoped("root::ffi::raw::call", "std::os::raw::c_int")
+        );
+        assert_eq!(None, scoped("root::ffi::call", "std::os::raw::c_int"));
+        assert_eq!(None, scoped("root::ffi::call", "everywhere::Type"));
+        assert_eq!(None, scoped("root::ffi::call", "root::Type"));
+
+        let err = serde_json::from_value::<Config>(json!({
+            "allowed_external_types_in": {"!root::*": ["libc::*"]},
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("can't be negated"), "{err}");
+    }
+
+    #[test]
+    fn test_layered_allowed_external_types_in() {
+        let config = Config::from_layers(
+            &json!({
+                "allowed_external_types": [],
+                "allowed_external_types_in": {"root::ffi::*": ["libc::*"]},
+            }),
+            &json!({"allowed_external_types_in": {
+                "root::ffi::*": ["windows_sys::*"],
+                "root::io::*": ["mio::*"],
+            }}),
+        )
+        .unwrap();
+        for (item_path, type_name) in [
+            ("root::ffi::call", "libc::c_int"),
+            ("root::ffi::call", "windows_sys::HANDLE"),
+            ("root::io::read", "mio::Token"),
+        ] {
+            assert!(
+                config
+                    .scoped_allowing_pattern("root", item_path, type_name)
+                    .is_some(),
+                "{type_name} in {item_path}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_denied_std_types() {
+        let config: Config = serde_json::from_value(json!({
+            "allowed_external_types": ["external::*"],
+            "denied_std_types": ["std::os::*", "!std::os::raw::*", "std::path::PathBuf", "std::time::Duration"],
+        }))
+        .unwrap();
+        let denied = |type_name| config.denies_std_type(type_name).map(Pattern::as_str);
+        assert_eq!(Some("std::os::*"), denied("std::os::fd::OwnedFd"));
+        assert_eq!(None, denied("std::os::raw::c_int"));
+        assert_eq!(Some("std::path::PathBuf"), denied("std::path::PathBuf"));
+        assert_eq!(Some("std::time::Duration"), denied("core::time::Duration"));
+        assert_eq!(None, denied("std::path::Path"));
+        assert_eq!(None, denied("external::os::Thing"));
+        assert!(!config.allows_type("root", "std::path::PathBuf"));
+        assert!(!config.allows_type("root", "core::time::Duration"));
+        assert!(config.allows_type("root", "std::path::Path"));
+        assert!(config.allows_type("root", "external::os::Thing"));
+
+        let err = serde_json::from_value::<Config>(json!({
+            "allowed_external_types": [],
+            "denied_std_types": ["core::time::*"],
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("must start with `std::`"), "{err}");
+    }
+
+    #[test]
+    fn test_severity() {
+        let config: Config = serde_json::from_value(json!({
+            "allowed_external_types": [],
+            "severity": {"hidden_module": "error", "hidden_item": "warning", "fields_stripped": "off"},
+        }))
+        .unwrap();
+        assert_eq!(Some(Severity::Error), config.severity("hidden_module"));
+        assert_eq!(Some(Severity::Warn), config.severity("hidden_item"));
+        assert_eq!(Some(Severity::Off), config.severity("fields_stripped"));
+        assert_eq!(None, config.severity("unapproved_external_type_ref"));
+
+        let config: Config = serde_json::from_value(json!({
+            "allowed_external_types": [],
+            "severity": {"unused_approval": "warn", "duplicate_approved": "error"},
+        }))
+        .unwrap();
+        assert_eq!(Some(Severity::Warn), config.severity("unused_approval"));
+        assert_eq!(Some(Severity::Error), config.severity("duplicate_approved"));
+
+        let err = serde_json::from_value::<Config>(json!({
+            "allowed_external_types": [],
+            "severity": {"unused_type": "warn"},
+        }))
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unknown kind of finding in `severity`: `unused_type`"),
+            "{err}"
+        );
+        let err = serde_json::from_value::<Config>(json!({
+            "allowed_external_types": [],
+            "severity": {"hidden_item": "fatal"},
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown variant `fatal`"), "{err}");
+    }
+
+    #[test]
+    fn test_serialize() {
+        let config: Config = serde_json::from_value(json!({
+            "allowed_external_types": [
+                "bytes::*",
+                {"pattern": "http::*", "reason": "Re-exported"},
+            ],
+            "allowed_external_types_in": {"crate::ffi::*": ["libc::*"]},
+            "target": {"cfg(windows)": {"allowed_external_types": ["windows_sys::*"]}},
+            "severity": {"hidden_item": "warning"},
+        }))
+        .unwrap();
+        assert_eq!(
+            json!({
+                "allow_alloc": true,
+                "allow_core": true,
+                "allow_std": true,
+                "allowed_external_types": [
+                    "bytes::*",
+                    {"pattern": "http::*", "reason": "Re-exported"},
+                ],
+                "allowed_external_types_exact": [],
+                "denied_external_types": [],
+                "denied_std_types": [],
+                "anchored_patterns": false,
+                "allowed_external_types_in": {"crate::ffi::*": ["libc::*"]},
+                "target": {
+                    "cfg(windows)": {
+                        "allowed_external_types": ["windows_sys::*"],
+                        "allowed_external_types_exact": [],
+                        "denied_external_types": [],
+                    },
+                },
+                "denied_dependency_sources": [],
+                "deny_unstable_crates": false,
+                "allow_public_dependencies": false,
+                "include_restricted_visibility": false,
+                "include_hidden_items": false,
+                "check_blanket_impls": false,
+                "check_where_bounds": false,
+                "match_surface_paths": false,
+                "severity": {"hidden_item": "warn"},
+                "merge_strategy": "extend",
+            }),
+            serde_json::to_value(&config).unwrap()
+        );
+    }
+
+    #[test]
+    fn json_schema_covers_every_setting() {
+        let schema = Config::json_schema();
+        let properties = schema["properties"].as_object().unwrap();
+        let config = serde_json::to_value(Config::default()).unwrap();
+        for key in config.as_object().unwrap().keys() {
+            assert!(
+                properties.contains_key(key),
+                "`{key}` is missing from the schema"
+            );
+        }
+        assert!(properties.contains_key("extends"));
+        assert!(properties.contains_key("target"));
+        assert_eq!(
+            ValidationError::KINDS.len(),
+            properties["severity"]["properties"]
+                .as_object()
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_target_configs() {
+        let config = || -> Config {
+            serde_json::from_value(json!({
+                "allowed_external_types": ["everywhere::*"],
+                "target": {
+                    "cfg(windows)": {"allowed_external_types": ["windows_sys::*"]},
+                    "cfg(unix)": {
+                        "allowed_external_types": ["libc::*"],
+                        "denied_external_types": ["libc::unstable::*"],
+                    },
+                    "wasm32-unknown-unknown": {"allowed_external_types_exact": ["js_sys::Promise"]},
+                },
+            }))
+            .unwrap()
+        };
+
+        let mut windows = config();
+        windows.apply_target("x86_64-pc-windows-msvc", &[Cfg::Name("windows".into())]);
+        assert!(windows.allows_type("root", "everywhere::Type"));
+        assert!(windows.allows_type("root", "windows_sys::HANDLE"));
+        assert!(!windows.allows_type("root", "libc::c_int"));
+        assert!(!windows.allows_type("root", "js_sys::Promise"));
+        assert!(windows.target.is_empty());
+
+        let mut linux = config();
+        linux.apply_target("x86_64-unknown-linux-gnu", &[Cfg::Name("unix".into())]);
+        assert!(!linux.allows_type("root", "windows_sys::HANDLE"));
+        assert!(linux.allows_type("root", "libc::c_int"));
+        assert!(!linux.allows_type("root", "libc::unstable::Thing"));
+
+        let mut wasm = config();
+        wasm.apply_target("wasm32-unknown-unknown", &[]);
+        assert!(wasm.allows_type("root", "js_sys::Promise"));
+
+        let err = serde_json::from_value::<Config>(json!({
+            "allowed_external_types": [],
+            "target": {"cfg(windows": {}},
+        }))
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("invalid target `cfg(windows`"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_extends() {
+        let dir = write_configs(
+            "extends",
+            &[
+                (
+                    "shared/base.toml",
+                    "allow_std = false\nallowed_external_types = [\"base::*\"]\n",
+                ),
+                (
+                    "shared/more.toml",
+                    "extends = [\"base.toml\"]\nallowed_external_types = [\"more::*\"]\n",
+                ),
+                (
+                    "crate/external-types.toml",
+                    "extends = [\"../shared/more.toml\"]\nallow_std = true\nallowed_external_types = [\"local::*\"]\n",
+                ),
+            ],
+        );
+        let config = Config::from_file(&dir.join("crate/external-types.toml")).unwrap();
+        assert!(config.allow_std);
+        assert!(config.allows_type("root", "base::Type"));
+        assert!(config.allows_type("root", "more::Type"));
+        assert!(config.allows_type("root", "local::Type"));
+        assert!(!config.allows_type("root", "other::Type"));
+
+        // Metadata tables can extend config files too
+        let config = resolve_extends(
+            json!({"extends": ["shared/base.toml"], "allowed_external_types": ["local::*"]}),
+            &dir,
+        )
+        .unwrap();
+        let config: Config = serde_json::from_value(config).unwrap();
+        assert!(!config.allow_std);
+        assert!(config.allows_type("root", "base::Type"));
+        assert!(config.allows_type("root", "local::Type"));
+    }
+
+    #[test]
+    fn test_json_and_yaml_config() {
+        let dir = write_configs(
+            "json-config",
+            &[
+                ("base.toml", "allowed_external_types = [\"base::*\"]\n"),
+                (
+                    "external-types.json",
+                    r#"{"extends": ["base.toml"], "allowed_external_types": [{"pattern": "local::*", "reason": "Generated"}]}"#,
+                ),
+                (
+                    "external-types.yaml",
+                    "extends:\n  - external-types.json\nallow_std: false\nallowed_external_types:\n  - pattern: \"yaml::*\"\n    reason: Generated\n",
+                ),
+                ("invalid.yml", "allowed_external_types: [\n"),
+            ],
+        );
+        let config = Config::from_file(&dir.join("external-types.json")).unwrap();
+        assert!(config.allows_type("root", "base::Type"));
+        assert!(config.allows_type("root", "local::Type"));
+        assert!(!config.allows_type("root", "other::Type"));
+
+        let config = Config::from_file(&dir.join("external-types.yaml")).unwrap();
+        assert!(!config.allow_std);
+        assert!(config.allows_type("root", "base::Type"));
+        assert!(config.allows_type("root", "local::Type"));
+        assert!(config.allows_type("root", "yaml::Type"));
+        assert!(!config.allows_type("root", "other::Type"));
+
+        let err = format!(
+            "{:#}",
+            Config::from_file(&dir.join("invalid.yml")).unwrap_err()
+        );
+        assert!(err.contains("failed to parse config file"), "{err}");
+    }
+
+    #[test]
+    fn test_extends_errors() {
+        let dir = write_configs(
+            "extends-errors",
+            &[
+                ("a.toml", "extends = [\"b.toml\"]\n"),
+                ("b.toml", "extends = [\"a.toml\"]\n"),
+                ("missing.toml", "extends = [\"does-not-exist.toml\"]\n"),
+                ("invalid.toml", "extends = \"a.toml\"\n"),
+            ],
+        );
+        let err = format!("{:#}", Config::from_file(&dir.join("a.toml")).unwrap_err());
+        assert!(
+            err.contains("config files extend each other in a cycle"),
+            "{err}"
+        );
+        assert!(err.contains("a.toml -> "), "{err}");
+        assert!(err.ends_with("a.toml"), "{err}");
+
+        let err = format!(
+            "{:#}",
+            Config::from_file(&dir.join("missing.toml")).unwrap_err()
+        );
+        assert!(err.contains("does-not-exist.toml"), "{err}");
+
+        let err = format!(
+            "{:#}",
+            Config::from_file(&dir.join("invalid.toml")).unwrap_err()
+        );
+        assert!(err.contains("`extends` must be a list"), "{err}");
     }
 }
@@ -0,0 +1,559 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+use cargo_metadata::semver::Version;
+use cargo_metadata::{DependencyKind, Metadata, Node, NodeDep, Package, PackageId, Source};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+
+/// Where the source code of a dependency comes from.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// A package registry, such as crates.io
+    Registry,
+    /// A git repository
+    Git,
+    /// A local path
+    Path,
+}
+
+impl SourceKind {
+    fn from_package(package: &Package) -> Self {
+        match &package.source {
+            Some(source) if source.repr.starts_with("git+") => Self::Git,
+            Some(_) => Self::Registry,
+            None => Self::Path,
+        }
+    }
+}
+
+impl fmt::Display for SourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Registry => "registry",
+            Self::Git => "git",
+            Self::Path => "path",
+        })
+    }
+}
+
+/// Information about a package that the crate being checked depends on.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Dependency {
+    /// Name of the package as declared in its Cargo.toml
+    pub package_name: String,
+    /// Version of the package that the dependency graph resolved to
+    pub version: Version,
+    /// Where the package's source code comes from
+    pub source_kind: SourceKind,
+    /// Short description of the package's source, such as `crates.io` or a git repository URL
+    pub source: String,
+    /// Whether the crate being checked marks the dependency with `public = true`. Only known for
+    /// direct dependencies of packages that use Cargo's public dependency feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public: Option<bool>,
+    /// Features of the crate being checked that enable the dependency, in alphabetical order,
+    /// if it's only in the dependency graph when one of them is enabled. This includes
+    /// `default` if the default features enable it. Transitive dependencies are optional if
+    /// every direct dependency they're reached through is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled_by_features: Option<Vec<String>>,
+}
+
+impl Dependency {
+    fn from_package(package: &Package) -> Self {
+        Self {
+            package_name: package.name.clone(),
+            version: package.version.clone(),
+            source_kind: SourceKind::from_package(package),
+            source: describe_source(package.source.as_ref()),
+            public: None,
+            enabled_by_features: None,
+        }
+    }
+}
+
+/// Formats the dependency the way Cargo identifies packages, such as `hyper 0.14.27 (crates.io)`.
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({})",
+            self.package_name, self.version, self.source
+        )
+    }
+}
+
+/// Describes where the package's source code comes from. Registries other than crates.io and
+/// git repositories are described by their URL, without the revision that was resolved.
+fn describe_source(source: Option<&Source>) -> String {
+    let Some(source) = source else {
+        return "path".into();
+    };
+    let repr = source.repr.as_str();
+    if source.is_crates_io() || repr == "sparse+https://index.crates.io/" {
+        return "crates.io".into();
+    }
+    let url = repr
+        .split_once('+')
+        .map_or(repr, |(_, url)| url)
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(repr);
+    url.to_string()
+}
+
+/// Dependencies of the crate being checked, keyed by the crate name that appears in rustdoc paths.
+///
+/// Only dependencies that are enabled in the feature-resolved dependency graph of the root
+/// package are included. Dev and build dependencies are excluded since they can't be exposed
+/// in the public API of a library.
+#[derive(Clone, Debug, Default)]
+pub struct Dependencies {
+    by_crate_name: HashMap<String, Dependency>,
+    /// Other names of crates whose dependency is renamed in Cargo.toml. The root package refers
+    /// to them by the renamed name, while rustdoc paths use the crate's own name.
+    aliases: HashMap<String, Vec<String>>,
+    /// Versions of each crate in the dependency graph, since more than one can be
+    versions: HashMap<String, Vec<Version>>,
+}
+
+impl Dependencies {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Walks the resolved dependency graph starting from `root` to discover all of its
+    /// (transitive) normal dependencies.
+    pub fn from_metadata(metadata: &Metadata, root: &PackageId) -> Self {
+        let mut dependencies = Self::new();
+        let Some(resolve) = &metadata.resolve else {
+            return dependencies;
+        };
+        let packages: HashMap<&PackageId, &Package> =
+            metadata.packages.iter().map(|p| (&p.id, p)).collect();
+        let nodes: HashMap<_, _> = resolve.nodes.iter().map(|n| (&n.id, n)).collect();
+        let public_dependencies = packages
+            .get(root)
+            .and_then(|package| fs::read_to_string(&package.manifest_path).ok())
+            .and_then(|manifest| public_dependency_names(&manifest));
+        let enabling_features = packages
+            .get(root)
+            .map(|package| feature_gates(package, &nodes, root))
+            .unwrap_or_default();
+
+        // Breadth-first so that when the same crate name is reachable through multiple
+        // packages, the one closest to the root wins.
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([root]);
+        while let Some(id) = queue.pop_front() {
+            let Some(node) = nodes.get(id) else { continue };
+            for dep in &node.deps {
+                if !is_normal(dep) || !visited.insert(&dep.pkg) {
+                    continue;
+                }
+                if let Some(package) = packages.get(&dep.pkg) {
+                    let mut dependency = Dependency::from_package(package);
+                    dependency.enabled_by_features = enabling_features
+                        .get(&dep.pkg)
+                        .cloned()
+                        .flatten()
+                        .map(|features| features.into_iter().collect());
+                    if id == root {
+                        dependency.public = public_dependencies
+                            .as_ref()
+                            .map(|public| public.contains(&dep.name));
+                        // Direct dependencies may be renamed, in which case rustdoc
+                        // can refer to them by either name.
+                        dependencies.insert(dep.name.clone(), dependency.clone());
+                    }
+                    if let Some(lib_name) = lib_target_name(package) {
+                        if id == root && dep.name != lib_name {
+                            dependencies.add_alias(&dep.name, &lib_name);
+                        }
+                        dependencies.add_version(&lib_name, &package.version);
+                        dependencies.insert(lib_name, dependency);
+                    }
+                }
+                queue.push_back(&dep.pkg);
+            }
+        }
+        dependencies
+    }
+
+    pub fn insert(&mut self, crate_name: impl Into<String>, dependency: Dependency) {
+        self.by_crate_name
+            .entry(crate_name.into())
+            .or_insert(dependency);
+    }
+
+    /// Records that `name` and `other_name` are two names of the same crate.
+    pub fn add_alias(&mut self, name: &str, other_name: &str) {
+        for (from, to) in [(name, other_name), (other_name, name)] {
+            let aliases = self.aliases.entry(from.to_string()).or_default();
+            if !aliases.iter().any(|alias| alias == to) {
+                aliases.push(to.to_string());
+            }
+        }
+    }
+
+    /// Returns the other names of the crate with the given name, if its dependency is renamed.
+    pub fn aliases(&self, crate_name: &str) -> &[String] {
+        self.aliases.get(crate_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns `path` with its crate name replaced by each of the crate's other names.
+    pub fn aliased_paths(&self, path: &str) -> Vec<String> {
+        let (crate_name, rest) = match path.split_once("::") {
+            Some((crate_name, rest)) => (crate_name, Some(rest)),
+            None => (path, None),
+        };
+        self.aliases(crate_name)
+            .iter()
+            .map(|alias| match rest {
+                Some(rest) => format!("{alias}::{rest}"),
+                None => alias.clone(),
+            })
+            .collect()
+    }
+
+    /// Records that version `version` of the crate with the given name is a dependency.
+    pub fn add_version(&mut self, crate_name: &str, version: &Version) {
+        let versions = self.versions.entry(crate_name.to_string()).or_default();
+        if let Err(index) = versions.binary_search(version) {
+            versions.insert(index, version.clone());
+        }
+    }
+
+    /// Returns the versions of the crate with the given name in the dependency graph, oldest
+    /// first.
+    pub fn versions(&self, crate_name: &str) -> &[Version] {
+        self.versions.get(crate_name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the name of a crate in the dependency graph that `crate_name` is likely a
+    /// misspelling of, such as `tokio_util` for `tokio-util`. Crate names in paths always use
+    /// `_` rather than `-`.
+    pub fn similar_crate_name(&self, crate_name: &str) -> Option<&str> {
+        let normalized = crate_name.replace('-', "_").to_lowercase();
+        self.by_crate_name
+            .keys()
+            .filter(|name| name.as_str() != crate_name && name.to_lowercase() == normalized)
+            .min()
+            .map(String::as_str)
+    }
+
+    /// Returns the dependency that provides the crate with the given name.
+    pub fn get(&self, crate_name: &str) -> Option<&Dependency> {
+        self.by_crate_name.get(crate_name)
+    }
+}
+
+/// Returns the names that the direct normal dependencies of `root` have in its code, in
+/// alphabetical order. Proc macro crates are left out, since they can't export types.
+pub fn direct_dependency_names(metadata: &Metadata, root: &PackageId) -> Vec<String> {
+    let Some(node) = metadata
+        .resolve
+        .as_ref()
+        .and_then(|resolve| resolve.nodes.iter().find(|node| &node.id == root))
+    else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = node
+        .deps
+        .iter()
+        .filter(|dep| {
+            dep.dep_kinds
+                .iter()
+                .any(|info| info.kind == DependencyKind::Normal)
+        })
+        .filter(|dep| {
+            !metadata.packages.iter().any(|package| {
+                package.id == dep.pkg
+                    && package
+                        .targets
+                        .iter()
+                        .any(|t| t.kind.iter().any(|k| k == "proc-macro"))
+            })
+        })
+        .map(|dep| dep.name.clone())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Returns the names, as they're used in code, of the dependencies that the Cargo.toml
+/// `manifest` marks with `public = true`, or `None` if the package doesn't use Cargo's public
+/// dependency feature. The feature is used if it's in `cargo-features`, or if any dependency
+/// sets `public`, since `-Zpublic-dependency` enables it too.
+fn public_dependency_names(manifest: &str) -> Option<HashSet<String>> {
+    let manifest: toml::Table = toml::from_str(manifest).ok()?;
+    let uses_feature = manifest
+        .get("cargo-features")
+        .and_then(toml::Value::as_array)
+        .is_some_and(|features| {
+            features
+                .iter()
+                .any(|feature| feature.as_str() == Some("public-dependency"))
+        });
+    let targets = manifest
+        .get("target")
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flat_map(|targets| targets.values());
+    let tables = std::iter::once(&manifest)
+        .chain(targets.filter_map(toml::Value::as_table))
+        .filter_map(|table| table.get("dependencies").and_then(toml::Value::as_table));
+    let mut sets_public = false;
+    let mut public = HashSet::new();
+    for (name, dependency) in tables.flat_map(|table| table.iter()) {
+        match dependency.get("public").and_then(toml::Value::as_bool) {
+            Some(true) => {
+                sets_public = true;
+                public.insert(name.replace('-', "_"));
+            }
+            Some(false) => sets_public = true,
+            None => {}
+        }
+    }
+    (uses_feature || sets_public).then_some(public)
+}
+
+/// Returns the features of `root` that each package in its dependency graph is gated behind,
+/// or `None` for packages that at least one of its required dependencies reaches.
+fn feature_gates<'a>(
+    root_package: &Package,
+    nodes: &HashMap<&'a PackageId, &'a Node>,
+    root: &PackageId,
+) -> HashMap<&'a PackageId, Option<BTreeSet<String>>> {
+    let optional = optional_dependency_features(root_package);
+    let mut gates: HashMap<&PackageId, Option<BTreeSet<String>>> = HashMap::new();
+    let Some(root_node) = nodes.get(root) else {
+        return gates;
+    };
+    for direct in root_node.deps.iter().filter(|dep| is_normal(dep)) {
+        let gate = optional.get(&direct.name);
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([&direct.pkg]);
+        while let Some(id) = queue.pop_front() {
+            if !visited.insert(id) {
+                continue;
+            }
+            match (gates.get_mut(id), gate) {
+                (Some(Some(features)), Some(gate)) => features.extend(gate.iter().cloned()),
+                (Some(existing), None) => *existing = None,
+                (Some(None), Some(_)) => {}
+                (None, gate) => {
+                    gates.insert(id, gate.cloned());
+                }
+            }
+            if let Some(node) = nodes.get(id) {
+                queue.extend(
+                    node.deps
+                        .iter()
+                        .filter(|dep| is_normal(dep))
+                        .map(|dep| &dep.pkg),
+                );
+            }
+        }
+    }
+    gates
+}
+
+fn is_normal(dep: &NodeDep) -> bool {
+    dep.dep_kinds
+        .iter()
+        .any(|info| info.kind == DependencyKind::Normal)
+}
+
+/// Returns the features that enable each of the optional normal dependencies of `package`,
+/// keyed by the name the dependency has in the package's code. Dependencies that are also
+/// required, such as for another target, are left out.
+fn optional_dependency_features(package: &Package) -> HashMap<String, BTreeSet<String>> {
+    let mut optional = HashMap::new();
+    let mut required = HashSet::new();
+    for dep in &package.dependencies {
+        if dep.kind != DependencyKind::Normal {
+            continue;
+        }
+        let name = dep.rename.as_ref().unwrap_or(&dep.name);
+        if dep.optional {
+            optional.insert(
+                name.replace('-', "_"),
+                enabling_features(&package.features, name),
+            );
+        } else {
+            required.insert(name.replace('-', "_"));
+        }
+    }
+    optional.retain(|name, _| !required.contains(name));
+    optional
+}
+
+/// Returns the features in `features` that enable the optional dependency `dep_name`, either
+/// directly or by enabling other features that do.
+fn enabling_features(features: &BTreeMap<String, Vec<String>>, dep_name: &str) -> BTreeSet<String> {
+    let dep_feature = format!("dep:{dep_name}");
+    let enables_dependency = |value: &String| {
+        *value == dep_feature
+            || value
+                .split_once('/')
+                .is_some_and(|(name, _)| name == dep_name)
+    };
+    let mut enabling: BTreeSet<String> = features
+        .iter()
+        .filter(|(_, values)| values.iter().any(enables_dependency))
+        .map(|(name, _)| name.clone())
+        .collect();
+    // Optional dependencies have an implicit feature of the same name, unless a feature refers
+    // to them with `dep:`
+    if !features
+        .values()
+        .flatten()
+        .any(|value| *value == dep_feature)
+    {
+        enabling.insert(dep_name.to_string());
+    }
+    loop {
+        let more: Vec<String> = features
+            .iter()
+            .filter(|(name, values)| {
+                !enabling.contains(*name) && values.iter().any(|value| enabling.contains(value))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if more.is_empty() {
+            return enabling;
+        }
+        enabling.extend(more);
+    }
+}
+
+fn lib_target_name(package: &Package) -> Option<String> {
+    package
+        .targets
+        .iter()
+        .find(|t| {
+            t.kind
+                .iter()
+                .any(|k| k == "lib" || k == "rlib" || k == "proc-macro")
+        })
+        .map(|t| t.name.replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(repr: &str) -> Option<Source> {
+        Some(Source { repr: repr.into() })
+    }
+
+    #[test]
+    fn find_public_dependencies() {
+        let manifest = r#"
+            cargo-features = ["public-dependency"]
+
+            [dependencies]
+            bytes = { version = "1", public = true }
+            http-body = { version = "1", public = true }
+            renamed = { package = "http", version = "1", public = true }
+            private = { version = "1", public = false }
+            serde = "1"
+
+            [target.'cfg(unix)'.dependencies]
+            libc = { version = "0.2", public = true }
+        "#;
+        let mut public: Vec<_> = public_dependency_names(manifest)
+            .unwrap()
+            .into_iter()
+            .collect();
+        public.sort();
+        assert_eq!(vec!["bytes", "http_body", "libc", "renamed"], public);
+
+        assert_eq!(
+            Some(HashSet::new()),
+            public_dependency_names("cargo-features = [\"public-dependency\"]\n")
+        );
+        assert_eq!(
+            None,
+            public_dependency_names("[dependencies]\nbytes = \"1\"\n")
+        );
+    }
+
+    #[test]
+    fn find_similar_crate_names() {
+        let mut dependencies = Dependencies::new();
+        let package: Package = serde_json::from_value(serde_json::json!({
+            "name": "tokio-util",
+            "version": "0.7.0",
+            "id": "tokio-util 0.7.0",
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": "/tokio-util/Cargo.toml",
+        }))
+        .unwrap();
+        dependencies.insert("tokio_util", Dependency::from_package(&package));
+        assert_eq!(
+            Some("tokio_util"),
+            dependencies.similar_crate_name("tokio-util")
+        );
+        assert_eq!(
+            Some("tokio_util"),
+            dependencies.similar_crate_name("Tokio_Util")
+        );
+        assert_eq!(None, dependencies.similar_crate_name("tokio_util"));
+        assert_eq!(None, dependencies.similar_crate_name("tokio"));
+    }
+
+    #[test]
+    fn find_enabling_features() {
+        let features: BTreeMap<String, Vec<String>> = [
+            ("default", vec!["client"]),
+            ("client", vec!["dep:hyper", "http-body?/std"]),
+            ("full", vec!["client", "server"]),
+            ("server", vec!["tokio/net"]),
+            ("tls", vec!["http-body?/tls"]),
+        ]
+        .into_iter()
+        .map(|(name, values)| (name.into(), values.into_iter().map(String::from).collect()))
+        .collect();
+        let names = |dep_name| Vec::from_iter(enabling_features(&features, dep_name));
+        assert_eq!(vec!["client", "default", "full"], names("hyper"));
+        assert_eq!(vec!["full", "server", "tokio"], names("tokio"));
+        assert_eq!(vec!["http-body"], names("http-body"));
+    }
+
+    #[test]
+    fn describe_sources() {
+        assert_eq!("path", describe_source(None));
+        assert_eq!(
+            "crates.io",
+            describe_source(
+                source("registry+https://github.com/rust-lang/crates.io-index").as_ref()
+            )
+        );
+        assert_eq!(
+            "crates.io",
+            describe_source(source("sparse+https://index.crates.io/").as_ref())
+        );
+        assert_eq!(
+            "https://github.com/hyperium/hyper",
+            describe_source(
+                source("git+https://github.com/hyperium/hyper?branch=master#0123456789abcdef")
+                    .as_ref()
+            )
+        );
+        assert_eq!(
+            "https://registry.example.com/index",
+            describe_source(source("sparse+https://registry.example.com/index").as_ref())
+        );
+    }
+}
@@ -4,15 +4,22 @@
  */
 
 use crate::bug;
+use crate::config::Pattern;
+use crate::dependency::Dependency;
+use crate::pattern_stats::{PatternReport, PatternStats};
+use crate::workspace::ExternalTypeSummary;
 use anyhow::{Context, Result};
+use cargo_metadata::semver::Version;
 use pest::Position;
 use rustdoc_types::Span;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::iter::Iterator;
+use std::io::Write;
+use std::iter::{self, Iterator};
 use std::path::{Path, PathBuf};
 
 /// Where the error occurred relative to the [`Path`](crate::path::Path).
@@ -22,7 +29,7 @@ use std::path::{Path, PathBuf};
 ///
 /// There is overlap in this enum with [`ComponentType`](crate::path::ComponentType) since
 /// some paths are specific enough to locate the external type.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum ErrorLocation {
     AssocType,
     ArgumentNamed(String),
@@ -74,9 +81,18 @@ impl fmt::Display for ErrorLocation {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Deserialize, Serialize)]
 pub struct ValidationErrors {
     errors: BTreeSet<ValidationError>,
+    /// Findings that would have been errors if not for a suppression mechanism
+    suppressed: BTreeMap<SuppressionMechanism, BTreeSet<ValidationError>>,
+    /// Findings suppressed by each allowed pattern
+    pattern_stats: PatternStats,
+    /// Paths of the public items that were visited
+    public_items: BTreeSet<String>,
+    /// Labels of the variations of the build, such as feature sets, that were merged with
+    /// [`merge_variation`](Self::merge_variation)
+    variations: BTreeSet<String>,
 }
 
 impl ValidationErrors {
@@ -100,37 +116,390 @@ impl ValidationErrors {
             .count()
     }
 
-    pub fn add(&mut self, error: ValidationError) {
-        self.errors.insert(error);
+    /// Adds an error, returning false if an equivalent error was already present.
+    pub fn add(&mut self, error: ValidationError) -> bool {
+        self.errors.insert(error)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &ValidationError> {
         self.errors.iter()
     }
 
+    /// Adds all errors and suppressed findings from `other`.
+    pub fn merge(&mut self, other: ValidationErrors) {
+        self.errors.extend(other.errors);
+        for (mechanism, suppressed) in other.suppressed {
+            self.suppressed
+                .entry(mechanism)
+                .or_default()
+                .extend(suppressed);
+        }
+        self.pattern_stats.merge(other.pattern_stats);
+        self.public_items.extend(other.public_items);
+        self.variations.extend(other.variations);
+    }
+
+    /// Adds the findings from checking another variation of the build, such as another feature
+    /// set, and records that each of them was found with the variation named `label`.
+    pub fn merge_variation(&mut self, label: &str, mut other: ValidationErrors) {
+        let errors = std::mem::take(&mut other.errors);
+        for error in errors {
+            let mut found_with = self
+                .errors
+                .take(&error)
+                .map(|existing| existing.found_with().to_vec())
+                .unwrap_or_default();
+            if !found_with.iter().any(|existing| existing == label) {
+                found_with.push(label.to_string());
+            }
+            self.errors.insert(error.with_found_with(found_with));
+        }
+        self.merge(other);
+        self.variations.insert(label.to_string());
+    }
+
+    /// Forgets which variations the findings were found with for the findings that were found
+    /// with all of them, once every variation is merged, since they don't depend on the
+    /// variation.
+    pub fn finish_variations(&mut self) {
+        let variations = self.variations.len();
+        self.errors = std::mem::take(&mut self.errors)
+            .into_iter()
+            .map(|error| {
+                if error.found_with().len() == variations {
+                    error.with_found_with(Vec::new())
+                } else {
+                    error
+                }
+            })
+            .collect();
+    }
+
+    /// Records that the public item at `path` was visited, for [`cleanliness`](Self::cleanliness).
+    pub fn record_public_item(&mut self, path: String) {
+        self.public_items.insert(path);
+    }
+
+    /// Returns how many of the public items that were visited don't reference external types
+    /// that aren't approved. Findings suppressed by `--baseline` or `--since` still count against
+    /// their items, since those only hide them from the output.
+    pub fn cleanliness(&self) -> Cleanliness {
+        let unapproved: BTreeSet<&str> = self
+            .errors
+            .iter()
+            .chain(self.suppressed(SuppressionMechanism::Baseline))
+            .chain(self.suppressed(SuppressionMechanism::Since))
+            .filter(|error| error.external_crate_name().is_some())
+            .filter_map(ValidationError::in_what_type)
+            .collect();
+        // Findings can be in items that aren't recorded themselves, such as impl blocks
+        let public_items = self
+            .public_items
+            .iter()
+            .map(String::as_str)
+            .chain(unapproved.iter().copied())
+            .collect::<BTreeSet<_>>()
+            .len();
+        Cleanliness {
+            public_items,
+            clean_items: public_items - unapproved.len(),
+        }
+    }
+
+    /// Records a finding that was suppressed by the given mechanism rather than reported.
+    pub fn suppress(&mut self, mechanism: SuppressionMechanism, error: ValidationError) {
+        self.suppressed.entry(mechanism).or_default().insert(error);
+    }
+
+    /// Moves the errors for which `predicate` returns true to the findings suppressed by the
+    /// given mechanism.
+    pub fn suppress_where(
+        &mut self,
+        mechanism: SuppressionMechanism,
+        predicate: impl Fn(&ValidationError) -> bool,
+    ) {
+        let (suppressed, errors) = std::mem::take(&mut self.errors)
+            .into_iter()
+            .partition(|error| predicate(error));
+        self.errors = errors;
+        self.suppressed
+            .entry(mechanism)
+            .or_default()
+            .extend::<BTreeSet<_>>(suppressed);
+    }
+
+    /// Records that a finding was suppressed because it matched `pattern`. This is in addition
+    /// to [`suppress`](ValidationErrors::suppress), which records the mechanism.
+    pub fn record_pattern_match(
+        &mut self,
+        pattern: &Pattern,
+        anchored: bool,
+        error: ValidationError,
+    ) {
+        self.pattern_stats.record(pattern, anchored, error);
+    }
+
+    /// Returns the findings suppressed by each allowed pattern.
+    pub fn pattern_stats(&self) -> &PatternStats {
+        &self.pattern_stats
+    }
+
+    /// Returns the findings that were suppressed by the given mechanism.
+    pub fn suppressed(
+        &self,
+        mechanism: SuppressionMechanism,
+    ) -> impl Iterator<Item = &ValidationError> {
+        self.suppressed.get(&mechanism).into_iter().flatten()
+    }
+
+    /// Returns the number of distinct findings suppressed by each mechanism.
+    pub fn suppressed_counts(&self) -> BTreeMap<SuppressionMechanism, usize> {
+        self.suppressed
+            .iter()
+            .map(|(&mechanism, errors)| (mechanism, errors.len()))
+            .collect()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.errors.is_empty()
     }
+
+    /// Returns how many violations each external crate contributes, ordered by the
+    /// number of violations (most first) and then by crate name.
+    pub fn crate_summaries(&self) -> Vec<CrateSummary> {
+        let mut types_by_crate: BTreeMap<&str, (usize, BTreeSet<&str>)> = BTreeMap::new();
+        for error in &self.errors {
+            for (crate_name, type_name) in error.external_types() {
+                let (violations, types) = types_by_crate.entry(crate_name).or_default();
+                *violations += 1;
+                types.insert(type_name);
+            }
+        }
+        let mut summaries: Vec<_> = types_by_crate
+            .into_iter()
+            .map(|(crate_name, (violations, types))| CrateSummary {
+                crate_name: crate_name.into(),
+                violations,
+                distinct_types: types.len(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| b.violations.cmp(&a.violations));
+        summaries
+    }
+
+    /// Returns all errors in the given order. Errors that compare equal stay ordered by location.
+    pub fn sorted_by(&self, sort_by: SortBy) -> Vec<&ValidationError> {
+        let mut errors: Vec<_> = self.errors.iter().collect();
+        errors.sort_by(|a, b| sort_by.compare(a, b));
+        errors
+    }
+}
+
+/// Order in which errors are output.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum SortBy {
+    /// By file and then line and column
+    #[default]
+    File,
+    /// By the name of the external crate the type comes from
+    Crate,
+    /// By the name of the external type
+    Type,
+    /// Errors before warnings
+    Severity,
+}
+
+impl SortBy {
+    /// Compares two errors by this order only, so that a stable sort keeps ties in their
+    /// existing order.
+    pub fn compare(&self, a: &ValidationError, b: &ValidationError) -> Ordering {
+        match self {
+            Self::File => a.cmp(b),
+            // Errors that don't reference an external crate go last
+            Self::Crate => (a.external_crate_name().is_none(), a.external_crate_name())
+                .cmp(&(b.external_crate_name().is_none(), b.external_crate_name())),
+            Self::Type => a.type_name().cmp(b.type_name()),
+            Self::Severity => a.level().cmp(&b.level()),
+        }
+    }
+}
+
+impl fmt::Display for SortBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::File => "file",
+            Self::Crate => "crate",
+            Self::Type => "type",
+            Self::Severity => "severity",
+        })
+    }
+}
+
+impl std::str::FromStr for SortBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Self::File),
+            "crate" => Ok(Self::Crate),
+            "type" => Ok(Self::Type),
+            "severity" => Ok(Self::Severity),
+            _ => Err(anyhow::anyhow!(
+                "invalid sort order: {}. Expected `file`, `crate`, `type`, or `severity`.",
+                s
+            )),
+        }
+    }
+}
+
+/// Something that hides a finding from the output.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuppressionMechanism {
+    /// The type matched a pattern in `allowed_external_types`
+    AllowedExternalTypes,
+    /// The type is listed in `allowed_external_types_exact`
+    AllowedExternalTypesExact,
+    /// The type matched a pattern in `allowed_external_types_in` for the item referencing it
+    AllowedExternalTypesIn,
+    /// The type is from a dependency marked `public = true`, and `allow_public_dependencies` is
+    /// set
+    AllowPublicDependencies,
+    /// The kind of finding is turned `off` in the `severity` table
+    Severity,
+    /// The finding is recorded in the `--baseline` file
+    Baseline,
+    /// The finding is outside of the lines that changed since the `--since` git ref
+    Since,
+}
+
+impl fmt::Display for SuppressionMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::AllowedExternalTypes => "allowed_external_types",
+            Self::AllowedExternalTypesExact => "allowed_external_types_exact",
+            Self::AllowedExternalTypesIn => "allowed_external_types_in",
+            Self::AllowPublicDependencies => "allow_public_dependencies",
+            Self::Severity => "severity",
+            Self::Baseline => "baseline",
+            Self::Since => "since",
+        })
+    }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+/// Number of violations that an external crate contributes to the public API.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct CrateSummary {
+    pub crate_name: String,
+    /// Total number of references to types from this crate
+    pub violations: usize,
+    /// Number of different types from this crate that are referenced
+    pub distinct_types: usize,
+}
+
+/// How much of the public API is free of unapproved external types.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Cleanliness {
+    /// Number of public items, including fields, variants, and associated items
+    pub public_items: usize,
+    /// Number of public items that don't reference any unapproved external types
+    pub clean_items: usize,
+}
+
+impl Cleanliness {
+    /// Returns the percentage of public items that are clean, or 100 if there are none.
+    pub fn percent_clean(&self) -> f64 {
+        if self.public_items == 0 {
+            100.0
+        } else {
+            self.clean_items as f64 * 100.0 / self.public_items as f64
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ErrorLevel {
     Error,
     Warning,
 }
 
 /// Error type for validation errors that get displayed to the user on the CLI.
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum ValidationError {
     UnapprovedExternalTypeRef {
         type_name: String,
         what: ErrorLocation,
         in_what_type: String,
         location: Option<Span>,
+        dependency: Option<Dependency>,
+        referenced_as: Option<String>,
+        /// Other types referenced along with `type_name` in `referenced_as`, such as the
+        /// generic arguments of a trait, which are reported in this error instead of their own
+        grouped_types: Vec<String>,
+        alias_chain: Vec<String>,
+        macro_invocation: Option<String>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    DeniedDependencySource {
+        type_name: String,
+        what: ErrorLocation,
+        in_what_type: String,
+        location: Option<Span>,
+        dependency: Dependency,
+        referenced_as: Option<String>,
+        grouped_types: Vec<String>,
+        alias_chain: Vec<String>,
+        macro_invocation: Option<String>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    UnstableDependency {
+        type_name: String,
+        what: ErrorLocation,
+        in_what_type: String,
+        location: Option<Span>,
+        dependency: Dependency,
+        referenced_as: Option<String>,
+        grouped_types: Vec<String>,
+        alias_chain: Vec<String>,
+        macro_invocation: Option<String>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    DeniedExternalTypeRef {
+        type_name: String,
+        what: ErrorLocation,
+        in_what_type: String,
+        location: Option<Span>,
+        dependency: Option<Dependency>,
+        referenced_as: Option<String>,
+        grouped_types: Vec<String>,
+        alias_chain: Vec<String>,
+        macro_invocation: Option<String>,
+        /// The pattern that the type matched
+        pattern: String,
+        /// The config list that the pattern is in, such as `denied_external_types`
+        denied_by: Cow<'static, str>,
+        /// Why the pattern is denied, if the config gives a reason
+        reason: Option<String>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
         sort_key: String,
     },
     FieldsStripped {
         type_name: String,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
     },
     HiddenModule {
         type_name: String,
@@ -138,11 +507,69 @@ pub enum ValidationError {
         in_what_type: String,
         location: Option<Span>,
         hidden_module: Option<String>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
     },
     HiddenItem {
         what: ErrorLocation,
         in_what_type: String,
         location: Option<Span>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    PatternType {
+        what: ErrorLocation,
+        in_what_type: String,
+        location: Option<Span>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    ReExportedCrate {
+        /// Name of the external crate that's re-exported as a whole
+        crate_name: String,
+        what: ErrorLocation,
+        in_what_type: String,
+        location: Option<Span>,
+        dependency: Option<Dependency>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    MultipleCrateVersions {
+        /// Name of the external crate that more than one version is referenced of
+        crate_name: String,
+        /// The type whose reference brought in another version of the crate
+        type_name: String,
+        what: ErrorLocation,
+        in_what_type: String,
+        location: Option<Span>,
+        /// Versions of the crate in the dependency graph
+        versions: Vec<Version>,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    UnusedApproval {
+        /// The `allowed_external_types` pattern that didn't allow any reference
+        pattern: String,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
+        sort_key: String,
+    },
+    DuplicateApproved {
+        /// The `allowed_external_types` pattern that's listed more than once
+        pattern: String,
+        cfg: Option<String>,
+        found_with: Vec<String>,
+        level: ErrorLevel,
         sort_key: String,
     },
 }
@@ -153,6 +580,8 @@ impl ValidationError {
         what: &ErrorLocation,
         in_what_type: impl Into<String>,
         location: Option<&Span>,
+        dependency: Option<&Dependency>,
+        referenced_as: Option<String>,
     ) -> Self {
         let type_name = type_name.into();
         let in_what_type = in_what_type.into();
@@ -168,22 +597,370 @@ impl ValidationError {
             what: what.clone(),
             in_what_type,
             location: location.cloned(),
+            dependency: dependency.cloned(),
+            referenced_as,
+            grouped_types: Vec::new(),
+            alias_chain: Vec::new(),
+            macro_invocation: None,
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Error,
             sort_key,
         }
     }
 
+    pub fn denied_dependency_source(
+        type_name: impl Into<String>,
+        what: &ErrorLocation,
+        in_what_type: impl Into<String>,
+        location: Option<&Span>,
+        dependency: &Dependency,
+        referenced_as: Option<String>,
+    ) -> Self {
+        let type_name = type_name.into();
+        let in_what_type = in_what_type.into();
+        let sort_key = format!(
+            "{}:{type_name}:{what}:{in_what_type}",
+            location_sort_key(location)
+        );
+        if location.is_none() {
+            bug!("An error is missing a span and will be printed without context, file name, and line number.");
+        }
+        Self::DeniedDependencySource {
+            type_name,
+            what: what.clone(),
+            in_what_type,
+            location: location.cloned(),
+            dependency: dependency.clone(),
+            referenced_as,
+            grouped_types: Vec::new(),
+            alias_chain: Vec::new(),
+            macro_invocation: None,
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Error,
+            sort_key,
+        }
+    }
+
+    /// References to types from a dependency whose version is below 1.0, which the config allows
+    /// without acknowledging that the dependency is unstable.
+    pub fn unstable_dependency(
+        type_name: impl Into<String>,
+        what: &ErrorLocation,
+        in_what_type: impl Into<String>,
+        location: Option<&Span>,
+        dependency: &Dependency,
+        referenced_as: Option<String>,
+    ) -> Self {
+        let type_name = type_name.into();
+        let in_what_type = in_what_type.into();
+        let sort_key = format!(
+            "{}:{type_name}:{what}:{in_what_type}",
+            location_sort_key(location)
+        );
+        if location.is_none() {
+            bug!("An error is missing a span and will be printed without context, file name, and line number.");
+        }
+        Self::UnstableDependency {
+            type_name,
+            what: what.clone(),
+            in_what_type,
+            location: location.cloned(),
+            dependency: dependency.clone(),
+            referenced_as,
+            grouped_types: Vec::new(),
+            alias_chain: Vec::new(),
+            macro_invocation: None,
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Error,
+            sort_key,
+        }
+    }
+
+    pub fn denied_external_type_ref(
+        type_name: impl Into<String>,
+        what: &ErrorLocation,
+        in_what_type: impl Into<String>,
+        location: Option<&Span>,
+        dependency: Option<&Dependency>,
+        referenced_as: Option<String>,
+        pattern: &Pattern,
+    ) -> Self {
+        let type_name = type_name.into();
+        let in_what_type = in_what_type.into();
+        let sort_key = format!(
+            "{}:{type_name}:{what}:{in_what_type}",
+            location_sort_key(location)
+        );
+        if location.is_none() {
+            bug!("An error is missing a span and will be printed without context, file name, and line number.");
+        }
+        Self::DeniedExternalTypeRef {
+            type_name,
+            what: what.clone(),
+            in_what_type,
+            location: location.cloned(),
+            dependency: dependency.cloned(),
+            referenced_as,
+            grouped_types: Vec::new(),
+            alias_chain: Vec::new(),
+            macro_invocation: None,
+            pattern: pattern.as_str().into(),
+            denied_by: "denied_external_types".into(),
+            reason: pattern.reason().map(String::from),
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Error,
+            sort_key,
+        }
+    }
+
+    /// Sets the config list that the pattern of a denied type is in, which is
+    /// `denied_external_types` by default.
+    pub fn with_denied_by(mut self, list: &'static str) -> Self {
+        if let Self::DeniedExternalTypeRef { denied_by, .. } = &mut self {
+            *denied_by = list.into();
+        }
+        self
+    }
+
+    /// Reports the other types in `types`, which are referenced along with this one at the same
+    /// use site for the same reason, in this error.
+    pub fn with_grouped_types(mut self, types: Vec<String>) -> Self {
+        if let Self::UnapprovedExternalTypeRef { grouped_types, .. }
+        | Self::DeniedDependencySource { grouped_types, .. }
+        | Self::UnstableDependency { grouped_types, .. }
+        | Self::DeniedExternalTypeRef { grouped_types, .. } = &mut self
+        {
+            *grouped_types = types;
+        }
+        self
+    }
+
+    /// Returns true if `other` is reported for the same reason as this error, so that the two
+    /// can be reported as one error when they're referenced at the same use site.
+    pub fn has_same_reason(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::UnapprovedExternalTypeRef { .. }, Self::UnapprovedExternalTypeRef { .. }) => {
+                true
+            }
+            (
+                Self::DeniedDependencySource { dependency, .. },
+                Self::DeniedDependencySource {
+                    dependency: other_dependency,
+                    ..
+                },
+            )
+            | (
+                Self::UnstableDependency { dependency, .. },
+                Self::UnstableDependency {
+                    dependency: other_dependency,
+                    ..
+                },
+            ) => dependency == other_dependency,
+            (
+                Self::DeniedExternalTypeRef {
+                    pattern, denied_by, ..
+                },
+                Self::DeniedExternalTypeRef {
+                    pattern: other_pattern,
+                    denied_by: other_denied_by,
+                    ..
+                },
+            ) => pattern == other_pattern && denied_by == other_denied_by,
+            _ => false,
+        }
+    }
+
+    /// Records the chain of local type aliases that the external type was referenced through,
+    /// starting from the alias written at the error location.
+    pub fn with_alias_chain(mut self, chain: Vec<String>) -> Self {
+        if let Self::UnapprovedExternalTypeRef { alias_chain, .. }
+        | Self::DeniedDependencySource { alias_chain, .. }
+        | Self::UnstableDependency { alias_chain, .. }
+        | Self::DeniedExternalTypeRef { alias_chain, .. } = &mut self
+        {
+            *alias_chain = chain;
+        }
+        self
+    }
+
+    /// Records the name of the macro whose invocation generated the item that contains the
+    /// reference.
+    pub fn with_macro_invocation(mut self, name: String) -> Self {
+        if let Self::UnapprovedExternalTypeRef {
+            macro_invocation, ..
+        }
+        | Self::DeniedDependencySource {
+            macro_invocation, ..
+        }
+        | Self::UnstableDependency {
+            macro_invocation, ..
+        }
+        | Self::DeniedExternalTypeRef {
+            macro_invocation, ..
+        } = &mut self
+        {
+            *macro_invocation = Some(name);
+        }
+        self
+    }
+
+    /// Records the `cfg` that the item with the reference is only compiled with, such as
+    /// `feature = "x"`.
+    pub fn with_cfg(mut self, new_cfg: String) -> Self {
+        match &mut self {
+            Self::UnapprovedExternalTypeRef { cfg, .. }
+            | Self::DeniedDependencySource { cfg, .. }
+            | Self::UnstableDependency { cfg, .. }
+            | Self::DeniedExternalTypeRef { cfg, .. }
+            | Self::FieldsStripped { cfg, .. }
+            | Self::HiddenModule { cfg, .. }
+            | Self::HiddenItem { cfg, .. }
+            | Self::PatternType { cfg, .. }
+            | Self::ReExportedCrate { cfg, .. }
+            | Self::MultipleCrateVersions { cfg, .. }
+            | Self::UnusedApproval { cfg, .. }
+            | Self::DuplicateApproved { cfg, .. } => *cfg = Some(new_cfg),
+        }
+        self
+    }
+
+    /// Returns the `cfg` that the item with the reference is only compiled with, if any.
+    pub fn cfg(&self) -> Option<&str> {
+        match self {
+            Self::UnapprovedExternalTypeRef { cfg, .. }
+            | Self::DeniedDependencySource { cfg, .. }
+            | Self::UnstableDependency { cfg, .. }
+            | Self::DeniedExternalTypeRef { cfg, .. }
+            | Self::FieldsStripped { cfg, .. }
+            | Self::HiddenModule { cfg, .. }
+            | Self::HiddenItem { cfg, .. }
+            | Self::PatternType { cfg, .. }
+            | Self::ReExportedCrate { cfg, .. }
+            | Self::MultipleCrateVersions { cfg, .. }
+            | Self::UnusedApproval { cfg, .. }
+            | Self::DuplicateApproved { cfg, .. } => cfg.as_deref(),
+        }
+    }
+
+    /// Names of all kinds of errors, as returned by [`kind`](ValidationError::kind).
+    pub const KINDS: &'static [&'static str] = &[
+        "unapproved_external_type_ref",
+        "denied_dependency_source",
+        "unstable_dependency",
+        "denied_external_type_ref",
+        "fields_stripped",
+        "hidden_module",
+        "hidden_item",
+        "pattern_type",
+        "reexported_crate",
+        "multiple_crate_versions",
+        "unused_approval",
+        "duplicate_approved",
+    ];
+
+    /// Returns a stable, machine-readable name for the kind of error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UnapprovedExternalTypeRef { .. } => "unapproved_external_type_ref",
+            Self::DeniedDependencySource { .. } => "denied_dependency_source",
+            Self::UnstableDependency { .. } => "unstable_dependency",
+            Self::DeniedExternalTypeRef { .. } => "denied_external_type_ref",
+            Self::FieldsStripped { .. } => "fields_stripped",
+            Self::HiddenModule { .. } => "hidden_module",
+            Self::HiddenItem { .. } => "hidden_item",
+            Self::PatternType { .. } => "pattern_type",
+            Self::ReExportedCrate { .. } => "reexported_crate",
+            Self::MultipleCrateVersions { .. } => "multiple_crate_versions",
+            Self::UnusedApproval { .. } => "unused_approval",
+            Self::DuplicateApproved { .. } => "duplicate_approved",
+        }
+    }
+
     pub fn level(&self) -> ErrorLevel {
         match self {
-            Self::UnapprovedExternalTypeRef { .. } => ErrorLevel::Error,
-            Self::HiddenModule { .. } | Self::HiddenItem { .. } | Self::FieldsStripped { .. } => {
-                ErrorLevel::Warning
-            }
+            Self::UnapprovedExternalTypeRef { level, .. }
+            | Self::DeniedDependencySource { level, .. }
+            | Self::UnstableDependency { level, .. }
+            | Self::DeniedExternalTypeRef { level, .. }
+            | Self::FieldsStripped { level, .. }
+            | Self::HiddenModule { level, .. }
+            | Self::HiddenItem { level, .. }
+            | Self::PatternType { level, .. }
+            | Self::ReExportedCrate { level, .. }
+            | Self::MultipleCrateVersions { level, .. }
+            | Self::UnusedApproval { level, .. }
+            | Self::DuplicateApproved { level, .. } => *level,
         }
     }
 
+    /// Records the feature sets or other variations of the build that the finding was found
+    /// with, when the crate is checked with several of them in one run.
+    pub fn with_found_with(mut self, labels: Vec<String>) -> Self {
+        match &mut self {
+            Self::UnapprovedExternalTypeRef { found_with, .. }
+            | Self::DeniedDependencySource { found_with, .. }
+            | Self::UnstableDependency { found_with, .. }
+            | Self::DeniedExternalTypeRef { found_with, .. }
+            | Self::FieldsStripped { found_with, .. }
+            | Self::HiddenModule { found_with, .. }
+            | Self::HiddenItem { found_with, .. }
+            | Self::PatternType { found_with, .. }
+            | Self::ReExportedCrate { found_with, .. }
+            | Self::MultipleCrateVersions { found_with, .. }
+            | Self::UnusedApproval { found_with, .. }
+            | Self::DuplicateApproved { found_with, .. } => *found_with = labels,
+        }
+        self
+    }
+
+    /// Returns the variations of the build that the finding was found with, if the crate was
+    /// checked with several and the finding wasn't found with all of them.
+    pub fn found_with(&self) -> &[String] {
+        match self {
+            Self::UnapprovedExternalTypeRef { found_with, .. }
+            | Self::DeniedDependencySource { found_with, .. }
+            | Self::UnstableDependency { found_with, .. }
+            | Self::DeniedExternalTypeRef { found_with, .. }
+            | Self::FieldsStripped { found_with, .. }
+            | Self::HiddenModule { found_with, .. }
+            | Self::HiddenItem { found_with, .. }
+            | Self::PatternType { found_with, .. }
+            | Self::ReExportedCrate { found_with, .. }
+            | Self::MultipleCrateVersions { found_with, .. }
+            | Self::UnusedApproval { found_with, .. }
+            | Self::DuplicateApproved { found_with, .. } => found_with,
+        }
+    }
+
+    /// Overrides the level of the error, which otherwise depends on its kind.
+    pub fn with_level(mut self, new_level: ErrorLevel) -> Self {
+        match &mut self {
+            Self::UnapprovedExternalTypeRef { level, .. }
+            | Self::DeniedDependencySource { level, .. }
+            | Self::UnstableDependency { level, .. }
+            | Self::DeniedExternalTypeRef { level, .. }
+            | Self::FieldsStripped { level, .. }
+            | Self::HiddenModule { level, .. }
+            | Self::HiddenItem { level, .. }
+            | Self::PatternType { level, .. }
+            | Self::ReExportedCrate { level, .. }
+            | Self::MultipleCrateVersions { level, .. }
+            | Self::UnusedApproval { level, .. }
+            | Self::DuplicateApproved { level, .. } => *level = new_level,
+        }
+        self
+    }
+
     pub fn fields_stripped(path: &crate::path::Path) -> Self {
         Self::FieldsStripped {
             type_name: path.to_string(),
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Warning,
         }
     }
 
@@ -203,6 +980,9 @@ impl ValidationError {
             in_what_type: in_what_type.into(),
             location: location.cloned(),
             hidden_module,
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Warning,
         }
     }
 
@@ -218,44 +998,346 @@ impl ValidationError {
             what: what.clone(),
             in_what_type: in_what_type.into(),
             location: location.cloned(),
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Warning,
+            sort_key: location_sort_key(location),
+        }
+    }
+
+    pub fn pattern_type(
+        what: &ErrorLocation,
+        in_what_type: impl Into<String>,
+        location: Option<&Span>,
+    ) -> Self {
+        if location.is_none() {
+            bug!("A warning is missing a span and will be printed without context, file name, and line number.");
+        }
+        Self::PatternType {
+            what: what.clone(),
+            in_what_type: in_what_type.into(),
+            location: location.cloned(),
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Warning,
             sort_key: location_sort_key(location),
         }
     }
 
+    /// Re-exports of a whole external crate, such as `pub use other_crate;`, which expose every
+    /// public item in it.
+    pub fn reexported_crate(
+        crate_name: impl Into<String>,
+        in_what_type: impl Into<String>,
+        location: Option<&Span>,
+        dependency: Option<&Dependency>,
+    ) -> Self {
+        let crate_name = crate_name.into();
+        let in_what_type = in_what_type.into();
+        let what = ErrorLocation::ReExport;
+        let sort_key = format!(
+            "{}:{crate_name}:{what}:{in_what_type}",
+            location_sort_key(location)
+        );
+        if location.is_none() {
+            bug!("An error is missing a span and will be printed without context, file name, and line number.");
+        }
+        Self::ReExportedCrate {
+            crate_name,
+            what,
+            in_what_type,
+            location: location.cloned(),
+            dependency: dependency.cloned(),
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Error,
+            sort_key,
+        }
+    }
+
+    /// References to types from a different version of an external crate than types from it
+    /// that were referenced before, which downstream crates can't use interchangeably.
+    pub fn multiple_crate_versions(
+        crate_name: impl Into<String>,
+        type_name: impl Into<String>,
+        what: &ErrorLocation,
+        in_what_type: impl Into<String>,
+        location: Option<&Span>,
+        versions: Vec<Version>,
+    ) -> Self {
+        let crate_name = crate_name.into();
+        let type_name = type_name.into();
+        let in_what_type = in_what_type.into();
+        // Keyed by the crate name so that it's distinct from errors about the type itself
+        let sort_key = format!(
+            "{}:{crate_name}:{what}:{in_what_type}",
+            location_sort_key(location)
+        );
+        if location.is_none() {
+            bug!("An error is missing a span and will be printed without context, file name, and line number.");
+        }
+        Self::MultipleCrateVersions {
+            crate_name,
+            type_name,
+            what: what.clone(),
+            in_what_type,
+            location: location.cloned(),
+            versions,
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Error,
+            sort_key,
+        }
+    }
+
+    /// Patterns in `allowed_external_types` that didn't allow any reference, which can be
+    /// removed from the config.
+    pub fn unused_approval(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        Self::UnusedApproval {
+            sort_key: format!("unused_approval:{pattern}"),
+            pattern,
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Warning,
+        }
+    }
+
+    /// Patterns that are listed more than once in `allowed_external_types`.
+    pub fn duplicate_approved(pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        Self::DuplicateApproved {
+            sort_key: format!("duplicate_approved:{pattern}"),
+            pattern,
+            cfg: None,
+            found_with: Vec::new(),
+            level: ErrorLevel::Warning,
+        }
+    }
+
     pub fn type_name(&self) -> &str {
         match self {
             Self::UnapprovedExternalTypeRef { type_name, .. }
+            | Self::DeniedDependencySource { type_name, .. }
+            | Self::UnstableDependency { type_name, .. }
+            | Self::DeniedExternalTypeRef { type_name, .. }
             | Self::HiddenModule { type_name, .. }
-            | Self::FieldsStripped { type_name } => type_name,
-            Self::HiddenItem { .. } => "N/A",
+            | Self::FieldsStripped { type_name, .. }
+            | Self::MultipleCrateVersions { type_name, .. }
+            | Self::ReExportedCrate {
+                crate_name: type_name,
+                ..
+            }
+            | Self::UnusedApproval {
+                pattern: type_name, ..
+            }
+            | Self::DuplicateApproved {
+                pattern: type_name, ..
+            } => type_name,
+            Self::HiddenItem { .. } | Self::PatternType { .. } => "N/A",
+        }
+    }
+
+    /// Returns the path of the item that contains the reference, if the error is about one.
+    pub fn in_what_type(&self) -> Option<&str> {
+        match self {
+            Self::UnapprovedExternalTypeRef { in_what_type, .. }
+            | Self::DeniedDependencySource { in_what_type, .. }
+            | Self::UnstableDependency { in_what_type, .. }
+            | Self::DeniedExternalTypeRef { in_what_type, .. }
+            | Self::HiddenModule { in_what_type, .. }
+            | Self::HiddenItem { in_what_type, .. }
+            | Self::PatternType { in_what_type, .. }
+            | Self::ReExportedCrate { in_what_type, .. }
+            | Self::MultipleCrateVersions { in_what_type, .. } => Some(in_what_type),
+            Self::FieldsStripped { .. }
+            | Self::UnusedApproval { .. }
+            | Self::DuplicateApproved { .. } => None,
+        }
+    }
+
+    /// Returns the name of the external crate that the referenced type belongs to, if this
+    /// error is about an external type reference.
+    pub fn external_crate_name(&self) -> Option<&str> {
+        match self {
+            Self::UnapprovedExternalTypeRef { type_name, .. }
+            | Self::DeniedDependencySource { type_name, .. }
+            | Self::UnstableDependency { type_name, .. }
+            | Self::DeniedExternalTypeRef { type_name, .. } => Some(crate_name_of(type_name)),
+            Self::ReExportedCrate { crate_name, .. }
+            | Self::MultipleCrateVersions { crate_name, .. } => Some(crate_name),
+            Self::FieldsStripped { .. }
+            | Self::HiddenModule { .. }
+            | Self::HiddenItem { .. }
+            | Self::PatternType { .. }
+            | Self::UnusedApproval { .. }
+            | Self::DuplicateApproved { .. } => None,
+        }
+    }
+
+    /// Returns the dependency that the referenced external type comes from, if known.
+    pub fn dependency(&self) -> Option<&Dependency> {
+        match self {
+            Self::UnapprovedExternalTypeRef { dependency, .. }
+            | Self::DeniedExternalTypeRef { dependency, .. }
+            | Self::ReExportedCrate { dependency, .. } => dependency.as_ref(),
+            Self::DeniedDependencySource { dependency, .. }
+            | Self::UnstableDependency { dependency, .. } => Some(dependency),
+            Self::FieldsStripped { .. }
+            | Self::MultipleCrateVersions { .. }
+            | Self::HiddenModule { .. }
+            | Self::HiddenItem { .. }
+            | Self::PatternType { .. }
+            | Self::UnusedApproval { .. }
+            | Self::DuplicateApproved { .. } => None,
         }
     }
 
     pub fn location(&self) -> Option<&Span> {
         match self {
             Self::UnapprovedExternalTypeRef { location, .. }
+            | Self::DeniedDependencySource { location, .. }
+            | Self::UnstableDependency { location, .. }
+            | Self::DeniedExternalTypeRef { location, .. }
             | Self::HiddenModule { location, .. }
-            | Self::HiddenItem { location, .. } => location.as_ref(),
-            Self::FieldsStripped { .. } => None,
+            | Self::HiddenItem { location, .. }
+            | Self::PatternType { location, .. }
+            | Self::ReExportedCrate { location, .. }
+            | Self::MultipleCrateVersions { location, .. } => location.as_ref(),
+            Self::FieldsStripped { .. }
+            | Self::UnusedApproval { .. }
+            | Self::DuplicateApproved { .. } => None,
         }
     }
 
     fn sort_key(&self) -> &str {
         match self {
-            Self::UnapprovedExternalTypeRef { sort_key, .. } => sort_key.as_ref(),
-            Self::FieldsStripped { type_name } | Self::HiddenModule { type_name, .. } => {
+            Self::UnapprovedExternalTypeRef { sort_key, .. }
+            | Self::DeniedDependencySource { sort_key, .. }
+            | Self::UnstableDependency { sort_key, .. }
+            | Self::DeniedExternalTypeRef { sort_key, .. }
+            | Self::ReExportedCrate { sort_key, .. }
+            | Self::MultipleCrateVersions { sort_key, .. }
+            | Self::UnusedApproval { sort_key, .. }
+            | Self::DuplicateApproved { sort_key, .. } => sort_key.as_ref(),
+            Self::FieldsStripped { type_name, .. } | Self::HiddenModule { type_name, .. } => {
                 type_name.as_ref()
             }
-            Self::HiddenItem { sort_key, .. } => sort_key.as_ref(),
+            Self::HiddenItem { sort_key, .. } | Self::PatternType { sort_key, .. } => {
+                sort_key.as_ref()
+            }
+        }
+    }
+
+    /// Returns the other types that this error is about besides [`Self::type_name`].
+    pub fn grouped_types(&self) -> &[String] {
+        match self {
+            Self::UnapprovedExternalTypeRef { grouped_types, .. }
+            | Self::DeniedDependencySource { grouped_types, .. }
+            | Self::UnstableDependency { grouped_types, .. }
+            | Self::DeniedExternalTypeRef { grouped_types, .. } => grouped_types,
+            Self::FieldsStripped { .. }
+            | Self::HiddenModule { .. }
+            | Self::HiddenItem { .. }
+            | Self::PatternType { .. }
+            | Self::ReExportedCrate { .. }
+            | Self::MultipleCrateVersions { .. }
+            | Self::UnusedApproval { .. }
+            | Self::DuplicateApproved { .. } => &[],
+        }
+    }
+
+    /// Returns every external type that this error is about, starting with
+    /// [`Self::type_name`].
+    pub fn type_names(&self) -> impl Iterator<Item = &str> {
+        iter::once(self.type_name()).chain(self.grouped_types().iter().map(String::as_str))
+    }
+
+    /// Returns the crate name and path of each external type that this error is about.
+    pub fn external_types(&self) -> Vec<(&str, &str)> {
+        let Some(crate_name) = self.external_crate_name() else {
+            return Vec::new();
+        };
+        iter::once((crate_name, self.type_name()))
+            .chain(
+                self.grouped_types()
+                    .iter()
+                    .map(|name| (crate_name_of(name), name.as_str())),
+            )
+            .collect()
+    }
+
+    /// Describes the types that the error is about for its headline, such as
+    /// ``type `external_lib::SomeStruct` `` or ``types in `SimpleGenericTrait<SomeStruct>` ``.
+    fn describe_types(&self) -> String {
+        match self {
+            Self::UnapprovedExternalTypeRef {
+                referenced_as: Some(referenced_as),
+                grouped_types,
+                ..
+            }
+            | Self::DeniedDependencySource {
+                referenced_as: Some(referenced_as),
+                grouped_types,
+                ..
+            }
+            | Self::UnstableDependency {
+                referenced_as: Some(referenced_as),
+                grouped_types,
+                ..
+            }
+            | Self::DeniedExternalTypeRef {
+                referenced_as: Some(referenced_as),
+                grouped_types,
+                ..
+            } if !grouped_types.is_empty() => format!("types in `{referenced_as}`"),
+            _ => format!("type `{}`", self.type_name()),
         }
     }
 
     pub fn fmt_headline(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::UnapprovedExternalTypeRef { type_name, .. } => {
+            Self::UnapprovedExternalTypeRef { .. } => {
+                write!(
+                    f,
+                    "Unapproved external {} referenced in public API",
+                    self.describe_types()
+                )
+            }
+            Self::DeniedDependencySource { dependency, .. } => {
+                write!(
+                    f,
+                    "External {} from {} dependency `{}` referenced in public API. Types from {} dependencies are denied by config",
+                    self.describe_types(),
+                    dependency.source_kind,
+                    dependency.package_name,
+                    dependency.source_kind
+                )
+            }
+            Self::UnstableDependency { dependency, .. } => {
+                write!(
+                    f,
+                    "External {} from pre-1.0 dependency `{} {}` referenced in public API. Allowing types from crates below 1.0 must be acknowledged since `deny_unstable_crates` is set",
+                    self.describe_types(),
+                    dependency.package_name,
+                    dependency.version
+                )
+            }
+            Self::DeniedExternalTypeRef {
+                pattern,
+                denied_by,
+                grouped_types,
+                ..
+            } => {
+                let matches = if grouped_types.is_empty() {
+                    "It matches"
+                } else {
+                    "They match"
+                };
                 write!(
                     f,
-                    "Unapproved external type `{type_name}` referenced in public API"
+                    "Denied external {} referenced in public API. {matches} `{pattern}` in `{denied_by}`",
+                    self.describe_types()
                 )
             }
             Self::HiddenModule {
@@ -277,12 +1359,50 @@ impl ValidationError {
                     "{what} {in_what_type} references a hidden item. Items marked `#[doc(hidden)]` cannot be checked for external types"
                 )
             }
-            Self::FieldsStripped { type_name } => {
+            Self::PatternType {
+                what, in_what_type, ..
+            } => {
+                write!(
+                    f,
+                    "{what} {in_what_type} uses a pattern type. Pattern types are unstable and internal to rustc, so only their base type is checked for external types"
+                )
+            }
+            Self::ReExportedCrate { crate_name, .. } => {
+                write!(
+                    f,
+                    "External crate `{crate_name}` is re-exported in public API, which exposes every public item in it. Only an `allowed_external_types` entry of exactly `{crate_name}` allows this"
+                )
+            }
+            Self::MultipleCrateVersions {
+                crate_name,
+                type_name,
+                versions,
+                ..
+            } => {
+                write!(
+                    f,
+                    "External type `{type_name}` is from a different version of `{crate_name}` than other types in public API. Versions {} of `{crate_name}` are in the dependency graph",
+                    describe_versions(versions)
+                )
+            }
+            Self::FieldsStripped { type_name, .. } => {
                 write!(
                     f,
                     "Fields on `{type_name}` marked `#[doc(hidden)]` cannot be checked for external types"
                 )
             }
+            Self::UnusedApproval { pattern, .. } => {
+                write!(
+                    f,
+                    "`{pattern}` in `allowed_external_types` doesn't allow any type referenced in public API, so it can be removed"
+                )
+            }
+            Self::DuplicateApproved { pattern, .. } => {
+                write!(
+                    f,
+                    "`{pattern}` is listed more than once in `allowed_external_types`"
+                )
+            }
         }
     }
 
@@ -290,16 +1410,220 @@ impl ValidationError {
         match self {
             Self::UnapprovedExternalTypeRef {
                 what, in_what_type, ..
+            }
+            | Self::DeniedDependencySource {
+                what, in_what_type, ..
+            }
+            | Self::UnstableDependency {
+                what, in_what_type, ..
+            }
+            | Self::DeniedExternalTypeRef {
+                what, in_what_type, ..
             } => format!("in {} `{}`", what, in_what_type).into(),
-            Self::FieldsStripped { .. } => "".into(),
+            Self::FieldsStripped { .. }
+            | Self::UnusedApproval { .. }
+            | Self::DuplicateApproved { .. } => "".into(),
             Self::HiddenModule {
                 what, in_what_type, ..
             }
             | Self::HiddenItem {
                 what, in_what_type, ..
+            }
+            | Self::PatternType {
+                what, in_what_type, ..
+            }
+            | Self::ReExportedCrate {
+                what, in_what_type, ..
+            }
+            | Self::MultipleCrateVersions {
+                what, in_what_type, ..
             } => format!("in {} `{}`", what, in_what_type).into(),
         }
     }
+
+    /// Additional notes that are printed below the error context.
+    pub fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if let Self::UnapprovedExternalTypeRef {
+            referenced_as: Some(referenced_as),
+            ..
+        }
+        | Self::DeniedDependencySource {
+            referenced_as: Some(referenced_as),
+            ..
+        }
+        | Self::UnstableDependency {
+            referenced_as: Some(referenced_as),
+            ..
+        }
+        | Self::DeniedExternalTypeRef {
+            referenced_as: Some(referenced_as),
+            ..
+        } = self
+        {
+            // Errors for several types already name how they were referenced in the headline
+            if self.grouped_types().is_empty() {
+                notes.push(format!("referenced as `{referenced_as}`"));
+            } else {
+                let types: Vec<String> =
+                    self.type_names().map(|name| format!("`{name}`")).collect();
+                notes.push(format!("the external types are {}", join_list(&types)));
+            }
+        }
+        if let Self::UnapprovedExternalTypeRef { alias_chain, .. }
+        | Self::DeniedDependencySource { alias_chain, .. }
+        | Self::UnstableDependency { alias_chain, .. }
+        | Self::DeniedExternalTypeRef { alias_chain, .. } = self
+        {
+            if !alias_chain.is_empty() {
+                let chain = alias_chain
+                    .iter()
+                    .map(|alias| format!("`{alias}`"))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                let aliases = if alias_chain.len() == 1 {
+                    "alias"
+                } else {
+                    "aliases"
+                };
+                notes.push(format!("resolved through type {aliases} {chain}"));
+            }
+        }
+        if let Self::UnapprovedExternalTypeRef {
+            macro_invocation: Some(name),
+            ..
+        }
+        | Self::DeniedDependencySource {
+            macro_invocation: Some(name),
+            ..
+        }
+        | Self::UnstableDependency {
+            macro_invocation: Some(name),
+            ..
+        }
+        | Self::DeniedExternalTypeRef {
+            macro_invocation: Some(name),
+            ..
+        } = self
+        {
+            notes.push(format!("generated by an invocation of the macro `{name}!`"));
+        }
+        if let Self::DeniedExternalTypeRef {
+            reason: Some(reason),
+            ..
+        } = self
+        {
+            notes.push(format!("denied because: {reason}"));
+        }
+        if let Some(cfg) = self.cfg() {
+            notes.push(format!("available on {} only", describe_cfg(cfg)));
+        }
+        if let Self::UnapprovedExternalTypeRef {
+            dependency: Some(dependency),
+            ..
+        }
+        | Self::DeniedExternalTypeRef {
+            dependency: Some(dependency),
+            ..
+        }
+        | Self::ReExportedCrate {
+            dependency: Some(dependency),
+            ..
+        } = self
+        {
+            notes.push(format!("from dependency `{dependency}`"));
+        }
+        if let Self::UnapprovedExternalTypeRef {
+            dependency:
+                Some(Dependency {
+                    package_name,
+                    public: Some(false),
+                    ..
+                }),
+            ..
+        } = self
+        {
+            notes.push(format!(
+                "if exposing types from `{package_name}` is intended, mark it with `public = true` in Cargo.toml"
+            ));
+        }
+        if let found_with @ [_, ..] = self.found_with() {
+            notes.push(format!("only found with {}", join_list(found_with)));
+        }
+        if let Self::UnapprovedExternalTypeRef {
+            dependency:
+                Some(Dependency {
+                    package_name,
+                    enabled_by_features: Some(features),
+                    ..
+                }),
+            ..
+        } = self
+        {
+            notes.push(format!(
+                "`{package_name}` is an optional dependency, {}",
+                describe_enabling_features(features)
+            ));
+        }
+        notes
+    }
+}
+
+/// Returns the name of the crate that the type with the given path is from.
+fn crate_name_of(type_name: &str) -> &str {
+    &type_name[0..type_name.find("::").unwrap_or(type_name.len())]
+}
+
+/// Lists versions as `0.1.0 and 0.2.0`, or `0.1.0, 0.2.0, and 1.0.0` for more than two.
+fn describe_versions(versions: &[Version]) -> String {
+    let versions: Vec<String> = versions.iter().map(ToString::to_string).collect();
+    join_list(&versions)
+}
+
+/// Joins items as `a and b`, or `a, b, and c` for more than two.
+fn join_list(items: &[String]) -> String {
+    match items {
+        [] => "???".into(),
+        [only] => only.clone(),
+        [first, second] => format!("{first} and {second}"),
+        [rest @ .., last] => format!("{}, and {last}", rest.join(", ")),
+    }
+}
+
+/// Describes which features enable an optional dependency, such as "enabled by feature `x`
+/// (not enabled by default)".
+fn describe_enabling_features(features: &[String]) -> String {
+    let enabled_by_default = features.iter().any(|feature| feature == "default");
+    let features: Vec<String> = features
+        .iter()
+        .filter(|feature| *feature != "default")
+        .map(|feature| format!("`{feature}`"))
+        .collect();
+    let default = if enabled_by_default {
+        "enabled by default"
+    } else {
+        "not enabled by default"
+    };
+    match features.as_slice() {
+        [] if enabled_by_default => default.into(),
+        [] => "not enabled by any feature".into(),
+        [only] => format!("enabled by feature {only} ({default})"),
+        [rest @ .., last] => format!(
+            "enabled by features {} or {last} ({default})",
+            rest.join(", ")
+        ),
+    }
+}
+
+/// Describes a `cfg` the way rustdoc does, such as "crate feature `x`" for `feature = "x"`.
+pub fn describe_cfg(cfg: &str) -> String {
+    match cfg
+        .strip_prefix("feature = \"")
+        .and_then(|feature| feature.strip_suffix('"'))
+    {
+        Some(feature) if !feature.contains('"') => format!("crate feature `{feature}`"),
+        _ => format!("`cfg({cfg})`"),
+    }
 }
 
 fn location_sort_key(location: Option<&Span>) -> String {
@@ -347,6 +1671,13 @@ impl fmt::Display for ValidationError {
 pub struct ErrorPrinter {
     workspace_root: PathBuf,
     file_cache: HashMap<PathBuf, String>,
+    /// Whether to read source files to show the code each error refers to
+    show_context: bool,
+    sort_by: SortBy,
+    /// Number of findings to output before the rest are only counted
+    max_errors: Option<usize>,
+    workspace_summaries: Vec<ExternalTypeSummary>,
+    pattern_stats: Vec<PatternReport>,
 }
 
 impl ErrorPrinter {
@@ -354,9 +1685,46 @@ impl ErrorPrinter {
         Self {
             workspace_root: workspace_root.into(),
             file_cache: HashMap::new(),
+            show_context: true,
+            sort_by: SortBy::default(),
+            max_errors: None,
+            workspace_summaries: Vec::new(),
+            pattern_stats: Vec::new(),
         }
     }
 
+    /// Sets the summaries of external types across workspace members to output after the errors.
+    pub fn with_workspace_summaries(mut self, summaries: Vec<ExternalTypeSummary>) -> Self {
+        self.workspace_summaries = summaries;
+        self
+    }
+
+    /// Sets the usage of allowed patterns to output after the errors.
+    pub fn with_pattern_stats(mut self, pattern_stats: Vec<PatternReport>) -> Self {
+        self.pattern_stats = pattern_stats;
+        self
+    }
+
+    /// Sets the order in which errors are output.
+    pub fn with_sort_by(mut self, sort_by: SortBy) -> Self {
+        self.sort_by = sort_by;
+        self
+    }
+
+    /// Sets how many errors and warnings are output before the rest are only counted. The
+    /// summaries after them still include every finding.
+    pub fn with_max_errors(mut self, max_errors: Option<usize>) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Sets whether source code snippets are shown for each error. When disabled, only the
+    /// headline and location of each error are output, and source files are never read.
+    pub fn with_context(mut self, show_context: bool) -> Self {
+        self.show_context = show_context;
+        self
+    }
+
     fn get_file_contents(&mut self, path: &Path) -> Result<&str> {
         if !self.file_cache.contains_key(path) {
             let full_file_name = self.workspace_root.join(path).canonicalize()?;
@@ -368,26 +1736,29 @@ impl ErrorPrinter {
         Ok(self.file_cache.get(path).unwrap())
     }
 
-    fn print_error_level(level: ErrorLevel) {
+    pub(crate) fn print_error_level(out: &mut dyn Write, level: ErrorLevel) -> Result<()> {
         use owo_colors::{OwoColorize, Stream};
         match level {
             ErrorLevel::Error => {
-                print!(
+                write!(
+                    out,
                     "{}",
                     "error: "
                         .if_supports_color(Stream::Stdout, |text| text.red())
                         .if_supports_color(Stream::Stdout, |text| text.bold())
-                );
+                )?;
             }
             ErrorLevel::Warning => {
-                print!(
+                write!(
+                    out,
                     "{}",
                     "warning: "
                         .if_supports_color(Stream::Stdout, |text| text.yellow())
                         .if_supports_color(Stream::Stdout, |text| text.bold())
-                );
+                )?;
             }
         }
+        Ok(())
     }
 
     /// Outputs a human readable error with file location context
@@ -402,8 +1773,15 @@ impl ErrorPrinter {
     ///    | ^-----------------------------------------------------------------------^
     ///    |
     ///    = in argument named `_one` of `test_crate::external_in_fn_input`
+    ///    = note: from dependency `external-lib 0.1.0 (path)`
     /// ```
-    pub fn pretty_print_error_context(&mut self, location: &Span, subtext: &str) {
+    pub fn pretty_print_error_context(
+        &mut self,
+        out: &mut dyn Write,
+        location: &Span,
+        subtext: &str,
+        notes: &[String],
+    ) -> Result<()> {
         match self.get_file_contents(&location.filename) {
             Ok(file_contents) => {
                 let begin = Self::position_from_line_col(file_contents, location.begin);
@@ -414,6 +1792,12 @@ impl ErrorPrinter {
                 let variant = pest::error::ErrorVariant::<()>::CustomError {
                     message: subtext.into(),
                 };
+                // Align the notes with the `=` that Pest places below the line numbers
+                let last_line = end
+                    .as_ref()
+                    .or(begin.as_ref())
+                    .map(|p| p.line_col().0)
+                    .unwrap_or_default();
                 let err_context = match (begin, end) {
                     (Some(b), Some(e)) => {
                         Some(pest::error::Error::new_from_span(variant, b.span(&e)))
@@ -422,27 +1806,59 @@ impl ErrorPrinter {
                     _ => None,
                 };
                 if let Some(err_context) = err_context {
-                    println!(
-                        "{}\n",
+                    writeln!(
+                        out,
+                        "{}",
                         err_context.with_path(&location.filename.to_string_lossy())
-                    );
+                    )?;
+                    let indent = " ".repeat(last_line.to_string().len());
+                    for note in notes {
+                        writeln!(out, "{indent} = note: {note}")?;
+                    }
+                    writeln!(out)?;
                 }
             }
             Err(err) => {
-                Self::print_error_level(ErrorLevel::Error);
-                println!("{subtext}");
-                println!(
+                Self::print_error_level(out, ErrorLevel::Error)?;
+                writeln!(out, "{subtext}")?;
+                writeln!(
+                    out,
                     "  --> {}:{}:{}",
                     location.filename.to_string_lossy(),
                     location.begin.0,
                     location.begin.1 + 1
-                );
-                println!("   | Failed to load {:?}", location.filename);
-                println!("   | relative to {:?}", self.workspace_root);
-                println!("   | to provide error message context.");
-                println!("   | Cause: {err:?}");
+                )?;
+                writeln!(out, "   | Failed to load {:?}", location.filename)?;
+                writeln!(out, "   | relative to {:?}", self.workspace_root)?;
+                writeln!(out, "   | to provide error message context.")?;
+                writeln!(out, "   | Cause: {err:?}")?;
+                for note in notes {
+                    writeln!(out, "   = note: {note}")?;
+                }
             }
         }
+        Ok(())
+    }
+
+    /// Outputs the location of an error without reading its source file
+    ///
+    /// # Example output
+    ///
+    /// ```text
+    /// error: Unapproved external type `external_lib::SomeStruct` referenced in public API
+    ///   --> test-crate/src/lib.rs:38:1
+    ///    = in argument named `_one` of `test_crate::external_in_fn_input`
+    /// ```
+    fn print_error_location(out: &mut dyn Write, location: &Span, subtext: &str) -> Result<()> {
+        writeln!(
+            out,
+            "  --> {}:{}:{}",
+            location.filename.to_string_lossy(),
+            location.begin.0,
+            location.begin.1 + 1
+        )?;
+        writeln!(out, "   = {subtext}")?;
+        Ok(())
     }
 
     fn position_from_line_col(contents: &str, (line, col): (usize, usize)) -> Option<Position> {
@@ -462,22 +1878,212 @@ impl ErrorPrinter {
         None
     }
 
-    pub fn pretty_print_errors(&mut self, errors: &ValidationErrors) {
-        for error in errors.iter() {
-            Self::print_error_level(error.level());
-            println!("{}", error);
-            if let Some(location) = error.location() {
-                self.pretty_print_error_context(location, error.subtext().as_ref())
+    /// Outputs a table of how many violations each external crate contributes
+    ///
+    /// # Example output
+    ///
+    /// ```text
+    /// Violations by external crate:
+    ///   Crate         Violations  Distinct types
+    ///   external_lib          48               7
+    /// ```
+    fn print_crate_summaries(out: &mut dyn Write, errors: &ValidationErrors) -> Result<()> {
+        let summaries = errors.crate_summaries();
+        if summaries.is_empty() {
+            return Ok(());
+        }
+        let crate_width = summaries
+            .iter()
+            .map(|s| s.crate_name.len())
+            .chain(Some("Crate".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(out, "Violations by external crate:")?;
+        writeln!(
+            out,
+            "  {:crate_width$}  Violations  Distinct types",
+            "Crate"
+        )?;
+        for summary in &summaries {
+            writeln!(
+                out,
+                "  {:crate_width$}  {:>10}  {:>14}",
+                summary.crate_name, summary.violations, summary.distinct_types
+            )?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// Outputs which workspace members reference each external type, if multiple members were
+    /// checked
+    ///
+    /// # Example output
+    ///
+    /// ```text
+    /// External types by workspace member:
+    ///   Type                        Violations  Members
+    ///   external_lib::SomeStruct             3  crate-a, crate-b
+    /// ```
+    fn print_workspace_summaries(
+        out: &mut dyn Write,
+        summaries: &[ExternalTypeSummary],
+    ) -> Result<()> {
+        if summaries.is_empty() {
+            return Ok(());
+        }
+        let type_width = summaries
+            .iter()
+            .map(|s| s.type_name.len())
+            .chain(Some("Type".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(out, "External types by workspace member:")?;
+        writeln!(out, "  {:type_width$}  Violations  Members", "Type")?;
+        for summary in summaries {
+            writeln!(
+                out,
+                "  {:type_width$}  {:>10}  {}",
+                summary.type_name,
+                summary.violations,
+                summary.members.join(", ")
+            )?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// Outputs how many findings each suppression mechanism hid, if any. Nothing is output when
+    /// there are no errors or warnings so that clean runs stay quiet.
+    ///
+    /// # Example output
+    ///
+    /// ```text
+    /// Suppressed findings:
+    ///   allowed_external_types  12
+    /// ```
+    fn print_suppressed_counts(out: &mut dyn Write, errors: &ValidationErrors) -> Result<()> {
+        let counts = errors.suppressed_counts();
+        if errors.is_empty() || counts.is_empty() {
+            return Ok(());
+        }
+        let width = counts
+            .keys()
+            .map(|mechanism| mechanism.to_string().len())
+            .max()
+            .unwrap_or_default();
+        writeln!(out, "Suppressed findings:")?;
+        for (mechanism, count) in counts {
+            writeln!(out, "  {:width$}  {count}", mechanism.to_string())?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// Outputs which types each allowed pattern matched, and a narrower replacement for patterns
+    /// that are broader than the types they matched
+    ///
+    /// # Example output
+    ///
+    /// ```text
+    /// Allowed pattern usage:
+    ///   `tokio::*`: 2 matches of 1 type
+    ///     Reason: Part of the runtime integration
+    ///     tokio::time::Duration
+    ///     Broader than needed. Consider `tokio::time::Duration` instead.
+    /// ```
+    fn print_pattern_stats(out: &mut dyn Write, pattern_stats: &[PatternReport]) -> Result<()> {
+        if pattern_stats.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "Allowed pattern usage:")?;
+        for report in pattern_stats {
+            writeln!(
+                out,
+                "  `{}`: {} {} of {} {}",
+                report.pattern,
+                report.matches,
+                if report.matches == 1 {
+                    "match"
+                } else {
+                    "matches"
+                },
+                report.types.len(),
+                if report.types.len() == 1 {
+                    "type"
+                } else {
+                    "types"
+                },
+            )?;
+            if let Some(reason) = &report.reason {
+                writeln!(out, "    Reason: {reason}")?;
             }
+            for type_name in &report.types {
+                writeln!(out, "    {type_name}")?;
+            }
+            if let Some(suggestion) = &report.suggestion {
+                writeln!(
+                    out,
+                    "    Broader than needed. Consider `{suggestion}` instead."
+                )?;
+            }
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    /// Outputs all errors followed by a summary to `out`
+    pub fn pretty_print_errors(
+        &mut self,
+        out: &mut dyn Write,
+        errors: &ValidationErrors,
+    ) -> Result<()> {
+        let sorted = errors.sorted_by(self.sort_by);
+        let shown = self.max_errors.unwrap_or(sorted.len()).min(sorted.len());
+        for error in &sorted[..shown] {
+            Self::print_error_level(out, error.level())?;
+            writeln!(out, "{}", error)?;
+            if let (false, Some(location)) = (self.show_context, error.location()) {
+                Self::print_error_location(out, location, error.subtext().as_ref())?;
+            } else if let Some(location) = error.location() {
+                self.pretty_print_error_context(
+                    out,
+                    location,
+                    error.subtext().as_ref(),
+                    &error.notes(),
+                )?;
+            }
+        }
+        if shown < sorted.len() {
+            writeln!(
+                out,
+                "... and {} more not shown, because of `--max-errors {shown}`",
+                sorted.len() - shown
+            )?;
+            writeln!(out)?;
         }
+        Self::print_crate_summaries(out, errors)?;
+        Self::print_workspace_summaries(out, &self.workspace_summaries)?;
+        Self::print_suppressed_counts(out, errors)?;
+        Self::print_pattern_stats(out, &self.pattern_stats)?;
         if !errors.is_empty() {
             use owo_colors::{OwoColorize, Stream};
+            let cleanliness = errors.cleanliness();
+            writeln!(
+                out,
+                "{} of {} public items ({:.1}%) are free of unapproved external types",
+                cleanliness.clean_items,
+                cleanliness.public_items,
+                cleanliness.percent_clean()
+            )?;
             let (error_count, warning_count) = (errors.error_count(), errors.warning_count());
-            println!(
+            writeln!(
+                out,
                 "{error_count} {errors}, {warning_count} {warnings} emitted",
                 errors = "errors".if_supports_color(Stream::Stdout, |text| text.red()),
                 warnings = "warnings".if_supports_color(Stream::Stdout, |text| text.yellow())
-            );
+            )?;
         }
+        Ok(())
     }
 }
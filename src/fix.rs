@@ -0,0 +1,287 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Updates the `allowed_external_types` of a config in place to match what the crate uses.
+//!
+//! Patterns for the unapproved types are added to the end of the list, so that they take
+//! precedence over any negated patterns before them, and patterns that didn't allow anything
+//! are removed. The rest of the document, including comments, is left as it is.
+
+use crate::fmt_config::{raw_str, INDENT};
+use anyhow::{bail, Context, Result};
+use toml_edit::{Array, DocumentMut, Item, Table, Value};
+
+const ALLOWED_EXTERNAL_TYPES: &str = "allowed_external_types";
+
+/// Changes that [`fix_config`] made to a config.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ConfigFix {
+    /// Patterns that were added
+    pub added: Vec<String>,
+    /// Patterns that were removed because they didn't allow any types
+    pub removed: Vec<String>,
+}
+
+impl ConfigFix {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Fixes the config in the TOML document `contents`, where the config is in the table at
+/// `table_path` (which is empty for standalone config files). Tables on the path are created
+/// if they don't exist, and a key on the path that's spelled with `-` rather than `_` is used
+/// if that's how the document spells it.
+///
+/// The `allow` patterns are added, and patterns that aren't negated and for which `is_used`
+/// returns false are removed.
+pub fn fix_config(
+    contents: &str,
+    table_path: &[&str],
+    allow: &[String],
+    is_used: impl Fn(&str) -> bool,
+) -> Result<(String, ConfigFix)> {
+    let mut document: DocumentMut = contents.parse().context("failed to parse config file")?;
+    let mut table = document.as_table_mut();
+    for (i, &key) in table_path.iter().enumerate() {
+        let hyphenated = key.replace('_', "-");
+        let key = if !table.contains_key(key) && table.contains_key(&hyphenated) {
+            hyphenated.as_str()
+        } else {
+            key
+        };
+        let item = table.entry(key).or_insert_with(|| {
+            let mut new_table = Table::new();
+            // Only the innermost table needs a header of its own
+            new_table.set_implicit(i + 1 < table_path.len());
+            Item::Table(new_table)
+        });
+        table = match item.as_table_mut() {
+            Some(table) => table,
+            None => bail!("`{}` must be a table", table_path[..=i].join(".")),
+        };
+    }
+    let item = table
+        .entry(ALLOWED_EXTERNAL_TYPES)
+        .or_insert_with(|| Item::Value(Value::Array(Array::new())));
+    let Some(array) = item.as_array_mut() else {
+        bail!("`{ALLOWED_EXTERNAL_TYPES}` must be an array");
+    };
+
+    let mut fix = ConfigFix::default();
+    for i in (0..array.len()).rev() {
+        let pattern = entry_pattern(array.get(i).unwrap())?;
+        if !pattern.starts_with('!') && !is_used(pattern) {
+            fix.removed.insert(0, pattern.to_string());
+            remove_entry(array, i);
+        }
+    }
+    for pattern in allow {
+        if !array
+            .iter()
+            .any(|value| entry_pattern(value).ok() == Some(pattern))
+        {
+            array.push(pattern.as_str());
+            fix.added.push(pattern.clone());
+        }
+    }
+    if !fix.added.is_empty() {
+        put_entries_on_own_lines(array);
+    }
+    if array.is_empty() {
+        array.set_trailing("");
+        array.set_trailing_comma(false);
+    }
+    Ok((document.to_string(), fix))
+}
+
+/// Returns the pattern of an entry, which is either a string or a `{ pattern, reason }` table.
+fn entry_pattern(value: &Value) -> Result<&str> {
+    match value {
+        Value::String(pattern) => Ok(pattern.value()),
+        Value::InlineTable(table) => match table.get("pattern").and_then(Value::as_str) {
+            Some(pattern) => Ok(pattern),
+            None => bail!("entries of `{ALLOWED_EXTERNAL_TYPES}` must have a `pattern` string"),
+        },
+        _ => bail!(
+            "`{ALLOWED_EXTERNAL_TYPES}` must only contain strings or `{{ pattern, reason }}` tables"
+        ),
+    }
+}
+
+/// Removes the entry at `index` along with its comments, keeping the comment on the same line
+/// as the entry before it.
+fn remove_entry(array: &mut Array, index: usize) {
+    let removed = array.remove(index);
+    let removed_prefix = raw_str(removed.decor().prefix());
+    let previous_comment = removed_prefix.split_once('\n').map_or("", |(line, _)| line);
+    // The next entry's prefix starts with the rest of the removed entry's line
+    let rest_of_next = |prefix: &str| match prefix.split_once('\n') {
+        Some((_, rest)) => format!("{previous_comment}\n{rest}"),
+        None => format!("{previous_comment}{prefix}"),
+    };
+    match array.get_mut(index) {
+        Some(next) => {
+            let prefix = rest_of_next(raw_str(next.decor().prefix()));
+            next.decor_mut().set_prefix(prefix);
+        }
+        None => {
+            let trailing = rest_of_next(raw_str(Some(array.trailing())));
+            array.set_trailing(trailing);
+        }
+    }
+}
+
+/// Puts each entry of `array` on a line of its own, unless they already are.
+fn put_entries_on_own_lines(array: &mut Array) {
+    let own_line = format!("\n{INDENT}");
+    for value in array.iter_mut() {
+        let prefix = raw_str(value.decor().prefix()).to_string();
+        if !prefix.contains('\n') {
+            value
+                .decor_mut()
+                .set_prefix(format!("{}{own_line}", prefix.trim_end()));
+            value.decor_mut().set_suffix("");
+        }
+    }
+    let trailing = raw_str(Some(array.trailing())).trim_end().to_string();
+    array.set_trailing(format!("{trailing}\n"));
+    array.set_trailing_comma(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fmt_config::CARGO_TOML_TABLE_PATH;
+    use pretty_assertions::assert_str_eq;
+
+    fn fix(contents: &str, table_path: &[&str], allow: &[&str], used: &[&str]) -> String {
+        let allow: Vec<String> = allow.iter().map(|&p| p.into()).collect();
+        fix_config(contents, table_path, &allow, |pattern| {
+            used.contains(&pattern)
+        })
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn fix_standalone_config() {
+        let config = r#"# Config for the crate
+allowed_external_types = [
+    # Why bytes is allowed
+    "bytes::*", # Trailing
+    "unused::*", # Unused
+    "!bytes::BytesMut",
+    { pattern = "http::*", reason = "Re-exported" },
+]
+"#;
+        let allow: Vec<String> = vec!["bytes::BytesMut".into(), "tokio::time::Duration".into()];
+        let (fixed, changes) =
+            fix_config(config, &[], &allow, |pattern| pattern != "unused::*").unwrap();
+        assert_str_eq!(
+            r#"# Config for the crate
+allowed_external_types = [
+    # Why bytes is allowed
+    "bytes::*", # Trailing
+    "!bytes::BytesMut",
+    { pattern = "http::*", reason = "Re-exported" },
+    "bytes::BytesMut",
+    "tokio::time::Duration",
+]
+"#,
+            fixed
+        );
+        assert_eq!(
+            ConfigFix {
+                added: vec!["bytes::BytesMut".into(), "tokio::time::Duration".into()],
+                removed: vec!["unused::*".into()],
+            },
+            changes
+        );
+    }
+
+    #[test]
+    fn fix_inline_arrays() {
+        assert_str_eq!(
+            "allowed_external_types = [\n    \"bytes::*\",\n    \"http::*\",\n]\n",
+            fix(
+                "allowed_external_types = [\"bytes::*\"]\n",
+                &[],
+                &["http::*"],
+                &["bytes::*"]
+            )
+        );
+        assert_str_eq!(
+            "allowed_external_types = []\n",
+            fix(
+                "allowed_external_types = [\n    \"bytes::*\", # Unused\n]\n",
+                &[],
+                &[],
+                &[]
+            )
+        );
+        assert_str_eq!(
+            "allowed_external_types = [\"bytes::*\"]\n",
+            fix(
+                "allowed_external_types = [\"bytes::*\"]\n",
+                &[],
+                &[],
+                &["bytes::*"]
+            )
+        );
+    }
+
+    #[test]
+    fn fix_cargo_toml_config() {
+        let manifest = r#"[package]
+name = "test-crate"
+
+[dependencies]
+bytes = "1"
+"#;
+        assert_str_eq!(
+            r#"[package]
+name = "test-crate"
+
+[package.metadata.cargo_check_external_types]
+allowed_external_types = [
+    "bytes::*",
+]
+
+[dependencies]
+bytes = "1"
+"#,
+            fix(manifest, CARGO_TOML_TABLE_PATH, &["bytes::*"], &[])
+        );
+
+        let manifest = r#"[package]
+name = "test-crate"
+
+[package.metadata.cargo-check-external-types]
+allow_std = false
+"#;
+        assert_str_eq!(
+            r#"[package]
+name = "test-crate"
+
+[package.metadata.cargo-check-external-types]
+allow_std = false
+allowed_external_types = [
+    "bytes::*",
+]
+"#,
+            fix(manifest, CARGO_TOML_TABLE_PATH, &["bytes::*"], &[])
+        );
+
+        let err = fix_config(
+            "[package]\nmetadata = 5\n",
+            CARGO_TOML_TABLE_PATH,
+            &[],
+            |_| true,
+        )
+        .unwrap_err();
+        assert_eq!("`package.metadata` must be a table", err.to_string());
+    }
+}
@@ -0,0 +1,402 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Rewrites a config into a normalized, canonical form.
+//!
+//! The `allowed_external_types`, `allowed_external_types_exact`, `denied_external_types`, and
+//! `denied_std_types` lists, as well as the lists in `allowed_external_types_in` and `target`
+//! tables, are sorted, exact duplicates are removed, and crate names are spelled the way they
+//! appear in type paths (with `_` rather than `-`). Comments on entries are kept with the entry
+//! they describe. Entries given as `{ pattern, reason }` tables keep their reason.
+
+use anyhow::{bail, Context, Result};
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Value};
+
+pub(crate) const INDENT: &str = "    ";
+
+/// Keys of the lists of type names and patterns that get formatted.
+const TYPE_LISTS: &[&str] = &[
+    "allowed_external_types",
+    "allowed_external_types_exact",
+    "denied_external_types",
+    "denied_std_types",
+];
+
+/// Key of the table of type lists that only apply to some items, which get formatted too.
+const SCOPED_TYPE_LISTS: &str = "allowed_external_types_in";
+
+/// Key of the tables of type lists that only apply to some targets, which get formatted too.
+const TARGET_TABLES: &str = "target";
+
+/// Path to the config table within a `Cargo.toml` file.
+pub const CARGO_TOML_TABLE_PATH: &[&str] = &["package", "metadata", "cargo_check_external_types"];
+
+/// Formats the config in the TOML document `contents`, where the config is in the table at
+/// `table_path` (which is empty for standalone config files).
+pub fn format_config(contents: &str, table_path: &[&str]) -> Result<String> {
+    let mut document: DocumentMut = contents.parse().context("failed to parse config file")?;
+    let mut item = document.as_item_mut();
+    for &key in table_path {
+        item = match item.get_mut(key) {
+            Some(item) => item,
+            None => bail!("failed to find `{}` in config file", table_path.join(".")),
+        };
+    }
+    format_type_lists(item, "")?;
+    if let Some(targets) = item
+        .as_table_like_mut()
+        .and_then(|table| table.get_mut(TARGET_TABLES))
+    {
+        let Some(targets) = targets.as_table_like_mut() else {
+            bail!("`{TARGET_TABLES}` must be a table");
+        };
+        for (target, lists) in targets.iter_mut() {
+            format_type_lists(lists, &format!("{TARGET_TABLES}.'{}'.", target.get()))?;
+        }
+    }
+    if let Some(scopes) = item
+        .as_table_like_mut()
+        .and_then(|table| table.get_mut(SCOPED_TYPE_LISTS))
+    {
+        let Some(scopes) = scopes.as_table_like_mut() else {
+            bail!("`{SCOPED_TYPE_LISTS}` must be a table");
+        };
+        for (scope, patterns) in scopes.iter_mut() {
+            let key = format!("{SCOPED_TYPE_LISTS}.\"{}\"", scope.get());
+            let Some(array) = patterns.as_array_mut() else {
+                bail!("`{key}` must be an array");
+            };
+            format_pattern_list(&key, array)?;
+        }
+    }
+    Ok(document.to_string())
+}
+
+/// Formats the [`TYPE_LISTS`] in the config table `item`, where `prefix` is the path to the
+/// table for error messages.
+fn format_type_lists(item: &mut Item, prefix: &str) -> Result<()> {
+    for key in TYPE_LISTS {
+        if let Some(patterns) = item
+            .as_table_like_mut()
+            .and_then(|table| table.get_mut(key))
+        {
+            let key = format!("{prefix}{key}");
+            let Some(array) = patterns.as_array_mut() else {
+                bail!("`{key}` must be an array");
+            };
+            format_pattern_list(&key, array)?;
+        }
+    }
+    Ok(())
+}
+
+/// An entry in a pattern list along with its comments.
+struct Entry {
+    pattern: String,
+    reason: Option<String>,
+    acknowledged: bool,
+    /// Comment lines above the entry
+    comments: Vec<String>,
+    /// Comment on the same line after the entry
+    trailing_comment: Option<String>,
+}
+
+fn format_pattern_list(key: &str, array: &mut Array) -> Result<()> {
+    let mut entries: Vec<Entry> = Vec::with_capacity(array.len());
+    for value in array.iter() {
+        let (pattern, reason, acknowledged) = match value {
+            Value::String(pattern) => (pattern.value().as_str(), None, false),
+            Value::InlineTable(table) => {
+                let Some(pattern) = table.get("pattern").and_then(Value::as_str) else {
+                    bail!("entries of `{key}` must have a `pattern` string");
+                };
+                let reason = match table.get("reason") {
+                    Some(reason) => match reason.as_str() {
+                        Some(reason) => Some(reason.to_string()),
+                        None => bail!("`reason` in `{key}` must be a string"),
+                    },
+                    None => None,
+                };
+                let acknowledged = match table.get("acknowledged") {
+                    Some(acknowledged) => match acknowledged.as_bool() {
+                        Some(acknowledged) => acknowledged,
+                        None => bail!("`acknowledged` in `{key}` must be a boolean"),
+                    },
+                    None => false,
+                };
+                (pattern, reason, acknowledged)
+            }
+            _ => bail!("`{key}` must only contain strings or `{{ pattern, reason }}` tables"),
+        };
+        let prefix = raw_str(value.decor().prefix());
+        // A comment on the same line as the previous entry ends up in this entry's prefix
+        let (same_line, above) = prefix.split_once('\n').unwrap_or((prefix, ""));
+        let mut comments = Vec::new();
+        match entries.last_mut() {
+            Some(previous) => previous.trailing_comment = comment(same_line),
+            // A comment after the opening bracket is kept with the first entry
+            None => comments.extend(comment(same_line)),
+        }
+        comments.extend(above.lines().filter_map(comment));
+        entries.push(Entry {
+            pattern: normalize_pattern(pattern),
+            reason,
+            acknowledged,
+            comments,
+            trailing_comment: comment(raw_str(value.decor().suffix())),
+        });
+    }
+    let trailing = raw_str(Some(array.trailing())).to_string();
+    let (same_line, dangling) = trailing.split_once('\n').unwrap_or((&trailing, ""));
+    if let Some(last) = entries.last_mut() {
+        last.trailing_comment = last.trailing_comment.take().or_else(|| comment(same_line));
+    }
+    let dangling: Vec<String> = dangling.lines().filter_map(comment).collect();
+
+    // Negated patterns only exclude types from the patterns before them, so the order of a list
+    // that has any is kept as it is
+    let ordered = entries.iter().any(|entry| entry.pattern.starts_with('!'));
+    if !ordered {
+        // Stable sort so that comments of duplicates are merged in their original order
+        entries.sort_by(|a, b| a.pattern.cmp(&b.pattern));
+    }
+    entries.dedup_by(|duplicate, kept| {
+        if duplicate.pattern == kept.pattern {
+            kept.comments.append(&mut duplicate.comments);
+            kept.reason = kept.reason.take().or(duplicate.reason.take());
+            kept.acknowledged |= duplicate.acknowledged;
+            kept.trailing_comment = kept
+                .trailing_comment
+                .take()
+                .or(duplicate.trailing_comment.take());
+            true
+        } else {
+            false
+        }
+    });
+
+    array.clear();
+    let mut previous_trailing_comment: Option<String> = None;
+    for entry in entries {
+        let mut prefix = String::new();
+        if let Some(trailing_comment) = previous_trailing_comment.take() {
+            prefix.push(' ');
+            prefix.push_str(&trailing_comment);
+        }
+        prefix.push('\n');
+        for comment in &entry.comments {
+            prefix.push_str(INDENT);
+            prefix.push_str(comment);
+            prefix.push('\n');
+        }
+        prefix.push_str(INDENT);
+        let mut value = if entry.reason.is_some() || entry.acknowledged {
+            let mut table = InlineTable::new();
+            table.insert("pattern", entry.pattern.into());
+            if let Some(reason) = entry.reason {
+                table.insert("reason", reason.into());
+            }
+            if entry.acknowledged {
+                table.insert("acknowledged", true.into());
+            }
+            table.fmt();
+            Value::InlineTable(table)
+        } else {
+            Value::from(entry.pattern)
+        };
+        value.decor_mut().set_prefix(prefix);
+        value.decor_mut().set_suffix("");
+        array.push_formatted(value);
+        previous_trailing_comment = entry.trailing_comment;
+    }
+
+    let mut trailing = String::new();
+    if let Some(trailing_comment) = previous_trailing_comment {
+        trailing.push(' ');
+        trailing.push_str(&trailing_comment);
+    }
+    if !array.is_empty() || !dangling.is_empty() {
+        trailing.push('\n');
+    }
+    for comment in &dangling {
+        trailing.push_str(INDENT);
+        trailing.push_str(comment);
+        trailing.push('\n');
+    }
+    array.set_trailing(trailing);
+    array.set_trailing_comma(!array.is_empty());
+    Ok(())
+}
+
+/// Spells the crate name of a pattern the way it appears in type paths. Regular expressions
+/// are left as they are.
+fn normalize_pattern(pattern: &str) -> String {
+    let pattern = pattern.trim();
+    if let Some(negated) = pattern.strip_prefix('!') {
+        return format!("!{}", normalize_pattern(negated));
+    }
+    if pattern.starts_with("regex:") {
+        return pattern.into();
+    }
+    match pattern.split_once("::") {
+        Some((crate_name, rest)) => format!("{}::{rest}", crate_name.replace('-', "_")),
+        None => pattern.replace('-', "_"),
+    }
+}
+
+pub(crate) fn raw_str(raw: Option<&toml_edit::RawString>) -> &str {
+    raw.and_then(|raw| raw.as_str()).unwrap_or_default()
+}
+
+/// Returns the comment in the given whitespace, if any.
+fn comment(whitespace: &str) -> Option<String> {
+    let comment = whitespace.trim();
+    if comment.starts_with('#') {
+        Some(comment.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_str_eq;
+
+    #[test]
+    fn format_standalone_config() {
+        let config = r#"# Config for the crate
+allow_std = false
+allowed_external_types = [ # Last letter
+    "zeta::*", # Trailing
+  # Why bytes is allowed
+  "bytes::Bytes",
+"my-crate::Type",
+    "regex:my-crate::.*",
+    "bytes::Bytes",
+    # Dangling
+]
+"#;
+        let expected = r#"# Config for the crate
+allow_std = false
+allowed_external_types = [
+    # Why bytes is allowed
+    "bytes::Bytes",
+    "my_crate::Type",
+    "regex:my-crate::.*",
+    # Last letter
+    "zeta::*", # Trailing
+    # Dangling
+]
+"#;
+        assert_str_eq!(expected, format_config(config, &[]).unwrap());
+        // Formatting is idempotent
+        assert_str_eq!(expected, format_config(expected, &[]).unwrap());
+    }
+
+    #[test]
+    fn format_cargo_toml_config() {
+        let config = r#"[package]
+name = "test"
+
+[package.metadata.cargo_check_external_types]
+allowed_external_types = ["b::*", "a::*"]
+denied_external_types = ["a::sync::*", "a::io::*"]
+"#;
+        let expected = r#"[package]
+name = "test"
+
+[package.metadata.cargo_check_external_types]
+allowed_external_types = [
+    "a::*",
+    "b::*",
+]
+denied_external_types = [
+    "a::io::*",
+    "a::sync::*",
+]
+"#;
+        assert_str_eq!(
+            expected,
+            format_config(config, CARGO_TOML_TABLE_PATH).unwrap()
+        );
+    }
+
+    #[test]
+    fn format_config_with_negated_patterns() {
+        let config = r#"allowed_external_types = ["my-crate::*", "!my-crate::inner::*", "!my-crate::inner::*", "a::*"]
+"#;
+        let expected = r#"allowed_external_types = [
+    "my_crate::*",
+    "!my_crate::inner::*",
+    "a::*",
+]
+"#;
+        assert_str_eq!(expected, format_config(config, &[]).unwrap());
+    }
+
+    #[test]
+    fn format_config_with_scoped_patterns() {
+        let config = r#"[allowed_external_types_in]
+"my_crate::ffi::*" = ["libc::c_int", "libc::c_char"]
+"#;
+        let expected = r#"[allowed_external_types_in]
+"my_crate::ffi::*" = [
+    "libc::c_char",
+    "libc::c_int",
+]
+"#;
+        assert_str_eq!(expected, format_config(config, &[]).unwrap());
+    }
+
+    #[test]
+    fn format_config_with_target_tables() {
+        let config = r#"[target.'cfg(windows)']
+allowed_external_types = ["windows-sys::*", "libc::*"]
+"#;
+        let expected = r#"[target.'cfg(windows)']
+allowed_external_types = [
+    "libc::*",
+    "windows_sys::*",
+]
+"#;
+        assert_str_eq!(expected, format_config(config, &[]).unwrap());
+
+        let config = "[target.'cfg(windows)']\nallowed_external_types = 5\n";
+        let err = format_config(config, &[]).unwrap_err().to_string();
+        assert_str_eq!(
+            "`target.'cfg(windows)'.allowed_external_types` must be an array",
+            err
+        );
+    }
+
+    #[test]
+    fn format_config_with_reasons() {
+        let config = r#"allowed_external_types = [
+    { pattern = "my-crate::*", reason = "Re-exported on purpose" },
+    "a::*",
+    {pattern="b::*"},
+    "my-crate::*",
+    {pattern="c::*",acknowledged=true},
+    { pattern = "d::*", acknowledged = false },
+]
+"#;
+        let expected = r#"allowed_external_types = [
+    "a::*",
+    "b::*",
+    { pattern = "c::*", acknowledged = true },
+    "d::*",
+    { pattern = "my_crate::*", reason = "Re-exported on purpose" },
+]
+"#;
+        assert_str_eq!(expected, format_config(config, &[]).unwrap());
+        assert_str_eq!(expected, format_config(expected, &[]).unwrap());
+
+        let config = "allowed_external_types = [{ reason = \"No pattern\" }]\n";
+        assert!(format_config(config, &[]).is_err());
+        let config = "allowed_external_types = [{ pattern = \"a::*\", acknowledged = \"yes\" }]\n";
+        assert!(format_config(config, &[]).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Parses rustdoc JSON from older format versions by upgrading it, one version at a time, to the
+//! [`FORMAT_VERSION`] that `rustdoc-types` parses.
+//!
+//! Each upgrade is behind a cargo feature, such as `format-v35` for upgrading format version 35
+//! to 36, so that builds that only need the latest format can leave them out.
+
+use anyhow::{bail, Context, Result};
+use rustdoc_types::{Crate, FORMAT_VERSION};
+use serde_json::Value;
+
+/// Oldest rustdoc JSON format version that can be parsed.
+pub const MIN_FORMAT_VERSION: u32 = if cfg!(feature = "format-v34") {
+    34
+} else if cfg!(feature = "format-v35") {
+    35
+} else {
+    FORMAT_VERSION
+};
+
+/// Returns true if rustdoc JSON with the given format version can be parsed.
+pub fn is_supported(format_version: u32) -> bool {
+    (MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&format_version)
+}
+
+/// Parses rustdoc JSON with the given format version, upgrading it to [`FORMAT_VERSION`] first
+/// if it's older.
+pub fn parse_crate(json: &str, format_version: u32) -> Result<Crate> {
+    if format_version == FORMAT_VERSION {
+        return serde_json::from_str(json).context("Failed to parse rustdoc output.");
+    }
+    if !is_supported(format_version) {
+        bail!("rustdoc JSON format version {format_version} isn't supported");
+    }
+    let mut value: Value = serde_json::from_str(json).context("Failed to parse rustdoc output.")?;
+    for version in format_version..FORMAT_VERSION {
+        upgrade_from(version, &mut value);
+    }
+    value["format_version"] = FORMAT_VERSION.into();
+    serde_json::from_value(value).with_context(|| {
+        format!("Failed to parse rustdoc output upgraded from format version {format_version}.")
+    })
+}
+
+/// Upgrades `value` from the given format version to the next one.
+#[cfg_attr(not(feature = "format-v35"), allow(unused_variables))]
+fn upgrade_from(version: u32, value: &mut Value) {
+    match version {
+        #[cfg(feature = "format-v34")]
+        34 => upgrade_from_v34(value),
+        #[cfg(feature = "format-v35")]
+        35 => upgrade_from_v35(value),
+        _ => unreachable!("format version {version} isn't supported"),
+    }
+}
+
+/// Format version 35 changed item IDs from strings, such as `0:1:1234`, to integers.
+#[cfg(feature = "format-v34")]
+fn upgrade_from_v34(value: &mut Value) {
+    let mut ids = std::collections::HashMap::new();
+    for map in ["index", "paths"] {
+        if let Some(Value::Object(items)) = value.get_mut(map) {
+            *items = std::mem::take(items)
+                .into_iter()
+                .map(|(key, item)| (intern_id(&mut ids, &key).to_string(), item))
+                .collect();
+        }
+    }
+    replace_ids(value, &mut ids);
+}
+
+/// Fields that hold an ID.
+#[cfg(feature = "format-v34")]
+const ID_FIELDS: &[&str] = &["id", "root"];
+
+/// Fields that hold a list of IDs. Tuple structs and variants list their fields in `tuple`, with
+/// `null` for fields that were stripped.
+#[cfg(feature = "format-v34")]
+const ID_LIST_FIELDS: &[&str] = &[
+    "items",
+    "impls",
+    "implementations",
+    "variants",
+    "fields",
+    "tuple",
+];
+
+/// Replaces the string IDs in `value` with their integer IDs. Only fields that hold IDs are
+/// changed, since docs, attributes, and constant expressions can contain text that looks like
+/// an ID.
+#[cfg(feature = "format-v34")]
+fn replace_ids(value: &mut Value, ids: &mut std::collections::HashMap<String, u32>) {
+    match value {
+        Value::Array(values) => values.iter_mut().for_each(|value| replace_ids(value, ids)),
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                match (key.as_str(), &mut *field) {
+                    (key, _) if ID_FIELDS.contains(&key) => replace_id(field, ids),
+                    (key, Value::Array(values)) if ID_LIST_FIELDS.contains(&key) => {
+                        values.iter_mut().for_each(|value| replace_id(value, ids))
+                    }
+                    ("links", Value::Object(links)) => {
+                        links.values_mut().for_each(|value| replace_id(value, ids))
+                    }
+                    _ => {}
+                }
+                replace_ids(field, ids);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces `value` with its integer ID if it's a string ID.
+#[cfg(feature = "format-v34")]
+fn replace_id(value: &mut Value, ids: &mut std::collections::HashMap<String, u32>) {
+    if let Value::String(id) = value {
+        if is_v34_id(id) {
+            *value = intern_id(ids, id).into();
+        }
+    }
+}
+
+/// Returns the integer ID for the string `id`, assigning the next one if it's new.
+#[cfg(feature = "format-v34")]
+fn intern_id(ids: &mut std::collections::HashMap<String, u32>, id: &str) -> u32 {
+    let next = ids.len() as u32;
+    *ids.entry(id.to_string()).or_insert(next)
+}
+
+/// Returns true if `string` is a format version 34 ID, such as `0:1:1234`, or `a:0:2:34-0:5:67`
+/// for auto trait and blanket impls.
+#[cfg(feature = "format-v34")]
+fn is_v34_id(string: &str) -> bool {
+    let rest = string
+        .strip_prefix("a:")
+        .or_else(|| string.strip_prefix("b:"))
+        .unwrap_or(string);
+    rest.starts_with(|c: char| c.is_ascii_digit())
+        && rest.contains(':')
+        && rest
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ':' || c == '-')
+}
+
+/// Format version 36 renamed `Trait::is_object_safe` to `is_dyn_compatible`.
+#[cfg(feature = "format-v35")]
+fn upgrade_from_v35(value: &mut Value) {
+    let Some(Value::Object(index)) = value.get_mut("index") else {
+        return;
+    };
+    for item in index.values_mut() {
+        if let Some(Value::Object(trait_)) = item.pointer_mut("/inner/trait") {
+            if let Some(is_dyn_compatible) = trait_.remove("is_object_safe") {
+                trait_.insert("is_dyn_compatible".into(), is_dyn_compatible);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustdoc_types::{Id, ItemEnum};
+
+    /// Returns rustdoc JSON for a crate with a trait, using `ids` for the crate root and trait,
+    /// and `object_safe` as the name of the trait's dyn compatibility field.
+    fn crate_json(format_version: u32, ids: [Value; 2], object_safe: &str) -> String {
+        let [root, trait_id] = ids;
+        let generics = serde_json::json!({ "params": [], "where_predicates": [] });
+        let item = |id: &Value, name: &str, inner: Value| {
+            serde_json::json!({
+                "id": id, "crate_id": 0, "name": name, "span": null, "visibility": "public",
+                "docs": null, "links": {}, "attrs": [], "deprecation": null, "inner": inner,
+            })
+        };
+        let index = serde_json::Map::from_iter([
+            (
+                root.to_string().trim_matches('"').to_string(),
+                item(
+                    &root,
+                    "my_crate",
+                    serde_json::json!({ "module": {
+                        "is_crate": true, "items": [trait_id], "is_stripped": false,
+                    }}),
+                ),
+            ),
+            (
+                trait_id.to_string().trim_matches('"').to_string(),
+                item(
+                    &trait_id,
+                    "MyTrait",
+                    serde_json::json!({ "trait": {
+                        "is_auto": false, "is_unsafe": false, object_safe: true, "items": [],
+                        "generics": generics, "bounds": [], "implementations": [],
+                    }}),
+                ),
+            ),
+        ]);
+        serde_json::json!({
+            "root": root, "crate_version": null, "includes_private": false, "index": index,
+            "paths": {}, "external_crates": {}, "format_version": format_version,
+        })
+        .to_string()
+    }
+
+    fn assert_dyn_compatible_trait(krate: &Crate) {
+        let ItemEnum::Module(module) = &krate.index[&krate.root].inner else {
+            panic!("root isn't a module");
+        };
+        let ItemEnum::Trait(trait_) = &krate.index[&module.items[0]].inner else {
+            panic!("item isn't a trait");
+        };
+        assert!(trait_.is_dyn_compatible);
+        assert_eq!(FORMAT_VERSION, krate.format_version);
+    }
+
+    #[test]
+    fn parse_current_version() {
+        let json = crate_json(FORMAT_VERSION, [0.into(), 1.into()], "is_dyn_compatible");
+        let krate = parse_crate(&json, FORMAT_VERSION).unwrap();
+        assert_eq!(Id(0), krate.root);
+        assert_dyn_compatible_trait(&krate);
+    }
+
+    #[cfg(feature = "format-v35")]
+    #[test]
+    fn upgrade_v35() {
+        assert!(is_supported(35));
+        let json = crate_json(35, [0.into(), 1.into()], "is_object_safe");
+        assert_dyn_compatible_trait(&parse_crate(&json, 35).unwrap());
+    }
+
+    #[cfg(feature = "format-v34")]
+    #[test]
+    fn upgrade_v34() {
+        assert!(is_supported(34));
+        let json = crate_json(34, ["0:0:1572".into(), "0:3:1234".into()], "is_object_safe");
+        assert_dyn_compatible_trait(&parse_crate(&json, 34).unwrap());
+    }
+
+    #[cfg(feature = "format-v34")]
+    #[test]
+    fn upgrade_v34_only_changes_ids() {
+        let json = crate_json(34, ["0:0:1572".into(), "0:3:1234".into()], "is_object_safe");
+        let mut value: Value = serde_json::from_str(&json).unwrap();
+        let trait_item = &mut value["index"]["0:3:1234"];
+        trait_item["docs"] = "1:30".into();
+        trait_item["attrs"] = serde_json::json!(["2:4"]);
+        trait_item["links"] = serde_json::json!({ "MyTrait": "0:3:1234" });
+        let krate = parse_crate(&value.to_string(), 34).unwrap();
+        let trait_item = &krate.index[&Id(1)];
+        assert_eq!(Some("1:30"), trait_item.docs.as_deref());
+        assert_eq!(vec!["2:4".to_string()], trait_item.attrs);
+        assert_eq!(Some(&Id(1)), trait_item.links.get("MyTrait"));
+        assert_dyn_compatible_trait(&krate);
+    }
+
+    #[cfg(feature = "format-v34")]
+    #[test]
+    fn find_v34_ids() {
+        assert!(is_v34_id("0:3:1234"));
+        assert!(is_v34_id("a:2:32413:1234-0:3:9876"));
+        assert!(is_v34_id("b:2:5:6-1:2:3"));
+        assert!(!is_v34_id("MyTrait"));
+        assert!(!is_v34_id("src/lib.rs"));
+        assert!(!is_v34_id("a:b"));
+        assert!(!is_v34_id("1234"));
+    }
+
+    #[test]
+    fn unsupported_versions() {
+        assert!(!is_supported(MIN_FORMAT_VERSION - 1));
+        assert!(!is_supported(FORMAT_VERSION + 1));
+        let json = crate_json(FORMAT_VERSION, [0.into(), 1.into()], "is_dyn_compatible");
+        assert!(parse_crate(&json, MIN_FORMAT_VERSION - 1).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Records a summary of each run in a history file, and reports whether the exposure of
+//! external types is increasing or decreasing over the recorded runs.
+//!
+//! The history file has one JSON object per line, so that runs can be appended to it without
+//! reading it first.
+
+use crate::error::{ErrorLevel, ValidationErrors};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Summary of the findings of one run.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    /// When the run finished, in seconds since the Unix epoch
+    pub timestamp: u64,
+    pub error_count: usize,
+    pub warning_count: usize,
+    /// Findings by the external crate they're about. Findings that aren't about an external
+    /// crate, such as hidden items, are only included in the totals.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub crates: BTreeMap<String, FindingCounts>,
+}
+
+/// Number of findings of each level.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FindingCounts {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl HistoryEntry {
+    /// Summarizes the reported findings in `errors` at the given time.
+    pub fn new(timestamp: u64, errors: &ValidationErrors) -> Self {
+        let mut crates: BTreeMap<String, FindingCounts> = BTreeMap::new();
+        for error in errors.iter() {
+            if let Some(crate_name) = error.external_crate_name() {
+                let counts = crates.entry(crate_name.to_string()).or_default();
+                match error.level() {
+                    ErrorLevel::Error => counts.errors += 1,
+                    ErrorLevel::Warning => counts.warnings += 1,
+                }
+            }
+        }
+        Self {
+            timestamp,
+            error_count: errors.error_count(),
+            warning_count: errors.warning_count(),
+            crates,
+        }
+    }
+
+    /// Summarizes the reported findings in `errors` at the current time.
+    pub fn now(errors: &ValidationErrors) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self::new(timestamp, errors)
+    }
+
+    /// Appends the entry to the history file at `path`, creating it if needed.
+    pub fn append_to(&self, path: &Path) -> Result<()> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .with_context(|| format!("failed to write history file {path:?}"))
+    }
+
+    fn findings(&self) -> usize {
+        self.error_count + self.warning_count
+    }
+}
+
+/// Reads the entries of the history file at `path`, oldest first.
+pub fn read_history(path: &Path) -> Result<Vec<HistoryEntry>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read history file {path:?}"))?;
+    let mut entries = contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).with_context(|| {
+                format!(
+                    "failed to parse line {} of history file {path:?}",
+                    index + 1
+                )
+            })
+        })
+        .collect::<Result<Vec<HistoryEntry>>>()?;
+    if entries.is_empty() {
+        bail!("history file {path:?} doesn't have any runs");
+    }
+    // The sort is stable, so runs with the same timestamp keep the order they were recorded in
+    entries.sort_by_key(|entry| entry.timestamp);
+    Ok(entries)
+}
+
+/// Outputs how the findings changed over the runs in `entries`, which must be oldest first.
+///
+/// # Example output
+///
+/// ```text
+/// 3 runs from 2026-10-01 09:30 UTC to 2026-10-15 17:05 UTC
+///   Run       Errors  Warnings
+///   First         40         2
+///   Previous      34         2
+///   Latest        32         2
+///
+/// Changes by external crate since the first run:
+///   Crate         Errors  Warnings
+///   external_lib      -9        +0
+///   tokio             +1        +0
+///
+/// Exposure is decreasing: 34 findings, down from 42 in the first run
+/// ```
+pub fn print_trend(out: &mut dyn Write, entries: &[HistoryEntry]) -> Result<()> {
+    let (Some(first), Some(latest)) = (entries.first(), entries.last()) else {
+        return Ok(());
+    };
+    if entries.len() == 1 {
+        writeln!(out, "1 run at {}", format_timestamp(first.timestamp))?;
+    } else {
+        writeln!(
+            out,
+            "{} runs from {} to {}",
+            entries.len(),
+            format_timestamp(first.timestamp),
+            format_timestamp(latest.timestamp)
+        )?;
+    }
+    let mut rows = vec![("First", first)];
+    if entries.len() > 2 {
+        rows.push(("Previous", &entries[entries.len() - 2]));
+    }
+    if entries.len() > 1 {
+        rows.push(("Latest", latest));
+    }
+    writeln!(out, "  {:8}  {:>6}  {:>8}", "Run", "Errors", "Warnings")?;
+    for (name, entry) in rows {
+        writeln!(
+            out,
+            "  {:8}  {:>6}  {:>8}",
+            name, entry.error_count, entry.warning_count
+        )?;
+    }
+    if entries.len() == 1 {
+        writeln!(out)?;
+        writeln!(out, "Only one run is recorded, so there's no trend yet")?;
+        return Ok(());
+    }
+
+    let crate_names: Vec<&String> = first
+        .crates
+        .keys()
+        .chain(latest.crates.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|name| first.crates.get(*name) != latest.crates.get(*name))
+        .collect();
+    if !crate_names.is_empty() {
+        let crate_width = crate_names
+            .iter()
+            .map(|name| name.len())
+            .chain(Some("Crate".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(out)?;
+        writeln!(out, "Changes by external crate since the first run:")?;
+        writeln!(out, "  {:crate_width$}  Errors  Warnings", "Crate")?;
+        for name in crate_names {
+            let before = first.crates.get(name).copied().unwrap_or_default();
+            let after = latest.crates.get(name).copied().unwrap_or_default();
+            writeln!(
+                out,
+                "  {:crate_width$}  {:>6}  {:>8}",
+                name,
+                format_change(before.errors, after.errors),
+                format_change(before.warnings, after.warnings)
+            )?;
+        }
+    }
+
+    writeln!(out)?;
+    let (before, after) = (first.findings(), latest.findings());
+    match after.cmp(&before) {
+        Ordering::Less => writeln!(
+            out,
+            "Exposure is decreasing: {after} findings, down from {before} in the first run"
+        )?,
+        Ordering::Greater => writeln!(
+            out,
+            "Exposure is increasing: {after} findings, up from {before} in the first run"
+        )?,
+        Ordering::Equal => writeln!(
+            out,
+            "Exposure is unchanged: {after} findings, as in the first run"
+        )?,
+    }
+    Ok(())
+}
+
+/// Formats the change from `before` to `after` with an explicit sign, such as `+2` or `-3`.
+fn format_change(before: usize, after: usize) -> String {
+    if after >= before {
+        format!("+{}", after - before)
+    } else {
+        format!("-{}", before - after)
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC date and time, such as `2026-10-15 17:05 UTC`.
+fn format_timestamp(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / 86_400, timestamp % 86_400);
+    // Converts days since the epoch to a date in the proleptic Gregorian calendar, using the
+    // algorithm from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_str_eq;
+
+    fn entry(timestamp: u64, crates: &[(&str, usize, usize)]) -> HistoryEntry {
+        let crates: BTreeMap<_, _> = crates
+            .iter()
+            .map(|&(name, errors, warnings)| (name.to_string(), FindingCounts { errors, warnings }))
+            .collect();
+        HistoryEntry {
+            timestamp,
+            error_count: crates.values().map(|counts| counts.errors).sum(),
+            warning_count: crates.values().map(|counts| counts.warnings).sum(),
+            crates,
+        }
+    }
+
+    fn trend(entries: &[HistoryEntry]) -> String {
+        let mut out = Vec::new();
+        print_trend(&mut out, entries).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn format_timestamps() {
+        assert_eq!("1970-01-01 00:00 UTC", format_timestamp(0));
+        assert_eq!("2000-02-29 23:59 UTC", format_timestamp(951_868_799));
+        assert_eq!("2026-10-15 17:05 UTC", format_timestamp(1_792_083_900));
+    }
+
+    #[test]
+    fn decreasing_trend() {
+        let entries = [
+            entry(1_790_847_000, &[("external_lib", 40, 2), ("bytes", 1, 0)]),
+            entry(1_791_500_000, &[("external_lib", 34, 2)]),
+            entry(1_792_083_900, &[("external_lib", 31, 2), ("tokio", 1, 0)]),
+        ];
+        assert_str_eq!(
+            "3 runs from 2026-10-01 09:30 UTC to 2026-10-15 17:05 UTC\n\
+            \x20 Run       Errors  Warnings\n\
+            \x20 First         41         2\n\
+            \x20 Previous      34         2\n\
+            \x20 Latest        32         2\n\
+            \n\
+            Changes by external crate since the first run:\n\
+            \x20 Crate         Errors  Warnings\n\
+            \x20 bytes             -1        +0\n\
+            \x20 external_lib      -9        +0\n\
+            \x20 tokio             +1        +0\n\
+            \n\
+            Exposure is decreasing: 34 findings, down from 43 in the first run\n",
+            trend(&entries)
+        );
+    }
+
+    #[test]
+    fn single_run() {
+        assert_str_eq!(
+            "1 run at 2026-10-15 17:05 UTC\n\
+            \x20 Run       Errors  Warnings\n\
+            \x20 First          1         0\n\
+            \n\
+            Only one run is recorded, so there's no trend yet\n",
+            trend(&[entry(1_792_083_900, &[("bytes", 1, 0)])])
+        );
+    }
+
+    #[test]
+    fn round_trip_through_json() {
+        let entry = entry(1_792_083_900, &[("bytes", 1, 0)]);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(
+            r#"{"timestamp":1792083900,"error_count":1,"warning_count":0,"crates":{"bytes":{"errors":1,"warnings":0}}}"#,
+            json
+        );
+        assert_eq!(entry, serde_json::from_str(&json).unwrap());
+    }
+}
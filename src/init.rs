@@ -0,0 +1,110 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Writes a starter config into a crate's `Cargo.toml`.
+
+use anyhow::{bail, Context, Result};
+use toml_edit::DocumentMut;
+
+/// Header of the config table that gets added to `Cargo.toml`.
+const TABLE_HEADER: &str = "[package.metadata.cargo_check_external_types]";
+
+/// Returns a starter config table for a crate with the given dependencies, as they're named in
+/// type paths. Each dependency is listed as a commented out pattern that allows all of its
+/// types, so that the ones that are part of the public API can be uncommented.
+pub fn starter_config(dependencies: &[String]) -> String {
+    let mut config = format!(
+        "{TABLE_HEADER}\n\
+        # External types that may be exposed in the public API, as globs such as `crate_name::*`\n\
+        # or as full type paths. Run `cargo check-external-types` to see which external types\n\
+        # the public API exposes, and uncomment or add the ones that are meant to be part of it.\n"
+    );
+    if dependencies.is_empty() {
+        config.push_str("allowed_external_types = []\n");
+    } else {
+        config.push_str("allowed_external_types = [\n");
+        for dependency in dependencies {
+            config.push_str(&format!("    # \"{dependency}::*\",\n"));
+        }
+        config.push_str("]\n");
+    }
+    config
+}
+
+/// Appends the starter config to the `Cargo.toml` document `manifest`.
+pub fn add_starter_config(manifest: &str, dependencies: &[String]) -> Result<String> {
+    let document: DocumentMut = manifest.parse().context("failed to parse Cargo.toml")?;
+    let metadata = document
+        .get("package")
+        .and_then(|package| package.get("metadata"));
+    if let Some(metadata) = metadata {
+        if metadata.get("cargo_check_external_types").is_some()
+            || metadata.get("cargo-check-external-types").is_some()
+        {
+            bail!("Cargo.toml already has a config for cargo-check-external-types");
+        }
+    }
+    let separator = match manifest {
+        "" => "",
+        _ if manifest.ends_with("\n\n") => "",
+        _ if manifest.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    let updated = format!("{manifest}{separator}{}", starter_config(dependencies));
+    // The header can't be added when `package.metadata` is defined inline
+    updated
+        .parse::<DocumentMut>()
+        .context("failed to add the config table to Cargo.toml")?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_str_eq;
+
+    #[test]
+    fn add_starter_config_to_manifest() {
+        let manifest = "[package]\nname = \"my-crate\"\n";
+        let dependencies = vec!["bytes".to_string(), "http_body".to_string()];
+        assert_str_eq!(
+            format!(
+                "[package]\nname = \"my-crate\"\n\n{TABLE_HEADER}\n\
+                # External types that may be exposed in the public API, as globs such as `crate_name::*`\n\
+                # or as full type paths. Run `cargo check-external-types` to see which external types\n\
+                # the public API exposes, and uncomment or add the ones that are meant to be part of it.\n\
+                allowed_external_types = [\n    # \"bytes::*\",\n    # \"http_body::*\",\n]\n"
+            ),
+            add_starter_config(manifest, &dependencies).unwrap()
+        );
+        assert!(add_starter_config(manifest, &[])
+            .unwrap()
+            .ends_with("allowed_external_types = []\n"));
+    }
+
+    #[test]
+    fn refuse_to_replace_config() {
+        let err = add_starter_config(
+            "[package]\nname = \"my-crate\"\n\n\
+            [package.metadata.cargo-check-external-types]\nallowed_external_types = []\n",
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(
+            "Cargo.toml already has a config for cargo-check-external-types",
+            err.to_string()
+        );
+
+        let err = add_starter_config(
+            "[package]\nname = \"my-crate\"\nmetadata = { docs = 1 }\n",
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(
+            "failed to add the config table to Cargo.toml",
+            err.to_string()
+        );
+    }
+}
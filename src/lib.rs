@@ -6,11 +6,29 @@
 pub(crate) const NEW_ISSUE_URL: &str =
     "https://github.com/awslabs/cargo-check-external-types/issues/new";
 
+pub mod allowlist;
+pub mod baseline;
 pub mod cargo;
+pub mod changes;
+pub mod compare;
 pub mod config;
+pub mod dependency;
 pub mod error;
+pub mod fix;
+pub mod fmt_config;
+pub mod format;
+pub mod history;
+pub mod init;
+pub mod lint_config;
+pub mod migrate;
+pub mod output;
 pub mod path;
+pub mod pattern_stats;
+pub mod published;
+pub mod render;
+pub mod source;
 pub mod visitor;
+pub mod workspace;
 
 /// A macro for attaching info to error messages pointing to the line of code responsible for the error.
 /// [Thanks to dtolnay for this macro](https://github.com/dtolnay/anyhow/issues/22#issuecomment-542309452)
@@ -0,0 +1,443 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checks a config for mistakes without running rustdoc.
+//!
+//! Patterns that are empty or don't parse are errors. Patterns that are redundant are warnings:
+//! exact duplicates, patterns that only match types another pattern in the same list already
+//! matches, and allowed patterns for types that are never reported, such as the crate's own
+//! types or `std` types while `allow_std` is true.
+
+use crate::config::{Pattern, REGEX_PREFIX};
+use crate::error::{ErrorLevel, ErrorPrinter};
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use toml::{Table, Value};
+
+/// Keys of the lists of patterns, and whether they allow types.
+const PATTERN_LISTS: &[(&str, bool)] = &[
+    ("allowed_external_types", true),
+    ("denied_external_types", false),
+    ("denied_std_types", false),
+];
+
+/// Key of the list of exact type names that are allowed.
+const EXACT_LIST: &str = "allowed_external_types_exact";
+
+/// Key of the table of pattern lists that only apply to some items.
+const SCOPED_LISTS: &str = "allowed_external_types_in";
+
+/// Key of the tables of lists that only apply to some targets.
+const TARGET_TABLES: &str = "target";
+
+/// Crates whose types are allowed by the `allow_*` setting of the same name.
+const STD_CRATES: &[&str] = &["alloc", "core", "std"];
+
+/// A mistake found in a config.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ConfigLint {
+    pub level: ErrorLevel,
+    pub message: String,
+}
+
+impl ConfigLint {
+    fn error(message: String) -> Self {
+        Self {
+            level: ErrorLevel::Error,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Self {
+            level: ErrorLevel::Warning,
+            message,
+        }
+    }
+}
+
+/// Lints the config in the TOML document `contents`, where the config is in the table at
+/// `table_path` (which is empty for standalone config files). Patterns for the types of
+/// `root_crate_name` are reported if it's given.
+pub fn lint_config(
+    contents: &str,
+    table_path: &[&str],
+    root_crate_name: Option<&str>,
+) -> Result<Vec<ConfigLint>> {
+    let document: Table = toml::from_str(contents).context("failed to parse config file")?;
+    let mut config = &document;
+    for &key in table_path {
+        let hyphenated = key.replace('_', "-");
+        config = match config.get(key).or_else(|| config.get(&hyphenated)) {
+            Some(Value::Table(table)) => table,
+            Some(_) => bail!("`{key}` in config file must be a table"),
+            None => bail!("failed to find `{}` in config file", table_path.join(".")),
+        };
+    }
+
+    let linter = Linter {
+        anchored: config.get("anchored_patterns").and_then(Value::as_bool) == Some(true),
+        allowed_std_crates: STD_CRATES
+            .iter()
+            .copied()
+            .filter(|name| {
+                let key = format!("allow_{name}");
+                config.get(&key).and_then(Value::as_bool) != Some(false)
+            })
+            .collect(),
+        root_crate_name: root_crate_name.map(|name| name.replace('-', "_")),
+    };
+    let mut lints = Vec::new();
+    linter.lint_lists(config, "", &mut lints)?;
+    if let Some(targets) = config.get(TARGET_TABLES) {
+        let Some(targets) = targets.as_table() else {
+            bail!("`{TARGET_TABLES}` must be a table");
+        };
+        for (target, lists) in targets {
+            let Some(lists) = lists.as_table() else {
+                bail!("`{TARGET_TABLES}.'{target}'` must be a table");
+            };
+            linter.lint_lists(lists, &format!("{TARGET_TABLES}.'{target}'."), &mut lints)?;
+        }
+    }
+    if let Some(scopes) = config.get(SCOPED_LISTS) {
+        let Some(scopes) = scopes.as_table() else {
+            bail!("`{SCOPED_LISTS}` must be a table");
+        };
+        for (scope, patterns) in scopes {
+            let key = format!("{SCOPED_LISTS}.'{scope}'");
+            if let Err(err) = Pattern::new(scope.as_str()) {
+                lints.push(ConfigLint::error(format!(
+                    "scope in `{SCOPED_LISTS}` is invalid: {err}"
+                )));
+            }
+            let patterns = list_patterns(patterns, &key)?;
+            linter.lint_patterns(&patterns, &key, true, &mut lints);
+        }
+    }
+    Ok(lints)
+}
+
+/// Settings of the config that the lints depend on.
+struct Linter {
+    anchored: bool,
+    allowed_std_crates: Vec<&'static str>,
+    root_crate_name: Option<String>,
+}
+
+impl Linter {
+    /// Lints the lists of types in `table`, whose keys are prefixed with `prefix` in messages.
+    fn lint_lists(&self, table: &Table, prefix: &str, lints: &mut Vec<ConfigLint>) -> Result<()> {
+        let mut allowed = Vec::new();
+        for &(list, allows) in PATTERN_LISTS {
+            let Some(value) = table.get(list) else {
+                continue;
+            };
+            let key = format!("{prefix}{list}");
+            let patterns = list_patterns(value, &key)?;
+            self.lint_patterns(&patterns, &key, allows, lints);
+            if allows {
+                allowed = patterns;
+            }
+        }
+        if let Some(value) = table.get(EXACT_LIST) {
+            let key = format!("{prefix}{EXACT_LIST}");
+            let names = list_patterns(value, &key)?;
+            let patterns_are_ordered = allowed.iter().any(|pattern| pattern.starts_with('!'));
+            for (i, &name) in names.iter().enumerate() {
+                if name.is_empty() {
+                    lints.push(ConfigLint::error(format!("`{key}` has an empty type name")));
+                } else if names[..i].contains(&name) {
+                    lints.push(ConfigLint::warning(format!(
+                        "`{name}` is listed more than once in `{key}`"
+                    )));
+                } else if let Some(broader) = allowed
+                    .iter()
+                    .filter(|_| !patterns_are_ordered)
+                    .find(|&&broader| self.covers(broader, name))
+                {
+                    lints.push(ConfigLint::warning(format!(
+                        "`{name}` in `{key}` is already allowed by `{broader}` in `{prefix}allowed_external_types`"
+                    )));
+                } else {
+                    self.lint_allowed(name, &key, lints);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lints the list of `patterns` at `key`, which allows types if `allows` is true.
+    fn lint_patterns(
+        &self,
+        patterns: &[&str],
+        key: &str,
+        allows: bool,
+        lints: &mut Vec<ConfigLint>,
+    ) {
+        // Negated patterns only exclude the types matched before them, so entries that look
+        // redundant can still change what the list matches
+        let ordered = patterns.iter().any(|pattern| pattern.starts_with('!'));
+        for (i, &pattern) in patterns.iter().enumerate() {
+            if pattern.trim_start_matches('!').is_empty() {
+                lints.push(ConfigLint::error(format!("`{key}` has an empty pattern")));
+                continue;
+            }
+            if let Err(err) = Pattern::new(pattern) {
+                lints.push(ConfigLint::error(format!(
+                    "pattern in `{key}` is invalid: {err}"
+                )));
+                continue;
+            }
+            let unnegated = pattern.trim_start_matches('!');
+            if key.ends_with("denied_std_types")
+                && !unnegated.starts_with(REGEX_PREFIX)
+                && !unnegated.starts_with("std::")
+            {
+                lints.push(ConfigLint::error(format!(
+                    "patterns in `{key}` must start with `std::`: `{pattern}`"
+                )));
+                continue;
+            }
+            if ordered {
+                continue;
+            }
+            if patterns[..i].contains(&pattern) {
+                lints.push(ConfigLint::warning(format!(
+                    "`{pattern}` is listed more than once in `{key}`"
+                )));
+            } else if let Some(broader) = patterns
+                .iter()
+                .find(|&&broader| broader != pattern && self.covers(broader, pattern))
+            {
+                lints.push(ConfigLint::warning(format!(
+                    "`{pattern}` in `{key}` only matches types that `{broader}` already matches"
+                )));
+            } else if allows {
+                self.lint_allowed(pattern, key, lints);
+            }
+        }
+    }
+
+    /// Lints the `pattern` at `key` for allowing types that are never reported anyway.
+    fn lint_allowed(&self, pattern: &str, key: &str, lints: &mut Vec<ConfigLint>) {
+        if pattern.starts_with(REGEX_PREFIX) {
+            return;
+        }
+        let crate_name = pattern.split("::").next().unwrap_or_default();
+        if Some(crate_name) == self.root_crate_name.as_deref() {
+            lints.push(ConfigLint::warning(format!(
+                "`{pattern}` in `{key}` refers to the crate's own types, which are never external"
+            )));
+        } else if self.allowed_std_crates.contains(&crate_name) {
+            lints.push(ConfigLint::warning(format!(
+                "`{pattern}` in `{key}` is redundant, since `allow_{crate_name}` is true"
+            )));
+        }
+    }
+
+    /// Returns true if `broader` matches every type that `narrower` matches. This only
+    /// recognizes the common cases: `narrower` is a type name without wildcards, or `broader`
+    /// matches everything that starts with the text before its trailing wildcard.
+    fn covers(&self, broader: &str, narrower: &str) -> bool {
+        const WILDCARDS: &[char] = &['*', '?', '[', '{'];
+        if broader.starts_with(REGEX_PREFIX) || narrower.starts_with(REGEX_PREFIX) {
+            return false;
+        }
+        if !narrower.contains(WILDCARDS) {
+            return match Pattern::new(broader) {
+                Ok(broader) if self.anchored => broader.matches_anchored(narrower),
+                Ok(broader) => broader.matches(narrower),
+                Err(_) => false,
+            };
+        }
+        let prefix = if self.anchored {
+            // A trailing `*` stops at `::` when anchored, so only `::**` matches any suffix
+            broader
+                .strip_suffix("**")
+                .filter(|prefix| prefix.ends_with("::"))
+        } else {
+            broader.strip_suffix('*')
+        };
+        let literal_start = narrower.split(WILDCARDS).next().unwrap_or_default();
+        match prefix {
+            Some(prefix) if !prefix.contains(WILDCARDS) => literal_start.starts_with(prefix),
+            _ => false,
+        }
+    }
+}
+
+/// Returns the patterns in the list `value` at `key`, which are either strings or
+/// `{ pattern, reason }` tables.
+fn list_patterns<'a>(value: &'a Value, key: &str) -> Result<Vec<&'a str>> {
+    let Some(entries) = value.as_array() else {
+        bail!("`{key}` must be a list");
+    };
+    entries
+        .iter()
+        .map(|entry| match entry {
+            Value::String(pattern) => Ok(pattern.as_str()),
+            Value::Table(table) => match table.get("pattern").and_then(Value::as_str) {
+                Some(pattern) => Ok(pattern),
+                None => bail!("entries of `{key}` must have a `pattern` string"),
+            },
+            _ => bail!("`{key}` must only contain strings or `{{ pattern, reason }}` tables"),
+        })
+        .collect()
+}
+
+/// Outputs the `lints` followed by a summary to `out`.
+pub fn print_config_lints(out: &mut dyn Write, lints: &[ConfigLint]) -> Result<()> {
+    use owo_colors::{OwoColorize, Stream};
+    for lint in lints {
+        ErrorPrinter::print_error_level(out, lint.level)?;
+        writeln!(out, "{}", lint.message)?;
+    }
+    let error_count = lints
+        .iter()
+        .filter(|lint| lint.level == ErrorLevel::Error)
+        .count();
+    writeln!(
+        out,
+        "{error_count} {errors}, {warning_count} {warnings} emitted",
+        errors = "errors".if_supports_color(Stream::Stdout, |text| text.red()),
+        warning_count = lints.len() - error_count,
+        warnings = "warnings".if_supports_color(Stream::Stdout, |text| text.yellow())
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fmt_config::CARGO_TOML_TABLE_PATH;
+
+    fn messages(contents: &str) -> Vec<String> {
+        lint_config(contents, &[], Some("my-crate"))
+            .unwrap()
+            .into_iter()
+            .map(|lint| format!("{:?}: {}", lint.level, lint.message))
+            .collect()
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert_eq!(
+            vec![
+                "Error: `allowed_external_types` has an empty pattern",
+                "Error: pattern in `allowed_external_types` is invalid: invalid glob in pattern \
+                `bytes::[`: unclosed character class `[`",
+                "Error: patterns in `denied_std_types` must start with `std::`: `core::cell::*`",
+                "Error: `allowed_external_types_exact` has an empty type name",
+            ],
+            messages(
+                r#"
+                allowed_external_types = ["", "bytes::["]
+                allowed_external_types_exact = [""]
+                denied_std_types = ["core::cell::*"]
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn redundant_patterns() {
+        assert_eq!(
+            vec![
+                "Warning: `bytes::Bytes` in `allowed_external_types` only matches types that \
+                `bytes::*` already matches",
+                "Warning: `http::*` is listed more than once in `allowed_external_types`",
+                "Warning: `tokio::time::Sleep` in `allowed_external_types_exact` is already \
+                allowed by `tokio::time::*` in `allowed_external_types`",
+                "Warning: `bytes::buf::*` in `target.'cfg(unix)'.allowed_external_types` only \
+                matches types that `bytes::*` already matches",
+                "Warning: `libc::c_int` in `allowed_external_types_in.'my_crate::ffi::*'` only \
+                matches types that `libc::*` already matches",
+            ],
+            messages(
+                r#"
+                allowed_external_types = [
+                    "bytes::Bytes",
+                    "bytes::*",
+                    "http::*",
+                    { pattern = "http::*", reason = "Re-exported" },
+                    "tokio::time::*",
+                ]
+                allowed_external_types_exact = ["tokio::time::Sleep"]
+
+                [target.'cfg(unix)']
+                allowed_external_types = ["bytes::*", "bytes::buf::*"]
+
+                [allowed_external_types_in]
+                "my_crate::ffi::*" = ["libc::*", "libc::c_int"]
+                "#
+            )
+        );
+        // The order of lists with negated patterns matters, so their entries aren't redundant
+        assert!(messages(
+            r#"allowed_external_types = ["bytes::*", "!bytes::buf::*", "bytes::buf::Chain"]"#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn anchored_redundant_patterns() {
+        let config = |patterns: &str| {
+            format!("anchored_patterns = true\nallowed_external_types = [{patterns}]\n")
+        };
+        assert!(messages(&config(r#""bytes::*", "bytes::buf::*""#)).is_empty());
+        assert!(messages(&config(r#""bytes::*", "bytes::buf::Chain""#)).is_empty());
+        assert_eq!(
+            vec![
+                "Warning: `bytes::buf::*` in `allowed_external_types` only matches types that \
+                `bytes::**` already matches"
+            ],
+            messages(&config(r#""bytes::**", "bytes::buf::*""#))
+        );
+    }
+
+    #[test]
+    fn types_that_are_never_reported() {
+        assert_eq!(
+            vec![
+                "Warning: `my_crate::Error` in `allowed_external_types` refers to the crate's own \
+                types, which are never external",
+                "Warning: `std::time::Duration` in `allowed_external_types_exact` is redundant, \
+                since `allow_std` is true",
+            ],
+            messages(
+                r#"
+                allow_core = false
+                allowed_external_types = ["my_crate::Error", "core::*"]
+                allowed_external_types_exact = ["std::time::Duration"]
+                denied_external_types = ["std::sync::*"]
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn lint_cargo_toml_config() {
+        let lints = lint_config(
+            "[package]\nname = \"my-crate\"\n\n\
+            [package.metadata.cargo-check-external-types]\nallowed_external_types = [\"\"]\n",
+            CARGO_TOML_TABLE_PATH,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![ConfigLint::error(
+                "`allowed_external_types` has an empty pattern".into()
+            )],
+            lints
+        );
+
+        let err = lint_config("[package]\n", CARGO_TOML_TABLE_PATH, None).unwrap_err();
+        assert_eq!(
+            "failed to find `package.metadata.cargo_check_external_types` in config file",
+            err.to_string()
+        );
+    }
+}
@@ -5,33 +5,71 @@
 
 use anyhow::{anyhow, bail};
 use anyhow::{Context, Result};
-use cargo_check_external_types::cargo::CargoRustDocJson;
-use cargo_check_external_types::config::Config;
-use cargo_check_external_types::error::{ErrorPrinter, ValidationError};
+use cargo_check_external_types::allowlist::{
+    allowlist_patterns, exposed_types, render_allowlist, render_member_allowlist, unapproved_types,
+    MemberAllowlists, WORKSPACE_TABLE_PATH,
+};
+use cargo_check_external_types::baseline::Baseline;
+use cargo_check_external_types::cargo::{
+    check_nightly_toolchain, CargoRustDocJson, TargetInfo, VersionInfo,
+};
+use cargo_check_external_types::changes::ChangedLines;
+use cargo_check_external_types::compare;
+use cargo_check_external_types::config::{resolve_extends, Config, ConfigFormat, Severity};
+use cargo_check_external_types::dependency::{direct_dependency_names, Dependencies};
+use cargo_check_external_types::error::{
+    ErrorLevel, ErrorPrinter, SortBy, SuppressionMechanism, ValidationError, ValidationErrors,
+};
+use cargo_check_external_types::fix::fix_config;
+use cargo_check_external_types::fmt_config;
 use cargo_check_external_types::here;
-use cargo_check_external_types::visitor::Visitor;
-use cargo_metadata::{CargoOpt, Metadata, Package};
+use cargo_check_external_types::history::{self, HistoryEntry};
+use cargo_check_external_types::init;
+use cargo_check_external_types::lint_config::{lint_config, print_config_lints};
+use cargo_check_external_types::migrate;
+use cargo_check_external_types::output::{
+    print_badge, print_json_failure, print_json_report, print_rdjson, print_teamcity,
+    MarkdownTablePrinter, Phase,
+};
+use cargo_check_external_types::published::PublishedCrate;
+use cargo_check_external_types::visitor::{UnknownCratePattern, Visitor};
+use cargo_check_external_types::workspace::WorkspaceErrors;
+use cargo_metadata::{CargoOpt, Metadata, Package, Target};
 use clap::Parser;
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 enum OutputFormat {
+    Badge,
+    #[default]
     Errors,
+    Json,
     MarkdownTable,
+    Rdjson,
+    TeamCity,
 }
 
 impl fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(match self {
+            Self::Badge => "badge",
             Self::Errors => "errors",
+            Self::Json => "json",
             Self::MarkdownTable => "markdown-table",
+            Self::Rdjson => "rdjson",
+            Self::TeamCity => "teamcity",
         })
     }
 }
@@ -41,17 +79,110 @@ impl FromStr for OutputFormat {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "badge" => Ok(OutputFormat::Badge),
             "errors" => Ok(OutputFormat::Errors),
+            "json" => Ok(OutputFormat::Json),
             "markdown-table" => Ok(OutputFormat::MarkdownTable),
+            "rdjson" => Ok(OutputFormat::Rdjson),
+            "teamcity" => Ok(OutputFormat::TeamCity),
             _ => Err(anyhow!(
-                "invalid output format: {}. Expected `errors` or `markdown-table`.",
+                "invalid output format: {}. Expected `badge`, `errors`, `json`, `markdown-table`, `rdjson`, or `teamcity`.",
                 s
             )),
         }
     }
 }
 
-#[derive(clap::Args, Debug, Eq, PartialEq)]
+impl CheckExternalTypesArgs {
+    /// Returns the flags that are passed through to every cargo invocation, such as `--locked`.
+    fn cargo_flags(&self) -> Vec<String> {
+        let mut flags: Vec<String> = [
+            (self.locked, "--locked"),
+            (self.frozen, "--frozen"),
+            (self.offline, "--offline"),
+        ]
+        .into_iter()
+        .filter(|&(enabled, _)| enabled)
+        .map(|(_, flag)| flag.to_string())
+        .collect();
+        for config in &self.cargo_config {
+            flags.extend(["--config".to_string(), config.clone()]);
+        }
+        for flag in &self.unstable_flags {
+            flags.extend(["-Z".to_string(), flag.clone()]);
+        }
+        flags
+    }
+}
+
+/// How `--emit-workspace-allowlist` lays out the allowlists it writes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum AllowlistLayout {
+    /// One workspace-level config that allows the types of every member
+    #[default]
+    Shared,
+    /// A config for each member that only allows the types it exposes
+    PerMember,
+}
+
+impl fmt::Display for AllowlistLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Shared => "shared",
+            Self::PerMember => "per-member",
+        })
+    }
+}
+
+impl FromStr for AllowlistLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shared" => Ok(AllowlistLayout::Shared),
+            "per-member" => Ok(AllowlistLayout::PerMember),
+            _ => Err(anyhow!(
+                "invalid allowlist layout: {}. Expected `shared` or `per-member`.",
+                s
+            )),
+        }
+    }
+}
+
+/// Which rustdoc flags come last, and so win for options where the last one given is used.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+enum RustdocFlagsPrecedence {
+    /// Flags inherited from `RUSTDOCFLAGS` or the cargo config
+    Inherited,
+    /// Flags this tool passes, such as `--document-private-items`
+    #[default]
+    Tool,
+}
+
+impl fmt::Display for RustdocFlagsPrecedence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Inherited => "inherited",
+            Self::Tool => "tool",
+        })
+    }
+}
+
+impl FromStr for RustdocFlagsPrecedence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inherited" => Ok(Self::Inherited),
+            "tool" => Ok(Self::Tool),
+            _ => Err(anyhow!(
+                "invalid rustdoc flags precedence: {s}. Expected `inherited` or `tool`."
+            )),
+        }
+    }
+}
+
+#[derive(clap::Args, Debug, Default, Eq, PartialEq)]
 struct CheckExternalTypesArgs {
     /// Enables all crate features
     #[arg(long, conflicts_with = "no_default_features")]
@@ -62,14 +193,104 @@ struct CheckExternalTypesArgs {
     /// Comma delimited list of features to enable in the crate
     #[arg(long, value_delimiter = ',')]
     features: Option<Vec<String>>,
+    /// Check the crate with each of the given comma delimited lists of features, and report
+    /// which of them each finding is found with (can be repeated). An empty list checks the
+    /// crate with its default features.
+    #[arg(
+        long,
+        value_name = "FEATURES",
+        conflicts_with_all = ["features", "all_features", "workspace", "published_crate"]
+    )]
+    check_features: Vec<String>,
+    /// Check the crate with its default features, with no features, with each feature on its
+    /// own, and with all features, and report which of them each finding is found with
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "features",
+            "all_features",
+            "no_default_features",
+            "check_features",
+            "workspace",
+            "published_crate",
+        ]
+    )]
+    feature_matrix: bool,
     /// Path to the Cargo manifest
     #[arg(long)]
     manifest_path: Option<PathBuf>,
-    /// Target triple
+    /// Workspace member to check, as a package name or `name@version`
+    #[arg(short, long, conflicts_with = "workspace")]
+    package: Option<String>,
+    /// Download and check a crate published to crates.io, as `name@version`
+    #[arg(
+        long = "crate",
+        value_name = "NAME@VERSION",
+        conflicts_with_all = ["package", "workspace", "manifest_path", "all_features"]
+    )]
+    published_crate: Option<PublishedCrate>,
+    /// Check all workspace members that have a lib target
     #[arg(long)]
-    target: Option<String>,
+    workspace: bool,
+    /// Workspace member to skip when checking the workspace (can be repeated)
+    #[arg(long, requires = "workspace")]
+    exclude: Vec<String>,
+    /// Name of the lib target to check, rather than looking it up in the package's targets
+    #[arg(long, conflicts_with = "workspace")]
+    lib_name: Option<String>,
+    /// Kind of lib target to check when the package has several, such as `rlib` or `cdylib`.
+    /// Defaults to the `lib` or `rlib` target.
+    #[arg(long, conflicts_with = "lib_name", value_parser = LIB_TARGET_KINDS.to_vec())]
+    lib_kind: Option<String>,
+    /// Target triple to check the crate for. Can be repeated to check each of the targets and
+    /// report which of them each finding is found with.
+    #[arg(long)]
+    target: Vec<String>,
+    /// Directory to generate the rustdoc JSON in, rather than the workspace's target directory
+    #[arg(long, value_name = "DIRECTORY")]
+    target_dir: Option<PathBuf>,
+    /// Print the `cargo rustdoc` command line, with the environment variables it sets, before
+    /// running it
+    #[arg(long)]
+    print_command: bool,
+    /// Print where the rustdoc JSON that's checked is, or copy it into the given directory
+    #[arg(long, value_name = "DIRECTORY", num_args = 0..=1)]
+    emit_rustdoc_json: Option<Option<PathBuf>>,
+    /// Cargo profile to generate the rustdoc JSON with, such as `release` or a custom profile
+    #[arg(long, value_name = "PROFILE-NAME")]
+    profile: Option<String>,
+    /// Rustup toolchain to generate the rustdoc JSON with, such as `nightly-2024-10-29`, rather
+    /// than the one rustup would pick for the crate
+    #[arg(long, value_name = "TOOLCHAIN")]
+    toolchain: Option<String>,
+    /// If the toolchain can't produce rustdoc JSON this tool can parse, install a compatible
+    /// nightly with rustup (if there isn't one already) and rerun rustdoc with it
+    #[arg(long)]
+    install_toolchain: bool,
+    /// Always run rustdoc and check its output, rather than reusing the JSON and findings from an
+    /// earlier run when no sources have changed since
+    #[arg(long)]
+    no_cache: bool,
+    /// Require Cargo.lock to be up to date, rather than letting cargo update it
+    #[arg(long, conflicts_with = "published_crate")]
+    locked: bool,
+    /// Require Cargo.lock and the cache to be up to date, which implies `--locked` and
+    /// `--offline`
+    #[arg(long, conflicts_with = "published_crate")]
+    frozen: bool,
+    /// Run cargo without accessing the network
+    #[arg(long)]
+    offline: bool,
+    /// Cargo config value to override, as `KEY=VALUE` or a path to a config file (can be
+    /// repeated). Passed to cargo as `--config`.
+    #[arg(long, value_name = "KEY=VALUE")]
+    cargo_config: Vec<String>,
+    /// Unstable cargo flag to pass to cargo, such as `-Z bindeps` (can be repeated)
+    #[arg(short = 'Z', value_name = "FLAG")]
+    unstable_flags: Vec<String>,
 
-    /// Path to config toml to read
+    /// Path to the config file to read, which is TOML unless its name ends in `.json`, `.yaml`,
+    /// or `.yml`
     #[arg(long)]
     config: Option<PathBuf>,
     /// Enable verbose output for debugging
@@ -78,6 +299,197 @@ struct CheckExternalTypesArgs {
     /// Format to output results in
     #[arg(long, default_value_t = OutputFormat::Errors)]
     output_format: OutputFormat,
+    /// Write the output to a file instead of stdout
+    #[arg(long)]
+    output_file: Option<PathBuf>,
+    /// Only output the first N errors and warnings with the `errors` format, and count the rest
+    #[arg(long, value_name = "N")]
+    max_errors: Option<usize>,
+    /// Output markdown table rows as they're discovered rather than sorting them
+    #[arg(long, conflicts_with = "sort_by")]
+    no_sort: bool,
+    /// Order to output results in: `file`, `crate`, `type`, or `severity`
+    #[arg(long)]
+    sort_by: Option<SortBy>,
+    /// Only output the headline and location of each error without reading source files
+    #[arg(long)]
+    no_context: bool,
+    /// Report which types each allowed pattern matched, and suggest narrower patterns for the
+    /// ones that are broader than needed
+    #[arg(long)]
+    pattern_stats: bool,
+    /// Write a config file to the given path whose `allowed_external_types` allow all of the
+    /// unapproved types that were found
+    #[arg(long, value_name = "PATH")]
+    emit_allowlist: Option<PathBuf>,
+    /// Write allowlists of every type that the workspace members expose to the given directory,
+    /// and report which types only one member exposes
+    #[arg(long, value_name = "DIR", requires = "workspace")]
+    emit_workspace_allowlist: Option<PathBuf>,
+    /// Whether `--emit-workspace-allowlist` writes a `shared` workspace-level config, or
+    /// `per-member` package configs
+    #[arg(long, default_value_t = AllowlistLayout::Shared)]
+    allowlist_layout: AllowlistLayout,
+    /// Suppress the findings that are recorded in the given baseline file, so that only new
+    /// findings are reported
+    #[arg(long, value_name = "PATH")]
+    baseline: Option<PathBuf>,
+    /// Record the findings in the given baseline file, to suppress them in later runs with
+    /// `--baseline`
+    #[arg(long, value_name = "PATH")]
+    write_baseline: Option<PathBuf>,
+    /// Only report findings on lines that changed since the given git ref, according to
+    /// `git diff <GIT_REF>`
+    #[arg(long, value_name = "GIT_REF")]
+    since: Option<String>,
+    /// Append a timestamped summary of the findings to the given history file, for the `trend`
+    /// subcommand to report on
+    #[arg(long, value_name = "PATH")]
+    history_file: Option<PathBuf>,
+    /// Update the config in place, by allowing the unapproved types that were found and removing
+    /// the `allowed_external_types` patterns that didn't allow anything
+    #[arg(long, conflicts_with = "published_crate")]
+    fix: bool,
+    /// Remove the `allowed_external_types` patterns that didn't allow anything from the config,
+    /// without allowing any new types
+    #[arg(long, conflicts_with_all = ["published_crate", "fix"])]
+    prune_config: bool,
+    /// Stop at the first error rather than looking for all of them, for quicker iteration on
+    /// large crates
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "fix",
+            "prune_config",
+            "emit_allowlist",
+            "emit_workspace_allowlist",
+            "baseline",
+            "write_baseline",
+            "since",
+            "history_file",
+        ]
+    )]
+    fail_fast: bool,
+    /// Print the config of each package, after merging it with the workspace's config, the
+    /// files it extends, and the defaults, instead of checking the package
+    #[arg(long, conflicts_with_all = ["fix", "prune_config", "emit_allowlist"])]
+    print_config: bool,
+    /// Check items marked `#[doc(hidden)]` as if they were public, rather than warning that
+    /// they can't be checked. This runs rustdoc with `--document-hidden-items`.
+    #[arg(long, visible_alias = "document-hidden-items")]
+    include_hidden_items: bool,
+    /// Ignore `RUSTDOCFLAGS` set in the environment, and `rustdocflags` set in the cargo config,
+    /// when running rustdoc
+    #[arg(long)]
+    no_inherit_rustdocflags: bool,
+    /// Whether the `inherited` rustdoc flags or the `tool`'s own flags are passed last, and so
+    /// take precedence for options where the last one given is used
+    #[arg(long, default_value_t = RustdocFlagsPrecedence::Tool)]
+    rustdocflags_precedence: RustdocFlagsPrecedence,
+    /// Print the rustdoc JSON format versions and nightly toolchains this tool supports, and exit
+    #[arg(long)]
+    version_info: bool,
+    /// Only fail when more than N errors are found, for crates that are partway through a
+    /// cleanup. Errors within the budget are still output.
+    #[arg(long, value_name = "N")]
+    max_allowed_errors: Option<usize>,
+    /// Fail when more than N warnings are found
+    #[arg(long, value_name = "N")]
+    max_allowed_warnings: Option<usize>,
+    /// Exit code when errors were found. Defaults to 1.
+    #[arg(long, value_name = "CODE")]
+    errors_exit_code: Option<u8>,
+    /// Exit code when only warnings were found, such as 3 to tell them apart from a clean run.
+    /// Defaults to 0.
+    #[arg(long, value_name = "CODE")]
+    warnings_exit_code: Option<u8>,
+    /// Exit code when the tool itself fails. Defaults to 2. Invalid arguments always exit with 2.
+    #[arg(long, value_name = "CODE")]
+    failure_exit_code: Option<u8>,
+    /// Print the JSON Schema of the config, for editors to validate and complete configs with,
+    /// and exit
+    #[arg(long)]
+    print_config_schema: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug, Eq, PartialEq)]
+enum Command {
+    /// Compare two reports written with `--output-format json`, and fail if the new one has
+    /// violations that the old one doesn't
+    Compare(CompareArgs),
+    /// Rewrite the config in a normalized form with sorted and deduplicated allowed types
+    FmtConfig(FmtConfigArgs),
+    /// Add a starter config that lists the crate's dependencies to its Cargo.toml, after
+    /// checking that a nightly toolchain is available
+    Init(InitArgs),
+    /// Check the config for invalid and redundant patterns, without running rustdoc
+    LintConfig(LintConfigArgs),
+    /// Move the config from a config file into the package metadata in Cargo.toml, or back
+    MigrateConfig(MigrateConfigArgs),
+    /// Report whether the findings recorded with `--history-file` are increasing or decreasing
+    Trend(TrendArgs),
+}
+
+#[derive(clap::Args, Debug, Default, Eq, PartialEq)]
+struct CompareArgs {
+    /// Path to the report to compare against, such as one from the base branch
+    old: PathBuf,
+    /// Path to the report to check for added violations
+    new: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Default, Eq, PartialEq)]
+struct LintConfigArgs {
+    /// Path to the config toml to check. Defaults to the package metadata in Cargo.toml.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Path to the Cargo manifest, whose package name is used to find patterns for the crate's
+    /// own types
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Default, Eq, PartialEq)]
+struct MigrateConfigArgs {
+    /// Path to the config file to move the config from, or to with `--to-file`
+    #[arg(long)]
+    config: PathBuf,
+    /// Path to the Cargo manifest
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+    /// Move the config from the package metadata in Cargo.toml to the config file instead
+    #[arg(long)]
+    to_file: bool,
+}
+
+#[derive(clap::Args, Debug, Default, Eq, PartialEq)]
+struct TrendArgs {
+    /// Path to the history file written by `--history-file`
+    #[arg(long, value_name = "PATH")]
+    history_file: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Default, Eq, PartialEq)]
+struct InitArgs {
+    /// Path to the Cargo manifest
+    #[arg(long)]
+    manifest_path: Option<PathBuf>,
+    /// Workspace member to add the config to, as a package name or `name@version`
+    #[arg(short, long)]
+    package: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Default, Eq, PartialEq)]
+struct FmtConfigArgs {
+    /// Path to the config toml to format. Defaults to the package metadata in Cargo.toml.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Path to the Cargo manifest
+    #[arg(long, conflicts_with = "config")]
+    manifest_path: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug, Eq, PartialEq)]
@@ -87,6 +499,8 @@ enum Args {
 }
 
 enum Error {
+    /// Only warnings were found
+    Warnings,
     ValidationErrors,
     Failure(anyhow::Error),
 }
@@ -97,19 +511,300 @@ impl From<anyhow::Error> for Error {
     }
 }
 
+impl CheckExternalTypesArgs {
+    /// Returns the exit code for the outcome of a run.
+    fn exit_code(&self, result: &Result<(), Error>) -> i32 {
+        i32::from(match result {
+            Ok(()) => 0,
+            Err(Error::Warnings) => self.warnings_exit_code.unwrap_or(0),
+            Err(Error::ValidationErrors) => self.errors_exit_code.unwrap_or(1),
+            Err(Error::Failure(_)) => self.failure_exit_code.unwrap_or(2),
+        })
+    }
+}
+
 fn main() {
-    process::exit(match run_main() {
-        Ok(_) => 0,
-        Err(Error::ValidationErrors) => 1,
-        Err(Error::Failure(err)) => {
-            println!("{:#}", dbg!(err));
-            2
+    let Args::CheckExternalTypes(args) = Args::parse();
+    if let Some(command) = &args.command {
+        let result = run_command(command);
+        if let Err(Error::Failure(err)) = &result {
+            println!("{err:#}");
+        }
+        process::exit(args.exit_code(&result));
+    }
+    if args.version_info || args.print_config_schema {
+        let result = if args.version_info {
+            print_version_info(&args)
+        } else {
+            print_config_schema(&args)
+        };
+        if let Err(err) = &result {
+            println!("{err:#}");
+        }
+        process::exit(args.exit_code(&result.map_err(Error::from)));
+    }
+    let mut phase = Phase::Setup;
+    let result = run_main(&args, &mut phase);
+    if let Err(Error::Failure(err)) = &result {
+        if args.output_format == OutputFormat::Json {
+            // Keep the machine-readable output parseable by only writing the failure object
+            // to it, and the human readable error to stderr.
+            eprintln!("{err:#}");
+            if let Err(output_err) =
+                open_output(&args).and_then(|mut out| print_json_failure(&mut out, phase, err))
+            {
+                eprintln!("failed to output failure summary: {output_err:#}");
+            }
+        } else {
+            println!("{err:#}");
+        }
+    }
+    process::exit(args.exit_code(&result));
+}
+
+fn run_command(command: &Command) -> Result<(), Error> {
+    match command {
+        Command::Compare(args) => {
+            let old = compare::read_report(&args.old)?;
+            let new = compare::read_report(&args.new)?;
+            let comparison = compare::Comparison::new(&old, &new);
+            let mut out = io::stdout().lock();
+            comparison.print(&mut out)?;
+            if !comparison.added.is_empty() {
+                return Err(Error::ValidationErrors);
+            }
+            Ok(())
+        }
+        Command::FmtConfig(args) => {
+            let (path, table_path) = match (&args.config, &args.manifest_path) {
+                (Some(config), _) => (config.clone(), &[][..]),
+                (None, manifest_path) => (
+                    manifest_path
+                        .clone()
+                        .unwrap_or_else(|| PathBuf::from("Cargo.toml")),
+                    fmt_config::CARGO_TOML_TABLE_PATH,
+                ),
+            };
+            if ConfigFormat::from_path(&path)? != ConfigFormat::Toml {
+                return Err(anyhow!("only TOML config files can be formatted: {path:?}").into());
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read config file {path:?}"))?;
+            let formatted = fmt_config::format_config(&contents, table_path)
+                .with_context(|| format!("failed to format config file {path:?}"))?;
+            if formatted != contents {
+                fs::write(&path, formatted)
+                    .with_context(|| format!("failed to write config file {path:?}"))?;
+                eprintln!("Formatted {}", path.display());
+            }
+            Ok(())
+        }
+        Command::Init(args) => {
+            let release = check_nightly_toolchain()?;
+            eprintln!("Found nightly toolchain {release}");
+            let mut cargo_metadata_cmd = cargo_metadata::MetadataCommand::new();
+            if let Some(manifest_path) = &args.manifest_path {
+                cargo_metadata_cmd.manifest_path(manifest_path);
+            }
+            let cargo_metadata = cargo_metadata_cmd.exec().context(here!())?;
+            let package = resolve_root_package(&cargo_metadata, args.package.as_deref())?;
+            let path = &package.manifest_path;
+            let dependencies = direct_dependency_names(&cargo_metadata, &package.id);
+            let manifest = fs::read_to_string(path)
+                .with_context(|| format!("failed to read manifest {path:?}"))?;
+            let updated = init::add_starter_config(&manifest, &dependencies)
+                .with_context(|| format!("failed to add a config to {path:?}"))?;
+            fs::write(path, updated)
+                .with_context(|| format!("failed to write manifest {path:?}"))?;
+            eprintln!(
+                "Added a config to {path} with {} dependencies to review",
+                dependencies.len()
+            );
+            Ok(())
+        }
+        Command::LintConfig(args) => {
+            let manifest_path = args
+                .manifest_path
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("Cargo.toml"));
+            let (path, table_path) = match &args.config {
+                Some(config) => (config.clone(), &[][..]),
+                None => (manifest_path.clone(), fmt_config::CARGO_TOML_TABLE_PATH),
+            };
+            if ConfigFormat::from_path(&path)? != ConfigFormat::Toml {
+                return Err(anyhow!("only TOML config files can be linted: {path:?}").into());
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("failed to read config file {path:?}"))?;
+            // A standalone config is linted without the crate's name if there's no manifest
+            let root_crate_name = if args.config.is_none() || manifest_path.exists() {
+                Some(manifest_package_name(&manifest_path)?)
+            } else {
+                None
+            };
+            let lints = lint_config(&contents, table_path, root_crate_name.as_deref())
+                .with_context(|| format!("failed to lint config file {path:?}"))?;
+            if lints.is_empty() {
+                eprintln!("No problems found in {}", path.display());
+                return Ok(());
+            }
+            let mut out = io::stdout().lock();
+            print_config_lints(&mut out, &lints)?;
+            if lints.iter().any(|lint| lint.level == ErrorLevel::Error) {
+                return Err(Error::ValidationErrors);
+            }
+            Err(Error::Warnings)
+        }
+        Command::MigrateConfig(args) => {
+            let manifest_path = args
+                .manifest_path
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("Cargo.toml"));
+            if ConfigFormat::from_path(&args.config)? != ConfigFormat::Toml {
+                return Err(
+                    anyhow!("only TOML config files can be migrated: {:?}", args.config).into(),
+                );
+            }
+            let manifest = fs::read_to_string(&manifest_path)
+                .with_context(|| format!("failed to read manifest {manifest_path:?}"))?;
+            let manifest_dir = parent_dir(&manifest_path)?;
+            let config_dir = parent_dir(&args.config)?;
+            if args.to_file {
+                if args.config.exists() {
+                    return Err(anyhow!("config file {:?} already exists", args.config).into());
+                }
+                let config =
+                    migrate::metadata_to_config_file(&manifest, &manifest_dir, &config_dir)?;
+                fs::write(&args.config, config)
+                    .with_context(|| format!("failed to write config file {:?}", args.config))?;
+                eprintln!(
+                    "Copied the config in {} to {}. Remove it from {}, and pass `--config {}`.",
+                    manifest_path.display(),
+                    args.config.display(),
+                    manifest_path.display(),
+                    args.config.display()
+                );
+            } else {
+                let config = fs::read_to_string(&args.config)
+                    .with_context(|| format!("failed to read config file {:?}", args.config))?;
+                let manifest = migrate::config_file_to_metadata(
+                    &config,
+                    &config_dir,
+                    &manifest,
+                    &manifest_dir,
+                )?;
+                fs::write(&manifest_path, manifest)
+                    .with_context(|| format!("failed to write manifest {manifest_path:?}"))?;
+                eprintln!(
+                    "Copied the config in {} to {}. Remove {}, and stop passing it with `--config`.",
+                    args.config.display(),
+                    manifest_path.display(),
+                    args.config.display()
+                );
+            }
+            Ok(())
+        }
+        Command::Trend(args) => {
+            let entries = history::read_history(&args.history_file)?;
+            let mut out = io::stdout().lock();
+            history::print_trend(&mut out, &entries)?;
+            Ok(())
         }
+    }
+}
+
+/// Returns the name of the package in the manifest at `manifest_path`.
+fn manifest_package_name(manifest_path: &Path) -> Result<String> {
+    let manifest: toml::Table = fs::read_to_string(manifest_path)
+        .with_context(|| format!("failed to read manifest {manifest_path:?}"))?
+        .parse()
+        .with_context(|| format!("failed to parse manifest {manifest_path:?}"))?;
+    match manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(toml::Value::as_str)
+    {
+        Some(name) => Ok(name.to_string()),
+        None => bail!("manifest {manifest_path:?} doesn't have a package name"),
+    }
+}
+
+/// Returns the absolute path of the directory that contains `path`.
+fn parent_dir(path: &Path) -> Result<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    fs::canonicalize(parent).with_context(|| format!("failed to find directory {parent:?}"))
+}
+
+/// Outputs the supported versions as JSON with `--output-format json`, or as text otherwise.
+fn print_version_info(args: &CheckExternalTypesArgs) -> Result<()> {
+    let info = VersionInfo::current();
+    let mut out = open_output(args)?;
+    if args.output_format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut out, &info)?;
+        writeln!(out)?;
+    } else {
+        write!(out, "{info}")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn print_config_schema(args: &CheckExternalTypesArgs) -> Result<()> {
+    let mut out = open_output(args)?;
+    serde_json::to_writer_pretty(&mut out, &Config::json_schema())?;
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Opens the writer that output should go to based on `--output-file`.
+fn open_output(args: &CheckExternalTypesArgs) -> Result<Box<dyn Write>> {
+    Ok(if let Some(output_file) = &args.output_file {
+        // Don't write color escape codes into files
+        owo_colors::set_override(false);
+        let file = fs::File::create(output_file)
+            .with_context(|| format!("failed to create output file {output_file:?}"))?;
+        Box::new(BufWriter::new(file))
+    } else {
+        Box::new(io::stdout().lock())
     })
 }
 
-fn run_main() -> Result<(), Error> {
-    let Args::CheckExternalTypes(args) = Args::parse();
+/// Prints the effective config of each of the `packages` as TOML.
+fn print_configs(
+    args: &CheckExternalTypesArgs,
+    cargo_metadata: &Metadata,
+    packages: &[&Package],
+) -> Result<()> {
+    let mut out = open_output(args)?;
+    let targets: Vec<Option<&str>> = match args.target.as_slice() {
+        [] => vec![None],
+        targets => targets.iter().map(|target| Some(target.as_str())).collect(),
+    };
+    let mut first = true;
+    for package in packages {
+        for &target in &targets {
+            let config = package_config(args, cargo_metadata, package, target)?;
+            let config = toml::to_string_pretty(&config).context("failed to serialize config")?;
+            if !first {
+                writeln!(out)?;
+            }
+            first = false;
+            match target {
+                Some(target) if targets.len() > 1 => {
+                    writeln!(out, "# Config of {} on {target}\n{config}", package.name)?
+                }
+                _ => writeln!(out, "# Config of {}\n{config}", package.name)?,
+            }
+        }
+    }
+    out.flush().context("failed to write output")
+}
+
+fn run_main(args: &CheckExternalTypesArgs, phase: &mut Phase) -> Result<(), Error> {
     if args.verbose {
         let filter_layer = EnvFilter::try_from_default_env()
             .or_else(|_| EnvFilter::try_new("debug"))
@@ -126,168 +821,1224 @@ fn run_main() -> Result<(), Error> {
             .init();
     }
 
+    *phase = Phase::Metadata;
     let mut cargo_metadata_cmd = cargo_metadata::MetadataCommand::new();
-    if args.all_features {
+    if let Some(published_crate) = &args.published_crate {
+        // The features are set on the scratch project's dependency, since they can't be set
+        // for packages outside of the workspace on the command line
+        eprintln!("Downloading {published_crate}...");
+        let manifest_path = published_crate.create_scratch_project(
+            args.features.as_deref().unwrap_or_default(),
+            !args.no_default_features,
+        )?;
+        cargo_metadata_cmd.manifest_path(manifest_path);
+    } else if args.all_features {
         cargo_metadata_cmd.features(CargoOpt::AllFeatures);
     }
-    if args.no_default_features {
-        cargo_metadata_cmd.features(CargoOpt::NoDefaultFeatures);
+    if args.published_crate.is_none() {
+        if args.no_default_features {
+            cargo_metadata_cmd.features(CargoOpt::NoDefaultFeatures);
+        }
+        if let Some(features) = &args.features {
+            cargo_metadata_cmd.features(CargoOpt::SomeFeatures(features.clone()));
+        }
     }
-    if let Some(features) = args.features {
-        cargo_metadata_cmd.features(CargoOpt::SomeFeatures(features));
+    if let Some(manifest_path) = &args.manifest_path {
+        cargo_metadata_cmd.manifest_path(manifest_path);
     }
-    let crate_path = if let Some(manifest_path) = args.manifest_path {
-        cargo_metadata_cmd.manifest_path(&manifest_path);
-        manifest_path
-            .canonicalize()
-            .context(here!())?
-            .parent()
-            .expect("parent path")
-            .to_path_buf()
-    } else {
-        std::env::current_dir()
-            .context(here!())?
-            .canonicalize()
-            .context(here!())?
-    };
+    cargo_metadata_cmd.other_options(args.cargo_flags());
     let cargo_metadata = cargo_metadata_cmd.exec().context(here!())?;
-
-    let config = if let Some(config_path) = &args.config {
-        let contents = fs::read_to_string(config_path).context("failed to read config file")?;
-        toml::from_str(&contents).context("failed to parse config file")?
+    let packages = if args.workspace {
+        resolve_workspace_packages(&cargo_metadata, &args.exclude)
+    } else if let Some(published_crate) = &args.published_crate {
+        vec![published_crate.find_package(&cargo_metadata)?]
     } else {
-        resolve_config(&cargo_metadata)
-            .context("failed to parse config from Cargo.toml metadata")?
+        vec![resolve_root_package(
+            &cargo_metadata,
+            args.package.as_deref(),
+        )?]
     };
 
-    let cargo_features = resolve_features(&cargo_metadata)?;
-    let cargo_lib_name = resolve_lib_name(&cargo_metadata)?;
-
-    eprintln!("Running rustdoc to produce json doc output...");
-    let package = CargoRustDocJson::new(
-        cargo_lib_name,
-        crate_path,
-        &cargo_metadata.target_directory,
-        cargo_features,
-        args.target.clone(),
-    )
-    .run()
-    .context(here!())?;
+    if args.print_config {
+        *phase = Phase::Config;
+        print_configs(args, &cargo_metadata, &packages)?;
+        return Ok(());
+    }
 
-    eprintln!("Examining all public types...");
-    let errors = Visitor::new(config, package)?.visit_all()?;
-    match args.output_format {
-        OutputFormat::Errors => {
-            ErrorPrinter::new(&cargo_metadata.workspace_root).pretty_print_errors(&errors);
-            if errors.error_count() > 0 {
-                return Err(Error::ValidationErrors);
+    let mut out = None;
+    let mut workspace_errors = WorkspaceErrors::new();
+    let mut anchored_patterns = false;
+    let mut fixes: BTreeMap<ConfigLocation, PendingFix> = BTreeMap::new();
+    let mut member_allowlists = MemberAllowlists::new();
+    let mut table = MarkdownTablePrinter::new(!args.no_sort, args.sort_by);
+    for package in packages {
+        if args.workspace {
+            eprintln!("Checking {}...", package.name);
+        }
+        *phase = Phase::Metadata;
+        let variations = build_variations(args, package)?;
+        let mut check = match variations.first() {
+            Some(variation) => {
+                eprintln!("Checking with {}...", variation.label);
+                variation.prepare_check(args, &cargo_metadata, package, phase)?
             }
+            None => prepare_check(
+                args,
+                &cargo_metadata,
+                package,
+                args.target.first().map(String::as_str),
+                phase,
+            )?,
+        };
+        if args.fail_fast {
+            check = check.fail_fast();
         }
-        OutputFormat::MarkdownTable => {
-            println!("| Crate | Type | Used In |");
-            println!("| ---   | ---  | ---     |");
-            let mut rows = Vec::new();
-            for error in errors.iter() {
-                if let ValidationError::UnapprovedExternalTypeRef { .. } = error {
-                    let type_name = error.type_name();
-                    let crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
-                    let location = error.location().unwrap();
-                    rows.push(format!(
-                        "| {} | {} | {}:{}:{} |",
-                        crate_name,
-                        type_name,
-                        location.filename.to_string_lossy(),
-                        location.begin.0,
-                        location.begin.1
-                    ));
+        let package_anchored_patterns = check.config().anchored_patterns;
+        let package_config = check.config().clone();
+        anchored_patterns |= package_anchored_patterns;
+        let unknown_crate_patterns = if args.fix || args.prune_config {
+            check.unknown_crate_patterns()
+        } else {
+            Vec::new()
+        };
+
+        *phase = Phase::Output;
+        let out = match &mut out {
+            Some(out) => out,
+            None => {
+                let mut new_out = open_output(args)?;
+                if args.output_format == OutputFormat::MarkdownTable {
+                    table.print_header(&mut new_out)?;
+                }
+                out.insert(new_out)
+            }
+        };
+
+        *phase = Phase::Visit;
+        let mut package_errors = if let Some(first) = variations.first() {
+            let mut package_errors = ValidationErrors::new();
+            package_errors.merge_variation(&first.label, check.visit_all()?);
+            for variation in &variations[1..] {
+                eprintln!("Checking with {}...", variation.label);
+                let mut check = variation.prepare_check(args, &cargo_metadata, package, phase)?;
+                if args.fail_fast {
+                    check = check.fail_fast();
+                }
+                *phase = Phase::Visit;
+                package_errors.merge_variation(&variation.label, check.visit_all()?);
+            }
+            package_errors.finish_variations();
+            // Rows aren't streamed, since the same finding can be found with several variations
+            if args.output_format == OutputFormat::MarkdownTable {
+                for error in package_errors.iter() {
+                    table.add_row(out, error)?;
                 }
             }
-            rows.sort();
-            rows.into_iter().for_each(|row| println!("{}", row));
+            package_errors
+        } else if args.output_format == OutputFormat::MarkdownTable {
+            // Visit on another thread so that rows can be output as they're discovered
+            let (sender, receiver) = mpsc::channel();
+            let check = check.stream_errors_to(sender);
+            thread::scope(|scope| {
+                let visiting = scope.spawn(move || check.visit_all());
+                for error in receiver {
+                    table.add_row(out, &error)?;
+                }
+                visiting.join().expect("visitor thread panicked")
+            })?
+        } else {
+            check.visit_all()?
+        };
+        add_approval_findings(&mut package_errors, &package_config);
+        if args.fix || args.prune_config {
+            // Packages can share a config file, so it's only fixed once all of them are checked
+            let fix = fixes
+                .entry(config_location(args, &cargo_metadata, package))
+                .or_default();
+            if args.fix {
+                fix.unapproved_types
+                    .extend(unapproved_types(&package_errors));
+            }
+            fix.used_patterns.extend(
+                package_errors
+                    .pattern_stats()
+                    .used_patterns()
+                    .map(String::from),
+            );
+            fix.anchored_patterns |= package_anchored_patterns;
+            // A pattern only names an unknown crate if none of the packages depend on it
+            fix.unknown_crate_patterns = Some(match fix.unknown_crate_patterns.take() {
+                Some(previous) => previous
+                    .into_iter()
+                    .filter(|pattern| unknown_crate_patterns.contains(pattern))
+                    .collect(),
+                None => unknown_crate_patterns,
+            });
+        }
+        if args.emit_workspace_allowlist.is_some() {
+            member_allowlists.add(&package.name, exposed_types(&package_errors));
+        }
+        let stop = args.fail_fast && package_errors.error_count() > 0;
+        workspace_errors.add(package.id.clone(), &package.name, package_errors);
+        if stop {
+            eprintln!("Stopped at the first error because of `--fail-fast`");
+            break;
         }
     }
-
-    Ok(())
-}
-
-fn resolve_config(metadata: &Metadata) -> Result<Config> {
-    let crate_metadata = match serde_json::from_value::<HashMap<String, serde_json::Value>>(
-        resolve_root_package(metadata)?.metadata.clone(),
-    ) {
-        Ok(m) => m,
-        // We avoid using ? on the serde_json::from_value because when the metadata is not provided
-        // this will err trying to unmarshal a null value into a map. In this instance we want to
-        // use the default config.
-        Err(_) => return Ok(Default::default()),
+    let workspace_summaries = if args.workspace {
+        workspace_errors.external_type_summaries()
+    } else {
+        Vec::new()
+    };
+    let mut errors = workspace_errors.into_merged();
+    *phase = Phase::Config;
+    for (location, fix) in fixes {
+        apply_fix(&location, &fix)?;
+    }
+    if args.fix {
+        // The types that were fixed are allowed by the config now
+        errors.suppress_where(SuppressionMechanism::AllowedExternalTypes, |error| {
+            matches!(error, ValidationError::UnapprovedExternalTypeRef { .. })
+        });
+    }
+    if let Some(path) = &args.baseline {
+        let baseline = Baseline::from_file(path)?;
+        errors.suppress_where(SuppressionMechanism::Baseline, |error| {
+            baseline.contains(error)
+        });
+        let stale = baseline.stale_entries(&errors).count();
+        if stale > 0 {
+            eprintln!(
+                "{stale} findings in {} no longer occur. Pass `--write-baseline` to remove them.",
+                path.display()
+            );
+        }
+    }
+    if let Some(path) = &args.write_baseline {
+        let baseline = Baseline::from_errors(&errors);
+        baseline.write(path)?;
+        eprintln!(
+            "Wrote baseline with {} findings to {}",
+            baseline.len(),
+            path.display()
+        );
+        // The recorded findings are accepted from now on
+        errors.suppress_where(SuppressionMechanism::Baseline, |_| true);
+    }
+    if let Some(git_ref) = &args.since {
+        let workspace_root = cargo_metadata.workspace_root.as_std_path();
+        let changes = ChangedLines::since(git_ref, workspace_root)?;
+        // Findings without a location can't be attributed to old code, so they're kept
+        errors.suppress_where(SuppressionMechanism::Since, |error| {
+            error
+                .location()
+                .is_some_and(|span| !changes.overlaps(span, workspace_root))
+        });
+    }
+    let pattern_stats = if args.pattern_stats {
+        errors.pattern_stats().reports()
+    } else {
+        Vec::new()
     };
 
-    Ok(
-        if let Some(our_metadata) = crate_metadata.get(env!("CARGO_CRATE_NAME")) {
-            // Here we do use ? to propagate the error from the unmarshal - it would indicate
-            // the metadata config is present, but invalid.
-            serde_json::from_value(our_metadata.clone())?
-        } else {
-            Default::default()
-        },
-    )
+    *phase = Phase::Output;
+    let mut out = match out {
+        Some(out) => out,
+        None => open_output(args)?,
+    };
+    match args.output_format {
+        OutputFormat::Errors => ErrorPrinter::new(&cargo_metadata.workspace_root)
+            .with_context(!args.no_context)
+            .with_sort_by(args.sort_by.unwrap_or_default())
+            .with_max_errors(args.max_errors)
+            .with_workspace_summaries(workspace_summaries)
+            .with_pattern_stats(pattern_stats)
+            .pretty_print_errors(&mut out, &errors)?,
+        OutputFormat::MarkdownTable => table.finish(&mut out, &errors)?,
+        OutputFormat::Json => {
+            print_json_report(&mut out, &errors, &workspace_summaries, &pattern_stats)?
+        }
+        OutputFormat::Rdjson => print_rdjson(&mut out, &errors)?,
+        OutputFormat::TeamCity => print_teamcity(&mut out, &errors)?,
+        OutputFormat::Badge => print_badge(&mut out, &errors)?,
+    }
+    out.flush().context("failed to write output")?;
+    if let Some(path) = &args.history_file {
+        HistoryEntry::now(&errors).append_to(path)?;
+    }
+    if let Some(path) = &args.emit_allowlist {
+        let allowlist = render_allowlist(&allowlist_patterns(
+            &unapproved_types(&errors),
+            anchored_patterns,
+        ))?;
+        fs::write(path, allowlist)
+            .with_context(|| format!("failed to write allowlist to {path:?}"))?;
+        eprintln!("Wrote allowlist to {}", path.display());
+    }
+    if let Some(dir) = &args.emit_workspace_allowlist {
+        write_workspace_allowlists(
+            dir,
+            args.allowlist_layout,
+            &member_allowlists,
+            anchored_patterns,
+        )?;
+    }
+    let errors_over_budget = match args.max_allowed_errors {
+        Some(budget) => check_budget("errors", errors.error_count(), budget),
+        None => errors.error_count() > 0,
+    };
+    let warnings_over_budget = args
+        .max_allowed_warnings
+        .is_some_and(|budget| check_budget("warnings", errors.warning_count(), budget));
+    // The markdown table is a discovery tool, so it doesn't fail on errors
+    if args.output_format == OutputFormat::MarkdownTable || errors.is_empty() {
+        Ok(())
+    } else if errors_over_budget || warnings_over_budget {
+        Err(Error::ValidationErrors)
+    } else {
+        // Errors within their budget are reported like warnings
+        Err(Error::Warnings)
+    }
 }
 
-fn resolve_features(metadata: &Metadata) -> Result<Vec<String>> {
-    let root_package = resolve_root_package(metadata)?;
-    if let Some(resolve) = &metadata.resolve {
-        let root_node = resolve
-            .nodes
-            .iter()
-            .find(|&n| n.id == root_package.id)
-            .ok_or_else(|| anyhow!("Failed to find node for root package"))?;
-        Ok(root_node.features.clone())
+/// Returns true if `count` findings of the given kind are over `budget`. Otherwise, suggests
+/// lowering the budget if there are fewer findings than it allows, so that it can only go down.
+fn check_budget(kind: &str, count: usize, budget: usize) -> bool {
+    if count > budget {
+        eprintln!("Found {count} {kind}, which is over the budget of {budget}");
+        true
     } else {
-        bail!("Cargo metadata didn't have resolved nodes");
+        if count < budget {
+            eprintln!(
+                "Found {count} {kind}, which is under the budget of {budget}. \
+                Lower the budget to {count} to keep it from coming back up."
+            );
+        }
+        false
     }
 }
 
-fn resolve_lib_name(metadata: &Metadata) -> Result<String> {
-    let lib_targets = resolve_root_package(metadata)?
-        .targets
-        .iter()
-        .filter(|t| t.kind.iter().any(|k| k == "lib"))
-        .collect::<Vec<_>>();
-    if lib_targets.len() != 1 {
-        bail!(
-            "Expected crate to define 1 lib target, found {}",
-            lib_targets.len()
-        );
+/// Returns the file name that `--emit-rustdoc-json` copies a lib's rustdoc JSON to, such as
+/// `my_crate.json`. When several feature sets or targets are checked, the `features` or `target`
+/// are added so that their JSON doesn't overwrite each other's, as in
+/// `my_crate.default,tls.x86_64-pc-windows-msvc.json`.
+fn emitted_json_name(lib_name: &str, target: Option<&str>, features: Option<&[String]>) -> String {
+    let mut name = lib_name.replace('-', "_");
+    if let Some(features) = features {
+        name.push('.');
+        if features.is_empty() {
+            name.push_str("no-features");
+        } else {
+            name.push_str(&features.join(","));
+        }
+    }
+    if let Some(target) = target {
+        name.push('.');
+        name.push_str(target);
     }
-    Ok(lib_targets.first().unwrap().name.clone())
+    name.push_str(".json");
+    name
 }
 
-fn resolve_root_package(metadata: &Metadata) -> Result<&Package> {
-    metadata
-        .root_package()
-        .ok_or_else(|| {
-            let workspace_members = metadata.workspace_members.as_slice().iter().map(|id| id.to_string()).collect::<Vec<_>>().join("\n");
-            if !workspace_members.is_empty() {
-                anyhow!("it appears you're trying to run `cargo-check-external-types` on a workspace Cargo.toml; Instead, run it on one of the workspace member Cargo.tomls directly:\n{workspace_members}")
-            } else {
-                anyhow!("No root package found")
-            }
-        })
+/// Returns the files that rustdoc JSON depends on: the directories of the packages that aren't
+/// from a registry or git, and the workspace's manifest, lock file, cargo config, and toolchain
+/// file. Changes to the others update Cargo.lock. The cargo config is listed on its own since
+/// hidden directories such as `.cargo` aren't looked in.
+fn reuse_inputs(cargo_metadata: &Metadata) -> Vec<PathBuf> {
+    let workspace_root = cargo_metadata.workspace_root.as_std_path();
+    let mut inputs = vec![
+        workspace_root.join("Cargo.toml"),
+        workspace_root.join("Cargo.lock"),
+        workspace_root.join(".cargo/config.toml"),
+        workspace_root.join(".cargo/config"),
+        workspace_root.join("rust-toolchain.toml"),
+        workspace_root.join("rust-toolchain"),
+    ];
+    inputs.extend(
+        cargo_metadata
+            .packages
+            .iter()
+            .filter(|package| package.source.is_none())
+            .filter_map(|package| package.manifest_path.parent())
+            .map(|dir| dir.as_std_path().to_path_buf()),
+    );
+    inputs
 }
 
-#[cfg(test)]
-mod cli_tests {
-    use super::*;
-    use clap::CommandFactory;
+/// Loads the config for `package` and runs rustdoc on it to get a visitor for its public API,
+/// unless an earlier run with the same inputs cached its findings.
+fn prepare_check(
+    args: &CheckExternalTypesArgs,
+    cargo_metadata: &Metadata,
+    package: &Package,
+    target: Option<&str>,
+    phase: &mut Phase,
+) -> Result<PackageCheck> {
+    *phase = Phase::Config;
+    let package_dir = package.manifest_path.parent().expect("parent path");
+    let config = package_config(args, cargo_metadata, package, target)?;
 
-    #[test]
-    fn verify_cli() {
-        Args::command().debug_assert();
+    let cargo_features = resolve_features(cargo_metadata, package)?;
+    let cargo_lib_name = match &args.lib_name {
+        Some(lib_name) => lib_name.clone(),
+        None => resolve_lib_name(&package.targets, args.lib_kind.as_deref())?,
+    };
+    let dependencies = Dependencies::from_metadata(cargo_metadata, &package.id);
+
+    *phase = Phase::Rustdoc;
+    let mut rustdoc_json = if args.published_crate.is_some() {
+        // Document the dependency from the scratch project so that its lock file and target
+        // directory are used
+        CargoRustDocJson::new(
+            &cargo_lib_name,
+            &cargo_metadata.workspace_root,
+            &cargo_metadata.target_directory,
+        )
+        .package(format!("{}@{}", package.name, package.version))
+    } else {
+        CargoRustDocJson::new(
+            &cargo_lib_name,
+            package_dir,
+            &cargo_metadata.target_directory,
+        )
+        .features(cargo_features.clone())
+    }
+    .inherit_rustdocflags(!args.no_inherit_rustdocflags)
+    .inherited_rustdocflags_last(args.rustdocflags_precedence == RustdocFlagsPrecedence::Inherited)
+    .locked(args.locked)
+    .frozen(args.frozen)
+    .offline(args.offline)
+    .print_command(args.print_command);
+    for config in &args.cargo_config {
+        rustdoc_json = rustdoc_json.cargo_config(config);
+    }
+    for flag in &args.unstable_flags {
+        rustdoc_json = rustdoc_json.unstable_flag(flag);
+    }
+    if let Some(target) = target {
+        rustdoc_json = rustdoc_json.target(target);
+    }
+    if let Some(profile) = &args.profile {
+        rustdoc_json = rustdoc_json.profile(profile);
+    }
+    if let Some(target_dir) = &args.target_dir {
+        // Cargo runs in the crate's directory, so relative paths must be resolved here
+        let target_dir = std::env::current_dir()
+            .context(here!("failed to get the current directory"))?
+            .join(target_dir);
+        rustdoc_json = rustdoc_json.output_dir(target_dir);
     }
+    if let Some(toolchain) = &args.toolchain {
+        rustdoc_json = rustdoc_json.toolchain(toolchain);
+    }
+    rustdoc_json = rustdoc_json.install_toolchain(args.install_toolchain);
+    if !args.no_cache {
+        rustdoc_json = rustdoc_json.reuse_if_newer_than(reuse_inputs(cargo_metadata));
+    }
+    // Hidden items are left out of the rustdoc JSON unless they're asked for
+    let hidden_items_docs = rustdoc_json.clone().document_hidden_items(true);
+    let include_hidden_items = args.include_hidden_items || config.include_hidden_items;
+    if include_hidden_items {
+        rustdoc_json = hidden_items_docs.clone();
+    }
+    // Only the JSON that's checked is emitted, not the JSON with hidden items that's used to
+    // look up re-exports
+    match &args.emit_rustdoc_json {
+        Some(Some(dir)) => {
+            let several_feature_sets = args.feature_matrix || args.check_features.len() > 1;
+            let name = emitted_json_name(
+                &cargo_lib_name,
+                target.filter(|_| args.target.len() > 1),
+                several_feature_sets.then_some(cargo_features.as_slice()),
+            );
+            rustdoc_json = rustdoc_json.copy_json_to(dir.join(name));
+        }
+        Some(None) => rustdoc_json = rustdoc_json.print_json_path(true),
+        None => {}
+    }
+    // Fixing the config needs the visitor, and emitting the JSON needs rustdoc to run
+    let cache_key =
+        if args.no_cache || args.fix || args.prune_config || args.emit_rustdoc_json.is_some() {
+            None
+        } else {
+            Some(results_cache_key(
+                &config,
+                include_hidden_items,
+                args.fail_fast,
+            )?)
+        };
+    if let Some(key) = cache_key {
+        if let Some(errors) = rustdoc_json.cached_results(key) {
+            return Ok(PackageCheck::Cached { config, errors });
+        }
+    }
+    eprintln!("Running rustdoc to produce json doc output...");
+    let package = rustdoc_json.run().context(here!())?;
+    let visitor = Visitor::new(config, package, dependencies)?
+        .with_sources(cargo_metadata.workspace_root.as_std_path());
+    let visitor = if include_hidden_items {
+        visitor
+    } else {
+        // Only needed for public modules that are re-exported out of hidden modules
+        visitor.with_hidden_items_docs(move || {
+            eprintln!("Running rustdoc with hidden items to check modules re-exported out of hidden modules...");
+            hidden_items_docs.run()
+        })
+    };
+    Ok(PackageCheck::Visit {
+        visitor,
+        cache: cache_key.map(|key| (Box::new(rustdoc_json), key)),
+    })
 }
 
-#[cfg(test)]
-mod arg_parse_tests {
-    use super::*;
+/// Returns the key that a package's findings are cached under, alongside the rustdoc JSON they're
+/// found in. It covers everything other than the JSON that the findings depend on.
+fn results_cache_key(config: &Config, include_hidden_items: bool, fail_fast: bool) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    env!("GIT_HASH").hash(&mut hasher);
+    env!("SOURCE_HASH").hash(&mut hasher);
+    rustdoc_types::FORMAT_VERSION.hash(&mut hasher);
+    serde_json::to_string(config)
+        .context(here!())?
+        .hash(&mut hasher);
+    include_hidden_items.hash(&mut hasher);
+    fail_fast.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// The visitor for a package's public API, or the findings that an earlier run with the same
+/// inputs found in it.
+enum PackageCheck {
+    Visit {
+        visitor: Visitor,
+        /// The rustdoc JSON builder and key to cache the findings with, if they're cached
+        cache: Option<(Box<CargoRustDocJson>, u64)>,
+    },
+    Cached {
+        config: Config,
+        errors: ValidationErrors,
+    },
+}
+
+impl PackageCheck {
+    fn config(&self) -> &Config {
+        match self {
+            Self::Visit { visitor, .. } => visitor.config(),
+            Self::Cached { config, .. } => config,
+        }
+    }
+
+    fn fail_fast(self) -> Self {
+        match self {
+            Self::Visit { visitor, cache } => Self::Visit {
+                visitor: visitor.fail_fast(),
+                cache,
+            },
+            cached => cached,
+        }
+    }
+
+    fn unknown_crate_patterns(&self) -> Vec<UnknownCratePattern> {
+        match self {
+            Self::Visit { visitor, .. } => visitor.unknown_crate_patterns(),
+            // Findings aren't cached when the config is fixed or pruned
+            Self::Cached { .. } => Vec::new(),
+        }
+    }
+
+    /// Sends each distinct error to `sender` as it's discovered, or right away if the errors
+    /// are cached.
+    fn stream_errors_to(self, sender: mpsc::Sender<ValidationError>) -> Self {
+        match self {
+            Self::Visit { visitor, cache } => Self::Visit {
+                visitor: visitor.stream_errors_to(sender),
+                cache,
+            },
+            Self::Cached { config, errors } => {
+                for error in errors.iter() {
+                    // The receiver only hangs up if output fails, which is reported instead
+                    let _ = sender.send(error.clone());
+                }
+                Self::Cached { config, errors }
+            }
+        }
+    }
+
+    /// Visits the public API, caching the findings if they're cached, or returns the cached
+    /// findings.
+    fn visit_all(self) -> Result<ValidationErrors> {
+        match self {
+            Self::Visit { visitor, cache } => {
+                eprintln!("Examining all public types...");
+                let errors = visitor.visit_all()?;
+                if let Some((rustdoc_json, key)) = cache {
+                    rustdoc_json.cache_results(key, &errors)?;
+                }
+                Ok(errors)
+            }
+            Self::Cached { errors, .. } => Ok(errors),
+        }
+    }
+}
+
+/// Returns the effective config of `package`, from `--config`, the Cargo.toml metadata, or a
+/// config file found next to the package, with the lists for `target` (or the host) added.
+fn package_config(
+    args: &CheckExternalTypesArgs,
+    cargo_metadata: &Metadata,
+    package: &Package,
+    target: Option<&str>,
+) -> Result<Config> {
+    let package_dir = package.manifest_path.parent().expect("parent path");
+    let mut config = if let Some(config_path) = &args.config {
+        Config::from_file(config_path)?
+    } else if metadata_config(&package.metadata).is_none()
+        && metadata_config(&cargo_metadata.workspace_metadata).is_none()
+    {
+        match find_default_config_file(
+            package_dir.as_std_path(),
+            cargo_metadata.workspace_root.as_std_path(),
+        ) {
+            Some(config_path) => {
+                eprintln!("Using config file {}", config_path.display());
+                Config::from_file(&config_path)?
+            }
+            None => Default::default(),
+        }
+    } else {
+        resolve_config(
+            &package.metadata,
+            package_dir.as_std_path(),
+            &cargo_metadata.workspace_metadata,
+            cargo_metadata.workspace_root.as_std_path(),
+        )?
+    };
+    if !config.target.is_empty() {
+        let target = TargetInfo::query(target)?;
+        config.apply_target(&target.name, &target.cfg);
+    }
+    Ok(config)
+}
+
+/// Writes the allowlists of `--emit-workspace-allowlist` to `dir`, and reports the types that
+/// each member exposes beyond the other members.
+fn write_workspace_allowlists(
+    dir: &Path,
+    layout: AllowlistLayout,
+    members: &MemberAllowlists,
+    anchored_patterns: bool,
+) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create directory {dir:?}"))?;
+    let write = |file_name: String, table_path: &[&str], types: &BTreeSet<String>| {
+        let path = dir.join(file_name);
+        let allowlist =
+            render_member_allowlist(table_path, &allowlist_patterns(types, anchored_patterns))?;
+        fs::write(&path, allowlist)
+            .with_context(|| format!("failed to write allowlist to {path:?}"))?;
+        eprintln!("Wrote allowlist to {}", path.display());
+        anyhow::Ok(())
+    };
+    match layout {
+        AllowlistLayout::Shared => write(
+            "workspace.toml".into(),
+            WORKSPACE_TABLE_PATH,
+            &members.union(),
+        )?,
+        AllowlistLayout::PerMember => {
+            for (member, types) in members.members() {
+                write(
+                    format!("{member}.toml"),
+                    fmt_config::CARGO_TOML_TABLE_PATH,
+                    types,
+                )?;
+            }
+        }
+    }
+    for (member, types) in members.members() {
+        let only_exposed = members.only_exposed_by(member);
+        eprint!("{member} exposes {} external types", types.len());
+        if only_exposed.is_empty() {
+            eprintln!();
+        } else {
+            let only_exposed: Vec<_> = only_exposed.into_iter().collect();
+            eprintln!(
+                ", and is the only member to expose: {}",
+                only_exposed.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Where the config of a package is read from, and the path to the config table within that
+/// file (which is empty for standalone config files).
+type ConfigLocation = (PathBuf, &'static [&'static str]);
+
+/// Returns where `--fix` or `--prune-config` should update the config of `package`. That's the config file that it
+/// was read from, or the package's `Cargo.toml` if the config is in the Cargo.toml metadata or
+/// there isn't one.
+fn config_location(
+    args: &CheckExternalTypesArgs,
+    cargo_metadata: &Metadata,
+    package: &Package,
+) -> ConfigLocation {
+    let package_dir = package.manifest_path.parent().expect("parent path");
+    if let Some(config_path) = &args.config {
+        return (config_path.clone(), &[]);
+    }
+    if metadata_config(&package.metadata).is_none()
+        && metadata_config(&cargo_metadata.workspace_metadata).is_none()
+    {
+        if let Some(config_path) = find_default_config_file(
+            package_dir.as_std_path(),
+            cargo_metadata.workspace_root.as_std_path(),
+        ) {
+            return (config_path, &[]);
+        }
+    }
+    (
+        package.manifest_path.clone().into_std_path_buf(),
+        fmt_config::CARGO_TOML_TABLE_PATH,
+    )
+}
+
+/// Returns the metadata that resolves each of the feature sets that `--check-features` or
+/// `--feature-matrix` checks `package` with, labeled by the features that are enabled. Feature
+/// sets that resolve to the same features as an earlier one are left out. Without either
+/// option, this is empty.
+fn feature_variations(
+    args: &CheckExternalTypesArgs,
+    package: &Package,
+) -> Result<Vec<(String, Metadata)>> {
+    let mut feature_sets: Vec<Vec<CargoOpt>> = Vec::new();
+    for features in &args.check_features {
+        let features: Vec<String> = features
+            .split(',')
+            .map(str::trim)
+            .filter(|feature| !feature.is_empty())
+            .map(String::from)
+            .collect();
+        let mut options = Vec::new();
+        if !features.is_empty() {
+            options.push(CargoOpt::SomeFeatures(features));
+        }
+        if args.no_default_features {
+            options.push(CargoOpt::NoDefaultFeatures);
+        }
+        feature_sets.push(options);
+    }
+    if args.feature_matrix {
+        feature_sets.push(Vec::new());
+        feature_sets.push(vec![CargoOpt::NoDefaultFeatures]);
+        for feature in package.features.keys().filter(|name| *name != "default") {
+            feature_sets.push(vec![
+                CargoOpt::NoDefaultFeatures,
+                CargoOpt::SomeFeatures(vec![feature.clone()]),
+            ]);
+        }
+        feature_sets.push(vec![CargoOpt::AllFeatures]);
+    }
+
+    let mut variations: Vec<(String, Metadata)> = Vec::new();
+    let mut checked = BTreeSet::new();
+    for options in feature_sets {
+        let mut command = cargo_metadata::MetadataCommand::new();
+        command.manifest_path(&package.manifest_path);
+        command.other_options(args.cargo_flags());
+        for option in options {
+            command.features(option);
+        }
+        let metadata = command.exec().context(here!())?;
+        let mut features = resolve_features(&metadata, package)?;
+        features.sort();
+        if checked.insert(features.clone()) {
+            variations.push((describe_features(&features), metadata));
+        }
+    }
+    Ok(variations)
+}
+
+/// A variation of the build that a package is checked with, when it's checked with several
+/// feature sets or targets in one run.
+struct Variation {
+    /// Describes the variation in notes, such as "features `default` on target `x`"
+    label: String,
+    /// Metadata that resolves the variation's features, or `None` to use the features that
+    /// were selected for the whole run
+    metadata: Option<Metadata>,
+    target: Option<String>,
+}
+
+impl Variation {
+    fn prepare_check(
+        &self,
+        args: &CheckExternalTypesArgs,
+        cargo_metadata: &Metadata,
+        package: &Package,
+        phase: &mut Phase,
+    ) -> Result<PackageCheck> {
+        prepare_check(
+            args,
+            self.metadata.as_ref().unwrap_or(cargo_metadata),
+            package,
+            self.target.as_deref(),
+            phase,
+        )
+    }
+}
+
+/// Returns every combination of the feature sets and targets to check `package` with. This is
+/// empty if there's only one of each, in which case the package is checked as usual.
+fn build_variations(args: &CheckExternalTypesArgs, package: &Package) -> Result<Vec<Variation>> {
+    let feature_sets = feature_variations(args, package)?;
+    if feature_sets.is_empty() && args.target.len() < 2 {
+        return Ok(Vec::new());
+    }
+    let feature_sets: Vec<(Option<String>, Option<Metadata>)> = if feature_sets.is_empty() {
+        vec![(None, None)]
+    } else {
+        feature_sets
+            .into_iter()
+            .map(|(label, metadata)| (Some(label), Some(metadata)))
+            .collect()
+    };
+    let targets: Vec<(Option<String>, Option<String>)> = match args.target.as_slice() {
+        [] => vec![(None, None)],
+        [only] => vec![(None, Some(only.clone()))],
+        targets => targets
+            .iter()
+            .map(|target| (Some(format!("target `{target}`")), Some(target.clone())))
+            .collect(),
+    };
+    let mut variations = Vec::new();
+    for (features_label, metadata) in &feature_sets {
+        for (target_label, target) in &targets {
+            let label: Vec<&str> = [features_label, target_label]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect();
+            variations.push(Variation {
+                label: label.join(" on "),
+                metadata: metadata.clone(),
+                target: target.clone(),
+            });
+        }
+    }
+    Ok(variations)
+}
+
+/// Describes a set of enabled features, such as "features `default,ext`".
+fn describe_features(features: &[String]) -> String {
+    if features.is_empty() {
+        "no features".into()
+    } else {
+        format!("features `{}`", features.join(","))
+    }
+}
+
+/// Adds the findings about duplicate or unused `allowed_external_types` entries of `config`
+/// that its `severity` table asks for. They're about the config rather than the crate, so
+/// they're only reported when asked for.
+fn add_approval_findings(errors: &mut ValidationErrors, config: &Config) {
+    for finding in errors.pattern_stats().approval_findings(config) {
+        let level = match config.severity(finding.kind()) {
+            Some(Severity::Error) => ErrorLevel::Error,
+            Some(Severity::Warn) => ErrorLevel::Warning,
+            Some(Severity::Off) | None => continue,
+        };
+        errors.add(finding.with_level(level));
+    }
+}
+
+/// Findings of the packages that share a config, which `--fix` and `--prune-config` update the
+/// config with.
+#[derive(Default)]
+struct PendingFix {
+    unapproved_types: BTreeSet<String>,
+    used_patterns: BTreeSet<String>,
+    anchored_patterns: bool,
+    /// Patterns whose crate none of the packages depend on, or `None` before any package is
+    /// checked
+    unknown_crate_patterns: Option<Vec<UnknownCratePattern>>,
+}
+
+/// Updates the config at `location` so that it allows the unapproved types in `fix`, and
+/// removes the patterns that no package used.
+fn apply_fix((path, table_path): &ConfigLocation, fix: &PendingFix) -> Result<()> {
+    if ConfigFormat::from_path(path)? != ConfigFormat::Toml {
+        bail!("only TOML config files can be fixed: {path:?}");
+    }
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read config file {path:?}"))?;
+    let (fixed, changes) = fix_config(
+        &contents,
+        table_path,
+        &allowlist_patterns(&fix.unapproved_types, fix.anchored_patterns),
+        |pattern| fix.used_patterns.contains(pattern),
+    )
+    .with_context(|| format!("failed to fix config file {path:?}"))?;
+    for unknown in fix.unknown_crate_patterns.iter().flatten() {
+        if !changes.removed.contains(&unknown.pattern) {
+            continue;
+        }
+        let suggestion = match &unknown.suggestion {
+            Some(suggestion) => format!(" Did you mean `{suggestion}`?"),
+            None => String::new(),
+        };
+        eprintln!(
+            "warning: removed `{}`, since `{}` isn't a dependency.{suggestion}",
+            unknown.pattern, unknown.crate_name
+        );
+    }
+    if !changes.is_empty() {
+        fs::write(path, fixed).with_context(|| format!("failed to write config file {path:?}"))?;
+        eprintln!(
+            "Updated {}: added {} and removed {} allowed external types",
+            path.display(),
+            changes.added.len(),
+            changes.removed.len()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the config from the package metadata merged with the workspace metadata, according to
+/// the package's `merge_strategy`. Config files that either one `extends` are relative to
+/// `package_dir` and `workspace_root` respectively.
+fn resolve_config(
+    package_metadata: &serde_json::Value,
+    package_dir: &Path,
+    workspace_metadata: &serde_json::Value,
+    workspace_root: &Path,
+) -> Result<Config> {
+    let package = metadata_config(package_metadata)
+        .map(|config| resolve_extends(config.clone(), package_dir))
+        .transpose()
+        .context("failed to load the config files extended by `package.metadata` in Cargo.toml")?;
+    let workspace = metadata_config(workspace_metadata)
+        .map(|config| resolve_extends(config.clone(), workspace_root))
+        .transpose()
+        .context(
+            "failed to load the config files extended by `workspace.metadata` in the workspace Cargo.toml",
+        )?;
+    // Here we do use ? to propagate the error from the unmarshal - it would indicate
+    // the metadata config is present, but invalid.
+    Ok(match (package, workspace) {
+        (Some(package), Some(workspace)) => Config::from_layers(&workspace, &package).context(
+            "failed to parse config merged from `package.metadata` and `workspace.metadata` in Cargo.toml",
+        )?,
+        (Some(package), None) => serde_json::from_value(package)
+            .context("failed to parse config from `package.metadata` in Cargo.toml")?,
+        (None, Some(workspace)) => serde_json::from_value(workspace).context(
+            "failed to parse config from `workspace.metadata` in the workspace Cargo.toml",
+        )?,
+        (None, None) => Default::default(),
+    })
+}
+
+/// Returns the path of the config file to use when there's neither a `--config` nor config in
+/// the Cargo.toml metadata. It's looked for in the package's directory, and then in the
+/// workspace root.
+fn find_default_config_file(package_dir: &Path, workspace_root: &Path) -> Option<PathBuf> {
+    [package_dir, workspace_root]
+        .into_iter()
+        .map(|dir| dir.join(DEFAULT_CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Returns the config table in the given `package.metadata` or `workspace.metadata` table, if
+/// there is one. The table can be named either `cargo_check_external_types` or
+/// `cargo-check-external-types`.
+fn metadata_config(metadata: &serde_json::Value) -> Option<&serde_json::Value> {
+    // When the metadata is not provided, it's null rather than a map. In this instance we want
+    // to use the default config.
+    let crate_metadata = metadata.as_object()?;
+    crate_metadata
+        .get(env!("CARGO_CRATE_NAME"))
+        .or_else(|| crate_metadata.get(env!("CARGO_PKG_NAME")))
+}
+
+fn resolve_features(metadata: &Metadata, root_package: &Package) -> Result<Vec<String>> {
+    if let Some(resolve) = &metadata.resolve {
+        let root_node = resolve
+            .nodes
+            .iter()
+            .find(|&n| n.id == root_package.id)
+            .ok_or_else(|| anyhow!("Failed to find node for root package"))?;
+        Ok(root_node.features.clone())
+    } else {
+        bail!("Cargo metadata didn't have resolved nodes");
+    }
+}
+
+/// Name of the config file that's used if there's no other config.
+const DEFAULT_CONFIG_FILE_NAME: &str = ".cargo-check-external-types.toml";
+
+/// Target kinds that can be documented as a library. Proc macro crates are left out since they
+/// can't export types.
+const LIB_TARGET_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib"];
+
+fn is_lib_target(target: &Target) -> bool {
+    target
+        .kind
+        .iter()
+        .any(|kind| LIB_TARGET_KINDS.contains(&kind.as_str()))
+}
+
+/// Returns the name of the lib target in `targets`. If there are multiple, then the target with
+/// the kind `lib_kind` is picked, or the `lib`/`rlib` target if no kind is given.
+fn resolve_lib_name(targets: &[Target], lib_kind: Option<&str>) -> Result<String> {
+    let lib_targets = targets
+        .iter()
+        .filter(|t| is_lib_target(t))
+        .collect::<Vec<_>>();
+    let has_kind =
+        |target: &&&Target, kinds: &[&str]| target.kind.iter().any(|k| kinds.contains(&k.as_str()));
+    let candidates = match lib_kind {
+        Some(kind) => lib_targets
+            .iter()
+            .filter(|t| has_kind(t, &[kind]))
+            .collect::<Vec<_>>(),
+        None if lib_targets.len() > 1 => lib_targets
+            .iter()
+            .filter(|t| has_kind(t, &["lib", "rlib"]))
+            .collect::<Vec<_>>(),
+        None => lib_targets.iter().collect(),
+    };
+    match (candidates.as_slice(), lib_kind) {
+        ([target], _) => Ok(target.name.clone()),
+        (_, Some(kind)) => bail!(
+            "Expected crate to define 1 `{kind}` target, found {}",
+            candidates.len()
+        ),
+        (_, None) if lib_targets.len() > 1 => bail!(
+            "Expected crate to define 1 lib target, found {}. Select one with `--lib-kind`.",
+            lib_targets.len()
+        ),
+        (_, None) => bail!(
+            "Expected crate to define 1 lib target, found {}",
+            lib_targets.len()
+        ),
+    }
+}
+
+/// Returns all workspace members with a lib target, except for those in `exclude`.
+fn resolve_workspace_packages<'a>(metadata: &'a Metadata, exclude: &[String]) -> Vec<&'a Package> {
+    let members = metadata.workspace_packages();
+    for name in exclude {
+        if !members.iter().any(|p| &p.name == name) {
+            eprintln!("warning: excluded package `{name}` isn't a member of the workspace");
+        }
+    }
+    members
+        .into_iter()
+        .filter(|package| !exclude.contains(&package.name))
+        .filter(|package| {
+            let has_lib = package.targets.iter().any(is_lib_target);
+            if !has_lib {
+                eprintln!("Skipping {} since it has no lib target", package.name);
+            }
+            has_lib
+        })
+        .collect()
+}
+
+fn resolve_root_package<'a>(metadata: &'a Metadata, package: Option<&str>) -> Result<&'a Package> {
+    let workspace_members = || {
+        metadata
+            .workspace_members
+            .as_slice()
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    if let Some(spec) = package {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (spec, None),
+        };
+        return metadata
+            .workspace_packages()
+            .into_iter()
+            .find(|p| p.name == name && version.map_or(true, |v| p.version.to_string() == v))
+            .ok_or_else(|| {
+                anyhow!(
+                    "package `{spec}` isn't a member of the workspace. Workspace members:\n{}",
+                    workspace_members()
+                )
+            });
+    }
+    metadata
+        .root_package()
+        .ok_or_else(|| {
+            let workspace_members = workspace_members();
+            if !workspace_members.is_empty() {
+                anyhow!("it appears you're trying to run `cargo-check-external-types` on a workspace Cargo.toml; Instead, select one of the workspace members with `-p` or run it on one of the workspace member Cargo.tomls directly:\n{workspace_members}")
+            } else {
+                anyhow!("No root package found")
+            }
+        })
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        Args::command().debug_assert();
+    }
+}
+
+#[cfg(test)]
+mod resolve_config_tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn package_metadata_extends_workspace_metadata() {
+        let config = resolve_config(
+            &json!({"cargo_check_external_types": {"allowed_external_types": ["package::*"]}}),
+            Path::new("."),
+            &json!({"cargo-check-external-types": {"allowed_external_types": ["workspace::*"]}}),
+            Path::new("."),
+        )
+        .unwrap();
+        assert!(config.allows_type("root", "package::Type"));
+        assert!(config.allows_type("root", "workspace::Type"));
+    }
+
+    #[test]
+    fn package_metadata_replaces_workspace_metadata() {
+        let config = resolve_config(
+            &json!({"cargo_check_external_types": {
+                "merge_strategy": "replace",
+                "allowed_external_types": ["package::*"],
+            }}),
+            Path::new("."),
+            &json!({"cargo-check-external-types": {"allowed_external_types": ["workspace::*"]}}),
+            Path::new("."),
+        )
+        .unwrap();
+        assert!(config.allows_type("root", "package::Type"));
+        assert!(!config.allows_type("root", "workspace::Type"));
+    }
+
+    #[test]
+    fn fall_back_to_workspace_metadata() {
+        let workspace_metadata =
+            json!({"cargo-check-external-types": {"allowed_external_types": ["workspace::*"]}});
+        for package_metadata in [json!(null), json!({"other_tool": {}})] {
+            let config = resolve_config(
+                &package_metadata,
+                Path::new("."),
+                &workspace_metadata,
+                Path::new("."),
+            )
+            .unwrap();
+            assert!(config.allows_type("root", "workspace::Type"));
+        }
+        let config =
+            resolve_config(&json!(null), Path::new("."), &json!(null), Path::new(".")).unwrap();
+        assert!(!config.allows_type("root", "workspace::Type"));
+    }
+
+    #[test]
+    fn find_default_config_file_in_package_then_workspace() {
+        let workspace_root = std::env::temp_dir()
+            .join("cargo-check-external-types-tests")
+            .join("default-config-file");
+        let package_dir = workspace_root.join("member");
+        let _ = fs::remove_dir_all(&workspace_root);
+        fs::create_dir_all(&package_dir).unwrap();
+        assert_eq!(
+            None,
+            find_default_config_file(&package_dir, &workspace_root)
+        );
+
+        fs::write(workspace_root.join(DEFAULT_CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(
+            Some(workspace_root.join(DEFAULT_CONFIG_FILE_NAME)),
+            find_default_config_file(&package_dir, &workspace_root)
+        );
+
+        fs::write(package_dir.join(DEFAULT_CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(
+            Some(package_dir.join(DEFAULT_CONFIG_FILE_NAME)),
+            find_default_config_file(&package_dir, &workspace_root)
+        );
+    }
+
+    #[test]
+    fn invalid_workspace_metadata() {
+        let err = resolve_config(
+            &json!(null),
+            Path::new("."),
+            &json!({"cargo_check_external_types": {"allowed_external_types": 5}}),
+            Path::new("."),
+        )
+        .unwrap_err();
+        assert!(format!("{err}").contains("workspace.metadata"));
+    }
+}
+
+#[cfg(test)]
+mod resolve_lib_name_tests {
+    use super::*;
+    use serde_json::json;
+
+    fn target(name: &str, kind: &[&str]) -> Target {
+        serde_json::from_value(json!({
+            "name": name,
+            "kind": kind,
+            "src_path": format!("src/{name}.rs"),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn single_lib_target() {
+        let targets = [
+            target("my_crate", &["rlib", "cdylib"]),
+            target("my-bin", &["bin"]),
+        ];
+        assert_eq!("my_crate", resolve_lib_name(&targets, None).unwrap());
+        assert_eq!(
+            "my_crate",
+            resolve_lib_name(&targets, Some("cdylib")).unwrap()
+        );
+        assert!(resolve_lib_name(&targets, Some("staticlib")).is_err());
+        assert!(resolve_lib_name(&[target("my-bin", &["bin"])], None).is_err());
+    }
+
+    #[test]
+    fn multiple_lib_targets() {
+        let targets = [
+            target("my_crate", &["lib"]),
+            target("my_crate_ffi", &["staticlib"]),
+        ];
+        assert_eq!("my_crate", resolve_lib_name(&targets, None).unwrap());
+        assert_eq!(
+            "my_crate_ffi",
+            resolve_lib_name(&targets, Some("staticlib")).unwrap()
+        );
+
+        let targets = [
+            target("my_crate_dylib", &["dylib"]),
+            target("my_crate_ffi", &["staticlib"]),
+        ];
+        let err = resolve_lib_name(&targets, None).unwrap_err();
+        assert!(format!("{err}").contains("--lib-kind"));
+    }
+}
+
+#[cfg(test)]
+mod arg_parse_tests {
+    use super::*;
     use clap::Parser;
 
     #[test]
@@ -297,11 +2048,59 @@ mod arg_parse_tests {
                 all_features: false,
                 no_default_features: false,
                 features: None,
+                check_features: vec![],
+                feature_matrix: false,
                 manifest_path: None,
-                target: None,
+                package: None,
+                published_crate: None,
+                workspace: false,
+                exclude: vec![],
+                lib_name: None,
+                lib_kind: None,
+                target: vec![],
+                target_dir: None,
+                print_command: false,
+                emit_rustdoc_json: None,
+                profile: None,
+                toolchain: None,
+                install_toolchain: false,
+                no_cache: false,
+                locked: false,
+                frozen: false,
+                offline: false,
+                cargo_config: vec![],
+                unstable_flags: vec![],
                 config: None,
                 verbose: false,
                 output_format: OutputFormat::Errors,
+                output_file: None,
+                no_sort: false,
+                sort_by: None,
+                no_context: false,
+                pattern_stats: false,
+                emit_allowlist: None,
+                emit_workspace_allowlist: None,
+                allowlist_layout: AllowlistLayout::Shared,
+                baseline: None,
+                write_baseline: None,
+                since: None,
+                history_file: None,
+                fix: false,
+                prune_config: false,
+                max_allowed_errors: None,
+                max_allowed_warnings: None,
+                errors_exit_code: None,
+                warnings_exit_code: None,
+                failure_exit_code: None,
+                max_errors: None,
+                fail_fast: false,
+                print_config: false,
+                include_hidden_items: false,
+                no_inherit_rustdocflags: false,
+                rustdocflags_precedence: RustdocFlagsPrecedence::Tool,
+                version_info: false,
+                print_config_schema: false,
+                command: None,
             }),
             Args::try_parse_from(["cargo", "check-external-types"]).unwrap()
         );
@@ -312,13 +2111,7 @@ mod arg_parse_tests {
         assert_eq!(
             Args::CheckExternalTypes(CheckExternalTypesArgs {
                 all_features: true,
-                no_default_features: false,
-                features: None,
-                manifest_path: None,
-                target: None,
-                config: None,
-                verbose: false,
-                output_format: OutputFormat::Errors,
+                ..Default::default()
             }),
             Args::try_parse_from(["cargo", "check-external-types", "--all-features"]).unwrap()
         );
@@ -328,14 +2121,8 @@ mod arg_parse_tests {
     fn no_default_features() {
         assert_eq!(
             Args::CheckExternalTypes(CheckExternalTypesArgs {
-                all_features: false,
                 no_default_features: true,
-                features: None,
-                manifest_path: None,
-                target: None,
-                config: None,
-                verbose: false,
-                output_format: OutputFormat::Errors,
+                ..Default::default()
             }),
             Args::try_parse_from(["cargo", "check-external-types", "--no-default-features"])
                 .unwrap()
@@ -346,14 +2133,8 @@ mod arg_parse_tests {
     fn feature_list() {
         assert_eq!(
             Args::CheckExternalTypes(CheckExternalTypesArgs {
-                all_features: false,
-                no_default_features: false,
                 features: Some(vec!["foo".into(), "bar".into()]),
-                manifest_path: None,
-                target: None,
-                config: None,
-                verbose: false,
-                output_format: OutputFormat::Errors,
+                ..Default::default()
             }),
             Args::try_parse_from(["cargo", "check-external-types", "--features", "foo,bar"])
                 .unwrap()
@@ -364,14 +2145,8 @@ mod arg_parse_tests {
     fn manifest_path() {
         assert_eq!(
             Args::CheckExternalTypes(CheckExternalTypesArgs {
-                all_features: false,
-                no_default_features: false,
-                features: None,
                 manifest_path: Some("test-path".into()),
-                target: None,
-                config: None,
-                verbose: false,
-                output_format: OutputFormat::Errors,
+                ..Default::default()
             }),
             Args::try_parse_from([
                 "cargo",
@@ -387,14 +2162,8 @@ mod arg_parse_tests {
     fn target() {
         assert_eq!(
             Args::CheckExternalTypes(CheckExternalTypesArgs {
-                all_features: false,
-                no_default_features: false,
-                features: None,
-                manifest_path: None,
-                target: Some("x86_64-unknown-linux-gnu".into()),
-                config: None,
-                verbose: false,
-                output_format: OutputFormat::Errors,
+                target: vec!["x86_64-unknown-linux-gnu".into()],
+                ..Default::default()
             }),
             Args::try_parse_from([
                 "cargo",
@@ -404,20 +2173,227 @@ mod arg_parse_tests {
             ])
             .unwrap()
         );
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                target: vec![
+                    "x86_64-unknown-linux-gnu".into(),
+                    "x86_64-pc-windows-msvc".into()
+                ],
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--target",
+                "x86_64-unknown-linux-gnu",
+                "--target",
+                "x86_64-pc-windows-msvc"
+            ])
+            .unwrap()
+        );
     }
 
     #[test]
-    fn verbose() {
+    fn target_variations() {
+        let package: Package = serde_json::from_value(serde_json::json!({
+            "name": "my-crate",
+            "version": "0.1.0",
+            "id": "my-crate 0.1.0",
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": "/my-crate/Cargo.toml",
+        }))
+        .unwrap();
+        let variations = |targets: &[&str]| {
+            let args = CheckExternalTypesArgs {
+                target: targets.iter().map(|target| target.to_string()).collect(),
+                ..Default::default()
+            };
+            build_variations(&args, &package)
+                .unwrap()
+                .into_iter()
+                .map(|variation| (variation.label, variation.target.unwrap()))
+                .collect::<Vec<_>>()
+        };
+        assert!(variations(&[]).is_empty());
+        assert!(variations(&["x86_64-unknown-linux-gnu"]).is_empty());
+        assert_eq!(
+            vec![
+                (
+                    "target `x86_64-unknown-linux-gnu`".to_string(),
+                    "x86_64-unknown-linux-gnu".to_string()
+                ),
+                (
+                    "target `x86_64-pc-windows-msvc`".to_string(),
+                    "x86_64-pc-windows-msvc".to_string()
+                ),
+            ],
+            variations(&["x86_64-unknown-linux-gnu", "x86_64-pc-windows-msvc"])
+        );
+    }
+
+    #[test]
+    fn target_dir() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                target_dir: Some("/tmp/docs".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--target-dir", "/tmp/docs"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn print_command() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                print_command: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--print-command"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn emit_rustdoc_json() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                emit_rustdoc_json: Some(None),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--emit-rustdoc-json"]).unwrap()
+        );
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                emit_rustdoc_json: Some(Some("json".into())),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--emit-rustdoc-json",
+                "json"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn emitted_json_names() {
+        assert_eq!("my_crate.json", emitted_json_name("my-crate", None, None));
+        assert_eq!(
+            "my_crate.default,tls.x86_64-pc-windows-msvc.json",
+            emitted_json_name(
+                "my_crate",
+                Some("x86_64-pc-windows-msvc"),
+                Some(&["default".into(), "tls".into()])
+            )
+        );
+        assert_eq!(
+            "my_crate.no-features.json",
+            emitted_json_name("my_crate", None, Some(&[]))
+        );
+    }
+
+    #[test]
+    fn profile() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                profile: Some("docs".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--profile", "docs"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn toolchain() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                toolchain: Some("nightly-2024-10-29".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--toolchain",
+                "nightly-2024-10-29"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn install_toolchain() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                install_toolchain: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--install-toolchain"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn no_cache() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                no_cache: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--no-cache"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn cargo_flags() {
+        let Args::CheckExternalTypes(args) =
+            Args::try_parse_from(["cargo", "check-external-types", "--offline", "--locked"])
+                .unwrap();
+        assert_eq!(vec!["--locked", "--offline"], args.cargo_flags());
+        let Args::CheckExternalTypes(args) = Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--cargo-config",
+            "build.jobs=1",
+            "-Z",
+            "bindeps",
+            "-Zgc",
+            "--cargo-config",
+            "net.offline=true",
+        ])
+        .unwrap();
+        assert_eq!(
+            vec![
+                "--config",
+                "build.jobs=1",
+                "--config",
+                "net.offline=true",
+                "-Z",
+                "bindeps",
+                "-Z",
+                "gc"
+            ],
+            args.cargo_flags()
+        );
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--frozen",
+            "--crate",
+            "bytes@1"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn verbose() {
         assert_eq!(
             Args::CheckExternalTypes(CheckExternalTypesArgs {
-                all_features: false,
-                no_default_features: false,
-                features: None,
-                manifest_path: None,
-                target: None,
-                config: None,
                 verbose: true,
-                output_format: OutputFormat::Errors,
+                ..Default::default()
             }),
             Args::try_parse_from(["cargo", "check-external-types", "--verbose"]).unwrap()
         );
@@ -427,14 +2403,8 @@ mod arg_parse_tests {
     fn output_format_markdown_table() {
         assert_eq!(
             Args::CheckExternalTypes(CheckExternalTypesArgs {
-                all_features: false,
-                no_default_features: false,
-                features: None,
-                manifest_path: None,
-                target: None,
-                config: None,
-                verbose: false,
                 output_format: OutputFormat::MarkdownTable,
+                ..Default::default()
             }),
             Args::try_parse_from([
                 "cargo",
@@ -446,6 +2416,688 @@ mod arg_parse_tests {
         );
     }
 
+    #[test]
+    fn output_file() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                output_file: Some("report.md".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--output-file",
+                "report.md"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn no_sort() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                output_format: OutputFormat::MarkdownTable,
+                no_sort: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--output-format",
+                "markdown-table",
+                "--no-sort"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn output_format_json() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                output_format: OutputFormat::Json,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--output-format", "json"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn output_format_rdjson() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                output_format: OutputFormat::Rdjson,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--output-format", "rdjson"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn output_format_badge() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                output_format: OutputFormat::Badge,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--output-format", "badge"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn output_format_teamcity() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                output_format: OutputFormat::TeamCity,
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--output-format",
+                "teamcity"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn sort_by() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                sort_by: Some(SortBy::Severity),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--sort-by", "severity"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn conflict_no_sort_sort_by() {
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--no-sort",
+            "--sort-by",
+            "crate"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn no_context() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                no_context: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--no-context"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn pattern_stats() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                pattern_stats: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--pattern-stats"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn feature_matrix() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                check_features: vec!["".into(), "a,b".into()],
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--check-features",
+                "",
+                "--check-features",
+                "a,b",
+            ])
+            .unwrap()
+        );
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--feature-matrix",
+            "--features",
+            "a",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn budgets() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                max_allowed_errors: Some(40),
+                max_allowed_warnings: Some(0),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--max-allowed-errors",
+                "40",
+                "--max-allowed-warnings",
+                "0"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn exit_codes() {
+        let Args::CheckExternalTypes(args) = Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--warnings-exit-code",
+            "3",
+            "--failure-exit-code",
+            "4",
+        ])
+        .unwrap();
+        assert_eq!(0, args.exit_code(&Ok(())));
+        assert_eq!(3, args.exit_code(&Err(Error::Warnings)));
+        assert_eq!(1, args.exit_code(&Err(Error::ValidationErrors)));
+        assert_eq!(4, args.exit_code(&Err(anyhow!("failed").into())));
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--errors-exit-code",
+            "256"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn max_errors() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                max_errors: Some(5),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--max-errors", "5"]).unwrap()
+        );
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "--max-errors", "-1"]).is_err()
+        );
+    }
+
+    #[test]
+    fn fail_fast() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                fail_fast: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--fail-fast"]).unwrap()
+        );
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "--fail-fast", "--fix"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn since() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                since: Some("origin/main".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--since", "origin/main"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn baseline() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                baseline: Some(PathBuf::from("baseline.json")),
+                write_baseline: Some(PathBuf::from("baseline.json")),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--baseline",
+                "baseline.json",
+                "--write-baseline",
+                "baseline.json"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn emit_workspace_allowlist() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                workspace: true,
+                emit_workspace_allowlist: Some(PathBuf::from("allowlists")),
+                allowlist_layout: AllowlistLayout::PerMember,
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--workspace",
+                "--emit-workspace-allowlist",
+                "allowlists",
+                "--allowlist-layout",
+                "per-member"
+            ])
+            .unwrap()
+        );
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--emit-workspace-allowlist",
+            "allowlists"
+        ])
+        .is_err());
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--allowlist-layout",
+            "shared-and-per-member"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn emit_allowlist() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                emit_allowlist: Some(PathBuf::from("allowlist.toml")),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--emit-allowlist",
+                "allowlist.toml"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn fix() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                fix: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--fix"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn prune_config() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                prune_config: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--prune-config"]).unwrap()
+        );
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "--prune-config", "--fix"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn print_config() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                print_config: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--print-config"]).unwrap()
+        );
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "--print-config", "--fix"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn include_hidden_items() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                include_hidden_items: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--include-hidden-items"])
+                .unwrap()
+        );
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                include_hidden_items: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--document-hidden-items"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn rustdocflags_precedence() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                rustdocflags_precedence: RustdocFlagsPrecedence::Inherited,
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--rustdocflags-precedence",
+                "inherited"
+            ])
+            .unwrap()
+        );
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--rustdocflags-precedence",
+            "first"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn no_inherit_rustdocflags() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                no_inherit_rustdocflags: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--no-inherit-rustdocflags"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn print_config_schema() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                print_config_schema: true,
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--print-config-schema"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn version_info() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                version_info: true,
+                output_format: OutputFormat::Json,
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--version-info",
+                "--output-format",
+                "json"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn lib_name() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                lib_name: Some("custom_lib".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--lib-name", "custom_lib"])
+                .unwrap()
+        );
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--workspace",
+            "--lib-name",
+            "custom_lib"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn lib_kind() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                lib_kind: Some("cdylib".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--lib-kind", "cdylib"])
+                .unwrap()
+        );
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "--lib-kind", "bin"]).is_err()
+        );
+    }
+
+    #[test]
+    fn published_crate() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                published_crate: Some("serde_yaml@0.9".parse().unwrap()),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "--crate", "serde_yaml@0.9"])
+                .unwrap()
+        );
+        assert!(Args::try_parse_from([
+            "cargo",
+            "check-external-types",
+            "--crate",
+            "serde_yaml@0.9",
+            "-p",
+            "test-crate"
+        ])
+        .is_err());
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "--crate", "serde_yaml"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn package() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                package: Some("test-crate".into()),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "-p", "test-crate"]).unwrap()
+        );
+    }
+
+    #[test]
+    fn workspace_exclude() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                workspace: true,
+                exclude: vec!["fuzz".into(), "codegen".into()],
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--workspace",
+                "--exclude",
+                "fuzz",
+                "--exclude",
+                "codegen"
+            ])
+            .unwrap()
+        );
+        // `--exclude` only makes sense for the workspace
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "--exclude", "fuzz"]).is_err()
+        );
+    }
+
+    #[test]
+    fn fmt_config() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                command: Some(Command::FmtConfig(FmtConfigArgs {
+                    config: Some("external-types.toml".into()),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "fmt-config",
+                "--config",
+                "external-types.toml"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn init() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                command: Some(Command::Init(InitArgs {
+                    package: Some("my-crate".into()),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            Args::try_parse_from(["cargo", "check-external-types", "init", "-p", "my-crate"])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn compare() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                command: Some(Command::Compare(CompareArgs {
+                    old: "old.json".into(),
+                    new: "new.json".into(),
+                })),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "compare",
+                "old.json",
+                "new.json"
+            ])
+            .unwrap()
+        );
+        assert!(
+            Args::try_parse_from(["cargo", "check-external-types", "compare", "old.json"]).is_err()
+        );
+    }
+
+    #[test]
+    fn history_file() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                history_file: Some(PathBuf::from("history.jsonl")),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "--history-file",
+                "history.jsonl"
+            ])
+            .unwrap()
+        );
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                command: Some(Command::Trend(TrendArgs {
+                    history_file: PathBuf::from("history.jsonl"),
+                })),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "trend",
+                "--history-file",
+                "history.jsonl"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn lint_config() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                command: Some(Command::LintConfig(LintConfigArgs {
+                    config: Some("external-types.toml".into()),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "lint-config",
+                "--config",
+                "external-types.toml"
+            ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn migrate_config() {
+        assert_eq!(
+            Args::CheckExternalTypes(CheckExternalTypesArgs {
+                command: Some(Command::MigrateConfig(MigrateConfigArgs {
+                    config: "external-types.toml".into(),
+                    to_file: true,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }),
+            Args::try_parse_from([
+                "cargo",
+                "check-external-types",
+                "migrate-config",
+                "--config",
+                "external-types.toml",
+                "--to-file"
+            ])
+            .unwrap()
+        );
+        assert!(Args::try_parse_from(["cargo", "check-external-types", "migrate-config"]).is_err());
+    }
+
     #[test]
     fn conflict_all_features_no_default_features() {
         // Check `--all-features` and `--no-default-features` conflict
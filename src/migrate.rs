@@ -0,0 +1,300 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Moves a config between a standalone config file and the package metadata in `Cargo.toml`.
+//!
+//! The config is moved as a TOML table, so the order of its settings and patterns, and the
+//! comments on them, are kept. Relative paths in `extends` are rewritten to be relative to the
+//! config's new location.
+
+use crate::config::EXTENDS_KEY;
+use anyhow::{bail, Context, Result};
+use std::path::{Component, Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Returns `manifest` with the config file `config` added as its package metadata config.
+/// The config file is in `config_dir`, and the manifest in `manifest_dir`.
+pub fn config_file_to_metadata(
+    config: &str,
+    config_dir: &Path,
+    manifest: &str,
+    manifest_dir: &Path,
+) -> Result<String> {
+    let config: DocumentMut = config.parse().context("failed to parse config file")?;
+    let mut manifest: DocumentMut = manifest.parse().context("failed to parse Cargo.toml")?;
+    if metadata_config(&manifest).is_some() {
+        bail!("Cargo.toml already has a config for cargo-check-external-types");
+    }
+    // Tables are output in the order of their positions, so the config goes after everything
+    // that's already in the manifest
+    let mut next_position = max_position(manifest.as_table()).map_or(0, |p| p + 1);
+    let mut table = config.as_table().clone();
+    rebase_extends(&mut table, config_dir, manifest_dir)?;
+    table.set_implicit(false);
+    table.decor_mut().set_prefix("\n");
+    renumber_positions(&mut table, &mut next_position);
+
+    let Some(package) = manifest.get_mut("package").and_then(Item::as_table_mut) else {
+        bail!("Cargo.toml doesn't have a `[package]` table");
+    };
+    let metadata = package.entry("metadata").or_insert_with(|| {
+        let mut metadata = Table::new();
+        metadata.set_implicit(true);
+        Item::Table(metadata)
+    });
+    let Some(metadata) = metadata.as_table_mut() else {
+        bail!("`package.metadata` in Cargo.toml must be a table");
+    };
+    metadata.insert("cargo_check_external_types", Item::Table(table));
+    Ok(manifest.to_string())
+}
+
+/// Returns the package metadata config in `manifest` as a standalone config file. The manifest
+/// is in `manifest_dir`, and the config file will be in `config_dir`.
+pub fn metadata_to_config_file(
+    manifest: &str,
+    manifest_dir: &Path,
+    config_dir: &Path,
+) -> Result<String> {
+    let manifest: DocumentMut = manifest.parse().context("failed to parse Cargo.toml")?;
+    let mut table = match metadata_config(&manifest) {
+        Some(Item::Table(table)) => table.clone(),
+        Some(Item::Value(Value::InlineTable(table))) => table.clone().into_table(),
+        Some(_) => bail!("the config in Cargo.toml must be a table"),
+        None => bail!("Cargo.toml doesn't have a config for cargo-check-external-types"),
+    };
+    rebase_extends(&mut table, manifest_dir, config_dir)?;
+    table.decor_mut().clear();
+    let mut document = DocumentMut::new();
+    *document.as_table_mut() = table;
+    Ok(document.to_string().trim_start().to_string())
+}
+
+/// Returns the config table in the package metadata of `manifest`, if there is one.
+fn metadata_config(manifest: &DocumentMut) -> Option<&Item> {
+    let metadata = manifest.get("package")?.get("metadata")?;
+    metadata
+        .get("cargo_check_external_types")
+        .or_else(|| metadata.get("cargo-check-external-types"))
+}
+
+/// Returns the largest position of the tables in `table`, including itself.
+fn max_position(table: &Table) -> Option<usize> {
+    table
+        .iter()
+        .filter_map(|(_, item)| item.as_table())
+        .filter_map(max_position)
+        .chain(table.position())
+        .max()
+}
+
+/// Gives `table` and the tables in it consecutive positions starting at `next_position`,
+/// keeping their relative order.
+fn renumber_positions(table: &mut Table, next_position: &mut usize) {
+    let mut paths = Vec::new();
+    table_paths(table, None, &mut Vec::new(), &mut paths);
+    // The sort is stable, so tables without a position of their own stay after their parent
+    paths.sort_by_key(|(position, _)| *position);
+    for (_, path) in paths {
+        let mut nested = &mut *table;
+        for key in &path {
+            nested = nested
+                .get_mut(key)
+                .and_then(Item::as_table_mut)
+                .expect("path of a table");
+        }
+        nested.set_position(*next_position);
+        *next_position += 1;
+    }
+}
+
+/// Adds the key paths of `table` and the tables in it to `paths`, along with their positions.
+/// Tables without a position take their parent's.
+fn table_paths(
+    table: &Table,
+    parent_position: Option<usize>,
+    path: &mut Vec<String>,
+    paths: &mut Vec<(Option<usize>, Vec<String>)>,
+) {
+    let position = table.position().or(parent_position);
+    paths.push((position, path.clone()));
+    for (key, item) in table.iter() {
+        if let Some(nested) = item.as_table() {
+            path.push(key.to_string());
+            table_paths(nested, position, path, paths);
+            path.pop();
+        }
+    }
+}
+
+/// Rewrites the relative paths in `extends` so that they're relative to `to_dir` rather than
+/// `from_dir`.
+fn rebase_extends(table: &mut Table, from_dir: &Path, to_dir: &Path) -> Result<()> {
+    if from_dir == to_dir {
+        return Ok(());
+    }
+    let Some(extends) = table.get_mut(EXTENDS_KEY) else {
+        return Ok(());
+    };
+    let Some(extends) = extends.as_array_mut() else {
+        bail!("`{EXTENDS_KEY}` must be a list of paths to config files");
+    };
+    for value in extends.iter_mut() {
+        let Some(path) = value.as_str() else {
+            bail!("`{EXTENDS_KEY}` must be a list of paths to config files");
+        };
+        if Path::new(path).is_absolute() {
+            continue;
+        }
+        let rebased = relative_path(&normalize(&from_dir.join(path)), &normalize(to_dir));
+        let decor = value.decor().clone();
+        *value = Value::from(rebased);
+        *value.decor_mut() = decor;
+    }
+    Ok(())
+}
+
+/// Resolves the `.` and `..` components of `path` without accessing the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Returns the path that leads from the directory `base` to `path`, with `/` separators.
+fn relative_path(path: &Path, base: &Path) -> String {
+    let path: Vec<_> = path.components().collect();
+    let base: Vec<_> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let parents = std::iter::repeat("..").take(base.len() - common);
+    let rest = path[common..]
+        .iter()
+        .map(|component| component.as_os_str().to_string_lossy());
+    parents
+        .map(Into::into)
+        .chain(rest)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_str_eq;
+
+    const CONFIG: &str = r#"# Types that the public API may expose
+extends = ["../shared/external-types.toml"]
+allowed_external_types = [
+    "bytes::*", # Re-exported
+    "!bytes::BytesMut",
+]
+
+[severity]
+hidden_item = "error"
+
+[target.'cfg(windows)']
+allowed_external_types = ["windows_sys::*"]
+"#;
+
+    const MANIFEST: &str = r#"[package]
+name = "my-crate"
+
+[dependencies]
+bytes = "1"
+"#;
+
+    const MIGRATED_MANIFEST: &str = r#"[package]
+name = "my-crate"
+
+[dependencies]
+bytes = "1"
+
+[package.metadata.cargo_check_external_types]
+# Types that the public API may expose
+extends = ["shared/external-types.toml"]
+allowed_external_types = [
+    "bytes::*", # Re-exported
+    "!bytes::BytesMut",
+]
+
+[package.metadata.cargo_check_external_types.severity]
+hidden_item = "error"
+
+[package.metadata.cargo_check_external_types.target.'cfg(windows)']
+allowed_external_types = ["windows_sys::*"]
+"#;
+
+    #[test]
+    fn move_config_file_to_metadata() {
+        let migrated = config_file_to_metadata(
+            CONFIG,
+            Path::new("/project/config"),
+            MANIFEST,
+            Path::new("/project"),
+        )
+        .unwrap();
+        assert_str_eq!(MIGRATED_MANIFEST, migrated);
+
+        let err = config_file_to_metadata(
+            CONFIG,
+            Path::new("/project"),
+            MIGRATED_MANIFEST,
+            Path::new("/project"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            "Cargo.toml already has a config for cargo-check-external-types",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn move_metadata_to_config_file() {
+        let migrated = metadata_to_config_file(
+            MIGRATED_MANIFEST,
+            Path::new("/project"),
+            Path::new("/project/config"),
+        )
+        .unwrap();
+        assert_str_eq!(CONFIG, migrated);
+
+        let migrated = metadata_to_config_file(
+            "[package.metadata.cargo-check-external-types]\nallowed_external_types = []\n",
+            Path::new("/project"),
+            Path::new("/project"),
+        )
+        .unwrap();
+        assert_str_eq!("allowed_external_types = []\n", migrated);
+
+        let err = metadata_to_config_file(MANIFEST, Path::new("/project"), Path::new("/project"))
+            .unwrap_err();
+        assert_eq!(
+            "Cargo.toml doesn't have a config for cargo-check-external-types",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn relative_paths() {
+        let path = |p: &str| normalize(Path::new(p));
+        assert_eq!(
+            "../shared/a.toml",
+            relative_path(&path("/project/shared/a.toml"), &path("/project/crate"))
+        );
+        assert_eq!(
+            "shared/a.toml",
+            relative_path(&path("/project/crate/../shared/a.toml"), &path("/project"))
+        );
+        assert_eq!("a.toml", relative_path(&path("/a.toml"), &path("/")));
+    }
+}
@@ -0,0 +1,497 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+use crate::dependency::Dependency;
+use crate::error::{
+    describe_cfg, Cleanliness, CrateSummary, ErrorLevel, SortBy, SuppressionMechanism,
+    ValidationError, ValidationErrors,
+};
+use crate::pattern_stats::PatternReport;
+use crate::workspace::ExternalTypeSummary;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+/// Printer for the `markdown-table` output format.
+///
+/// Only references to external types are included in the table. Rows are either buffered and
+/// sorted until [`finish`](MarkdownTablePrinter::finish) is called, or written as soon as
+/// they're added when sorting is disabled. Sorted rows are ordered by their text (crate, type,
+/// and then location) unless a [`SortBy`] is given.
+///
+/// # Example output
+///
+/// ```text
+/// | Crate | Type | Used In |
+/// | ---   | ---  | ---     |
+/// | external_lib | external_lib::SimpleTrait | test-crate/src/lib.rs:103:4 |
+///
+/// | Crate | Violations | Distinct Types |
+/// | ---   | ---        | ---            |
+/// | external_lib | 1 | 1 |
+/// ```
+pub struct MarkdownTablePrinter {
+    sort: bool,
+    sort_by: Option<SortBy>,
+    rows: Vec<(ValidationError, String)>,
+}
+
+impl MarkdownTablePrinter {
+    pub fn new(sort: bool, sort_by: Option<SortBy>) -> Self {
+        Self {
+            sort,
+            sort_by,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn print_header(&self, out: &mut dyn Write) -> Result<()> {
+        writeln!(out, "| Crate | Type | Used In |")?;
+        writeln!(out, "| ---   | ---  | ---     |")?;
+        Ok(())
+    }
+
+    /// Adds a row for each external type that the given error references.
+    pub fn add_row(&mut self, out: &mut dyn Write, error: &ValidationError) -> Result<()> {
+        let Some(location) = error.location() else {
+            return Ok(());
+        };
+        for (crate_name, type_name) in error.external_types() {
+            let row = format!(
+                "| {} | {} | {}:{}:{} |",
+                crate_name,
+                type_name,
+                location.filename.to_string_lossy(),
+                location.begin.0,
+                location.begin.1
+            );
+            if self.sort {
+                self.rows.push((error.clone(), row));
+            } else {
+                writeln!(out, "{row}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes out any buffered rows, followed by a table of violations per external crate.
+    pub fn finish(&mut self, out: &mut dyn Write, errors: &ValidationErrors) -> Result<()> {
+        self.rows.sort_by(|(_, a), (_, b)| a.cmp(b));
+        if let Some(sort_by) = self.sort_by {
+            self.rows.sort_by(|(a, _), (b, _)| sort_by.compare(a, b));
+        }
+        for (_, row) in self.rows.drain(..) {
+            writeln!(out, "{row}")?;
+        }
+
+        let summaries = errors.crate_summaries();
+        if !summaries.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "| Crate | Violations | Distinct Types |")?;
+            writeln!(out, "| ---   | ---        | ---            |")?;
+            for summary in summaries {
+                writeln!(
+                    out,
+                    "| {} | {} | {} |",
+                    summary.crate_name, summary.violations, summary.distinct_types
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Phase of a run, reported in the `json` output format when the tool fails.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Parsing arguments and preparing output
+    Setup,
+    /// Running `cargo metadata`
+    Metadata,
+    /// Loading the config
+    Config,
+    /// Generating the rustdoc JSON
+    Rustdoc,
+    /// Examining the public API
+    Visit,
+    /// Writing the results
+    Output,
+}
+
+/// Overall outcome of a run, reported in the `json` output format.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// No errors were found (there may still be warnings)
+    Success,
+    /// At least one error was found
+    ValidationErrors,
+    /// The tool failed to complete the check
+    Failure,
+}
+
+#[derive(Serialize)]
+struct JsonLocation<'a> {
+    file: std::borrow::Cow<'a, str>,
+    begin: (usize, usize),
+    end: (usize, usize),
+}
+
+#[derive(Serialize)]
+struct JsonError<'a> {
+    level: ErrorLevel,
+    kind: &'static str,
+    message: String,
+    context: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    type_name: Option<&'a str>,
+    /// Other unapproved types that were referenced along with `type_name` and are reported
+    /// together with it
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    grouped_types: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none", rename = "crate")]
+    crate_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependency: Option<&'a Dependency>,
+    /// The `cfg` that the item with the reference is only compiled with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cfg: Option<&'a str>,
+    /// The feature sets or targets that the finding was found with, if it wasn't found with all
+    /// of the ones that were checked
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    found_with: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<JsonLocation<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+}
+
+impl<'a> JsonError<'a> {
+    fn new(error: &'a ValidationError) -> Self {
+        Self {
+            level: error.level(),
+            kind: error.kind(),
+            message: error.to_string(),
+            context: error.subtext().into_owned(),
+            type_name: error.external_crate_name().map(|_| error.type_name()),
+            grouped_types: error.grouped_types(),
+            crate_name: error.external_crate_name(),
+            dependency: error.dependency(),
+            cfg: error.cfg(),
+            found_with: error.found_with(),
+            location: error.location().map(|location| JsonLocation {
+                file: location.filename.to_string_lossy(),
+                begin: location.begin,
+                end: location.end,
+            }),
+            notes: error.notes(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonCleanliness {
+    public_items: usize,
+    clean_items: usize,
+    /// Rounded to one decimal place
+    percent_clean: f64,
+}
+
+impl JsonCleanliness {
+    fn new(cleanliness: Cleanliness) -> Self {
+        Self {
+            public_items: cleanliness.public_items,
+            clean_items: cleanliness.clean_items,
+            percent_clean: (cleanliness.percent_clean() * 10.0).round() / 10.0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    status: Status,
+    error_count: usize,
+    warning_count: usize,
+    errors: Vec<JsonError<'a>>,
+    crate_summaries: Vec<CrateSummary>,
+    cleanliness: JsonCleanliness,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    workspace_summaries: &'a [ExternalTypeSummary],
+    suppressed: BTreeMap<SuppressionMechanism, usize>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pattern_stats: &'a [PatternReport],
+}
+
+#[derive(Serialize)]
+struct JsonFailure {
+    status: Status,
+    phase: Phase,
+    message: String,
+}
+
+/// Prints the results of a run in the `json` output format.
+///
+/// The output is a single JSON object with the overall `status`, error and warning counts,
+/// all errors, per-crate summaries, the share of public items that are free of unapproved
+/// external types, and the number of findings suppressed by each mechanism.
+/// When multiple workspace members were checked, it also has the `workspace_summaries`, and when
+/// pattern statistics were requested, the `pattern_stats`.
+pub fn print_json_report(
+    out: &mut dyn Write,
+    errors: &ValidationErrors,
+    workspace_summaries: &[ExternalTypeSummary],
+    pattern_stats: &[PatternReport],
+) -> Result<()> {
+    let report = JsonReport {
+        status: if errors.error_count() > 0 {
+            Status::ValidationErrors
+        } else {
+            Status::Success
+        },
+        error_count: errors.error_count(),
+        warning_count: errors.warning_count(),
+        errors: errors.iter().map(JsonError::new).collect(),
+        crate_summaries: errors.crate_summaries(),
+        cleanliness: JsonCleanliness::new(errors.cleanliness()),
+        workspace_summaries,
+        suppressed: errors.suppressed_counts(),
+        pattern_stats,
+    };
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Prints a failure of the tool itself in the `json` output format, so that machine consumers
+/// always receive a summary object, even when no check results are available.
+pub fn print_json_failure(out: &mut dyn Write, phase: Phase, error: &anyhow::Error) -> Result<()> {
+    let failure = JsonFailure {
+        status: Status::Failure,
+        phase,
+        message: format!("{error:#}"),
+    };
+    serde_json::to_writer_pretty(&mut *out, &failure)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct RdjsonSource {
+    name: &'static str,
+    url: &'static str,
+}
+
+#[derive(Serialize)]
+struct RdjsonPosition {
+    line: usize,
+    column: usize,
+}
+
+#[derive(Serialize)]
+struct RdjsonRange {
+    start: RdjsonPosition,
+    end: RdjsonPosition,
+}
+
+#[derive(Serialize)]
+struct RdjsonLocation<'a> {
+    path: std::borrow::Cow<'a, str>,
+    range: RdjsonRange,
+}
+
+#[derive(Serialize)]
+struct RdjsonCode {
+    value: &'static str,
+}
+
+#[derive(Serialize)]
+struct RdjsonDiagnostic<'a> {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<RdjsonLocation<'a>>,
+    severity: &'static str,
+    code: RdjsonCode,
+}
+
+#[derive(Serialize)]
+struct RdjsonReport<'a> {
+    source: RdjsonSource,
+    diagnostics: Vec<RdjsonDiagnostic<'a>>,
+}
+
+/// Prints the results of a run in reviewdog's Diagnostic JSON (`rdjson`) format, so that they
+/// can be posted as review comments with reviewdog.
+pub fn print_rdjson(out: &mut dyn Write, errors: &ValidationErrors) -> Result<()> {
+    let report = RdjsonReport {
+        source: RdjsonSource {
+            name: env!("CARGO_PKG_NAME"),
+            url: env!("CARGO_PKG_REPOSITORY"),
+        },
+        diagnostics: errors
+            .iter()
+            .map(|error| RdjsonDiagnostic {
+                message: message_with_context(error),
+                // rdjson columns are 1-based, while rustdoc's are 0-based
+                location: error.location().map(|location| RdjsonLocation {
+                    path: location.filename.to_string_lossy(),
+                    range: RdjsonRange {
+                        start: RdjsonPosition {
+                            line: location.begin.0,
+                            column: location.begin.1 + 1,
+                        },
+                        end: RdjsonPosition {
+                            line: location.end.0,
+                            column: location.end.1 + 1,
+                        },
+                    },
+                }),
+                severity: match error.level() {
+                    ErrorLevel::Error => "ERROR",
+                    ErrorLevel::Warning => "WARNING",
+                },
+                code: RdjsonCode {
+                    value: error.kind(),
+                },
+            })
+            .collect(),
+    };
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BadgeEndpoint {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+/// Prints the results of a run as a shields.io endpoint badge, so that the number of errors can
+/// be shown in a README with `https://img.shields.io/endpoint?url=<url of the output>`.
+///
+/// The badge says `clean` in green when there are no findings. Otherwise it counts the errors,
+/// in orange when there are fewer than 10 and in red when there are more, or the warnings in
+/// yellow if there are no errors.
+///
+/// # Example output
+///
+/// ```text
+/// {"schemaVersion":1,"label":"external types","message":"3 errors","color":"orange"}
+/// ```
+pub fn print_badge(out: &mut dyn Write, errors: &ValidationErrors) -> Result<()> {
+    let plural =
+        |count: usize, noun: &str| format!("{count} {noun}{}", if count == 1 { "" } else { "s" });
+    let (message, color) = match (errors.error_count(), errors.warning_count()) {
+        (0, 0) => ("clean".to_string(), "brightgreen"),
+        (0, warnings) => (plural(warnings, "warning"), "yellow"),
+        (errors @ 1..=9, _) => (plural(errors, "error"), "orange"),
+        (errors, _) => (plural(errors, "error"), "red"),
+    };
+    let badge = BadgeEndpoint {
+        schema_version: 1,
+        label: "external types",
+        message,
+        color,
+    };
+    serde_json::to_writer(&mut *out, &badge)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Prints the results of a run as TeamCity service messages, so that they show up in the
+/// inspections tab of a TeamCity build.
+///
+/// # Example output
+///
+/// ```text
+/// ##teamcity[inspectionType id='unapproved_external_type_ref' name='unapproved_external_type_ref' description='External types referenced in the public API' category='External types']
+/// ##teamcity[inspection typeId='unapproved_external_type_ref' message='Unapproved external type `external_lib::SimpleTrait` referenced in public API' file='test-crate/src/lib.rs' line='37' SEVERITY='ERROR']
+/// ```
+pub fn print_teamcity(out: &mut dyn Write, errors: &ValidationErrors) -> Result<()> {
+    let kinds: BTreeSet<&str> = errors.iter().map(ValidationError::kind).collect();
+    for kind in kinds {
+        writeln!(
+            out,
+            "##teamcity[inspectionType id='{kind}' name='{kind}' description='{description}' \
+            category='External types']",
+            description = teamcity_escape(inspection_description(kind)),
+        )?;
+    }
+    for error in errors.iter() {
+        write!(
+            out,
+            "##teamcity[inspection typeId='{}' message='{}'",
+            error.kind(),
+            teamcity_escape(&message_with_context(error)),
+        )?;
+        if let Some(location) = error.location() {
+            write!(
+                out,
+                " file='{}' line='{}'",
+                teamcity_escape(&location.filename.to_string_lossy()),
+                location.begin.0
+            )?;
+        }
+        let severity = match error.level() {
+            ErrorLevel::Error => "ERROR",
+            ErrorLevel::Warning => "WARNING",
+        };
+        writeln!(out, " SEVERITY='{severity}']")?;
+    }
+    Ok(())
+}
+
+fn inspection_description(kind: &str) -> &'static str {
+    match kind {
+        "unapproved_external_type_ref" => "External types referenced in the public API",
+        "denied_dependency_source" => "External types from denied dependency sources",
+        "unstable_dependency" => "External types from unacknowledged pre-1.0 dependencies",
+        "fields_stripped" => "Hidden fields that can't be checked",
+        "hidden_module" | "hidden_item" => "Hidden items that can't be checked",
+        "pattern_type" => "Pattern types that can't be fully checked",
+        "reexported_crate" => "External crates re-exported in the public API",
+        "multiple_crate_versions" => "External crates referenced in more than one version",
+        "unused_approval" | "duplicate_approved" => "Redundant allowed external types",
+        _ => "",
+    }
+}
+
+/// Returns the message of `error` followed by where it is and which `cfg` it's behind, for
+/// formats that only have room for a single line.
+fn message_with_context(error: &ValidationError) -> String {
+    match error.cfg() {
+        Some(cfg) => format!(
+            "{error} ({}, available on {} only)",
+            error.subtext(),
+            describe_cfg(cfg)
+        ),
+        None => format!("{error} ({})", error.subtext()),
+    }
+}
+
+/// Escapes a value for use in a TeamCity service message.
+fn teamcity_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '|' => escaped.push_str("||"),
+            '\'' => escaped.push_str("|'"),
+            '[' => escaped.push_str("|["),
+            ']' => escaped.push_str("|]"),
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            '\u{0085}' => escaped.push_str("|x"),
+            '\u{2028}' => escaped.push_str("|l"),
+            '\u{2029}' => escaped.push_str("|p"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -33,11 +33,18 @@ struct Component {
     typ: ComponentType,
     name: String,
     span: Option<Span>,
+    /// Conditions from the item's `#[cfg(...)]` and `#[doc(cfg(...))]` attributes
+    cfgs: Vec<String>,
 }
 
 impl Component {
     fn new(typ: ComponentType, name: String, span: Option<Span>) -> Self {
-        Self { typ, name, span }
+        Self {
+            typ,
+            name,
+            span,
+            cfgs: Vec::new(),
+        }
     }
 }
 
@@ -63,6 +70,7 @@ impl Path {
 
     pub fn push(&mut self, typ: ComponentType, item: &Item) {
         self.push_raw(typ, item.name.as_ref().expect("name"), item.span.as_ref());
+        self.add_cfgs(&item.attrs);
     }
 
     pub fn push_raw(&mut self, typ: ComponentType, name: &str, span: Option<&Span>) {
@@ -70,9 +78,52 @@ impl Path {
             .push(Component::new(typ, name.into(), span.cloned()));
     }
 
+    /// Records the conditions of the `cfg` attributes in `attrs`, which are the attributes of
+    /// the item that the last component stands for.
+    pub fn add_cfgs(&mut self, attrs: &[String]) {
+        if let Some(component) = self.stack.last_mut() {
+            for cfg in attrs.iter().filter_map(|attr| cfg_of_attr(attr)) {
+                if !component.cfgs.iter().any(|existing| existing == cfg) {
+                    component.cfgs.push(cfg.to_string());
+                }
+            }
+        }
+    }
+
+    /// Returns the condition that the item at this path is only compiled with, combining the
+    /// `cfg` attributes of it and the items that enclose it, or `None` if it's unconditional.
+    pub fn cfg(&self) -> Option<String> {
+        let mut cfgs: Vec<&str> = Vec::new();
+        for cfg in self.stack.iter().flat_map(|c| &c.cfgs) {
+            // Flatten `all(...)` so that the conditions can be combined into a single `all(...)`
+            let conditions = match cfg.strip_prefix("all(").and_then(|c| c.strip_suffix(')')) {
+                Some(conditions) => split_top_level(conditions),
+                None => vec![cfg.as_str()],
+            };
+            for condition in conditions {
+                if !cfgs.contains(&condition) {
+                    cfgs.push(condition);
+                }
+            }
+        }
+        match cfgs.as_slice() {
+            [] => None,
+            [cfg] => Some(cfg.to_string()),
+            _ => Some(format!("all({})", cfgs.join(", "))),
+        }
+    }
+
+    /// Returns the number of components in the path, including the crate.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
     /// Returns the span (file + beginning and end positions) of the last `Component` in the stack.
+    ///
+    /// Items that macros generate sometimes don't have a span, in which case the span of the
+    /// closest enclosing item that has one is returned instead.
     pub fn last_span(&self) -> Option<&Span> {
-        self.stack.last().and_then(|c| c.span.as_ref())
+        self.stack.iter().rev().find_map(|c| c.span.as_ref())
     }
 
     /// Returns the [`ComponentType`] of the last `Component` in the path.
@@ -92,3 +143,77 @@ impl fmt::Display for Path {
         write!(f, "{}", names.join("::"))
     }
 }
+
+/// Returns the condition of a `#[cfg(...)]` or `#[doc(cfg(...))]` attribute, as rustdoc renders
+/// attributes, or `None` for any other attribute.
+fn cfg_of_attr(attr: &str) -> Option<&str> {
+    let attr = attr.strip_prefix("#[")?.strip_suffix(']')?.trim();
+    attr.strip_prefix("cfg(")
+        .and_then(|cfg| cfg.strip_suffix(')'))
+        .or_else(|| {
+            attr.strip_prefix("doc(cfg(")
+                .and_then(|cfg| cfg.strip_suffix("))"))
+        })
+        .map(str::trim)
+}
+
+/// Splits `list` at the commas that aren't inside parentheses or string literals.
+fn split_top_level(list: &str) -> Vec<&str> {
+    let (mut parts, mut depth, mut in_string, mut start) = (Vec::new(), 0, false, 0);
+    for (index, c) in list.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(list[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(list[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cfg_attrs() {
+        assert_eq!(
+            Some(r#"feature = "gated""#),
+            cfg_of_attr(r#"#[cfg(feature = "gated")]"#)
+        );
+        assert_eq!(
+            Some(r#"all(unix, feature = "gated")"#),
+            cfg_of_attr(r#"#[doc(cfg(all(unix, feature = "gated")))]"#)
+        );
+        assert_eq!(None, cfg_of_attr("#[doc(hidden)]"));
+        assert_eq!(None, cfg_of_attr("#[cfg_attr(docsrs, doc(cfg(unix)))]"));
+        assert_eq!(None, cfg_of_attr("#![feature(doc_cfg)]"));
+    }
+
+    #[test]
+    fn combine_cfgs_of_enclosing_items() {
+        let mut path = Path::new("my_crate");
+        assert_eq!(None, path.cfg());
+        path.push_raw(ComponentType::Module, "gated", None);
+        path.add_cfgs(&[
+            r#"#[cfg(feature = "gated")]"#.into(),
+            r#"#[doc(cfg(feature = "gated"))]"#.into(),
+        ]);
+        assert_eq!(Some(r#"feature = "gated""#.into()), path.cfg());
+        path.push_raw(ComponentType::Struct, "Unix", None);
+        path.add_cfgs(&["#[cfg(unix)]".into()]);
+        assert_eq!(Some(r#"all(feature = "gated", unix)"#.into()), path.cfg());
+        path.push_raw(ComponentType::StructField, "field", None);
+        path.add_cfgs(&[r#"#[doc(cfg(all(unix, any(feature = "a", feature = "b,c"))))]"#.into()]);
+        assert_eq!(
+            Some(r#"all(feature = "gated", unix, any(feature = "a", feature = "b,c"))"#.into()),
+            path.cfg()
+        );
+    }
+}
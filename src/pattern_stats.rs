@@ -0,0 +1,299 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Usage statistics of the `allowed_external_types` patterns.
+//!
+//! Each finding that a pattern suppressed is recorded against that pattern, so that patterns
+//! that allow far more than they need to can be pointed out along with a narrower replacement.
+
+use crate::config::{Config, Pattern, REGEX_PREFIX};
+use crate::error::ValidationError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Characters that make a glob pattern match more than its literal text.
+const GLOB_SPECIAL_CHARS: &[char] = &['*', '?', '[', '{'];
+
+/// Findings suppressed by each allowed pattern, keyed by the pattern as written in the config.
+#[derive(Default, Deserialize, Serialize)]
+pub struct PatternStats {
+    by_pattern: BTreeMap<String, PatternUsage>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct PatternUsage {
+    reason: Option<String>,
+    /// Whether the pattern was matched with `anchored_patterns` enabled
+    anchored: bool,
+    matches: BTreeSet<ValidationError>,
+}
+
+/// How often an allowed pattern was used, and a narrower replacement if it's broader than
+/// the types it matched.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PatternReport {
+    pub pattern: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Number of distinct references that the pattern allowed
+    pub matches: usize,
+    /// Names of the types that the pattern allowed, in alphabetical order
+    pub types: Vec<String>,
+    /// A narrower pattern that still allows all of the `types`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl PatternStats {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records that `pattern` allowed the reference in `error`.
+    pub fn record(&mut self, pattern: &Pattern, anchored: bool, error: ValidationError) {
+        self.by_pattern
+            .entry(pattern.as_str().into())
+            .or_insert_with(|| PatternUsage {
+                reason: pattern.reason().map(String::from),
+                anchored,
+                matches: BTreeSet::new(),
+            })
+            .matches
+            .insert(error);
+    }
+
+    /// Adds all recorded matches from `other`.
+    pub fn merge(&mut self, other: PatternStats) {
+        for (pattern, usage) in other.by_pattern {
+            match self.by_pattern.get_mut(&pattern) {
+                Some(existing) => existing.matches.extend(usage.matches),
+                None => {
+                    self.by_pattern.insert(pattern, usage);
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_pattern.is_empty()
+    }
+
+    /// Returns the patterns, as written in the config, that allowed at least one reference.
+    pub fn used_patterns(&self) -> impl Iterator<Item = &str> {
+        self.by_pattern.keys().map(String::as_str)
+    }
+
+    /// Returns findings for the `allowed_external_types` patterns of `config` that are listed
+    /// more than once, or that didn't allow any reference. Negated patterns only take types away
+    /// from the patterns before them, so they're never reported as unused.
+    pub fn approval_findings(&self, config: &Config) -> Vec<ValidationError> {
+        let mut seen = BTreeSet::new();
+        let mut findings = Vec::new();
+        for pattern in &config.allowed_external_types {
+            if !seen.insert(pattern.as_str()) {
+                findings.push(ValidationError::duplicate_approved(pattern.as_str()));
+            } else if !pattern.is_negated() && !self.by_pattern.contains_key(pattern.as_str()) {
+                findings.push(ValidationError::unused_approval(pattern.as_str()));
+            }
+        }
+        findings
+    }
+
+    /// Returns the usage of each pattern, with the patterns that have a narrower replacement
+    /// first, and then by pattern.
+    pub fn reports(&self) -> Vec<PatternReport> {
+        let mut reports: Vec<_> = self
+            .by_pattern
+            .iter()
+            .map(|(pattern, usage)| {
+                let types: Vec<String> = usage
+                    .matches
+                    .iter()
+                    .map(|error| error.type_name().to_string())
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect();
+                PatternReport {
+                    pattern: pattern.clone(),
+                    reason: usage.reason.clone(),
+                    matches: usage.matches.len(),
+                    suggestion: suggest_narrower_pattern(pattern, usage.anchored, &types),
+                    types,
+                }
+            })
+            .collect();
+        reports.sort_by_key(|report| report.suggestion.is_none());
+        reports
+    }
+}
+
+/// Returns a pattern that's narrower than `pattern` but still matches all of `types`, if there
+/// is one.
+///
+/// A pattern that only matched one type is replaced by that type. Otherwise, the wildcard is
+/// moved into the deepest module that all of the types have in common.
+fn suggest_narrower_pattern(pattern: &str, anchored: bool, types: &[String]) -> Option<String> {
+    let literal_prefix = match pattern.strip_prefix(REGEX_PREFIX) {
+        Some(_) => "",
+        None if pattern.contains(GLOB_SPECIAL_CHARS) => {
+            &pattern[..pattern.find(GLOB_SPECIAL_CHARS).unwrap()]
+        }
+        // Patterns without wildcards only match themselves
+        None => return None,
+    };
+    if let [only_type] = types {
+        return Some(only_type.clone());
+    }
+
+    let paths: Vec<Vec<&str>> = types.iter().map(|t| t.split("::").collect()).collect();
+    let shortest_module = paths.iter().map(|p| p.len() - 1).min()?;
+    let common_depth = (0..shortest_module)
+        .take_while(|&i| paths.iter().all(|path| path[i] == paths[0][i]))
+        .count();
+    // Only whole segments of the literal prefix narrow down the pattern
+    let literal_depth = literal_prefix.matches("::").count();
+    if common_depth == 0 || common_depth <= literal_depth {
+        return None;
+    }
+    let module = paths[0][..common_depth].join("::");
+    let all_direct_children = paths.iter().all(|path| path.len() == common_depth + 1);
+    if anchored && !all_direct_children {
+        Some(format!("{module}::**"))
+    } else {
+        Some(format!("{module}::*"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorLocation;
+
+    fn unapproved(type_name: &str) -> ValidationError {
+        ValidationError::unapproved_external_type_ref(
+            type_name,
+            &ErrorLocation::StructField,
+            "Thing",
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn stats(pattern: &str, anchored: bool, types: &[&str]) -> PatternReport {
+        let pattern = Pattern::new(pattern).unwrap().with_reason("Because");
+        let mut stats = PatternStats::new();
+        for type_name in types {
+            stats.record(&pattern, anchored, unapproved(type_name));
+        }
+        stats.reports().remove(0)
+    }
+
+    #[test]
+    fn count_matches_per_pattern() {
+        let report = stats("tokio::*", false, &["tokio::time::Duration"]);
+        assert_eq!(
+            PatternReport {
+                pattern: "tokio::*".into(),
+                reason: Some("Because".into()),
+                matches: 1,
+                types: vec!["tokio::time::Duration".into()],
+                suggestion: Some("tokio::time::Duration".into()),
+            },
+            report
+        );
+
+        let pattern = Pattern::new("a::*").unwrap();
+        let mut stats = PatternStats::new();
+        stats.record(&pattern, false, unapproved("a::One"));
+        stats.record(&pattern, false, unapproved("a::One"));
+        let mut other = PatternStats::new();
+        other.record(&pattern, false, unapproved("a::Two"));
+        stats.merge(other);
+        let report = &stats.reports()[0];
+        assert_eq!(2, report.matches);
+        assert_eq!(vec!["a::One", "a::Two"], report.types);
+    }
+
+    #[test]
+    fn suggest_narrower_patterns() {
+        let suggestion =
+            |pattern, anchored, types: &[&str]| stats(pattern, anchored, types).suggestion;
+        assert_eq!(
+            Some("tokio::time::*".into()),
+            suggestion(
+                "tokio::*",
+                false,
+                &["tokio::time::Duration", "tokio::time::Instant"]
+            )
+        );
+        assert_eq!(
+            Some("tokio::time::*".into()),
+            suggestion(
+                "regex:tokio::.*",
+                false,
+                &["tokio::time::Duration", "tokio::time::Instant"]
+            )
+        );
+        assert_eq!(
+            Some("tokio::time::**".into()),
+            suggestion(
+                "tokio::**",
+                true,
+                &["tokio::time::Duration", "tokio::time::error::Elapsed"]
+            )
+        );
+        assert_eq!(
+            None,
+            suggestion(
+                "tokio::time::*",
+                false,
+                &["tokio::time::Duration", "tokio::time::Instant"]
+            )
+        );
+        assert_eq!(
+            None,
+            suggestion(
+                "tokio::*",
+                false,
+                &["tokio::Runtime", "tokio::time::Instant"]
+            )
+        );
+        assert_eq!(None, suggestion("{a,b}::*", false, &["a::One", "b::Two"]));
+        assert_eq!(
+            None,
+            suggestion("tokio::Runtime", false, &["tokio::Runtime"])
+        );
+    }
+
+    #[test]
+    fn report_unused_and_duplicate_approvals() {
+        let config = Config {
+            allowed_external_types: ["a::*", "b::*", "a::*", "!a::Hidden"]
+                .into_iter()
+                .map(|pattern| Pattern::new(pattern).unwrap())
+                .collect(),
+            ..Default::default()
+        };
+        let mut stats = PatternStats::new();
+        stats.record(
+            &config.allowed_external_types[0],
+            false,
+            unapproved("a::One"),
+        );
+        let findings: Vec<_> = stats
+            .approval_findings(&config)
+            .iter()
+            .map(|finding| (finding.kind(), finding.type_name().to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("unused_approval", "b::*".to_string()),
+                ("duplicate_approved", "a::*".to_string()),
+            ],
+            findings
+        );
+    }
+}
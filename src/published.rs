@@ -0,0 +1,178 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Checking of crates published to crates.io.
+//!
+//! The published crate is added as the only dependency of a scratch project, so that Cargo takes
+//! care of downloading its source and resolving its dependencies. Rustdoc is then run on the
+//! dependency from within the scratch project.
+
+use anyhow::{anyhow, bail, Context, Result};
+use cargo_metadata::semver::VersionReq;
+use cargo_metadata::{Metadata, Package};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const SCRATCH_PACKAGE_NAME: &str = "check-external-types-scratch";
+
+/// A published crate given as `name@version`, where the version can be a requirement such as
+/// `0.9` for the newest `0.9.x` release.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PublishedCrate {
+    pub name: String,
+    pub version: VersionReq,
+}
+
+impl PublishedCrate {
+    /// Returns the directory of the scratch project for this crate. It's kept between runs so
+    /// that the download and build can be reused.
+    pub fn scratch_dir(&self) -> PathBuf {
+        std::env::temp_dir()
+            .join("cargo-check-external-types")
+            .join(format!("{}@{}", self.name, self.version).replace(['*', ' ', ','], "_"))
+    }
+
+    /// Writes the scratch project for this crate, and returns the path of its manifest.
+    pub fn create_scratch_project(
+        &self,
+        features: &[String],
+        default_features: bool,
+    ) -> Result<PathBuf> {
+        let dir = self.scratch_dir();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create scratch project in {dir:?}"))?;
+        fs::write(dir.join("lib.rs"), "").context("failed to write scratch project")?;
+        let manifest_path = dir.join("Cargo.toml");
+        fs::write(
+            &manifest_path,
+            self.scratch_manifest(features, default_features),
+        )
+        .context("failed to write scratch project")?;
+        Ok(manifest_path)
+    }
+
+    fn scratch_manifest(&self, features: &[String], default_features: bool) -> String {
+        let mut dependency = toml::Table::new();
+        dependency.insert("version".into(), self.version.to_string().into());
+        if !default_features {
+            dependency.insert("default-features".into(), false.into());
+        }
+        if !features.is_empty() {
+            dependency.insert("features".into(), features.to_vec().into());
+        }
+        // Crate names never need quoting, and the dependency is formatted as an inline table
+        let dependency = toml::Value::Table(dependency);
+        format!(
+            "[package]\n\
+            name = \"{SCRATCH_PACKAGE_NAME}\"\n\
+            version = \"0.0.0\"\n\
+            edition = \"2021\"\n\
+            publish = false\n\n\
+            [lib]\n\
+            path = \"lib.rs\"\n\n\
+            # Keep the project out of any workspace it happens to be in\n\
+            [workspace]\n\n\
+            [dependencies]\n\
+            {name} = {dependency}\n",
+            name = self.name,
+        )
+    }
+
+    /// Returns the package for this crate from the scratch project's metadata.
+    pub fn find_package<'a>(&self, metadata: &'a Metadata) -> Result<&'a Package> {
+        let root = metadata
+            .root_package()
+            .ok_or_else(|| anyhow!("failed to find the scratch package"))?;
+        let resolve = metadata
+            .resolve
+            .as_ref()
+            .ok_or_else(|| anyhow!("Cargo metadata didn't have resolved nodes"))?;
+        let root_node = resolve
+            .nodes
+            .iter()
+            .find(|node| node.id == root.id)
+            .ok_or_else(|| anyhow!("failed to find node for the scratch package"))?;
+        root_node
+            .deps
+            .iter()
+            .filter_map(|dep| metadata.packages.iter().find(|p| p.id == dep.pkg))
+            .find(|p| p.name == self.name && self.version.matches(&p.version))
+            .ok_or_else(|| anyhow!("failed to find `{self}` in the scratch project's dependencies"))
+    }
+}
+
+impl fmt::Display for PublishedCrate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+impl FromStr for PublishedCrate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, version)) = s.split_once('@') else {
+            bail!("invalid crate: {s}. Expected `name@version`.");
+        };
+        if name.is_empty() {
+            bail!("invalid crate: {s}. Expected `name@version`.");
+        }
+        let version = VersionReq::parse(version)
+            .with_context(|| format!("invalid version requirement in crate: {s}"))?;
+        Ok(Self {
+            name: name.into(),
+            version,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_str_eq;
+
+    #[test]
+    fn parse_published_crate() {
+        let krate: PublishedCrate = "serde_yaml@0.9".parse().unwrap();
+        assert_eq!("serde_yaml", krate.name);
+        assert_eq!(VersionReq::parse("0.9").unwrap(), krate.version);
+        assert_eq!("serde_yaml@^0.9", krate.to_string());
+
+        assert!("serde_yaml".parse::<PublishedCrate>().is_err());
+        assert!("@0.9".parse::<PublishedCrate>().is_err());
+        assert!("serde_yaml@latest".parse::<PublishedCrate>().is_err());
+    }
+
+    #[test]
+    fn write_scratch_manifest() {
+        let krate: PublishedCrate = "my-crate@=1.2.3".parse().unwrap();
+        let expected = r#"[package]
+name = "check-external-types-scratch"
+version = "0.0.0"
+edition = "2021"
+publish = false
+
+[lib]
+path = "lib.rs"
+
+# Keep the project out of any workspace it happens to be in
+[workspace]
+
+[dependencies]
+my-crate = { default-features = false, features = ["a", "b"], version = "=1.2.3" }
+"#;
+        assert_str_eq!(
+            expected,
+            krate.scratch_manifest(&["a".into(), "b".into()], false)
+        );
+        let manifest: toml::Table = toml::from_str(&krate.scratch_manifest(&[], true)).unwrap();
+        assert_eq!(
+            Some("=1.2.3"),
+            manifest["dependencies"]["my-crate"]["version"].as_str()
+        );
+    }
+}
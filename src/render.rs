@@ -0,0 +1,272 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Renders rustdoc types back into Rust syntax.
+//!
+//! Type names are rendered as they were written at the use site, along with their concrete
+//! generic arguments. For example, a reference to `SimpleGenericTrait<SomeStruct>` renders
+//! exactly like that rather than as two separate types.
+
+use rustdoc_types::{GenericArg, GenericArgs, GenericBound, Path, Term, TraitBoundModifier, Type};
+
+/// Renders a type as Rust source code.
+pub fn render_type(typ: &Type) -> String {
+    let mut out = String::new();
+    write_type(&mut out, typ);
+    out
+}
+
+/// Renders a path (such as a struct or trait reference) with its generic arguments.
+pub fn render_path(path: &Path) -> String {
+    let mut out = String::new();
+    write_path(&mut out, path);
+    out
+}
+
+/// Returns true if the given path has any generic arguments that would be rendered.
+pub fn has_generic_args(path: &Path) -> bool {
+    match path.args.as_deref() {
+        Some(GenericArgs::AngleBracketed { args, constraints }) => {
+            !args.is_empty() || !constraints.is_empty()
+        }
+        Some(GenericArgs::Parenthesized { .. }) => true,
+        None => false,
+    }
+}
+
+fn write_path(out: &mut String, path: &Path) {
+    out.push_str(&path.name);
+    if let Some(args) = &path.args {
+        write_generic_args(out, args);
+    }
+}
+
+fn write_generic_args(out: &mut String, args: &GenericArgs) {
+    match args {
+        GenericArgs::AngleBracketed { args, constraints } => {
+            if args.is_empty() && constraints.is_empty() {
+                return;
+            }
+            out.push('<');
+            let mut first = true;
+            for arg in args {
+                write_separator(out, &mut first, ", ");
+                match arg {
+                    GenericArg::Lifetime(lifetime) => out.push_str(lifetime),
+                    GenericArg::Type(typ) => write_type(out, typ),
+                    GenericArg::Const(constant) => out.push_str(&constant.expr),
+                    GenericArg::Infer => out.push('_'),
+                }
+            }
+            for constraint in constraints {
+                write_separator(out, &mut first, ", ");
+                out.push_str(&constraint.name);
+                write_generic_args(out, &constraint.args);
+                match &constraint.binding {
+                    rustdoc_types::AssocItemConstraintKind::Equality(term) => {
+                        out.push_str(" = ");
+                        match term {
+                            Term::Type(typ) => write_type(out, typ),
+                            Term::Constant(constant) => out.push_str(&constant.expr),
+                        }
+                    }
+                    rustdoc_types::AssocItemConstraintKind::Constraint(bounds) => {
+                        out.push_str(": ");
+                        write_bounds(out, bounds);
+                    }
+                }
+            }
+            out.push('>');
+        }
+        GenericArgs::Parenthesized { inputs, output } => {
+            out.push('(');
+            let mut first = true;
+            for input in inputs {
+                write_separator(out, &mut first, ", ");
+                write_type(out, input);
+            }
+            out.push(')');
+            if let Some(output) = output {
+                out.push_str(" -> ");
+                write_type(out, output);
+            }
+        }
+    }
+}
+
+fn write_bounds(out: &mut String, bounds: &[GenericBound]) {
+    let mut first = true;
+    for bound in bounds {
+        match bound {
+            GenericBound::TraitBound {
+                trait_, modifier, ..
+            } => {
+                write_separator(out, &mut first, " + ");
+                match modifier {
+                    TraitBoundModifier::None => {}
+                    TraitBoundModifier::Maybe => out.push('?'),
+                    TraitBoundModifier::MaybeConst => out.push_str("~const "),
+                }
+                write_path(out, trait_);
+            }
+            GenericBound::Outlives(lifetime) => {
+                write_separator(out, &mut first, " + ");
+                out.push_str(lifetime);
+            }
+            GenericBound::Use(_) => {}
+        }
+    }
+}
+
+fn write_type(out: &mut String, typ: &Type) {
+    match typ {
+        Type::ResolvedPath(path) => write_path(out, path),
+        Type::DynTrait(dyn_trait) => {
+            out.push_str("dyn ");
+            let mut first = true;
+            for trait_ in &dyn_trait.traits {
+                write_separator(out, &mut first, " + ");
+                write_path(out, &trait_.trait_);
+            }
+            if let Some(lifetime) = &dyn_trait.lifetime {
+                write_separator(out, &mut first, " + ");
+                out.push_str(lifetime);
+            }
+        }
+        Type::Generic(name) | Type::Primitive(name) => out.push_str(name),
+        Type::FunctionPointer(fp) => {
+            out.push_str("fn(");
+            let mut first = true;
+            for (_, input) in &fp.sig.inputs {
+                write_separator(out, &mut first, ", ");
+                write_type(out, input);
+            }
+            out.push(')');
+            if let Some(output) = &fp.sig.output {
+                out.push_str(" -> ");
+                write_type(out, output);
+            }
+        }
+        Type::Tuple(types) => {
+            out.push('(');
+            let mut first = true;
+            for typ in types {
+                write_separator(out, &mut first, ", ");
+                write_type(out, typ);
+            }
+            if types.len() == 1 {
+                out.push(',');
+            }
+            out.push(')');
+        }
+        Type::Slice(typ) => {
+            out.push('[');
+            write_type(out, typ);
+            out.push(']');
+        }
+        Type::Array { type_, len } => {
+            out.push('[');
+            write_type(out, type_);
+            out.push_str("; ");
+            out.push_str(len);
+            out.push(']');
+        }
+        Type::Pat { type_, .. } => write_type(out, type_),
+        Type::ImplTrait(bounds) => {
+            out.push_str("impl ");
+            write_bounds(out, bounds);
+        }
+        Type::Infer => out.push('_'),
+        Type::RawPointer { is_mutable, type_ } => {
+            out.push_str(if *is_mutable { "*mut " } else { "*const " });
+            write_type(out, type_);
+        }
+        Type::BorrowedRef {
+            lifetime,
+            is_mutable,
+            type_,
+        } => {
+            out.push('&');
+            if let Some(lifetime) = lifetime {
+                out.push_str(lifetime);
+                out.push(' ');
+            }
+            if *is_mutable {
+                out.push_str("mut ");
+            }
+            write_type(out, type_);
+        }
+        Type::QualifiedPath {
+            name,
+            args,
+            self_type,
+            trait_,
+        } => {
+            match trait_ {
+                Some(trait_) => {
+                    out.push('<');
+                    write_type(out, self_type);
+                    out.push_str(" as ");
+                    write_path(out, trait_);
+                    out.push('>');
+                }
+                None => write_type(out, self_type),
+            }
+            out.push_str("::");
+            out.push_str(name);
+            write_generic_args(out, args);
+        }
+    }
+}
+
+fn write_separator(out: &mut String, first: &mut bool, separator: &str) {
+    if !*first {
+        out.push_str(separator);
+    }
+    *first = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustdoc_types::Id;
+
+    fn path(name: &str, args: Vec<GenericArg>) -> Path {
+        Path {
+            name: name.into(),
+            id: Id(0),
+            args: Some(Box::new(GenericArgs::AngleBracketed {
+                args,
+                constraints: Vec::new(),
+            })),
+        }
+    }
+
+    #[test]
+    fn render_generic_path() {
+        let some_struct = Type::ResolvedPath(path("SomeStruct", vec![]));
+        let trait_path = path(
+            "SimpleGenericTrait",
+            vec![GenericArg::Type(some_struct.clone())],
+        );
+        assert!(has_generic_args(&trait_path));
+        assert!(!has_generic_args(&path("SomeStruct", vec![])));
+        assert_eq!("SimpleGenericTrait<SomeStruct>", render_path(&trait_path));
+        assert_eq!(
+            "&'a mut [Option<SomeStruct>; 2]",
+            render_type(&Type::BorrowedRef {
+                lifetime: Some("'a".into()),
+                is_mutable: true,
+                type_: Box::new(Type::Array {
+                    type_: Box::new(Type::ResolvedPath(path(
+                        "Option",
+                        vec![GenericArg::Type(some_struct)]
+                    ))),
+                    len: "2".into(),
+                }),
+            })
+        );
+    }
+}
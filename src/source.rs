@@ -0,0 +1,97 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Reads the source code that rustdoc spans point into, so that items generated by macros can
+//! be attributed to the macro invocation that generated them.
+
+use rustdoc_types::Span;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Source files of the crate being visited, read as they're needed. Clones share the files read
+/// so far.
+#[derive(Clone, Debug)]
+pub struct SourceFiles {
+    /// Directory that the file names in spans are relative to
+    root: PathBuf,
+    /// Contents of each file read so far, or `None` if it couldn't be read
+    cache: Arc<Mutex<HashMap<PathBuf, Option<String>>>>,
+}
+
+impl SourceFiles {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the name of the macro if `span` starts with a macro invocation, such as
+    /// `make_struct!(Name);`. Rustdoc gives items that a macro generated the span of the
+    /// invocation rather than a span inside the macro definition.
+    pub fn macro_invocation(&self, span: &Span) -> Option<String> {
+        let mut cache = self.cache.lock().unwrap();
+        let contents = cache
+            .entry(span.filename.clone())
+            .or_insert_with(|| fs::read_to_string(self.root.join(&span.filename)).ok());
+        macro_name_at(contents.as_deref()?, span.begin)
+    }
+}
+
+/// Returns the macro name of the invocation at the 1-based `line` and 0-based `column` of
+/// `contents`, if there's one.
+fn macro_name_at(contents: &str, (line, column): (usize, usize)) -> Option<String> {
+    let text: String = contents
+        .lines()
+        .nth(line.checked_sub(1)?)?
+        .chars()
+        .skip(column)
+        .collect();
+    let path_len = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':' || c == '#'))
+        .unwrap_or(text.len());
+    let (path, rest) = text.split_at(path_len);
+    let name = path.rsplit("::").next()?.trim_start_matches("r#");
+    let is_ident = name.starts_with(|c: char| c.is_alphabetic() || c == '_');
+    if is_ident && rest.trim_start().starts_with('!') && name != "macro_rules" {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+use external_lib::SomeStruct;
+
+make_struct!(FromMacro);
+    other_crate::make_fn! { generated }
+pub fn not_a_macro() -> bool { !true }
+macro_rules! make_struct {
+";
+
+    #[test]
+    fn find_macro_invocations() {
+        assert_eq!(
+            Some("make_struct".to_string()),
+            macro_name_at(SOURCE, (3, 0))
+        );
+        assert_eq!(Some("make_fn".to_string()), macro_name_at(SOURCE, (4, 4)));
+    }
+
+    #[test]
+    fn ignore_other_items() {
+        assert_eq!(None, macro_name_at(SOURCE, (1, 0)));
+        assert_eq!(None, macro_name_at(SOURCE, (5, 0)));
+        assert_eq!(None, macro_name_at(SOURCE, (6, 0)));
+        assert_eq!(None, macro_name_at(SOURCE, (0, 0)));
+        assert_eq!(None, macro_name_at(SOURCE, (100, 0)));
+    }
+}
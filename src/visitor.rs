@@ -3,18 +3,29 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
-use crate::config::Config;
-use crate::error::{ErrorLocation, ValidationError, ValidationErrors};
+use crate::config::{Config, Severity};
+use crate::dependency::Dependencies;
+use crate::error::{
+    ErrorLevel, ErrorLocation, SuppressionMechanism, ValidationError, ValidationErrors,
+};
 use crate::path::{ComponentType, Path};
+use crate::render::{has_generic_args, render_path};
+use crate::source::SourceFiles;
 use crate::{bug_panic, here};
 use anyhow::{anyhow, Context, Result};
+use rayon::prelude::*;
 use rustdoc_types::{
     Crate, FunctionSignature, GenericArgs, GenericBound, GenericParamDef, GenericParamDefKind,
-    Generics, Id, Item, ItemEnum, ItemSummary, Path as RustDocPath, Struct, StructKind, Term,
-    Trait, Type, Union, Variant, VariantKind, Visibility, WherePredicate,
+    Generics, Id, Item, ItemEnum, ItemKind, ItemSummary, Module, Path as RustDocPath, Struct,
+    StructKind, Term, Trait, Type, TypeAlias, Union, Variant, VariantKind, Visibility,
+    WherePredicate,
 };
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
 use tracing::{debug, instrument, warn};
 
 macro_rules! unstable_rust_feature {
@@ -40,6 +51,9 @@ pub(crate) type Paths = HashMap<Id, ItemSummary>;
 
 /// Visits all items in the Rustdoc JSON output to discover external types in public APIs
 /// and track them as validation errors if the [`Config`] doesn't allow them.
+///
+/// The top-level items of the crate are visited in parallel, each in its own [`Traversal`], and
+/// the errors found in them are merged once they're all visited.
 pub struct Visitor {
     /// Parsed config file from the user, or the defaults if none was provided
     config: Config,
@@ -51,26 +65,210 @@ pub struct Visitor {
     index: Index,
     /// Map of rustdoc [`Id`] to rustdoc [`ItemSummary`]
     paths: Paths,
+    /// Names of the items in the index, for finding the hidden module in an import path
+    item_names: HashSet<String>,
+    /// Dependencies of the crate being visited, used to attribute external types to packages
+    dependencies: Dependencies,
+
+    /// Optional channel that newly discovered errors are sent to once the top-level item that
+    /// they're found in has been visited, in item order
+    error_sender: Option<Sender<ValidationError>>,
+    /// Errors sent to `error_sender` so far, so that errors found by more than one traversal
+    /// are only sent once
+    sent_errors: Mutex<BTreeSet<ValidationError>>,
+    /// Source code of the crate, used to attribute errors in macro-generated items to the
+    /// macro that generated them
+    sources: Option<SourceFiles>,
+    /// Whether to stop visiting once an error is found. The top-level items are visited one
+    /// at a time then, so that the error that stops the visit is the first one.
+    fail_fast: bool,
+    /// Set when an error is found while failing fast, so that no more items are visited
+    stopped: AtomicBool,
+    /// Produces the rustdoc JSON with hidden items included, which has the items of public
+    /// modules that are re-exported out of hidden modules. Rustdoc strips those items otherwise.
+    hidden_items_docs: Option<HiddenItemsDocs>,
+    /// Visitor for the rustdoc JSON with hidden items, created the first time it's needed
+    hidden_items_visitor: Mutex<Option<Arc<Visitor>>>,
+}
 
+/// State of visiting one top-level item of the crate. It derefs to the [`Visitor`] so that the
+/// rustdoc JSON and config can be looked up.
+struct Traversal<'a> {
+    visitor: &'a Visitor,
     /// Set of errors
     ///
-    /// The visitor adds errors to this set while it visits each item in the rustdoc
+    /// The traversal adds errors to this set while it visits each item in the rustdoc
     /// output.
     errors: RefCell<ValidationErrors>,
+    /// How the outermost generic type currently being checked was written, such as
+    /// `SimpleGenericTrait<SomeStruct>`, so that the unapproved types in it can be reported
+    /// together as written.
+    use_site: RefCell<Option<String>>,
+    /// Unapproved external types found at the current use site, which are reported as one
+    /// error once the whole use site has been checked.
+    use_site_errors: RefCell<Vec<ValidationError>>,
+    /// Local type aliases followed so far while resolving a generic default, or `None` when
+    /// not inside of a generic default.
+    alias_chain: RefCell<Option<Vec<String>>>,
+    /// The first reference to each version of each external crate, in the order they were
+    /// found. More than one version of the same crate means that an error is added when the
+    /// traversals are merged, since which reference comes first depends on the item order.
+    crate_references: RefCell<Vec<CrateReference>>,
+    /// Public items visited so far, with the cfg conditions that they were visited under.
+    /// Other than the path that errors are reported at, what's found in an item only depends on
+    /// those, so an item that's reached again, such as through another re-export or impl, isn't
+    /// visited again. Items reached from more than one top-level item are still visited once
+    /// for each, so that the errors don't depend on which traversal gets to them first. Items
+    /// are always visited again when the config has `allowed_external_types_in`, since what
+    /// it allows depends on the path.
+    visited: RefCell<HashSet<(Id, Option<String>)>>,
+    /// Errors found so far that haven't been sent to the visitor's `error_sender`, in the order
+    /// they were found. They're sent when the traversal is merged, so that they arrive in item
+    /// order no matter which traversals finish first.
+    unsent_errors: RefCell<Vec<ValidationError>>,
+}
+
+/// The first reference that a traversal found to a version of an external crate.
+struct CrateReference {
+    crate_name: String,
+    /// Rustdoc crate ID of the version
+    crate_id: u32,
+    /// Whether the reference is in the rustdoc JSON with hidden items, which has its own
+    /// crate IDs
+    in_hidden_items_docs: bool,
+    path: Path,
+    what: ErrorLocation,
+    type_name: String,
 }
 
+impl CrateReference {
+    /// Returns true if both are references to the same crate, in the same rustdoc JSON.
+    fn same_crate(&self, other: &CrateReference) -> bool {
+        self.crate_name == other.crate_name
+            && self.in_hidden_items_docs == other.in_hidden_items_docs
+    }
+
+    /// Returns true if both are references to the same version of the crate.
+    fn same_version(&self, other: &CrateReference) -> bool {
+        self.same_crate(other) && self.crate_id == other.crate_id
+    }
+
+    /// Returns the error for referencing this version of the crate after another one.
+    fn error(&self, dependencies: &Dependencies) -> ValidationError {
+        ValidationError::multiple_crate_versions(
+            &self.crate_name,
+            &self.type_name,
+            &self.what,
+            self.path.to_string(),
+            self.path.last_span(),
+            dependencies.versions(&self.crate_name).to_vec(),
+        )
+    }
+}
+
+/// A pattern in `allowed_external_types` whose crate isn't a dependency.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownCratePattern {
+    /// The pattern as written in the config
+    pub pattern: String,
+    /// Name of the crate that the pattern starts with
+    pub crate_name: String,
+    /// The pattern with the crate name of a dependency that it's likely a misspelling of
+    pub suggestion: Option<String>,
+}
+
+type HiddenItemsDocs = Box<dyn Fn() -> Result<Crate> + Send + Sync>;
+
 impl Visitor {
-    pub fn new(config: Config, package: Crate) -> Result<Self> {
+    pub fn new(config: Config, package: Crate, dependencies: Dependencies) -> Result<Self> {
         Ok(Visitor {
             config,
             root_crate_id: Self::root_crate_id(&package)?,
             root_crate_name: Self::root_crate_name(&package)?,
+            item_names: package
+                .index
+                .values()
+                .filter_map(|item| item.name.clone())
+                .collect(),
             index: package.index,
             paths: package.paths,
-            errors: RefCell::new(ValidationErrors::new()),
+            dependencies,
+            error_sender: None,
+            sent_errors: Mutex::new(BTreeSet::new()),
+            sources: None,
+            fail_fast: false,
+            stopped: AtomicBool::new(false),
+            hidden_items_docs: None,
+            hidden_items_visitor: Mutex::new(None),
         })
     }
 
+    /// Returns the config that the public API is checked against.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the patterns in `allowed_external_types` that name a crate that isn't in the
+    /// dependency graph at all, which usually means that the crate name is misspelled.
+    pub fn unknown_crate_patterns(&self) -> Vec<UnknownCratePattern> {
+        self.config
+            .allowed_external_types
+            .iter()
+            .filter(|pattern| !pattern.is_negated())
+            .filter_map(|pattern| {
+                let crate_name = pattern.crate_name()?;
+                let is_known = crate_name == self.root_crate_name
+                    || matches!(crate_name, "alloc" | "core" | "std")
+                    || self.dependencies.get(crate_name).is_some();
+                if is_known {
+                    return None;
+                }
+                Some(UnknownCratePattern {
+                    pattern: pattern.as_str().into(),
+                    crate_name: crate_name.into(),
+                    suggestion: self
+                        .dependencies
+                        .similar_crate_name(crate_name)
+                        .map(|name| pattern.as_str().replacen(crate_name, name, 1)),
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the source files, with file names relative to `root`, to note which errors are in
+    /// items that a macro generated.
+    pub fn with_sources(mut self, root: impl Into<std::path::PathBuf>) -> Self {
+        self.sources = Some(SourceFiles::new(root));
+        self
+    }
+
+    /// Sends each distinct error to `sender` as it's discovered, in addition to collecting it
+    /// into the [`ValidationErrors`] returned by [`visit_all`](Visitor::visit_all). Errors are
+    /// sent in the order of the top-level items they're found in, once those have been visited,
+    /// so the order doesn't depend on how the items were visited in parallel.
+    pub fn stream_errors_to(mut self, sender: Sender<ValidationError>) -> Self {
+        self.error_sender = Some(sender);
+        self
+    }
+
+    /// Checks public modules that are re-exported out of hidden modules using the rustdoc JSON
+    /// that `docs` produces, which must include hidden items. It's only called if there are
+    /// such modules. Without it, those modules are reported as not being checkable.
+    pub fn with_hidden_items_docs(
+        mut self,
+        docs: impl Fn() -> Result<Crate> + Send + Sync + 'static,
+    ) -> Self {
+        self.hidden_items_docs = Some(Box::new(docs));
+        self
+    }
+
+    /// Stops visiting items once the first error is found, rather than looking for all of them.
+    /// Warnings don't stop the visit.
+    pub fn fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
+
     /// This is the entry point for visiting the entire Rustdoc JSON tree, starting
     /// from the root module (the only module where `is_crate` is true).
     pub fn visit_all(self) -> Result<ValidationErrors> {
@@ -88,17 +286,45 @@ impl Visitor {
             .find(|module| module.is_crate)
             .ok_or_else(|| anyhow!("failed to find crate root module"))?;
 
-        for id in &root_module.items {
+        let visit = |id: &Id| -> Result<Traversal<'_>> {
+            let traversal = Traversal::new(&self);
             let item = self.item(id).context(here!())?;
-            self.visit_item(&root_path, item, VisibilityCheck::Default)?;
+            traversal.visit_item(&root_path, item, VisibilityCheck::Default)?;
+            Ok(traversal)
+        };
+        let merged = Traversal::new(&self);
+        if self.fail_fast {
+            for id in &root_module.items {
+                merged.merge(visit(id)?);
+                if self.is_stopped() {
+                    break;
+                }
+            }
+        } else {
+            let traversals = root_module
+                .items
+                .par_iter()
+                .map(visit)
+                .collect::<Result<Vec<_>>>()?;
+            for traversal in traversals {
+                merged.merge(traversal);
+            }
         }
-        Ok(self.errors.take())
+        Ok(merged.errors.take())
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
     }
 
     /// Returns true if the given item is public. In some cases, this must be determined
     /// by examining the surrounding context. For example, enum variants are public if the
     /// enum is public, even if their visibility is set to `Visibility::Default`.
-    fn is_public(path: &Path, item: &Item) -> bool {
+    ///
+    /// Items with restricted visibility, such as `pub(crate)` or `pub(in path)`, are never
+    /// visible outside of the crate, so they're only considered public when the config asks to
+    /// include them.
+    fn is_public(&self, path: &Path, item: &Item) -> bool {
         match item.visibility {
             Visibility::Public => true,
             // This code is much clearer with a match statement
@@ -114,7 +340,143 @@ impl Visitor {
                 (_, Some(ComponentType::Trait)) => true,
                 _ => false,
             },
-            _ => false,
+            Visibility::Crate | Visibility::Restricted { .. } => {
+                self.config.include_restricted_visibility
+            }
+        }
+    }
+
+    fn item(&self, id: &Id) -> Result<&Item> {
+        self.index
+            .get(id)
+            .ok_or_else(|| {
+                if let Some(item_summary) = self.paths.get(id) {
+                    anyhow!("Failed to find item in index for ID {:?} but did find an item summary: {item_summary:?}", id)
+                } else {
+                    anyhow!("Failed to find item in index for ID {:?}", id)
+                }
+            })
+            .context(here!())
+    }
+
+    fn item_summary(&self, id: &Id) -> Option<&ItemSummary> {
+        self.paths.get(id)
+    }
+
+    fn type_name(&self, id: &Id) -> Result<String> {
+        Ok(self.item_summary(id).context(here!())?.path.join("::"))
+    }
+
+    /// Returns the name and full path of each item that a glob re-export of the external module
+    /// or enum `id` brings in, sorted by path. Only the items that rustdoc lists in `paths` can
+    /// be found.
+    fn external_glob_items(&self, id: &Id) -> Vec<(&str, String)> {
+        let Some(parent) = self.item_summary(id) else {
+            return Vec::new();
+        };
+        let mut items: Vec<_> = self
+            .paths
+            .values()
+            .filter(|summary| {
+                summary.crate_id == parent.crate_id
+                    && summary.path.len() == parent.path.len() + 1
+                    && summary.path.starts_with(&parent.path)
+            })
+            .filter_map(|summary| Some((summary.path.last()?.as_str(), summary.path.join("::"))))
+            .collect();
+        items.sort_by(|a, b| a.1.cmp(&b.1));
+        items.dedup();
+        items
+    }
+
+    fn root_crate_id(package: &Crate) -> Result<u32> {
+        Ok(Self::root(package)?.crate_id)
+    }
+
+    /// Returns `true` if the given `id` belongs to the root crate.
+    ///
+    /// Checks index for info on containing crate. If the item is not found in
+    /// the index, it is assumed to be external.
+    fn in_root_crate(&self, id: &Id) -> bool {
+        if let Ok(item) = self.item(id) {
+            item.crate_id == self.root_crate_id
+        } else {
+            false
+        }
+    }
+
+    fn root_crate_name(package: &Crate) -> Result<String> {
+        Ok(Self::root(package)?
+            .name
+            .as_ref()
+            .expect("root should always have a name")
+            .clone())
+    }
+
+    fn root(package: &Crate) -> Result<&Item> {
+        package
+            .index
+            .get(&package.root)
+            .ok_or_else(|| anyhow!("root not found in index"))
+            .context(here!())
+    }
+}
+
+impl Deref for Traversal<'_> {
+    type Target = Visitor;
+
+    fn deref(&self) -> &Visitor {
+        self.visitor
+    }
+}
+
+impl<'a> Traversal<'a> {
+    fn new(visitor: &'a Visitor) -> Self {
+        Traversal {
+            visitor,
+            errors: RefCell::new(ValidationErrors::new()),
+            use_site: RefCell::new(None),
+            use_site_errors: RefCell::new(Vec::new()),
+            alias_chain: RefCell::new(None),
+            crate_references: RefCell::new(Vec::new()),
+            visited: RefCell::new(HashSet::new()),
+            unsent_errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Adds the errors found by `other`, which visited an item after the ones that this
+    /// traversal has found errors in so far.
+    fn merge(&self, other: Traversal<'_>) {
+        self.errors.borrow_mut().merge(other.errors.into_inner());
+        self.unsent_errors
+            .borrow_mut()
+            .extend(other.unsent_errors.into_inner());
+        let mut references = self.crate_references.borrow_mut();
+        for reference in other.crate_references.into_inner() {
+            if references.iter().any(|seen| seen.same_version(&reference)) {
+                continue;
+            }
+            // Only the first reference of each version after the first is reported
+            if references.iter().any(|seen| seen.same_crate(&reference)) {
+                self.add_error(&reference.path, reference.error(&self.dependencies));
+            }
+            references.push(reference);
+        }
+        self.send_errors();
+    }
+
+    /// Sends the errors that haven't been sent yet to the visitor's `error_sender`, skipping
+    /// ones that an earlier traversal already sent.
+    fn send_errors(&self) {
+        let Some(sender) = &self.error_sender else {
+            return;
+        };
+        let mut sent_errors = self.sent_errors.lock().unwrap();
+        for error in self.unsent_errors.take() {
+            if sent_errors.insert(error.clone()) {
+                // The receiver going away just means nobody is listening anymore
+                let _ = sender.send(error);
+            }
         }
     }
 
@@ -125,11 +487,20 @@ impl Visitor {
         item: &Item,
         visibility_check: VisibilityCheck,
     ) -> Result<()> {
-        if visibility_check == VisibilityCheck::Default && !Self::is_public(path, item) {
+        if self.is_stopped()
+            || visibility_check == VisibilityCheck::Default && !self.is_public(path, item)
+        {
+            return Ok(());
+        }
+        if self.config.allowed_external_types_in.is_empty()
+            && !self.visited.borrow_mut().insert((item.id, path.cfg()))
+        {
+            debug!("already visited");
             return Ok(());
         }
 
         let mut path = path.clone();
+        let parent_depth = path.depth();
         match &item.inner {
             ItemEnum::AssocConst { type_, .. } => {
                 path.push(ComponentType::AssocConst, item);
@@ -178,13 +549,22 @@ impl Visitor {
                     .context(here!())?;
             }
             ItemEnum::Use(use_) => {
+                let reexporting_path = path.clone();
                 path.push_raw(ComponentType::ReExport, &use_.name, item.span.as_ref());
+                path.add_cfgs(&item.attrs);
                 // look at the type the `use` statement is referencing
                 if let Some(target_id) = &use_.id {
                     // if the item is in the index, check to see if it's in the
                     // root crate.
                     if let Ok(item) = self.item(target_id).context(here!()) {
-                        if self.in_root_crate(target_id) {
+                        if let ItemEnum::Module(Module {
+                            is_stripped: true, ..
+                        }) = &item.inner
+                        {
+                            // The module is declared in a hidden module, so rustdoc left out
+                            // its items
+                            self.visit_stripped_module(&path, item, &use_.source)?;
+                        } else if self.in_root_crate(target_id) {
                             // If yes, then visit it.
                             self.visit_item(&path, item, VisibilityCheck::AssumePublic)?
                         }
@@ -193,28 +573,63 @@ impl Visitor {
                         // type. Check if it's allowed by the config. If it's
                         // not referenced in `paths` then it's assumed to be an
                         // external hidden module.
-                        if let Ok(type_name) = self.type_name(target_id) {
-                            if !self.config.allows_type(&self.root_crate_name, &type_name) {
-                                self.add_error(ValidationError::unapproved_external_type_ref(
-                                    type_name,
+                        let reexported_crate = self
+                            .item_summary(target_id)
+                            .filter(|summary| {
+                                !use_.is_glob
+                                    && summary.kind == ItemKind::Module
+                                    && summary.path.len() == 1
+                            })
+                            .map(|summary| summary.path[0].as_str());
+                        let glob_items = if use_.is_glob {
+                            self.external_glob_items(target_id)
+                        } else {
+                            Vec::new()
+                        };
+                        if let Some(crate_name) = reexported_crate {
+                            self.check_crate_reexport(&path, crate_name);
+                        } else if !glob_items.is_empty() {
+                            // Each item that the glob brings in is re-exported under its own
+                            // name, at the span of the glob
+                            for (name, type_name) in glob_items {
+                                let mut item_path = reexporting_path.clone();
+                                item_path.push_raw(
+                                    ComponentType::ReExport,
+                                    name,
+                                    item.span.as_ref(),
+                                );
+                                item_path.add_cfgs(&item.attrs);
+                                let written_as = format!("{}::{name}", use_.source);
+                                self.check_allow_type(
+                                    &item_path,
                                     &ErrorLocation::ReExport,
-                                    path.to_string(),
-                                    path.last_span(),
-                                ));
+                                    type_name,
+                                    Some(&written_as),
+                                );
                             }
+                        } else if let Ok(type_name) = self.type_name(target_id) {
+                            self.check_allow_type(
+                                &path,
+                                &ErrorLocation::ReExport,
+                                type_name,
+                                Some(&use_.source),
+                            );
                         } else {
                             let first_hidden_module_in_path =
                                 infer_first_hidden_module_in_import_source(
                                     &use_.source,
-                                    &self.index,
+                                    &self.item_names,
                                 );
-                            self.add_error(ValidationError::hidden_module(
-                                use_.name.clone(),
-                                &ErrorLocation::ReExport,
-                                path.to_string(),
-                                path.last_span(),
-                                first_hidden_module_in_path,
-                            ));
+                            self.add_error(
+                                &path,
+                                ValidationError::hidden_module(
+                                    use_.name.clone(),
+                                    &ErrorLocation::ReExport,
+                                    path.to_string(),
+                                    path.last_span(),
+                                    first_hidden_module_in_path,
+                                ),
+                            );
                         }
                     }
                 }
@@ -236,7 +651,6 @@ impl Visitor {
                     }
                 }
             }
-            // ItemEnum::OpaqueTy(_) => unstable_rust_feature!("type_alias_impl_trait", "https://doc.rust-lang.org/beta/unstable-book/language-features/type-alias-impl-trait.html"),
             ItemEnum::Static(sttc) => {
                 path.push(ComponentType::Static, item);
                 self.visit_type(&path, &ErrorLocation::Static, &sttc.type_)
@@ -257,6 +671,8 @@ impl Visitor {
             }
             ItemEnum::TypeAlias(alias) => {
                 path.push(ComponentType::TypeAlias, item);
+                // With `type_alias_impl_trait`, the type is an `impl Trait` whose bounds are
+                // checked like those of any other opaque type
                 self.visit_type(&path, &ErrorLocation::TypeAlias, &alias.type_)
                     .context(here!())?;
                 self.visit_generics(&path, &alias.generics)
@@ -274,12 +690,24 @@ impl Visitor {
                 path.push(ComponentType::EnumVariant, item);
                 self.visit_variant(&path, variant).context(here!())?;
             }
-            ItemEnum::ExternCrate { .. }
-            | ItemEnum::Impl(_)
+            ItemEnum::ExternCrate { name, rename } => {
+                // `pub extern crate` exposes the whole crate. For `extern crate foo as bar`,
+                // rustdoc gives `bar` as the name and `foo` as the rename.
+                path.push(ComponentType::ReExport, item);
+                self.check_crate_reexport(&path, rename.as_deref().unwrap_or(name));
+            }
+            ItemEnum::Impl(_)
             | ItemEnum::Macro(_)
             | ItemEnum::Primitive(_)
             | ItemEnum::ProcMacro(_) => {}
         }
+        // Items that aren't part of the path, such as the crate root and impl blocks, aren't
+        // counted as public items of their own
+        if path.depth() > parent_depth {
+            self.errors
+                .borrow_mut()
+                .record_public_item(path.to_string());
+        }
         Ok(())
     }
 
@@ -292,6 +720,7 @@ impl Visitor {
                 "",
                 impl_item.span.as_ref().or_else(|| path.last_span()),
             );
+            impl_path.add_cfgs(&impl_item.attrs);
             self.visit_impl(&impl_path, impl_item).context(here!())?;
         }
         Ok(())
@@ -311,7 +740,7 @@ impl Visitor {
                 has_stripped_fields,
             } => {
                 if *has_stripped_fields {
-                    self.add_error(ValidationError::fields_stripped(path));
+                    self.add_error(path, ValidationError::fields_stripped(path));
                 }
                 fields.clone()
             }
@@ -343,6 +772,21 @@ impl Visitor {
             let item = self.item(id).context(here!())?;
             self.visit_item(path, item, VisibilityCheck::Default)?;
         }
+        if self.config.check_blanket_impls {
+            // Blanket impls aren't in the `impls` of any type, so they're only found through the
+            // trait that they implement
+            let blanket_impls: Vec<Id> = trt
+                .implementations
+                .iter()
+                .filter(|id| self.in_root_crate(id))
+                .filter(|id| {
+                    matches!(self.item(id), Ok(Item { inner: ItemEnum::Impl(imp), .. })
+                        if imp.blanket_impl.is_none() && is_blanket_target(&imp.for_))
+                })
+                .cloned()
+                .collect();
+            self.visit_impls(path, &blanket_impls)?;
+        }
         Ok(())
     }
 
@@ -358,19 +802,14 @@ impl Visitor {
             if let Some(trait_) = &imp.trait_ {
                 if let Ok(trait_item) = self.item(&trait_.id) {
                     // Don't look for exposure in impls of private traits
-                    if !Self::is_public(path, trait_item) {
+                    if !self.is_public(path, trait_item) {
                         return Ok(());
                     }
-
-                    if let Some(_generic_args) = &trait_.args {
-                        // The `trait_` can have generic `args`, but we don't need to visit them
-                        // since they are on the trait itself. If the trait is part of the root crate,
-                        // it will be visited and checked for external types. If the trait is external,
-                        // then what it references doesn't matter for the purposes of this impl that is
-                        // being visited.
-                    }
                 }
 
+                // This also visits the generic args of the trait, since an impl such as
+                // `impl From<external::Type> for MyType` exposes `external::Type` even when the
+                // trait itself is allowed
                 self.check_rustdoc_path(path, &ErrorLocation::ImplementedTrait, trait_)
                     .context(here!())?;
             }
@@ -411,17 +850,17 @@ impl Visitor {
             Type::ResolvedPath(resolved_path) => {
                 self.check_rustdoc_path(path, what, resolved_path)
                     .context(here!())?;
-                if let Some(args) = &resolved_path.args {
-                    self.visit_generic_args(path, args.as_ref())?;
-                }
             }
             Type::Generic(_) => {}
             Type::Primitive(_) => {}
-            Type::Pat { .. } => {
-                panic!(
-                    "Pattern types are unstable and rustc internal rust-lang#120131. \
-                     They are unsuported by cargo-check-external-types."
-                )
+            Type::Pat { type_, .. } => {
+                // Pattern types are unstable and rustc internal (rust-lang#120131), but can still
+                // show up through re-exports of core internals
+                self.add_error(
+                    path,
+                    ValidationError::pattern_type(what, path.to_string(), path.last_span()),
+                );
+                self.visit_type(path, what, type_).context(here!())?;
             }
             Type::FunctionPointer(fp) => {
                 self.visit_fn_sig(path, &fp.sig)?;
@@ -469,7 +908,10 @@ impl Visitor {
                 self.visit_type(path, what, type_).context(here!())?
             }
             Type::QualifiedPath {
-                self_type, trait_, ..
+                args,
+                self_type,
+                trait_,
+                ..
             } => {
                 self.visit_type(path, &ErrorLocation::QualifiedSelfType, self_type)?;
                 if let Some(trait_) = trait_ {
@@ -479,6 +921,8 @@ impl Visitor {
                         trait_,
                     )?;
                 }
+                // Generic associated types can take arguments, such as `Self::Gat<SomeStruct>`
+                self.visit_generic_args(path, args)?;
             }
         }
         Ok(())
@@ -552,8 +996,18 @@ impl Visitor {
                 } => {
                     self.visit_generic_bounds(path, bounds)?;
                     if let Some(typ) = default {
-                        self.visit_type(path, &ErrorLocation::GenericDefaultBinding, typ)
-                            .context(here!())?;
+                        // Follow local type aliases in the default to find the external types
+                        // that they ultimately stand for
+                        let is_outermost = self.alias_chain.borrow().is_none();
+                        if is_outermost {
+                            self.alias_chain.replace(Some(Vec::new()));
+                        }
+                        let result =
+                            self.visit_type(path, &ErrorLocation::GenericDefaultBinding, typ);
+                        if is_outermost {
+                            self.alias_chain.take();
+                        }
+                        result.context(here!())?;
                     }
                 }
                 GenericParamDefKind::Const { type_, .. } => {
@@ -574,13 +1028,16 @@ impl Visitor {
         for where_pred in &generics.where_predicates {
             match where_pred {
                 WherePredicate::BoundPredicate {
-                    type_: _,
+                    type_,
                     bounds,
                     generic_params,
                 } => {
+                    // The type is opt-in, since pin-project-lite puts private types in where bounds:
                     // https://github.com/taiki-e/pin-project-lite/issues/86#issuecomment-2438300474
-                    // self.visit_type(path, &ErrorLocation::WhereBound, type_)
-                    //     .context(here!())?;
+                    if self.config.check_where_bounds {
+                        self.visit_type(path, &ErrorLocation::WhereBound, type_)
+                            .context(here!())?;
+                    }
                     self.visit_generic_bounds(path, bounds)?;
                     self.visit_generic_param_defs(path, generic_params)?;
                 }
@@ -637,105 +1094,507 @@ impl Visitor {
         what: &ErrorLocation,
         rustdoc_path: &RustDocPath,
     ) -> Result<()> {
-        self.check_external(path, what, &rustdoc_path.id)
-            .context(here!())?;
-        if let Some(generic_args) = &rustdoc_path.args {
-            self.visit_generic_args(path, generic_args.as_ref())
-                .context(here!())?;
+        if self.alias_chain.borrow().is_some() {
+            if let Some(alias) = self.local_type_alias(&rustdoc_path.id) {
+                return self.visit_type_alias_target(path, what, rustdoc_path, alias);
+            }
         }
-        Ok(())
+        let is_outermost_use_site =
+            has_generic_args(rustdoc_path) && self.use_site.borrow().is_none();
+        if is_outermost_use_site {
+            self.use_site.replace(Some(render_path(rustdoc_path)));
+        }
+        let result = self
+            .check_external(path, what, &rustdoc_path.id, &rustdoc_path.name)
+            .and_then(|_| match &rustdoc_path.args {
+                Some(generic_args) => self.visit_generic_args(path, generic_args.as_ref()),
+                None => Ok(()),
+            })
+            .context(here!());
+        if is_outermost_use_site {
+            self.use_site.take();
+            self.add_use_site_errors(path);
+        }
+        result
     }
 
-    fn check_external(&self, path: &Path, what: &ErrorLocation, id: &Id) -> Result<()> {
-        if let Ok(type_name) = self.type_name(id) {
-            if !self.config.allows_type(&self.root_crate_name, &type_name) {
-                self.add_error(ValidationError::unapproved_external_type_ref(
-                    type_name,
-                    what,
-                    path.to_string(),
-                    path.last_span(),
-                ));
+    /// Adds the errors found at the use site that was just checked, with the errors for types
+    /// that are reported for the same reason combined into one.
+    fn add_use_site_errors(&self, path: &Path) {
+        let mut grouped: Vec<(ValidationError, Vec<String>)> = Vec::new();
+        for error in self.use_site_errors.take() {
+            match grouped
+                .iter_mut()
+                .find(|(first, _)| first.has_same_reason(&error))
+            {
+                Some((first, types)) => {
+                    let type_name = error.type_name();
+                    if type_name != first.type_name() && !types.iter().any(|t| t == type_name) {
+                        types.push(type_name.to_string());
+                    }
+                }
+                None => grouped.push((error, Vec::new())),
             }
+        }
+        for (error, types) in grouped {
+            self.add_error(path, error.with_grouped_types(types));
+        }
+    }
+
+    /// Adds an error about a referenced type, or holds on to it until the whole use site has
+    /// been checked if a use site is being checked.
+    fn add_type_error(&self, path: &Path, error: ValidationError) {
+        if self.use_site.borrow().is_some() {
+            self.use_site_errors.borrow_mut().push(error);
+        } else {
+            self.add_error(path, error);
+        }
+    }
+
+    /// Returns the type alias with the given `id` if it's declared in the root crate.
+    fn local_type_alias(&self, id: &Id) -> Option<&TypeAlias> {
+        match self.item(id) {
+            Ok(Item {
+                inner: ItemEnum::TypeAlias(alias),
+                crate_id,
+                ..
+            }) if *crate_id == self.root_crate_id => Some(alias),
+            _ => None,
+        }
+    }
+
+    /// Visits the type that a local type alias stands for, recording the alias in the chain.
+    fn visit_type_alias_target(
+        &self,
+        path: &Path,
+        what: &ErrorLocation,
+        rustdoc_path: &RustDocPath,
+        alias: &TypeAlias,
+    ) -> Result<()> {
+        let alias_name = self
+            .type_name(&rustdoc_path.id)
+            .unwrap_or_else(|_| rustdoc_path.name.clone());
+        let is_cycle = self
+            .alias_chain
+            .borrow()
+            .as_ref()
+            .is_some_and(|chain| chain.contains(&alias_name));
+        if is_cycle {
+            return Ok(());
+        }
+        if let Some(chain) = self.alias_chain.borrow_mut().as_mut() {
+            chain.push(alias_name);
+        }
+        let result =
+            self.visit_type(path, what, &alias.type_)
+                .and_then(|_| match &rustdoc_path.args {
+                    Some(generic_args) => self.visit_generic_args(path, generic_args.as_ref()),
+                    None => Ok(()),
+                });
+        if let Some(chain) = self.alias_chain.borrow_mut().as_mut() {
+            chain.pop();
+        }
+        result.context(here!())
+    }
+
+    fn check_external(
+        &self,
+        path: &Path,
+        what: &ErrorLocation,
+        id: &Id,
+        written_as: &str,
+    ) -> Result<()> {
+        if let Ok(type_name) = self.type_name(id) {
+            self.check_crate_version(path, what, id, &type_name);
+            self.check_allow_type(path, what, type_name, Some(written_as));
         } else if !self.in_root_crate(id) {
-            self.add_error(ValidationError::hidden_item(
-                what,
-                path.to_string(),
-                path.last_span(),
-            ));
+            // Rustdoc lists every item of external crates in `paths`, including hidden ones, so
+            // only items of the root crate that were stripped for being hidden end up here
+            self.add_error(
+                path,
+                ValidationError::hidden_item(what, path.to_string(), path.last_span()),
+            );
         }
         Ok(())
     }
 
-    fn add_error(&self, error: ValidationError) {
-        debug!("detected error {:?}", error);
-        self.errors.borrow_mut().add(error);
+    /// Records the first reference to each version of an external crate, so that an error can
+    /// be added the first time that a type from another version of the crate is referenced
+    /// than the version that types from it were referenced from before.
+    fn check_crate_version(&self, path: &Path, what: &ErrorLocation, id: &Id, type_name: &str) {
+        let Some(crate_id) = self.item_summary(id).map(|summary| summary.crate_id) else {
+            return;
+        };
+        let reference = CrateReference {
+            crate_name: type_name[0..type_name.find("::").unwrap_or(type_name.len())].to_string(),
+            crate_id,
+            in_hidden_items_docs: false,
+            path: path.clone(),
+            what: what.clone(),
+            type_name: type_name.to_string(),
+        };
+        let mut references = self.crate_references.borrow_mut();
+        if !references.iter().any(|seen| seen.same_version(&reference)) {
+            references.push(reference);
+        }
     }
 
-    fn item(&self, id: &Id) -> Result<&Item> {
-        self.index
-            .get(id)
-            .ok_or_else(|| {
-                if let Some(item_summary) = self.paths.get(id) {
-                    anyhow!("Failed to find item in index for ID {:?} but did find an item summary: {item_summary:?}", id)
+    /// Adds an error if the config doesn't allow the type with the given `type_name` to be
+    /// referenced, either because it isn't approved or because of where its crate comes from.
+    /// `written_as` is the path that the reference is written with, if known. With
+    /// `match_surface_paths`, the config's lists are matched against it too.
+    fn check_allow_type(
+        &self,
+        path: &Path,
+        what: &ErrorLocation,
+        type_name: String,
+        written_as: Option<&str>,
+    ) {
+        let surface_path = written_as.and_then(|written| self.surface_path(written, &type_name));
+        let referenced_as = self.use_site.borrow().clone().or(surface_path.clone());
+        let mut names = vec![type_name.clone()];
+        if self.config.match_surface_paths {
+            names.extend(surface_path.clone());
+        }
+        // Config can refer to a renamed dependency by either of its names
+        let aliased: Vec<String> = names
+            .iter()
+            .flat_map(|name| self.dependencies.aliased_paths(name))
+            .collect();
+        names.extend(aliased);
+        let crate_name = &type_name[0..type_name.find("::").unwrap_or(type_name.len())];
+        // The crate name the path is written with tells renamed dependencies apart, which
+        // matters when they're different versions of the same crate
+        let dependency = surface_path
+            .as_deref()
+            .and_then(|surface| surface.split_once("::"))
+            .and_then(|(surface_crate, _)| self.dependencies.get(surface_crate))
+            .filter(|dependency| {
+                self.dependencies.get(crate_name).map(|d| &d.package_name)
+                    == Some(&dependency.package_name)
+            })
+            .or_else(|| self.dependencies.get(crate_name));
+        if let Some(dependency) = dependency {
+            if !self.config.allows_source_kind(dependency.source_kind) {
+                self.add_type_error(
+                    path,
+                    ValidationError::denied_dependency_source(
+                        type_name,
+                        what,
+                        path.to_string(),
+                        path.last_span(),
+                        dependency,
+                        referenced_as,
+                    )
+                    .with_alias_chain(self.alias_chain.borrow().clone().unwrap_or_default()),
+                );
+                return;
+            }
+        }
+        let denied = names.iter().find_map(|name| {
+            self.config
+                .denies_type(&self.root_crate_name, name)
+                .map(|pattern| (pattern, "denied_external_types"))
+                .or_else(|| {
+                    self.config
+                        .denies_std_type(name)
+                        .map(|pattern| (pattern, "denied_std_types"))
+                })
+        });
+        if let Some((pattern, denied_by)) = denied {
+            self.add_type_error(
+                path,
+                ValidationError::denied_external_type_ref(
+                    type_name,
+                    what,
+                    path.to_string(),
+                    path.last_span(),
+                    dependency,
+                    referenced_as,
+                    pattern,
+                )
+                .with_denied_by(denied_by)
+                .with_alias_chain(self.alias_chain.borrow().clone().unwrap_or_default()),
+            );
+            return;
+        }
+        // The name that the config allows the type by, if any
+        let allowed_as = names
+            .iter()
+            .find(|name| self.config.allows_type(&self.root_crate_name, name));
+        let allowed = allowed_as.is_some();
+        let suppressed = allowed_as.is_some_and(|name| {
+            self.config
+                .allows_type_by_pattern(&self.root_crate_name, name)
+        });
+        let item_path = path.to_string();
+        let scope_pattern = if allowed {
+            None
+        } else {
+            names.iter().find_map(|name| {
+                self.config
+                    .scoped_allowing_pattern(&self.root_crate_name, &item_path, name)
+            })
+        };
+        let allowing_pattern = scope_pattern.or_else(|| {
+            allowed_as.and_then(|name| self.config.allowing_pattern(&self.root_crate_name, name))
+        });
+        // Types from crates below 1.0 are only reported when the config allows them, since
+        // types that aren't allowed are reported as unapproved already
+        let unstable_dependency = dependency
+            .filter(|dependency| self.config.deny_unstable_crates && dependency.version.major == 0);
+        if let Some(dependency) = unstable_dependency {
+            if (suppressed || scope_pattern.is_some())
+                && !allowing_pattern.is_some_and(|pattern| pattern.is_acknowledged())
+            {
+                let error = ValidationError::unstable_dependency(
+                    type_name,
+                    what,
+                    item_path,
+                    path.last_span(),
+                    dependency,
+                    referenced_as,
+                )
+                .with_alias_chain(self.alias_chain.borrow().clone().unwrap_or_default());
+                // The pattern is still needed, even though it doesn't acknowledge the dependency
+                if let Some(pattern) = allowing_pattern {
+                    self.errors.borrow_mut().record_pattern_match(
+                        pattern,
+                        self.config.anchored_patterns,
+                        error.clone(),
+                    );
+                }
+                self.add_type_error(path, error);
+                return;
+            }
+        }
+        if !allowed || suppressed {
+            let error = ValidationError::unapproved_external_type_ref(
+                type_name,
+                what,
+                item_path,
+                path.last_span(),
+                dependency,
+                referenced_as,
+            )
+            .with_alias_chain(self.alias_chain.borrow().clone().unwrap_or_default());
+            let public_dependency = self.config.allow_public_dependencies
+                && dependency.is_some_and(|dependency| dependency.public == Some(true));
+            if !suppressed && scope_pattern.is_none() && public_dependency {
+                self.errors
+                    .borrow_mut()
+                    .suppress(SuppressionMechanism::AllowPublicDependencies, error);
+            } else if suppressed || scope_pattern.is_some() {
+                let mechanism = if scope_pattern.is_some() {
+                    SuppressionMechanism::AllowedExternalTypesIn
+                } else if allowed_as.is_some_and(|name| self.config.allows_type_exactly(name)) {
+                    SuppressionMechanism::AllowedExternalTypesExact
                 } else {
-                    anyhow!("Failed to find item in index for ID {:?}", id)
+                    SuppressionMechanism::AllowedExternalTypes
+                };
+                let mut errors = self.errors.borrow_mut();
+                if let Some(pattern) = allowing_pattern {
+                    debug!(
+                        type_name = error.type_name(),
+                        pattern = pattern.as_str(),
+                        reason = pattern.reason(),
+                        "external type allowed by config"
+                    );
+                    errors.record_pattern_match(
+                        pattern,
+                        self.config.anchored_patterns,
+                        error.clone(),
+                    );
                 }
-            })
-            .context(here!())
+                errors.suppress(mechanism, error);
+            } else {
+                self.add_type_error(path, error);
+            }
+        }
     }
 
-    fn item_summary(&self, id: &Id) -> Option<&ItemSummary> {
-        self.paths.get(id)
+    /// Visits the public module `module` that `path` re-exports from `import_source`, after
+    /// finding it by its span in the rustdoc JSON with hidden items. Adds a warning instead if
+    /// that JSON isn't available or doesn't have the module.
+    fn visit_stripped_module(&self, path: &Path, module: &Item, import_source: &str) -> Result<()> {
+        let Some(docs) = &self.hidden_items_docs else {
+            self.add_stripped_module_warning(path, import_source);
+            return Ok(());
+        };
+        let visitor = {
+            let mut hidden_items_visitor = self.hidden_items_visitor.lock().unwrap();
+            if hidden_items_visitor.is_none() {
+                let mut visitor = Visitor::new(
+                    self.config.clone(),
+                    docs().context(here!())?,
+                    self.dependencies.clone(),
+                )?;
+                visitor.sources = self.sources.clone();
+                visitor.error_sender = self.error_sender.clone();
+                visitor.fail_fast = self.fail_fast;
+                *hidden_items_visitor = Some(Arc::new(visitor));
+            }
+            Arc::clone(hidden_items_visitor.as_ref().expect("created above"))
+        };
+        let found = module.span.as_ref().and_then(|span| {
+            visitor.index.values().find(|item| {
+                matches!(item.inner, ItemEnum::Module(_)) && item.span.as_ref() == Some(span)
+            })
+        });
+        match found {
+            Some(found) => {
+                let traversal = Traversal::new(&visitor);
+                traversal
+                    .visit_item(path, found, VisibilityCheck::AssumePublic)
+                    .context(here!())?;
+                self.errors
+                    .borrow_mut()
+                    .merge(traversal.errors.into_inner());
+                self.unsent_errors
+                    .borrow_mut()
+                    .extend(traversal.unsent_errors.into_inner());
+                self.crate_references.borrow_mut().extend(
+                    traversal
+                        .crate_references
+                        .into_inner()
+                        .into_iter()
+                        .map(|reference| CrateReference {
+                            in_hidden_items_docs: true,
+                            ..reference
+                        }),
+                );
+                if visitor.is_stopped() {
+                    self.stopped.store(true, Ordering::Relaxed);
+                }
+            }
+            None => self.add_stripped_module_warning(path, import_source),
+        }
+        Ok(())
     }
 
-    fn type_name(&self, id: &Id) -> Result<String> {
-        Ok(self.item_summary(id).context(here!())?.path.join("::"))
+    fn add_stripped_module_warning(&self, path: &Path, import_source: &str) {
+        let name = import_source.rsplit("::").next().unwrap_or(import_source);
+        self.add_error(
+            path,
+            ValidationError::hidden_module(
+                name,
+                &ErrorLocation::ReExport,
+                path.to_string(),
+                path.last_span(),
+                infer_first_hidden_module_in_import_source(import_source, &self.item_names),
+            ),
+        );
     }
 
-    fn root_crate_id(package: &Crate) -> Result<u32> {
-        Ok(Self::root(package)?.crate_id)
+    /// Returns the path that an external type is `written` with, such as
+    /// `futures::stream::Stream`, if it starts with the name of a crate and differs from the
+    /// type's path in the crate that defines it, such as `futures_core::stream::Stream`. Paths
+    /// that are relative to an import can't be matched against the config.
+    fn surface_path(&self, written: &str, type_name: &str) -> Option<String> {
+        let written = written.strip_prefix("::").unwrap_or(written);
+        let (crate_name, _) = written.split_once("::")?;
+        let is_crate = matches!(crate_name, "alloc" | "core" | "std")
+            || self.dependencies.get(crate_name).is_some();
+        (is_crate && written != type_name).then(|| written.to_string())
     }
 
-    /// Returns `true` if the given `id` belongs to the root crate.
-    ///
-    /// Checks index for info on containing crate. If the item is not found in
-    /// the index, it is assumed to be external.
-    fn in_root_crate(&self, id: &Id) -> bool {
-        if let Ok(item) = self.item(id) {
-            item.crate_id == self.root_crate_id
+    /// Adds an error for a re-export of the whole external crate `crate_name`, unless the config
+    /// allows it by the crate's exact name.
+    fn check_crate_reexport(&self, path: &Path, crate_name: &str) {
+        let error = ValidationError::reexported_crate(
+            crate_name,
+            path.to_string(),
+            path.last_span(),
+            self.dependencies.get(crate_name),
+        );
+        let names: Vec<&str> = std::iter::once(crate_name)
+            .chain(
+                self.dependencies
+                    .aliases(crate_name)
+                    .iter()
+                    .map(String::as_str),
+            )
+            .collect();
+        if let Some(pattern) = names
+            .iter()
+            .find_map(|name| self.config.crate_reexport_pattern(name))
+        {
+            let mut errors = self.errors.borrow_mut();
+            errors.record_pattern_match(pattern, self.config.anchored_patterns, error.clone());
+            errors.suppress(SuppressionMechanism::AllowedExternalTypes, error);
+        } else if names
+            .iter()
+            .any(|name| self.config.allows_type_exactly(name))
+        {
+            self.errors
+                .borrow_mut()
+                .suppress(SuppressionMechanism::AllowedExternalTypesExact, error);
         } else {
-            false
+            self.add_error(path, error);
         }
     }
 
-    fn root_crate_name(package: &Crate) -> Result<String> {
-        Ok(Self::root(package)?
-            .name
+    fn add_error(&self, path: &Path, error: ValidationError) {
+        debug!("detected error {:?}", error);
+        if self.is_stopped() {
+            return;
+        }
+        let macro_invocation = self
+            .sources
             .as_ref()
-            .expect("root should always have a name")
-            .clone())
+            .zip(error.location())
+            .and_then(|(sources, span)| sources.macro_invocation(span));
+        let error = match macro_invocation {
+            Some(name) => error.with_macro_invocation(name),
+            None => error,
+        };
+        let error = match path.cfg() {
+            Some(cfg) => error.with_cfg(cfg),
+            None => error,
+        };
+        let error = match self.config.severity(error.kind()) {
+            None => error,
+            Some(Severity::Error) => error.with_level(ErrorLevel::Error),
+            Some(Severity::Warn) => error.with_level(ErrorLevel::Warning),
+            Some(Severity::Off) => {
+                self.errors
+                    .borrow_mut()
+                    .suppress(SuppressionMechanism::Severity, error);
+                return;
+            }
+        };
+        if self.fail_fast && error.level() == ErrorLevel::Error {
+            self.stopped.store(true, Ordering::Relaxed);
+        }
+        if self.error_sender.is_some() {
+            self.unsent_errors.borrow_mut().push(error.clone());
+        }
+        self.errors.borrow_mut().add(error);
     }
+}
 
-    fn root(package: &Crate) -> Result<&Item> {
-        package
-            .index
-            .get(&package.root)
-            .ok_or_else(|| anyhow!("root not found in index"))
-            .context(here!())
+/// Returns true if an `impl` for `typ` is a blanket impl, such as `impl<T> Trait for T` or
+/// `impl<T> Trait for &T`, rather than an impl for a particular type.
+fn is_blanket_target(typ: &Type) -> bool {
+    match typ {
+        Type::Generic(_) => true,
+        Type::BorrowedRef { type_, .. } | Type::RawPointer { type_, .. } | Type::Slice(type_) => {
+            is_blanket_target(type_)
+        }
+        Type::Array { type_, .. } => is_blanket_target(type_),
+        _ => false,
     }
 }
 
-/// Check each segment of a module path against the index. If a segment isn't present in the index,
-/// assume that it's the hidden module and return it. Because the path
+/// Check each segment of a module path against the names of the items in the index. If a
+/// segment isn't the name of any item, assume that it's the hidden module and return it.
 fn infer_first_hidden_module_in_import_source(
     import_source: &str,
-    index: &Index,
+    item_names: &HashSet<String>,
 ) -> Option<String> {
     import_source.split("::").find_map(|part| {
         // When the path part is included in the index, we skip it. If it's not indexed, then it's likely hidden.
-        let part_is_not_indexed = !index
-            .values()
-            .any(|v| v.name.as_ref().map(|name| name == part).unwrap_or_default());
+        let part_is_not_indexed = !item_names.contains(part);
 
         part_is_not_indexed.then_some(part.to_owned())
     })
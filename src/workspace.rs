@@ -0,0 +1,161 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Aggregation of results across multiple workspace members.
+
+use crate::error::ValidationErrors;
+use cargo_metadata::PackageId;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Validation errors of each checked workspace member, keyed by package ID.
+#[derive(Default)]
+pub struct WorkspaceErrors {
+    by_package: BTreeMap<PackageId, PackageErrors>,
+}
+
+struct PackageErrors {
+    package_name: String,
+    errors: ValidationErrors,
+}
+
+/// An external type that's referenced in the public API of one or more workspace members.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct ExternalTypeSummary {
+    pub type_name: String,
+    /// Total number of references to this type across all members
+    pub violations: usize,
+    /// Names of the members that reference this type, in alphabetical order
+    pub members: Vec<String>,
+}
+
+impl WorkspaceErrors {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the errors found in the package with the given ID.
+    pub fn add(
+        &mut self,
+        package_id: PackageId,
+        package_name: impl Into<String>,
+        errors: ValidationErrors,
+    ) {
+        let package_name = package_name.into();
+        match self.by_package.get_mut(&package_id) {
+            Some(existing) => existing.errors.merge(errors),
+            None => {
+                self.by_package.insert(
+                    package_id,
+                    PackageErrors {
+                        package_name,
+                        errors,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Returns how often each external type is referenced across members, and by which
+    /// members, with the most referenced types first.
+    pub fn external_type_summaries(&self) -> Vec<ExternalTypeSummary> {
+        let mut by_type: BTreeMap<&str, (usize, BTreeSet<&str>)> = BTreeMap::new();
+        for package in self.by_package.values() {
+            for error in package.errors.iter() {
+                for (_, type_name) in error.external_types() {
+                    let (violations, members) = by_type.entry(type_name).or_default();
+                    *violations += 1;
+                    members.insert(&package.package_name);
+                }
+            }
+        }
+        let mut summaries: Vec<_> = by_type
+            .into_iter()
+            .map(|(type_name, (violations, members))| ExternalTypeSummary {
+                type_name: type_name.into(),
+                violations,
+                members: members.into_iter().map(String::from).collect(),
+            })
+            .collect();
+        summaries
+            .sort_by(|a, b| (b.members.len(), b.violations).cmp(&(a.members.len(), a.violations)));
+        summaries
+    }
+
+    /// Combines the errors of all members into one set.
+    pub fn into_merged(self) -> ValidationErrors {
+        let mut merged = ValidationErrors::new();
+        for package in self.by_package.into_values() {
+            merged.merge(package.errors);
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorLocation, ValidationError};
+    use rustdoc_types::Span;
+
+    fn unapproved(type_name: &str, line: usize) -> ValidationError {
+        let span = Span {
+            filename: "src/lib.rs".into(),
+            begin: (line, 0),
+            end: (line, 10),
+        };
+        ValidationError::unapproved_external_type_ref(
+            type_name,
+            &ErrorLocation::StructField,
+            "Thing",
+            Some(&span),
+            None,
+            None,
+        )
+    }
+
+    fn errors(errors: impl IntoIterator<Item = ValidationError>) -> ValidationErrors {
+        let mut result = ValidationErrors::new();
+        for error in errors {
+            result.add(error);
+        }
+        result
+    }
+
+    fn package_id(name: &str) -> PackageId {
+        PackageId {
+            repr: format!("path+file:///{name}#0.1.0"),
+        }
+    }
+
+    #[test]
+    fn summarize_external_types_across_members() {
+        let mut workspace = WorkspaceErrors::new();
+        workspace.add(
+            package_id("b"),
+            "b",
+            errors([unapproved("ext::One", 1), unapproved("ext::Two", 2)]),
+        );
+        workspace.add(package_id("a"), "a", errors([unapproved("ext::One", 1)]));
+        workspace.add(package_id("a"), "a", errors([unapproved("ext::One", 5)]));
+
+        assert_eq!(
+            vec![
+                ExternalTypeSummary {
+                    type_name: "ext::One".into(),
+                    violations: 3,
+                    members: vec!["a".into(), "b".into()],
+                },
+                ExternalTypeSummary {
+                    type_name: "ext::Two".into(),
+                    violations: 1,
+                    members: vec!["b".into()],
+                },
+            ],
+            workspace.external_type_summaries()
+        );
+        assert_eq!(3, workspace.into_merged().error_count());
+    }
+}
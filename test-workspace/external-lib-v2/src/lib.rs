@@ -0,0 +1,11 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+#![no_std]
+
+//! A second version of `external-lib`, for testing cargo-check-external-types against a crate
+//! that exposes types from two versions of the same crate
+
+pub struct SomeStruct;
@@ -52,3 +52,13 @@ pub struct ReprCType {
     i: i32,
     f: f32,
 }
+
+#[doc(hidden)]
+pub mod __private {
+    pub struct HiddenStruct;
+}
+
+mod private {
+    pub struct DefinedInPrivateModule;
+}
+pub use private::DefinedInPrivateModule;
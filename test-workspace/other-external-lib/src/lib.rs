@@ -0,0 +1,11 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+#![no_std]
+
+//! A second external crate, for testing cargo-check-external-types against crates that treat
+//! their dependencies differently
+
+pub struct OtherStruct;
@@ -3,14 +3,20 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
+#![feature(type_alias_impl_trait)]
 #![allow(dead_code)]
 
 //! This crate is used to test the cargo-check-external-types by exercising the all possible
 //! exposure of external types in a public API.
 
+pub mod test_aliases;
+pub mod test_async_trait;
 pub mod test_assoc_type;
 pub mod test_fields_stripped;
+pub mod test_generic_assoc_type;
+pub mod test_macros;
 pub mod test_structs;
+pub mod test_type_alias_impl_trait;
 pub mod test_union;
 
 use external_lib::{
@@ -19,11 +25,6 @@ use external_lib::{
     SimpleTrait,
     SomeOtherStruct,
     SomeStruct,
-    // Remove this comment if more lines are needed for imports in the future to preserve line numbers
-    // Remove this comment if more lines are needed for imports in the future to preserve line numbers
-    // Remove this comment if more lines are needed for imports in the future to preserve line numbers
-    // Remove this comment if more lines are needed for imports in the future to preserve line numbers
-    // Remove this comment if more lines are needed for imports in the future to preserve line numbers
 };
 
 pub struct LocalStruct;
@@ -163,3 +164,30 @@ pub fn hidden_arg(arg0: HiddenStruct) {
 
 #[doc(hidden)]
 pub struct HiddenStruct;
+
+#[doc(hidden)]
+pub fn hidden_fn_with_external_arg(_arg: SomeStruct) {
+    unimplemented!()
+}
+
+pub trait LocalTraitWithBlanketImpl {}
+
+// This is only an error with `check_blanket_impls`, since the blanket impl isn't in the `impl`
+// blocks of any type
+impl<T: SimpleTrait> LocalTraitWithBlanketImpl for T {}
+
+// This is only an error with `check_where_bounds`, since `SomeStruct` is in the type that the
+// where bound applies to
+pub fn fn_with_external_where_bound_subject<T>(_thing: T)
+where
+    Vec<SomeStruct>: Extend<T>,
+{
+}
+
+// Rustdoc lists the hidden items of external crates in `paths` too, so this is reported with the
+// type's path rather than as a hidden item
+pub fn fn_with_external_hidden_type(_thing: external_lib::__private::HiddenStruct) {}
+
+// The type is reported by its path in the module that defines it, with the path it's written with
+// as a note
+pub fn fn_with_surface_path(_thing: external_lib::DefinedInPrivateModule) {}
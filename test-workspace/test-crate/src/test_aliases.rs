@@ -0,0 +1,14 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+pub mod inner {
+    pub type InnerAlias = external_lib::SomeOtherStruct;
+}
+
+pub type MiddleAlias = inner::InnerAlias;
+
+pub struct GenericDefaultThroughAliases<T = MiddleAlias> {
+    pub field: T,
+}
@@ -0,0 +1,30 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+#![allow(async_fn_in_trait)]
+
+use external_lib::{SimpleTrait, SomeOtherStruct, SomeStruct};
+
+pub trait AsyncTraitWithExternals {
+    // This should be an error since `SomeStruct` is exposed in the public API
+    async fn external_in_async_output(&self) -> SomeStruct;
+
+    // This should be an error since `SomeOtherStruct` is exposed in the public API
+    async fn external_in_async_input(&self, input: SomeOtherStruct);
+
+    // This should be an error since `SimpleTrait` is exposed in the public API
+    fn external_in_return_position_impl_trait(&self) -> impl SimpleTrait;
+
+    // This should be an error since `SomeStruct` is exposed in the public API
+    fn external_in_return_position_impl_trait_args(&self) -> impl Iterator<Item = SomeStruct>;
+}
+
+trait PrivateAsyncTraitWithExternals {
+    // This should be allowed because the trait is private
+    async fn external_in_async_output(&self) -> SomeStruct;
+
+    // This should be allowed because the trait is private
+    fn external_in_return_position_impl_trait(&self) -> impl SimpleTrait;
+}
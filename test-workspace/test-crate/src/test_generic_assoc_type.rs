@@ -0,0 +1,43 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+use external_lib::{SimpleGenericTrait, SimpleTrait, SomeOtherStruct, SomeStruct};
+
+pub trait TraitWithGenericAssocTypes {
+    // This should be an error since `SimpleTrait` is exposed in the public API
+    type WithBoundOnParam<'a, T: SimpleTrait + 'a>;
+
+    // This should be an error since `SimpleGenericTrait` and `SomeStruct` are exposed in the public API
+    type WithWhereClause<T>
+    where
+        T: SimpleGenericTrait<SomeStruct>;
+
+    // This should be an error since `SimpleTrait` is exposed in the public API
+    type WithBoundOnItself<T>: SimpleTrait;
+
+    type Unbounded<T>;
+
+    // This should be an error since `SomeOtherStruct` is exposed in the public API
+    fn external_in_gat_args(&self) -> Self::Unbounded<SomeOtherStruct>;
+}
+
+pub trait TraitWithGenericAssocTypeDefault {
+    type Defaulted<T>;
+}
+
+pub struct ImplsTraitWithGenericAssocTypeDefault;
+
+impl TraitWithGenericAssocTypeDefault for ImplsTraitWithGenericAssocTypeDefault {
+    // This should be an error since `SomeStruct` is exposed in the public API
+    type Defaulted<T> = Result<T, SomeStruct>;
+}
+
+trait PrivateTraitWithGenericAssocTypes {
+    // This should be allowed because the trait is private
+    type WithBoundOnParam<T: SimpleTrait>;
+
+    // This should be allowed because the trait is private
+    fn external_in_gat_args(&self) -> Self::WithBoundOnParam<()>;
+}
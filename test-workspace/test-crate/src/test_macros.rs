@@ -0,0 +1,18 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+use external_lib::SomeStruct;
+
+macro_rules! struct_with_field {
+    ($name:ident, $typ:ty) => {
+        pub struct $name {
+            pub field: $typ,
+        }
+    };
+}
+
+// This should be an error since `SomeStruct` is exposed in the public API. The error should point
+// to the macro invocation rather than to the macro definition.
+struct_with_field!(StructFromMacro, SomeStruct);
@@ -29,3 +29,17 @@ impl external_lib::SimpleGenericTrait<external_lib::SomeStruct> for ImplsGeneric
         0
     }
 }
+
+// This should be an error that notes the feature that the struct is only available with
+#[cfg(feature = "gated")]
+pub struct GatedStructWithExternalType {
+    pub field: external_lib::SomeStruct,
+}
+
+// This should be an error since `SomeOtherStruct` is exposed through the generic args of the
+// implemented trait, even though the trait itself is allowed
+impl From<external_lib::SomeOtherStruct> for ImplsGenericTrait {
+    fn from(_: external_lib::SomeOtherStruct) -> Self {
+        unimplemented!()
+    }
+}
@@ -0,0 +1,31 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+use external_lib::{SimpleGenericTrait, SimpleTrait, SomeStruct};
+
+// This should be an error since `SimpleTrait` is exposed in the public API
+pub type OpaqueExternalTrait = impl SimpleTrait;
+
+pub fn opaque_external_trait() -> OpaqueExternalTrait {}
+
+// This should be an error since `SimpleGenericTrait` and `SomeStruct` are exposed in the public API
+pub type OpaqueExternalGenericTrait = impl SimpleGenericTrait<SomeStruct>;
+
+pub fn opaque_external_generic_trait() -> OpaqueExternalGenericTrait {
+    PrivateStruct
+}
+
+// This should be allowed because the alias is private
+type PrivateOpaqueExternalTrait = impl SimpleTrait;
+
+fn private_opaque_external_trait() -> PrivateOpaqueExternalTrait {}
+
+struct PrivateStruct;
+
+impl SimpleGenericTrait<SomeStruct> for PrivateStruct {
+    fn something(&self, _thing: SomeStruct) -> u32 {
+        0
+    }
+}
@@ -0,0 +1,17 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! This crate is used to test cargo-check-external-types against a crate that
+//! exposes types from two versions of `external-lib` in its public API.
+
+pub fn fn_with_old_version(_one: external_lib::SomeStruct) {}
+
+pub fn fn_with_new_version(_one: external_lib_v2::SomeStruct) {}
+
+pub fn fn_with_both_versions(
+    _one: external_lib::SomeOtherStruct,
+    _two: external_lib_v2::SomeStruct,
+) {
+}
@@ -0,0 +1,14 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Exposes types from optional dependencies, one of which is enabled by default
+
+#[cfg(feature = "ext")]
+pub fn optional_fn(_one: external_lib::SomeStruct) {}
+
+#[cfg(feature = "other")]
+pub fn default_fn(_one: other_external_lib::OtherStruct) {}
+
+pub struct Local;
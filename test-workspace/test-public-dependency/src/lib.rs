@@ -0,0 +1,10 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Exposes types from a private and a public dependency
+
+pub fn private_dependency_fn(_one: external_lib::SomeStruct) {}
+
+pub fn public_dependency_fn(_one: other_external_lib::OtherStruct) {}
@@ -14,3 +14,15 @@ pub mod something {
 
 pub use external_lib::SomeOtherStruct;
 pub use external_lib::SomeStruct;
+
+pub mod glob {
+    pub use external_lib::*;
+}
+
+pub mod whole_crate {
+    pub use external_lib;
+}
+
+pub mod extern_crate {
+    pub extern crate external_lib as renamed_external_lib;
+}
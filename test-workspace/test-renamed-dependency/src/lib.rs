@@ -0,0 +1,14 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! This crate is used to test cargo-check-external-types against a crate that
+//! renames its dependency in its `Cargo.toml`, so that the crate's code refers to
+//! `external_lib` as `renamed_lib`.
+
+use renamed_lib::{SimpleNewType, SomeStruct};
+
+pub fn fn_with_renamed_dependency(_one: SomeStruct) -> SimpleNewType {
+    unimplemented!()
+}
@@ -0,0 +1,30 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+#![allow(dead_code)]
+
+use external_lib::{SimpleTrait, SomeOtherStruct, SomeStruct};
+
+pub fn public_fn(_one: &SomeStruct) {}
+
+pub(crate) fn crate_visible_fn(_one: &SomeOtherStruct) {}
+
+pub mod outer {
+    use external_lib::{SimpleNewType, SimpleTrait};
+
+    pub(super) struct SuperVisibleStruct {
+        pub field: SimpleNewType,
+    }
+
+    pub mod inner {
+        use external_lib::ReprCType;
+
+        pub(in crate::outer) fn restricted_fn(_one: ReprCType) {}
+    }
+
+    pub(crate) trait CrateVisibleTrait: SimpleTrait {}
+}
+
+fn private_fn(_one: &dyn SimpleTrait) {}
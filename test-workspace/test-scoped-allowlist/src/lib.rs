@@ -0,0 +1,21 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Re-exports the same function from a module where its external type is allowed, and from one
+//! where it isn't
+
+mod private {
+    pub fn takes_repr_c(_one: external_lib::ReprCType) {}
+}
+
+pub mod api {
+    pub mod ffi {
+        pub use crate::private::takes_repr_c;
+    }
+
+    pub mod safe {
+        pub use crate::private::takes_repr_c;
+    }
+}
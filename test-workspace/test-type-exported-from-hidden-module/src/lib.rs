@@ -10,7 +10,7 @@
 pub use hidden_module::{
     inner_fn, InnerEnum, InnerStructA, InnerStructB, InnerStructC, SimpleNewType,
 };
-// TODO: Because this public module is declared within a hidden module, it can't be checked for external types.
+// This public module is declared within a hidden module, so it's checked using the docs with hidden items.
 pub use hidden_module::public_module;
 
 #[doc(hidden)]
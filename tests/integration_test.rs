@@ -3,11 +3,11 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
-use cargo_check_external_types::cargo::handle_failure;
+use cargo_check_external_types::cargo::{handle_failure, LATEST_TESTED_NIGHTLY};
 use pretty_assertions::assert_str_eq;
 use std::fs;
-use std::path::Path;
-use std::process::Output;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
 use test_bin::get_test_bin;
 
 /// Returns (stdout, stderr)
@@ -36,6 +36,38 @@ fn run_with_args(in_path: impl AsRef<Path>, args: &[&str]) -> String {
     stdout
 }
 
+/// Runs the tool in `in_path` with `args`, and returns (stdout, stderr) whether it passes or not.
+fn run_with_output(in_path: impl AsRef<Path>, args: &[&str]) -> (String, String) {
+    let output = get_test_bin("cargo-check-external-types")
+        .current_dir(in_path.as_ref())
+        .arg("check-external-types")
+        .args(args)
+        .output()
+        .expect("failed to start cargo-check-external-types");
+    output_text(&output)
+}
+
+/// Creates a crate called `name` that depends on `external-lib` in a temporary directory, with
+/// `lib_rs` as its `src/lib.rs`, and returns the directory. It's a workspace of its own, so that
+/// tests can change it without affecting the test workspace.
+fn temp_crate(name: &str, lib_rs: &str) -> PathBuf {
+    let crate_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let external_lib = fs::canonicalize("test-workspace/external-lib").unwrap();
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::write(
+        crate_dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+            [dependencies]\nexternal-lib = {{ path = {external_lib:?} }}\n\n\
+            [package.metadata.cargo_check_external_types]\nallowed_external_types = []\n\n\
+            [workspace]\n"
+        ),
+    )
+    .unwrap();
+    fs::write(crate_dir.join("src/lib.rs"), lib_rs).unwrap();
+    crate_dir
+}
+
 #[test]
 fn with_default_config() {
     let expected_output = fs::read_to_string("tests/default-config-expected-output.md").unwrap();
@@ -51,6 +83,37 @@ fn with_custom_lib_name() {
     assert_str_eq!(expected_output, actual_output);
 }
 
+#[test]
+fn with_lib_name_override() {
+    let expected_output =
+        fs::read_to_string("tests/default-config-custom-lib-name-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate-custom-lib-name",
+        &["--lib-name", "custom_lib"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_renamed_dependency() {
+    // The config allows `SomeStruct` by the name the crate gives its dependency, while rustdoc
+    // refers to it by the dependency's own crate name
+    let expected_output =
+        fs::read_to_string("tests/renamed-dependency-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-renamed-dependency",
+        &["--config", "../../tests/renamed-dependency.toml"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_multiple_crate_versions() {
+    let expected_output = fs::read_to_string("tests/multiple-versions-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace/test-multiple-versions", &[]);
+    assert_str_eq!(expected_output, actual_output);
+}
+
 #[test]
 fn with_some_allowed_types() {
     let expected_output = fs::read_to_string("tests/allow-some-types-expected-output.md").unwrap();
@@ -61,6 +124,27 @@ fn with_some_allowed_types() {
     assert_str_eq!(expected_output, actual_output);
 }
 
+#[test]
+fn with_some_allowed_types_in_json_config() {
+    let expected_output = fs::read_to_string("tests/allow-some-types-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--config", "../../tests/allow-some-types.json"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_some_allowed_types_for_target() {
+    // The test suite runs on Unix, so only the `cfg(unix)` allowlist applies
+    let expected_output = fs::read_to_string("tests/allow-some-types-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--config", "../../tests/allow-some-types-per-target.toml"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
 #[test]
 fn with_some_allowed_types_in_metadata() {
     let expected_output =
@@ -114,3 +198,1305 @@ fn test_type_exported_from_hidden_module() {
     let actual_output = run_with_args("test-workspace/test-type-exported-from-hidden-module", &[]);
     assert_str_eq!(expected_output, actual_output);
 }
+
+#[test]
+fn with_denied_dependency_sources() {
+    let expected_output =
+        fs::read_to_string("tests/deny-path-dependencies-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--config", "../../tests/deny-path-dependencies.toml"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_denied_external_types() {
+    let expected_output = fs::read_to_string("tests/deny-some-types-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--no-context",
+            "--config",
+            "../../tests/deny-some-types.toml",
+        ],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_types_allowed_in_module() {
+    let expected_output =
+        fs::read_to_string("tests/allow-types-in-module-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--no-context",
+            "--config",
+            "../../tests/allow-types-in-module.toml",
+        ],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_types_allowed_in_module_and_reexported_elsewhere() {
+    let expected_output = fs::read_to_string("tests/scoped-allowlist-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace/test-scoped-allowlist", &[]);
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_print_config() {
+    let expected_output = fs::read_to_string("tests/print-config-expected-output.toml").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--print-config",
+            "--config",
+            "../../tests/allow-types-in-module.toml",
+        ],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn lint_config() {
+    let expected_output = fs::read_to_string("tests/lint-config-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["lint-config", "--config", "../../tests/lint-config.toml"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn compare() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("compare");
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("allow-nothing.toml");
+    fs::write(&config, "allowed_external_types = []\n").unwrap();
+    let old_report = dir.join("old.json");
+    let new_report = dir.join("new.json");
+    run_with_args(
+        "test-workspace/test-crate-metadata-config",
+        &[
+            "--output-format",
+            "json",
+            "--output-file",
+            old_report.to_str().unwrap(),
+        ],
+    );
+    run_with_args(
+        "test-workspace/test-crate-metadata-config",
+        &[
+            "--output-format",
+            "json",
+            "--output-file",
+            new_report.to_str().unwrap(),
+            "--config",
+            config.to_str().unwrap(),
+        ],
+    );
+
+    // Allowing fewer types adds violations, which fails the comparison
+    let output = get_test_bin("cargo-check-external-types")
+        .args(["check-external-types", "compare"])
+        .args([&old_report, &new_report])
+        .output()
+        .expect("failed to start cargo-check-external-types");
+    assert_eq!(Some(1), output.status.code());
+    let expected_output = fs::read_to_string("tests/compare-expected-output.md").unwrap();
+    let (stdout, _) = output_text(&output);
+    assert_str_eq!(expected_output, stdout);
+
+    // The other way around, they're removed
+    let output = run_with_args(
+        ".",
+        &[
+            "compare",
+            new_report.to_str().unwrap(),
+            old_report.to_str().unwrap(),
+        ],
+    );
+    assert!(
+        output.ends_with("0 added, 7 removed, 1 unchanged\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_pattern_stats() {
+    let expected_output = fs::read_to_string("tests/pattern-stats-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--no-context",
+            "--pattern-stats",
+            "--config",
+            "../../tests/allow-some-types.toml",
+        ],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_denied_std_types() {
+    let expected_output = fs::read_to_string("tests/deny-std-types-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--no-context",
+            "--config",
+            "../../tests/deny-std-types.toml",
+        ],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_hidden_items() {
+    let expected_output = fs::read_to_string("tests/hidden-items-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--include-hidden-items"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_blanket_impls() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("blanket-impls.toml");
+    fs::write(
+        &config_file,
+        "allowed_external_types = []\ncheck_blanket_impls = true\n",
+    )
+    .unwrap();
+    let blanket_impl_error = "error: Unapproved external type `external_lib::SimpleTrait` \
+        referenced in public API\n  --> test-crate/src/lib.rs:177:1\n   \
+        = in trait bound of `test_crate::LocalTraitWithBlanketImpl`\n";
+
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(output.contains(blanket_impl_error), "{output}");
+
+    // Blanket impls aren't checked by default
+    let output = run_with_args("test-workspace/test-crate", &["--no-context"]);
+    assert!(!output.contains("LocalTraitWithBlanketImpl"), "{output}");
+}
+
+#[test]
+fn with_reexported_crate() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reexported-crate.toml");
+    let reexport_error = "error: External crate `external_lib` is re-exported in public API";
+
+    // Allowing all the types in a crate doesn't allow re-exporting the crate itself
+    fs::write(
+        &config_file,
+        "allowed_external_types = [\"external_lib::*\"]\n",
+    )
+    .unwrap();
+    let output = run_with_args(
+        "test-workspace/test-reexports-crate",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(output.contains(reexport_error), "{output}");
+    assert!(
+        output.contains(
+            "in re-export named `test_reexports_crate::extern_crate::renamed_external_lib`"
+        ),
+        "{output}"
+    );
+    assert!(output.contains("2 errors, 0 warnings emitted"), "{output}");
+
+    fs::write(
+        &config_file,
+        "allowed_external_types = [\"external_lib::*\", \"external_lib\"]\n",
+    )
+    .unwrap();
+    let output = run_with_args(
+        "test-workspace/test-reexports-crate",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(!output.contains(reexport_error), "{output}");
+}
+
+#[test]
+fn with_unstable_crates_denied() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("unstable-crates.toml");
+    let unstable_error =
+        "error: External type `external_lib::SomeStruct` from pre-1.0 dependency `external-lib 0.1.0`";
+
+    // `external-lib` is below 1.0, so allowing its types isn't enough
+    fs::write(
+        &config_file,
+        "deny_unstable_crates = true\nallowed_external_types = [\"external_lib::*\"]\n",
+    )
+    .unwrap();
+    let output = run_with_args(
+        "test-workspace/test-renamed-dependency",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(output.contains(unstable_error), "{output}");
+    assert!(output.contains("2 errors, 0 warnings emitted"), "{output}");
+
+    // Types that aren't allowed at all are still reported as unapproved
+    fs::write(
+        &config_file,
+        "deny_unstable_crates = true\n\
+        allowed_external_types = [{ pattern = \"external_lib::SomeStruct\", acknowledged = true }]\n",
+    )
+    .unwrap();
+    let output = run_with_args(
+        "test-workspace/test-renamed-dependency",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(!output.contains(unstable_error), "{output}");
+    assert!(
+        output.contains("error: Unapproved external type `external_lib::SimpleNewType`"),
+        "{output}"
+    );
+    assert!(output.contains("1 errors, 0 warnings emitted"), "{output}");
+}
+
+#[test]
+fn with_public_dependencies() {
+    let expected_output = fs::read_to_string("tests/public-dependency-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace/test-public-dependency", &[]);
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_optional_dependencies() {
+    let expected_output =
+        fs::read_to_string("tests/optional-dependency-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-optional-dependency",
+        &["--features", "ext"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_reused_rustdoc_json() {
+    let crate_dir = temp_crate("reused-json-crate", "pub struct Local;\n");
+    let run = |args: &[&str]| run_with_output(&crate_dir, args);
+    let error = "error: Unapproved external type `external_lib::SomeStruct`";
+
+    let (stdout, stderr) = run(&[]);
+    assert!(!stderr.contains("Reusing rustdoc JSON"), "{stderr}");
+    assert!(!stdout.contains(error), "{stdout}");
+
+    // Nothing changed, so the JSON from the first run is reused. `--fail-fast` changes the
+    // findings, so they're computed again.
+    let (stdout, stderr) = run(&["--fail-fast"]);
+    assert!(stderr.contains("Reusing rustdoc JSON"), "{stderr}");
+    assert!(!stdout.contains(error), "{stdout}");
+    let (_, stderr) = run(&["--no-cache"]);
+    assert!(!stderr.contains("Reusing rustdoc JSON"), "{stderr}");
+
+    // The cargo config is in a hidden directory, but it's still checked for changes
+    fs::create_dir_all(crate_dir.join(".cargo")).unwrap();
+    fs::write(crate_dir.join(".cargo/config.toml"), "[build]\n").unwrap();
+    let (_, stderr) = run(&[]);
+    assert!(stderr.contains("Running rustdoc"), "{stderr}");
+    assert!(!stderr.contains("Reusing"), "{stderr}");
+
+    fs::write(
+        crate_dir.join("src/lib.rs"),
+        "pub struct Local;\n\npub fn public_fn(_one: external_lib::SomeStruct) {}\n",
+    )
+    .unwrap();
+    let (stdout, stderr) = run(&[]);
+    assert!(!stderr.contains("Reusing rustdoc JSON"), "{stderr}");
+    assert!(stdout.contains(error), "{stdout}");
+}
+
+#[test]
+fn with_cached_results() {
+    let crate_dir = temp_crate(
+        "cached-results-crate",
+        "pub fn public_fn(_one: external_lib::SomeStruct) {}\n",
+    );
+    let run = |args: &[&str]| run_with_output(&crate_dir, args);
+    let error = "error: Unapproved external type `external_lib::SomeStruct`";
+
+    let (stdout, stderr) = run(&["--no-cache"]);
+    assert!(stdout.contains(error), "{stdout}");
+    assert!(stderr.contains("Running rustdoc"), "{stderr}");
+    let (first_stdout, stderr) = run(&[]);
+    assert!(!stderr.contains("Reusing results"), "{stderr}");
+
+    // Nothing changed, so neither rustdoc nor the check runs again
+    let (stdout, stderr) = run(&[]);
+    assert!(stderr.contains("Reusing results"), "{stderr}");
+    assert!(!stderr.contains("Running rustdoc"), "{stderr}");
+    assert!(!stderr.contains("Examining all public types"), "{stderr}");
+    assert_eq!(first_stdout, stdout);
+    let (stdout, stderr) = run(&["--output-format", "markdown-table"]);
+    assert!(stderr.contains("Reusing results"), "{stderr}");
+    assert!(stdout.contains("external_lib::SomeStruct"), "{stdout}");
+
+    // Findings depend on the settings they're found with
+    let (_, stderr) = run(&["--include-hidden-items"]);
+    assert!(!stderr.contains("Reusing results"), "{stderr}");
+    let (_, stderr) = run(&["--emit-rustdoc-json"]);
+    assert!(!stderr.contains("Reusing results"), "{stderr}");
+    let (_, stderr) = run(&["--no-cache"]);
+    assert!(!stderr.contains("Reusing results"), "{stderr}");
+
+    fs::write(crate_dir.join("src/lib.rs"), "pub struct Local;\n").unwrap();
+    let (stdout, stderr) = run(&[]);
+    assert!(!stderr.contains("Reusing results"), "{stderr}");
+    assert!(!stdout.contains(error), "{stdout}");
+}
+
+#[test]
+fn with_item_reexported_in_same_module() {
+    let crate_dir = temp_crate(
+        "reexported-item-crate",
+        "pub mod inner {\n    pub struct Wrapper {\n        pub field: external_lib::SomeStruct,\n    }\n\n    \
+        pub use self::Wrapper as Alias;\n    pub use self::Wrapper as OtherAlias;\n}\n",
+    );
+
+    // The struct is only visited once, so its field is only reported once
+    let output = run_with_args(&crate_dir, &["--no-cache"]);
+    assert_eq!(
+        1,
+        output
+            .matches("error: Unapproved external type `external_lib::SomeStruct`")
+            .count(),
+        "{output}"
+    );
+    assert!(
+        output.contains("in struct field of `reexported_item_crate::inner::Wrapper::field`"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_configured_rustdocflags() {
+    let crate_dir = temp_crate(
+        "configured-rustdocflags-crate",
+        "#[cfg(docsrs)]\npub fn docs_only(_one: external_lib::SomeStruct) {}\n",
+    );
+    fs::create_dir_all(crate_dir.join(".cargo")).unwrap();
+    fs::write(
+        crate_dir.join(".cargo/config.toml"),
+        "[build]\nrustdocflags = [\"--cfg\", \"docsrs\"]\n",
+    )
+    .unwrap();
+    let error = "error: Unapproved external type `external_lib::SomeStruct`";
+
+    let output = run_with_args(&crate_dir, &[]);
+    assert!(output.contains(error), "{output}");
+    let output = run_with_args(&crate_dir, &["--rustdocflags-precedence", "inherited"]);
+    assert!(output.contains(error), "{output}");
+    let output = run_with_args(&crate_dir, &["--no-inherit-rustdocflags"]);
+    assert!(!output.contains(error), "{output}");
+}
+
+#[test]
+fn with_cargo_config_and_unstable_flags() {
+    let crate_dir = temp_crate(
+        "cargo-config-crate",
+        "#[cfg(docsrs)]\npub fn docs_only(_one: external_lib::SomeStruct) {}\n",
+    );
+    let error = "error: Unapproved external type `external_lib::SomeStruct`";
+
+    let output = run_with_args(&crate_dir, &[]);
+    assert!(!output.contains(error), "{output}");
+    let output = run_with_args(
+        &crate_dir,
+        &[
+            "--cargo-config",
+            r#"build.rustdocflags=["--cfg", "docsrs"]"#,
+            "-Z",
+            "no-index-update",
+        ],
+    );
+    assert!(output.contains(error), "{output}");
+}
+
+#[test]
+fn with_feature_matrix() {
+    let output = run_with_args("test-workspace/test-crate", &["--feature-matrix"]);
+    assert!(
+        output.contains(
+            "= in struct field of `test_crate::test_structs::GatedStructWithExternalType::field`\n   \
+            = note: available on crate feature `gated` only\n   \
+            = note: from dependency `external-lib 0.1.0 (path)`\n   \
+            = note: only found with features `default,gated` and features `gated`\n"
+        ),
+        "{output}"
+    );
+    // Findings that are found with every feature set aren't annotated
+    assert_eq!(1, output.matches("only found with").count(), "{output}");
+
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--no-default-features",
+            "--check-features",
+            "",
+            "--check-features",
+            "gated",
+        ],
+    );
+    assert!(
+        output.contains("= note: only found with features `gated`\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_locked_and_offline() {
+    let expected_output = fs::read_to_string("tests/default-config-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace/test-crate", &["--locked", "--offline"]);
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_profile() {
+    let expected_output = fs::read_to_string("tests/default-config-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace/test-crate", &["--profile", "release"]);
+    assert_str_eq!(expected_output, actual_output);
+
+    let output = get_test_bin("cargo-check-external-types")
+        .current_dir("test-workspace/test-crate")
+        .args(["check-external-types", "--profile", "undefined"])
+        .output()
+        .expect("failed to start cargo-check-external-types");
+    let (stdout, _) = output_text(&output);
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains("profile `undefined` is not defined"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn with_target_dir() {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("rustdoc-target-dir");
+    let _ = fs::remove_dir_all(&target_dir);
+    let expected_output = fs::read_to_string("tests/default-config-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--target-dir", target_dir.to_str().unwrap()],
+    );
+    assert_str_eq!(expected_output, actual_output);
+    assert!(target_dir.join("doc/test_crate.json").exists());
+}
+
+#[test]
+fn with_print_command() {
+    let output = get_test_bin("cargo-check-external-types")
+        .current_dir("test-workspace/test-crate")
+        .args(["check-external-types", "--print-command", "--no-cache"])
+        .output()
+        .expect("failed to start cargo-check-external-types");
+    let (_, stderr) = output_text(&output);
+    let test_crate = fs::canonicalize("test-workspace/test-crate").unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "Running `cd {} && env -u RUSTDOCFLAGS CARGO_ENCODED_RUSTDOCFLAGS=",
+            test_crate.display()
+        )),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(" rustdoc --lib --no-default-features --features default,gated`"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn with_emit_rustdoc_json() {
+    let emit_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("emitted-rustdoc-json");
+    let _ = fs::remove_dir_all(&emit_dir);
+    let emit = |args: &[&str]| {
+        let output = get_test_bin("cargo-check-external-types")
+            .current_dir("test-workspace/test-crate")
+            .arg("check-external-types")
+            .args(args)
+            .output()
+            .expect("failed to start cargo-check-external-types");
+        output_text(&output).1
+    };
+
+    let stderr = emit(&["--emit-rustdoc-json"]);
+    let path = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("Rustdoc JSON is at "))
+        .unwrap_or_else(|| panic!("{stderr}"));
+    let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    assert!(json["index"].is_object());
+
+    let stderr = emit(&["--emit-rustdoc-json", emit_dir.to_str().unwrap()]);
+    assert!(stderr.contains("Copied rustdoc JSON to"), "{stderr}");
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(emit_dir.join("test_crate.json")).unwrap())
+            .unwrap();
+    assert!(json["index"].is_object());
+}
+
+#[test]
+fn with_toolchain() {
+    let expected_output = fs::read_to_string("tests/default-config-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--toolchain", LATEST_TESTED_NIGHTLY],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_incompatible_toolchain() {
+    let output = get_test_bin("cargo-check-external-types")
+        .current_dir("test-workspace/test-crate")
+        .args(["check-external-types", "--toolchain", "stable"])
+        .output()
+        .expect("failed to start cargo-check-external-types");
+    let (stdout, _) = output_text(&output);
+    assert!(!output.status.success());
+    assert!(
+        stdout.contains(&format!(
+            "The compatible toolchain {LATEST_TESTED_NIGHTLY} is installed. \
+            Rerun with `--toolchain {LATEST_TESTED_NIGHTLY}` to use it."
+        )),
+        "{stdout}"
+    );
+
+    // The compatible toolchain is already installed, so it's used without installing anything
+    let expected_output = fs::read_to_string("tests/default-config-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--toolchain", "stable", "--install-toolchain"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_surface_paths() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("surface-paths.toml");
+    let allowed = "allowed_external_types = [\"external_lib::DefinedInPrivateModule\"]\n";
+    let error = "error: Unapproved external type `external_lib::private::DefinedInPrivateModule`";
+
+    // Types are matched by their path in the module that defines them by default
+    fs::write(&config_file, allowed).unwrap();
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(output.contains(error), "{output}");
+
+    fs::write(
+        &config_file,
+        format!("{allowed}match_surface_paths = true\n"),
+    )
+    .unwrap();
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(!output.contains(error), "{output}");
+}
+
+#[test]
+fn with_where_bounds() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("where-bounds.toml");
+    fs::write(
+        &config_file,
+        "allowed_external_types = []\ncheck_where_bounds = true\n",
+    )
+    .unwrap();
+    let where_bound_error = "error: Unapproved external type `external_lib::SomeStruct` \
+        referenced in public API\n  --> test-crate/src/lib.rs:181:1\n   \
+        = in generic arg of `test_crate::fn_with_external_where_bound_subject`\n";
+
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--config", config_file.to_str().unwrap()],
+    );
+    assert!(output.contains(where_bound_error), "{output}");
+
+    // The types that where bounds apply to aren't checked by default
+    let output = run_with_args("test-workspace/test-crate", &["--no-context"]);
+    assert!(
+        !output.contains("fn_with_external_where_bound_subject"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_severity() {
+    let expected_output = fs::read_to_string("tests/severity-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--config", "../../tests/severity.toml"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_output_file() {
+    let expected_output = fs::read_to_string("tests/default-config-expected-output.md").unwrap();
+    let output_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("with-output-file.md");
+    let actual_stdout = run_with_args(
+        "test-workspace/test-crate",
+        &["--output-file", output_file.to_str().unwrap()],
+    );
+    assert_str_eq!("", actual_stdout);
+    let actual_output = fs::read_to_string(&output_file).unwrap();
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_emit_allowlist() {
+    let expected_allowlist =
+        fs::read_to_string("tests/emit-allowlist-expected-output.toml").unwrap();
+    let allowlist_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("emit-allowlist.toml");
+    run_with_args(
+        "test-workspace/test-crate",
+        &["--emit-allowlist", allowlist_file.to_str().unwrap()],
+    );
+    let actual_allowlist = fs::read_to_string(&allowlist_file).unwrap();
+    assert_str_eq!(expected_allowlist, actual_allowlist);
+
+    // The allowlist approves every type, leaving only the warnings
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--config", allowlist_file.to_str().unwrap()],
+    );
+    assert!(
+        output.ends_with("0 errors, 2 warnings emitted\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_emit_workspace_allowlist() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("workspace-allowlist");
+    let _ = fs::remove_dir_all(&dir);
+    let args = |layout| {
+        run_with_args(
+            "test-workspace",
+            &[
+                "--workspace",
+                "--exclude",
+                "test-crate",
+                "--exclude",
+                "test-crate-custom-lib-name",
+                "--exclude",
+                "test-restricted-visibility",
+                "--exclude",
+                "test-optional-dependency",
+                "--exclude",
+                "test-public-dependency",
+                "--exclude",
+                "test-scoped-allowlist",
+                "--emit-workspace-allowlist",
+                dir.to_str().unwrap(),
+                "--allowlist-layout",
+                layout,
+            ],
+        )
+    };
+
+    args("shared");
+    assert_str_eq!(
+        "# Allows the external types that were exposed when this file was generated\n\
+        [workspace.metadata.cargo_check_external_types]\n\
+        allowed_external_types = [\n    \"external_lib::*\",\n]\n",
+        fs::read_to_string(dir.join("workspace.toml")).unwrap()
+    );
+
+    args("per-member");
+    assert_str_eq!(
+        "# Allows the external types that were exposed when this file was generated\n\
+        [package.metadata.cargo_check_external_types]\n\
+        allowed_external_types = [\n    \"external_lib::SimpleNewType\",\n]\n",
+        fs::read_to_string(dir.join("test-hidden-module-reexported-type.toml")).unwrap()
+    );
+    assert!(dir.join("test-reexports-crate.toml").exists());
+}
+
+#[test]
+fn with_baseline() {
+    let baseline_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("baseline.json");
+    let baseline_path = baseline_file.to_str().unwrap();
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--write-baseline", baseline_path],
+    );
+    assert_str_eq!("", output);
+
+    // Every recorded finding is suppressed, wherever it is
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--baseline", baseline_path],
+    );
+    assert_str_eq!("", output);
+
+    // Findings that aren't in the baseline are still reported
+    let mut baseline: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&baseline_file).unwrap()).unwrap();
+    let findings = baseline["findings"].as_array_mut().unwrap();
+    let index = findings
+        .iter()
+        .position(|finding| finding["type_name"] == "external_lib::ReprCType")
+        .unwrap();
+    findings.remove(index);
+    fs::write(&baseline_file, baseline.to_string()).unwrap();
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--baseline", baseline_path],
+    );
+    assert!(
+        output.contains("Unapproved external type `external_lib::ReprCType`"),
+        "{output}"
+    );
+    assert!(
+        output.ends_with("2 errors, 0 warnings emitted\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_max_errors() {
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--max-errors", "3"],
+    );
+    assert_eq!(3, output.matches("\n  --> ").count(), "{output}");
+    assert!(
+        output.contains("... and 62 more not shown, because of `--max-errors 3`\n"),
+        "{output}"
+    );
+    // The summary still counts everything
+    assert!(
+        output.ends_with("63 errors, 2 warnings emitted\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_exit_codes() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("exit-codes.toml");
+    fs::write(
+        &config_file,
+        "allowed_external_types = [\"external_lib::*\"]\n",
+    )
+    .unwrap();
+    let exit_code = |args: &[&str]| {
+        get_test_bin("cargo-check-external-types")
+            .current_dir("test-workspace/test-crate")
+            .arg("check-external-types")
+            .args(args)
+            .output()
+            .expect("failed to start cargo-check-external-types")
+            .status
+            .code()
+    };
+    let config = config_file.to_str().unwrap();
+
+    // Only warnings are left once every external type is allowed
+    assert_eq!(Some(0), exit_code(&["--config", config]));
+    assert_eq!(
+        Some(3),
+        exit_code(&["--config", config, "--warnings-exit-code", "3"])
+    );
+    assert_eq!(Some(10), exit_code(&["--errors-exit-code", "10"]));
+    assert_eq!(
+        Some(20),
+        exit_code(&[
+            "--config",
+            "does-not-exist.toml",
+            "--failure-exit-code",
+            "20"
+        ])
+    );
+}
+
+#[test]
+fn with_budgets() {
+    let run = |args: &[&str]| {
+        let output = get_test_bin("cargo-check-external-types")
+            .current_dir("test-workspace/test-crate")
+            .args(["check-external-types", "--no-context"])
+            .args(args)
+            .output()
+            .expect("failed to start cargo-check-external-types");
+        let (_, stderr) = output_text(&output);
+        (output.status.code(), stderr)
+    };
+
+    // The test crate has 63 errors and 2 warnings
+    let (code, stderr) = run(&["--max-allowed-errors", "63"]);
+    assert_eq!(Some(0), code, "{stderr}");
+    let (code, stderr) = run(&["--max-allowed-errors", "70"]);
+    assert_eq!(Some(0), code, "{stderr}");
+    assert!(
+        stderr.contains("Found 63 errors, which is under the budget of 70."),
+        "{stderr}"
+    );
+    let (code, stderr) = run(&["--max-allowed-errors", "62"]);
+    assert_eq!(Some(1), code, "{stderr}");
+    assert!(
+        stderr.contains("Found 63 errors, which is over the budget of 62"),
+        "{stderr}"
+    );
+    let (code, stderr) = run(&["--max-allowed-errors", "63", "--max-allowed-warnings", "1"]);
+    assert_eq!(Some(1), code, "{stderr}");
+}
+
+#[test]
+fn with_fail_fast() {
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &["--no-context", "--fail-fast"],
+    );
+    assert!(output.starts_with("error: "), "{output}");
+    assert!(
+        output.ends_with("1 errors, 0 warnings emitted\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_history_file() {
+    let history_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("history.jsonl");
+    let _ = fs::remove_file(&history_file);
+    let history_path = history_file.to_str().unwrap();
+    run_with_args(
+        "test-workspace/test-crate",
+        &["--history-file", history_path],
+    );
+    run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--history-file",
+            history_path,
+            "--config",
+            "../../tests/allow-some-types.toml",
+        ],
+    );
+    assert_eq!(
+        2,
+        fs::read_to_string(&history_file).unwrap().lines().count()
+    );
+
+    let output = run_with_args(".", &["trend", "--history-file", history_path]);
+    assert!(output.starts_with("2 runs from "), "{output}");
+    assert!(
+        output.contains("Changes by external crate since the first run:"),
+        "{output}"
+    );
+    assert!(output.contains("Exposure is decreasing: "), "{output}");
+}
+
+#[test]
+fn with_fix() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fix.toml");
+    fs::write(
+        &config_file,
+        "allowed_external_types = [\n    \"external_lib::S*\",\n    \"unused::*\",\n]\n",
+    )
+    .unwrap();
+    let output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--no-context",
+            "--fix",
+            "--config",
+            config_file.to_str().unwrap(),
+        ],
+    );
+    assert!(
+        output.ends_with("0 errors, 2 warnings emitted\n"),
+        "{output}"
+    );
+    assert_str_eq!(
+        r#"allowed_external_types = [
+    "external_lib::S*",
+    "external_lib::*",
+]
+"#,
+        fs::read_to_string(&config_file).unwrap()
+    );
+}
+
+#[test]
+fn with_prune_config() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("prune-config.toml");
+    fs::write(
+        &config_file,
+        r#"allowed_external_types = [
+    # Used
+    "external_lib::S*",
+    "unused::*", # Not used
+    "!unused::Negated",
+]
+"#,
+    )
+    .unwrap();
+    let expected_output = fs::read_to_string("tests/allow-some-types-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--prune-config", "--config", config_file.to_str().unwrap()],
+    );
+    assert_str_eq!(expected_output, actual_output);
+    assert_str_eq!(
+        r#"allowed_external_types = [
+    # Used
+    "external_lib::S*",
+    "!unused::Negated",
+]
+"#,
+        fs::read_to_string(&config_file).unwrap()
+    );
+}
+
+#[test]
+fn with_prune_config_unknown_crates() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("prune-unknown-crates.toml");
+    fs::write(
+        &config_file,
+        "allowed_external_types = [\"external_lib::S*\", \"external-lib::*\", \"unknown::*\"]\n",
+    )
+    .unwrap();
+    let output = get_test_bin("cargo-check-external-types")
+        .current_dir("test-workspace/test-crate")
+        .args(["check-external-types", "--prune-config", "--config"])
+        .arg(&config_file)
+        .output()
+        .expect("failed to start cargo-check-external-types");
+    let (_, stderr) = output_text(&output);
+    assert!(
+        stderr.contains(
+            "warning: removed `external-lib::*`, since `external-lib` isn't a dependency. \
+            Did you mean `external_lib::*`?"
+        ),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("warning: removed `unknown::*`, since `unknown` isn't a dependency.\n"),
+        "{stderr}"
+    );
+    assert_str_eq!(
+        "allowed_external_types = [\"external_lib::S*\"]\n",
+        fs::read_to_string(&config_file).unwrap()
+    );
+}
+
+#[test]
+fn with_output_format_markdown_table_unsorted() {
+    let expected_output =
+        fs::read_to_string("tests/output-format-markdown-table-unsorted-expected-output.md")
+            .unwrap();
+    // Cached findings are output all at once in sorted order, so check a visit
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &[
+            "--output-format",
+            "markdown-table",
+            "--no-sort",
+            "--no-cache",
+        ],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_output_format_json() {
+    let expected_output =
+        fs::read_to_string("tests/output-format-json-expected-output.json").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate-metadata-config",
+        &["--output-format", "json"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_output_format_json_on_failure() {
+    let output = get_test_bin("cargo-check-external-types")
+        .current_dir("test-workspace/test-crate")
+        .args([
+            "check-external-types",
+            "--output-format",
+            "json",
+            "--config",
+            "does-not-exist.toml",
+        ])
+        .output()
+        .expect("failed to start cargo-check-external-types");
+    assert_eq!(Some(2), output.status.code());
+    let (stdout, _) = output_text(&output);
+    let summary: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!("failure", summary["status"]);
+    assert_eq!("config", summary["phase"]);
+    assert!(summary["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("failed to read config file"));
+}
+
+#[test]
+fn with_no_context() {
+    let expected_output = fs::read_to_string("tests/no-context-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace/test-crate", &["--no-context"]);
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_sort_by_type() {
+    let expected_output = fs::read_to_string("tests/sort-by-type-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate",
+        &["--sort-by", "type", "--no-context"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_restricted_visibility() {
+    let expected_output =
+        fs::read_to_string("tests/restricted-visibility-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace/test-restricted-visibility", &[]);
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_include_restricted_visibility() {
+    let expected_output =
+        fs::read_to_string("tests/include-restricted-visibility-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-restricted-visibility",
+        &["--config", "../../tests/include-restricted-visibility.toml"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_output_format_badge() {
+    let actual_output = run_with_args(
+        "test-workspace/test-crate-metadata-config",
+        &["--output-format", "badge"],
+    );
+    assert_str_eq!(
+        "{\"schemaVersion\":1,\"label\":\"external types\",\"message\":\"1 error\",\"color\":\"orange\"}\n",
+        actual_output
+    );
+}
+
+#[test]
+fn with_output_format_teamcity() {
+    let expected_output =
+        fs::read_to_string("tests/output-format-teamcity-expected-output.txt").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-type-exported-from-hidden-module",
+        &["--output-format", "teamcity"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_output_format_rdjson() {
+    let expected_output =
+        fs::read_to_string("tests/output-format-rdjson-expected-output.json").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace/test-crate-metadata-config",
+        &["--output-format", "rdjson"],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn fmt_config() {
+    let config_file = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fmt-config.toml");
+    fs::write(
+        &config_file,
+        "allowed_external_types = [\"external-lib::SomeStruct\", \"external_lib::AssociatedGenericTrait\"]\n",
+    )
+    .unwrap();
+    let actual_stdout = run_with_args(
+        "test-workspace/test-crate",
+        &["fmt-config", "--config", config_file.to_str().unwrap()],
+    );
+    assert_str_eq!("", actual_stdout);
+    assert_str_eq!(
+        "allowed_external_types = [\n    \"external_lib::AssociatedGenericTrait\",\n    \"external_lib::SomeStruct\",\n]\n",
+        fs::read_to_string(&config_file).unwrap()
+    );
+}
+
+#[test]
+fn init() {
+    let crate_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("init-crate");
+    let external_lib = fs::canonicalize("test-workspace/external-lib").unwrap();
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    fs::write(crate_dir.join("src/lib.rs"), "").unwrap();
+    let manifest = format!(
+        "[package]\nname = \"init-crate\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+        [dependencies]\nexternal-lib = {{ path = {external_lib:?} }}\n\n[workspace]\n"
+    );
+    fs::write(crate_dir.join("Cargo.toml"), &manifest).unwrap();
+    let actual_stdout = run_with_args(&crate_dir, &["init"]);
+    assert_str_eq!("", actual_stdout);
+    assert_str_eq!(
+        format!(
+            "{manifest}\n[package.metadata.cargo_check_external_types]\n\
+            # External types that may be exposed in the public API, as globs such as `crate_name::*`\n\
+            # or as full type paths. Run `cargo check-external-types` to see which external types\n\
+            # the public API exposes, and uncomment or add the ones that are meant to be part of it.\n\
+            allowed_external_types = [\n    # \"external_lib::*\",\n]\n"
+        ),
+        fs::read_to_string(crate_dir.join("Cargo.toml")).unwrap()
+    );
+}
+
+#[test]
+fn migrate_config() {
+    let crate_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("migrate-config-crate");
+    fs::create_dir_all(&crate_dir).unwrap();
+    let manifest = "[package]\nname = \"migrate-config-crate\"\n";
+    let config = "allowed_external_types = [\n    \"bytes::*\", # Re-exported\n]\n";
+    fs::write(crate_dir.join("Cargo.toml"), manifest).unwrap();
+    fs::write(crate_dir.join("external-types.toml"), config).unwrap();
+
+    run_with_args(
+        &crate_dir,
+        &["migrate-config", "--config", "external-types.toml"],
+    );
+    assert_str_eq!(
+        format!("{manifest}\n[package.metadata.cargo_check_external_types]\n{config}"),
+        fs::read_to_string(crate_dir.join("Cargo.toml")).unwrap()
+    );
+
+    fs::remove_file(crate_dir.join("external-types.toml")).unwrap();
+    run_with_args(
+        &crate_dir,
+        &[
+            "migrate-config",
+            "--config",
+            "external-types.toml",
+            "--to-file",
+        ],
+    );
+    assert_str_eq!(
+        config,
+        fs::read_to_string(crate_dir.join("external-types.toml")).unwrap()
+    );
+}
+
+#[test]
+fn with_since() {
+    let crate_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("since-crate");
+    let _ = fs::remove_dir_all(&crate_dir);
+    fs::create_dir_all(crate_dir.join("src")).unwrap();
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .current_dir(&crate_dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .expect("failed to start git");
+        handle_failure("git", &output).unwrap();
+    };
+    fs::write(
+        crate_dir.join("Cargo.toml"),
+        "[package]\nname = \"since-crate\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+        [workspace]\n\n\
+        [package.metadata.cargo_check_external_types]\n\
+        allowed_external_types = []\ndenied_std_types = [\"std::boxed::*\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        crate_dir.join("src/lib.rs"),
+        "pub fn old() -> Box<u8> {\n    Box::new(0)\n}\n",
+    )
+    .unwrap();
+    git(&["init", "-q"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "old"]);
+    fs::write(
+        crate_dir.join("src/lib.rs"),
+        "pub fn old() -> Box<u8> {\n    Box::new(0)\n}\n\n\
+        pub fn new() -> Box<u16> {\n    Box::new(0)\n}\n",
+    )
+    .unwrap();
+
+    let output = run_with_args(&crate_dir, &["--no-context"]);
+    assert!(
+        output.ends_with("2 errors, 0 warnings emitted\n"),
+        "{output}"
+    );
+    let output = run_with_args(&crate_dir, &["--no-context", "--since", "HEAD"]);
+    assert!(output.contains("src/lib.rs:5:1"), "{output}");
+    assert!(
+        output.ends_with("1 errors, 0 warnings emitted\n"),
+        "{output}"
+    );
+}
+
+#[test]
+fn with_package_from_workspace_root() {
+    let expected_output =
+        fs::read_to_string("tests/allow-some-types-metadata-expected-output.md").unwrap();
+    let actual_output = run_with_args("test-workspace", &["-p", "test-crate-metadata-config"]);
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn with_workspace_and_exclude() {
+    let expected_output = fs::read_to_string("tests/workspace-expected-output.md").unwrap();
+    let actual_output = run_with_args(
+        "test-workspace",
+        &[
+            "--workspace",
+            "--exclude",
+            "test-crate",
+            "--exclude",
+            "test-crate-custom-lib-name",
+            "--exclude",
+            "test-restricted-visibility",
+            "--exclude",
+            "test-optional-dependency",
+            "--exclude",
+            "test-public-dependency",
+            "--exclude",
+            "test-scoped-allowlist",
+        ],
+    );
+    assert_str_eq!(expected_output, actual_output);
+}
+
+#[test]
+fn print_config_schema() {
+    let output = run_with_args(".", &["--print-config-schema"]);
+    let schema: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        Some("cargo-check-external-types config"),
+        schema["title"].as_str()
+    );
+    assert!(schema["properties"]["allowed_external_types"].is_object());
+}
+
+#[test]
+fn version_info_json() {
+    let output = run_with_args(".", &["--version-info", "--output-format", "json"]);
+    let info: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert_eq!(
+        Some(u64::from(rustdoc_types::FORMAT_VERSION)),
+        info["max_format_version"].as_u64()
+    );
+    assert_eq!(
+        Some("cargo-check-external-types"),
+        run_with_args(".", &["--version-info"])
+            .split_whitespace()
+            .next()
+    );
+}